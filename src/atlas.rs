@@ -0,0 +1,47 @@
+/// Empty pixels around each packed image, so linear filtering never bleeds between neighbours
+const GUTTER: u32 = 1;
+
+/// Placement of images in one shared texture
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AtlasLayout {
+    pub width: u32,
+    pub height: u32,
+    /// Top-left corner of each image, in input order (None = didn't fit)
+    pub positions: Vec<Option<(u32, u32)>>,
+}
+
+/// Pack images of the given sizes into rows ("shelves"), tallest first, keeping the atlas
+/// within `max_dimension` on both axes; images that don't fit get no position
+pub fn pack(sizes: &[(u32, u32)], max_dimension: u32) -> AtlasLayout {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(sizes[index].1));
+
+    let mut layout = AtlasLayout {
+        width: 1,
+        height: 1,
+        positions: vec![None; sizes.len()],
+    };
+    let (mut shelf_x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let (width, height) = (sizes[index].0 + GUTTER, sizes[index].1 + GUTTER);
+        if width > max_dimension || height > max_dimension {
+            continue;
+        }
+        // Start a new shelf when this row is full
+        if shelf_x + width > max_dimension {
+            shelf_y += shelf_height;
+            shelf_x = 0;
+            shelf_height = 0;
+        }
+        if shelf_y + height > max_dimension {
+            continue;
+        }
+
+        layout.positions[index] = Some((shelf_x, shelf_y));
+        shelf_x += width;
+        shelf_height = shelf_height.max(height);
+        layout.width = layout.width.max(shelf_x);
+        layout.height = layout.height.max(shelf_y + shelf_height);
+    }
+    layout
+}
@@ -0,0 +1,48 @@
+use std::collections::HashSet;
+
+/// Why the mirrored output is currently blanked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlankReason {
+    /// The captured display is showing a Space the user marked as private
+    PrivateSpace,
+    /// Nobody has touched the keyboard or mouse for `idle_pause_minutes`
+    Idle,
+    /// The source picker is open: its thumbnails are unredacted and its window isn't always
+    /// excluded from the capture
+    SourcePicker,
+    /// The region selection overlay covers the display and isn't always excluded from the
+    /// capture
+    RegionSelection,
+}
+
+/// Blanking state machine: the output is hidden while at least one reason is active
+#[derive(Debug, Default)]
+pub struct BlankingController {
+    reasons: HashSet<BlankReason>,
+}
+
+impl BlankingController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Activate or clear a blanking reason; returns true if the overall blanked state changed
+    pub fn set(&mut self, reason: BlankReason, active: bool) -> bool {
+        let was_blanked = self.is_blanked();
+        if active {
+            self.reasons.insert(reason);
+        } else {
+            self.reasons.remove(&reason);
+        }
+        was_blanked != self.is_blanked()
+    }
+
+    pub fn is_blanked(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+
+    /// Currently active blanking reasons
+    pub fn reasons(&self) -> impl Iterator<Item = &BlankReason> {
+        self.reasons.iter()
+    }
+}
@@ -0,0 +1,386 @@
+use crate::encoding::VideoCodec;
+use crate::sinks::pipe::PipeFormat;
+use crate::source_list::SourceListing;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Frame rate used by `--pipe` unless `--pipe-fps` is given
+const DEFAULT_PIPE_FPS: u32 = 30;
+
+/// WHIP port `cloakshare receive` listens on unless `--port` is given
+pub const DEFAULT_RECEIVE_PORT: u16 = 8787;
+
+/// Name a receiver advertises unless `--name` is given
+const DEFAULT_RECEIVER_NAME: &str = "CloakShare Receiver";
+
+pub const USAGE: &str = "Usage: cloakshare [options]
+       cloakshare doctor
+       cloakshare verify <recording>
+       cloakshare list-displays [--json]
+       cloakshare list-windows [--json]
+       cloakshare receive [--port <port>] [--rtp-port <port>] [--name <name>]
+       cloakshare record --duration <seconds> -o <file> [--display <id>] [--codec <codec>]
+       cloakshare screenshot [--display <id>] [-o <file>]
+       cloakshare daemon [--display <id>] [--control-socket <path>]
+
+Commands:
+  doctor              Check permissions, displays, GPUs and capture backends and print a report
+                      to paste into bug reports
+  verify <recording>  Check a signed recording against its manifest (sign_recordings)
+  list-displays       List displays with their IDs, sizes and scale factors
+  list-windows        List windows with their IDs (for window sources)
+  receive             Show a stream from another CloakShare or a WHIP/RTP H.264 sender in a
+                      window, advertised on the local network
+  record              Capture, redact and encode to a file without opening a window
+                      (automated recordings on build machines)
+  screenshot          Save one redacted frame and exit (documentation screenshots)
+  daemon              Capture without a window and start outputs (recordings, streams to
+                      receivers) when asked over a control socket; see daemon.rs for commands
+
+Options:
+  --pipe [y4m|raw]    Write frames to stdout (default y4m), e.g. cloakshare --pipe | ffmpeg -i - out.mp4
+  --pipe-fps <fps>    Frame rate for --pipe (default 30)
+  --socket <path>     Stream frames + JSON metadata to local clients over a Unix socket
+  --whip <url>        Stream to a WHIP ingest URL (Cloudflare Stream, LiveKit, MediaMTX, ...)
+  --whip-token <token>
+                      Bearer token for --whip, or required from senders by receive
+                      (default: $CLOAK_SHARE_WHIP_TOKEN)
+  --quic <host:port>  Stream to a CloakShare receiver over QUIC (lowest latency on a LAN)
+  --send-to <name>    Stream to a CloakShare receiver found on the local network, over
+                      QUIC if both ends support it
+  --stream-key <key>  Key shared with CloakShare receivers; both ends must know it
+                      (default: $CLOAK_SHARE_STREAM_KEY, then the stream_key config option)
+  --compliance-log <path>
+                      On exit, export capture targets, blanking and redaction hits
+                      (JSON, or CSV for .csv paths)
+  --no-disk           Never write frames to disk: recordings, screenshots and file outputs
+                      are refused
+  --profile <name>    Apply a profile from the config file at startup
+  --capture-backend <name>
+                      Capture with this backend instead of the best available one
+  --port <port>       WHIP port for receive (default 8787)
+  --rtp-port <port>   Also accept plain RTP/H.264 on this UDP port (receive)
+  --name <name>       Receiver name shown to senders (default \"CloakShare Receiver\")
+  --display <id>      Display for record, screenshot or daemon (an ID from list-displays;
+                      default: the main display)
+  --control-socket <path>
+                      Control socket for daemon (default: control.sock next to the config)
+  --duration <seconds>
+                      How long to record
+  -o, --output <file> Recording file (.mp4, .mov or .h264 for H.264, .ivf for AV1 or VP9),
+                      or screenshot file (.png or .webp; default: the Desktop)
+  --codec <codec>     h264, av1 or vp9 (default: from the file extension)
+  --remote-input      Send the receive window's mouse and keyboard input to QUIC senders
+                      (they must set allow_remote_input and agree in a prompt)
+  --list-gpus         List GPU adapters (for the gpu_adapter config option) and exit
+  --list-cameras      List cameras (for the webcam_device config option) and exit
+  --list-windows      Same as list-windows
+  --json              Print list-displays and list-windows as JSON
+  --list-receivers    List CloakShare receivers on the local network (for --send-to) and exit
+  -h, --help          Show this help";
+
+/// Settings for writing frames to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipeOptions {
+    pub format: PipeFormat,
+    pub fps: u32,
+}
+
+/// Settings for `cloakshare receive`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiveOptions {
+    pub port: u16,
+    /// UDP port for plain RTP senders
+    pub rtp_port: Option<u16>,
+    /// Name advertised on the local network
+    pub name: String,
+    /// Forward mouse and keyboard input over the window to QUIC senders
+    pub remote_input: bool,
+}
+
+/// Settings for `cloakshare record`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordOptions {
+    /// Display ID (None = the main display)
+    pub display: Option<u32>,
+    pub duration: Duration,
+    pub output: PathBuf,
+    /// None = chosen from the output's extension
+    pub codec: Option<VideoCodec>,
+}
+
+/// Settings for `cloakshare screenshot`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenshotCommand {
+    /// Display ID (None = the main display)
+    pub display: Option<u32>,
+    /// None = a timestamped file on the Desktop
+    pub output: Option<PathBuf>,
+}
+
+/// Settings for `cloakshare daemon`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonOptions {
+    /// Display ID (None = the main display)
+    pub display: Option<u32>,
+    /// None = `control.sock` next to the config file
+    pub control_socket: Option<PathBuf>,
+}
+
+/// Parsed command-line options
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliOptions {
+    pub pipe: Option<PipeOptions>,
+    /// Unix socket path for the frame transport
+    pub socket: Option<PathBuf>,
+    /// WHIP ingest URL to stream to
+    pub whip: Option<String>,
+    /// Bearer token for the WHIP endpoint
+    pub whip_token: Option<String>,
+    /// CloakShare receiver (`host:port`) to stream to over QUIC
+    pub quic: Option<String>,
+    /// Name of a discovered receiver to stream to
+    pub send_to: Option<String>,
+    /// Key shared with receivers, overriding the config file
+    pub stream_key: Option<String>,
+    /// Compliance event export path, overriding the config file
+    pub compliance_log: Option<PathBuf>,
+    /// Refuse every output that writes frames to disk
+    pub no_disk: bool,
+    /// Config profile applied at startup
+    pub profile: Option<String>,
+    /// Capture backend overriding the config file and automatic choice
+    pub capture_backend: Option<String>,
+    pub list_gpus: bool,
+    pub list_cameras: bool,
+    /// `cloakshare list-displays` / `list-windows`: print sources and exit
+    pub list: Option<SourceListing>,
+    /// Print the list as JSON instead of a table
+    pub json: bool,
+    pub list_receivers: bool,
+    /// `cloakshare doctor`: print a diagnostics report and exit
+    pub doctor: bool,
+    /// `cloakshare verify <recording>`: check a recording's signed manifest and exit
+    pub verify: Option<PathBuf>,
+    /// `cloakshare receive`: show incoming streams instead of capturing
+    pub receive: Option<ReceiveOptions>,
+    /// `cloakshare record`: record to a file without a window
+    pub record: Option<RecordOptions>,
+    /// `cloakshare screenshot`: save one redacted frame without a window
+    pub screenshot: Option<ScreenshotCommand>,
+    /// `cloakshare daemon`: capture without a window, controlled over a socket
+    pub daemon: Option<DaemonOptions>,
+    pub help: bool,
+}
+
+impl CliOptions {
+    /// Parse arguments (without the program name)
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = CliOptions::default();
+        let mut pipe_fps = DEFAULT_PIPE_FPS;
+        let mut receive_port = None;
+        let mut rtp_port = None;
+        let mut receiver_name = None;
+        let mut remote_input = false;
+        let mut record = false;
+        let mut screenshot = false;
+        let mut daemon = false;
+        let mut control_socket = None;
+        let mut display = None;
+        let mut duration = None;
+        let mut output = None;
+        let mut codec = None;
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "doctor" => options.doctor = true,
+                "list-displays" => options.list = Some(SourceListing::Displays),
+                "list-windows" | "--list-windows" => options.list = Some(SourceListing::Windows),
+                "--json" => options.json = true,
+                "verify" => {
+                    let path = args.next().ok_or("verify needs a recording")?;
+                    options.verify = Some(PathBuf::from(path));
+                }
+                "receive" => {
+                    options.receive = Some(ReceiveOptions {
+                        port: DEFAULT_RECEIVE_PORT,
+                        rtp_port: None,
+                        name: DEFAULT_RECEIVER_NAME.to_string(),
+                        remote_input: false,
+                    });
+                }
+                "record" => record = true,
+                "screenshot" => screenshot = true,
+                "daemon" => daemon = true,
+                "--control-socket" => {
+                    let path = args.next().ok_or("--control-socket needs a path")?;
+                    control_socket = Some(PathBuf::from(path));
+                }
+                "--display" => {
+                    let value = args.next().ok_or("--display needs a display ID")?;
+                    display = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid --display value: {}", value))?,
+                    );
+                }
+                "--duration" => {
+                    let value = args.next().ok_or("--duration needs a number of seconds")?;
+                    let seconds: f64 = value
+                        .parse()
+                        .ok()
+                        .filter(|seconds: &f64| seconds.is_finite() && *seconds > 0.0)
+                        .ok_or_else(|| format!("Invalid --duration value: {}", value))?;
+                    duration = Some(Duration::from_secs_f64(seconds));
+                }
+                "-o" | "--output" => {
+                    let path = args.next().ok_or("--output needs a file")?;
+                    output = Some(PathBuf::from(path));
+                }
+                "--codec" => {
+                    let value = args.next().ok_or("--codec needs a codec")?;
+                    codec = Some(
+                        VideoCodec::parse(&value)
+                            .ok_or_else(|| format!("Unknown codec: {}", value))?,
+                    );
+                }
+                "--port" => receive_port = Some(parse_port(&arg, args.next())?),
+                "--rtp-port" => rtp_port = Some(parse_port(&arg, args.next())?),
+                "--name" => {
+                    receiver_name = Some(args.next().ok_or("--name needs a name")?);
+                }
+                "--remote-input" => remote_input = true,
+                "-h" | "--help" => options.help = true,
+                "--list-gpus" => options.list_gpus = true,
+                "--list-cameras" => options.list_cameras = true,
+                "--list-receivers" => options.list_receivers = true,
+                "--no-disk" => options.no_disk = true,
+                "--pipe" => {
+                    // The format is optional; only consume the next argument if it is one
+                    let format = match args.peek().and_then(|next| PipeFormat::parse(next)) {
+                        Some(format) => {
+                            args.next();
+                            format
+                        }
+                        None => PipeFormat::Y4m,
+                    };
+                    options.pipe = Some(PipeOptions {
+                        format,
+                        fps: DEFAULT_PIPE_FPS,
+                    });
+                }
+                "--pipe-fps" => {
+                    let value = args.next().ok_or("--pipe-fps needs a value")?;
+                    pipe_fps = value
+                        .parse()
+                        .ok()
+                        .filter(|fps| *fps > 0)
+                        .ok_or_else(|| format!("Invalid --pipe-fps value: {}", value))?;
+                }
+                "--whip" => {
+                    options.whip = Some(args.next().ok_or("--whip needs a URL")?);
+                }
+                "--quic" => {
+                    options.quic = Some(args.next().ok_or("--quic needs a host:port")?);
+                }
+                "--send-to" => {
+                    options.send_to = Some(args.next().ok_or("--send-to needs a receiver name")?);
+                }
+                "--stream-key" => {
+                    options.stream_key = Some(args.next().ok_or("--stream-key needs a key")?);
+                }
+                "--whip-token" => {
+                    options.whip_token = Some(args.next().ok_or("--whip-token needs a token")?);
+                }
+                "--compliance-log" => {
+                    let path = args.next().ok_or("--compliance-log needs a path")?;
+                    options.compliance_log = Some(PathBuf::from(path));
+                }
+                "--profile" => {
+                    options.profile = Some(args.next().ok_or("--profile needs a name")?);
+                }
+                "--capture-backend" => {
+                    options.capture_backend =
+                        Some(args.next().ok_or("--capture-backend needs a name")?);
+                }
+                "--socket" => {
+                    let path = args.next().ok_or("--socket needs a path")?;
+                    options.socket = Some(PathBuf::from(path));
+                }
+                other => return Err(format!("Unknown argument: {}", other)),
+            }
+        }
+
+        // Keep tokens out of the process list where possible
+        if (options.whip.is_some() || options.receive.is_some()) && options.whip_token.is_none() {
+            options.whip_token = std::env::var("CLOAK_SHARE_WHIP_TOKEN").ok();
+        }
+        if options.stream_key.is_none() {
+            options.stream_key = std::env::var("CLOAK_SHARE_STREAM_KEY").ok();
+        }
+        if let Some(pipe) = &mut options.pipe {
+            pipe.fps = pipe_fps;
+        }
+        if record {
+            options.record = Some(RecordOptions {
+                display,
+                duration: duration.ok_or("record needs --duration")?,
+                output: output.ok_or("record needs an output file (-o)")?,
+                codec,
+            });
+        } else if duration.is_some() || codec.is_some() {
+            return Err("--duration and --codec only apply to record".to_string());
+        } else if screenshot {
+            options.screenshot = Some(ScreenshotCommand { display, output });
+        } else if daemon && output.is_none() {
+            options.daemon = Some(DaemonOptions {
+                display,
+                control_socket: control_socket.take(),
+            });
+        } else if display.is_some() || output.is_some() {
+            return Err(
+                "--display only applies to record, screenshot and daemon, --output to record \
+                 and screenshot"
+                    .to_string(),
+            );
+        }
+        if control_socket.is_some() {
+            return Err("--control-socket only applies to daemon".to_string());
+        }
+        if options.json && options.list.is_none() {
+            return Err("--json only applies to list-displays and list-windows".to_string());
+        }
+        match &mut options.receive {
+            Some(receive) => {
+                receive.port = receive_port.unwrap_or(DEFAULT_RECEIVE_PORT);
+                receive.rtp_port = rtp_port;
+                receive.remote_input = remote_input;
+                if let Some(name) = receiver_name {
+                    receive.name = name;
+                }
+            }
+            None if receive_port.is_some()
+                || rtp_port.is_some()
+                || receiver_name.is_some()
+                || remote_input =>
+            {
+                return Err(
+                    "--port, --rtp-port, --name and --remote-input only apply to receive"
+                        .to_string(),
+                );
+            }
+            None => {}
+        }
+        Ok(options)
+    }
+}
+
+fn parse_port(flag: &str, value: Option<String>) -> Result<u16, String> {
+    let value = value.ok_or_else(|| format!("{} needs a port", flag))?;
+    value
+        .parse()
+        .ok()
+        .filter(|port| *port > 0)
+        .ok_or_else(|| format!("Invalid {} value: {}", flag, value))
+}
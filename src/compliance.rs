@@ -0,0 +1,249 @@
+use crate::redaction::RedactionRect;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Something a security review needs to know about a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ComplianceEvent {
+    SessionStarted,
+    /// Capture switched to a new source
+    CaptureTarget {
+        source: String,
+        /// Window title and owning app, or display name, when known
+        title: Option<String>,
+    },
+    /// Outputs started showing a blank frame instead of the capture
+    Blanked {
+        reasons: Vec<String>,
+    },
+    /// Outputs show the capture again
+    Unblanked,
+    /// A redaction rule masked content in `frames` published frames between the two times
+    RedactionHits {
+        rule: RedactionRect,
+        frames: u64,
+        first_ms: u64,
+        last_ms: u64,
+    },
+    SessionEnded,
+}
+
+/// A timestamped event
+#[derive(Debug, Clone, Serialize)]
+pub struct ComplianceRecord {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: ComplianceEvent,
+}
+
+/// Export format, chosen from the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComplianceFormat {
+    Json,
+    Csv,
+}
+
+impl ComplianceFormat {
+    /// `.csv` exports CSV, anything else JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => ComplianceFormat::Csv,
+            _ => ComplianceFormat::Json,
+        }
+    }
+}
+
+/// Per-rule hit counter for the current rule set
+#[derive(Debug, Clone, Copy)]
+struct RuleHits {
+    rule: RedactionRect,
+    frames: u64,
+    first_ms: u64,
+    last_ms: u64,
+}
+
+/// Session log of capture targets, blanking and redaction hits
+/// Hits are counted per rule and logged as one event when the rules change, so the log
+/// stays small however long the session runs
+#[derive(Debug)]
+pub struct ComplianceLog {
+    records: Vec<ComplianceRecord>,
+    hits: Vec<RuleHits>,
+    /// Rules generation `hits` belongs to
+    hits_generation: Option<u64>,
+}
+
+impl Default for ComplianceLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComplianceLog {
+    pub fn new() -> Self {
+        let mut log = Self {
+            records: Vec::new(),
+            hits: Vec::new(),
+            hits_generation: None,
+        };
+        log.record(ComplianceEvent::SessionStarted);
+        log
+    }
+
+    pub fn record(&mut self, event: ComplianceEvent) {
+        self.records.push(ComplianceRecord {
+            timestamp_ms: now_ms(),
+            event,
+        });
+    }
+
+    /// Count a published frame against every rule that masked part of it
+    /// `generation` changes whenever the rules may have changed
+    pub fn record_redaction(
+        &mut self,
+        generation: u64,
+        rules: &[RedactionRect],
+        width: u32,
+        height: u32,
+    ) {
+        if self.hits_generation != Some(generation) {
+            self.flush_hits();
+            self.hits_generation = Some(generation);
+            self.hits = rules
+                .iter()
+                .map(|&rule| RuleHits {
+                    rule,
+                    frames: 0,
+                    first_ms: 0,
+                    last_ms: 0,
+                })
+                .collect();
+        }
+
+        let now = now_ms();
+        for hits in &mut self.hits {
+            // Rules entirely outside the frame hide nothing
+            let rule = hits.rule;
+            if rule.width == 0 || rule.height == 0 || rule.x >= width || rule.y >= height {
+                continue;
+            }
+            if hits.frames == 0 {
+                hits.first_ms = now;
+            }
+            hits.frames += 1;
+            hits.last_ms = now;
+        }
+    }
+
+    /// Log and reset the hit counters of the current rule set
+    fn flush_hits(&mut self) {
+        for hits in std::mem::take(&mut self.hits) {
+            if hits.frames > 0 {
+                self.record(hit_event(&hits));
+            }
+        }
+    }
+
+    /// Everything logged so far, plus pending hit counts and a closing event
+    pub fn snapshot(&self) -> Vec<ComplianceRecord> {
+        let mut records = self.records.clone();
+        let now = now_ms();
+        records.extend(self.hits.iter().filter(|hits| hits.frames > 0).map(|hits| {
+            ComplianceRecord {
+                timestamp_ms: now,
+                event: hit_event(hits),
+            }
+        }));
+        records.push(ComplianceRecord {
+            timestamp_ms: now,
+            event: ComplianceEvent::SessionEnded,
+        });
+        records
+    }
+
+    /// Write the session so far to `path` (JSON array, or CSV for `.csv` files)
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let records = self.snapshot();
+        let contents = match ComplianceFormat::from_path(path) {
+            ComplianceFormat::Json => serde_json::to_vec_pretty(&records)
+                .map_err(|e| format!("Failed to encode compliance log: {}", e))?,
+            ComplianceFormat::Csv => to_csv(&records),
+        };
+        std::fs::File::create(path)
+            .and_then(|mut file| file.write_all(&contents))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+fn hit_event(hits: &RuleHits) -> ComplianceEvent {
+    ComplianceEvent::RedactionHits {
+        rule: hits.rule,
+        frames: hits.frames,
+        first_ms: hits.first_ms,
+        last_ms: hits.last_ms,
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// One row per record; columns that don't apply to an event stay empty
+fn to_csv(records: &[ComplianceRecord]) -> Vec<u8> {
+    let mut csv = String::from(
+        "timestamp_ms,event,source,title,reasons,rule_x,rule_y,rule_width,rule_height,frames,first_ms,last_ms\n",
+    );
+    for record in records {
+        let mut columns = vec![String::new(); 12];
+        columns[0] = record.timestamp_ms.to_string();
+        match &record.event {
+            ComplianceEvent::SessionStarted => columns[1] = "session_started".into(),
+            ComplianceEvent::SessionEnded => columns[1] = "session_ended".into(),
+            ComplianceEvent::Unblanked => columns[1] = "unblanked".into(),
+            ComplianceEvent::CaptureTarget { source, title } => {
+                columns[1] = "capture_target".into();
+                columns[2] = source.clone();
+                columns[3] = title.clone().unwrap_or_default();
+            }
+            ComplianceEvent::Blanked { reasons } => {
+                columns[1] = "blanked".into();
+                columns[4] = reasons.join(";");
+            }
+            ComplianceEvent::RedactionHits {
+                rule,
+                frames,
+                first_ms,
+                last_ms,
+            } => {
+                columns[1] = "redaction_hits".into();
+                columns[5] = rule.x.to_string();
+                columns[6] = rule.y.to_string();
+                columns[7] = rule.width.to_string();
+                columns[8] = rule.height.to_string();
+                columns[9] = frames.to_string();
+                columns[10] = first_ms.to_string();
+                columns[11] = last_ms.to_string();
+            }
+        }
+        let row: Vec<String> = columns.iter().map(|column| csv_field(column)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv.into_bytes()
+}
+
+/// Quote a field if it contains separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
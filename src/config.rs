@@ -0,0 +1,490 @@
+use crate::encoding::VideoCodec;
+use crate::frame_bus::FramePolicy;
+use crate::gpu_renderer::{GpuSettings, SurfaceAlphaMode, SurfaceFormat};
+use crate::platform::{CaptureScale, CaptureSource};
+use crate::plugin::PluginConfig;
+use crate::policy::Policy;
+use crate::redaction::{MaskFill, RedactionRect};
+use crate::scene::{Layer, Scene};
+use crate::screenshot::{ScreenshotFormat, ScreenshotOptions};
+use crate::theme::Theme;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A named preset bundling source, redaction rules and outputs
+/// Defined as `[profiles."Conference demo"]` tables in the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Capture source to switch to (None keeps the current source)
+    pub source: Option<CaptureSource>,
+
+    /// Regions (in capture pixels) that are always masked while the profile is active
+    pub redactions: Vec<RedactionRect>,
+
+    /// Mask fill for this profile (None uses the global `mask_fill`)
+    pub mask_fill: Option<MaskFill>,
+
+    /// Serve frames on this Unix socket while the profile is active
+    pub socket: Option<PathBuf>,
+
+    /// Record to this file while the profile is active
+    pub recording: Option<PathBuf>,
+
+    /// Codec for `recording` ("h264", "av1", "vp9"; default h264)
+    pub recording_codec: Option<VideoCodec>,
+}
+
+/// User configuration loaded from `config.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// macOS Space IDs that must never be mirrored (output is blanked while one is active);
+    /// Spaces marked with Cmd+Shift+B are kept in `private_spaces.json` instead
+    pub private_spaces: Vec<u64>,
+
+    /// Minutes without keyboard or mouse input after which capture and outputs pause (the
+    /// output is blanked), until the next input; None = never
+    pub idle_pause_minutes: Option<u32>,
+
+    /// While on battery: capture at most 30 fps, prefer the integrated GPU and skip
+    /// sharpening, effects and the float pipeline; everything returns when plugged in
+    pub low_power_on_battery: bool,
+
+    /// Regions (in capture pixels) masked at all times, on top of the active profile's rules
+    pub redactions: Vec<RedactionRect>,
+
+    /// Switch capture to the display under the cursor (multi-monitor setups)
+    pub follow_cursor: bool,
+
+    /// How long the cursor must stay on another display before following it
+    pub follow_cursor_debounce_ms: u64,
+
+    /// Downscale factor for the on-screen preview (1 = native), applied on the GPU; outputs
+    /// always get native resolution
+    pub preview_scale: u32,
+
+    /// Capture resolution: "native" (backing pixels) or "points" (lower bandwidth on Retina displays)
+    pub capture_scale: CaptureScale,
+
+    /// Capture backend by name (e.g. "screencapturekit"); None = best available
+    pub capture_backend: Option<String>,
+
+    /// Allow recording to files (administrators can turn this off with managed preferences)
+    pub allow_recording: bool,
+
+    /// Write an ed25519-signed manifest of segment hashes next to every recording, so recipients
+    /// can check it wasn't edited (`cloakshare verify <file>`; needs the signed-recordings feature)
+    pub sign_recordings: bool,
+
+    /// Signing key for recordings (default: `recording-signing.key` next to the config file,
+    /// created on first use)
+    pub recording_signing_key: Option<PathBuf>,
+
+    /// Write `<recording>.redactions.vtt` next to every recording: a WebVTT timeline of when
+    /// and where content was masked or the output blanked, for reviewers auditing the masking
+    /// (positions only, never the masked content)
+    pub recording_redaction_log: bool,
+
+    /// Lock frame buffers into RAM so raw screen content can't be swapped to disk
+    /// Falls back to ordinary memory (with a warning) when the locked memory limit is too low.
+    /// Intermediate copies (format conversion, GPU readbacks, encoder input) aren't locked
+    pub lock_frame_memory: bool,
+
+    /// "Frames never touch disk": refuse recordings, screenshots and any output that
+    /// writes frames to a file (for regulated environments; also `--no-disk`)
+    pub no_disk: bool,
+
+    /// Key shared with CloakShare receivers (also `--stream-key` or `$CLOAK_SHARE_STREAM_KEY`)
+    /// Sender and receiver prove they know it during signaling, which authenticates the
+    /// DTLS-SRTP session the stream is encrypted with; `receive` without one only listens on
+    /// localhost
+    pub stream_key: Option<String>,
+
+    /// Lower the stream bitrate, then resolution, when the network shows loss, rising RTT or
+    /// backpressure, and recover once it clears (WHIP outputs)
+    pub adaptive_bitrate: bool,
+
+    /// Refuse network streams that could be intercepted or spoofed: WHIP only to https:// (or
+    /// local) endpoints unless a stream key authenticates the receiver, QUIC only with a stream
+    /// key, and receivers only accept senders that know the stream key; on by default
+    pub require_encrypted_streams: bool,
+
+    /// Let CloakShare receivers (`receive --remote-input`) control the mouse and keyboard over
+    /// QUIC streams; needs a `stream_key`, and each connection still needs your consent in a
+    /// prompt (needs the quic feature)
+    pub allow_remote_input: bool,
+
+    /// Check `update_feed_url` for a newer release at startup and once a day, and mention it in
+    /// the window title (needs the update-check feature); managed deployments can lock this off
+    /// with the policy's `locked_settings` or an MDM profile
+    pub check_for_updates: bool,
+
+    /// Release feed: JSON with `version` and `url`, or a GitHub "latest release" API URL
+    pub update_feed_url: String,
+
+    /// Write the session's capture targets, blanking and redaction hits here on exit
+    /// (JSON, or CSV for `.csv` paths)
+    pub compliance_log: Option<PathBuf>,
+
+    /// Shared libraries adding capture backends or outputs (`[[plugins]]` tables)
+    pub plugins: Vec<PluginConfig>,
+
+    /// Pin rendering to the GPU whose name contains this (see `--list-gpus`)
+    pub gpu_adapter: Option<String>,
+
+    /// Render with a software adapter instead of the GPU (slow; for machines without a usable GPU)
+    pub gpu_software_fallback: bool,
+
+    /// Force the window surface format (e.g. "bgra8_unorm_srgb") if colors look washed out
+    pub surface_format: Option<SurfaceFormat>,
+
+    /// Force the window surface alpha mode ("opaque", "pre_multiplied", "post_multiplied", "inherit")
+    pub surface_alpha_mode: Option<SurfaceAlphaMode>,
+
+    /// Render through linear 16-bit float textures and tone-map to the window at the end, for
+    /// smoother gradients and EDR highlights (uses more GPU memory and bandwidth)
+    pub float_pipeline: bool,
+
+    /// Most GPU memory (MB) the mirror's textures may use; above it effects are turned off and
+    /// scene layers drawn at lower resolution. None = no limit (it's still lowered if the GPU
+    /// runs out)
+    pub gpu_memory_budget_mb: Option<u32>,
+
+    /// Show the capture at whole-number scales (1x, 2x, or 1/2, 1/3 when larger than the window)
+    /// with nearest sampling and black bars around it, for pixel-perfect terminals and pixel art
+    /// (only while no scene layers are shown)
+    pub integer_scaling: bool,
+
+    /// Fit the capture inside the window at its own aspect ratio, with black bars, instead of
+    /// stretching it to the window's shape (only while no scene layers are shown)
+    pub letterbox: bool,
+
+    /// On displays faster than 60 Hz (ProMotion), capture and present at the display's rate (up
+    /// to 120 fps) instead of 60; other displays stay at 60
+    pub high_refresh: bool,
+
+    /// Capture at this many frames per second instead of following the display
+    pub frame_rate: Option<u32>,
+
+    /// Reopen the mirror window with the size, position, monitor and fullscreen state it had
+    /// when CloakShare last quit (saved as window.json next to this file)
+    pub remember_window: bool,
+
+    /// Present new frames on the mirror display's refresh (macOS display link) instead of as
+    /// soon as they arrive, for evenly spaced frames
+    pub pace_to_display: bool,
+
+    /// Latency-priority mode for interactive demos: always show the newest capture, present
+    /// immediately instead of on the display's refresh, and skip sharpening, effects and the
+    /// float pipeline (overrides `capture_frame_policy`, `pace_to_display` and those settings)
+    pub low_latency: bool,
+
+    /// Sharpen the captured content, recovering text edges softened when the capture is
+    /// downscaled (Cmd+Shift+K toggles it while running)
+    pub sharpen: bool,
+
+    /// Sharpening strength, 0.0 (subtle) to 1.0 (strongest)
+    pub sharpen_strength: f32,
+
+    /// Directory of WGSL post-processing effects run over the output in file name order
+    /// (default: `effects` next to the config file; see src/effect.wgsl for the interface)
+    pub effects_dir: Option<PathBuf>,
+
+    /// Colors, font and corner radius of the overlay UI (HUD, error card, picker, region
+    /// selection) and of text layers that don't set their own font or corners (`[theme]`)
+    pub theme: Theme,
+
+    /// Language of dialogs, error cards and the window title, e.g. "de" or "pt-BR" (default:
+    /// the system language; English wherever a translation is missing)
+    pub language: Option<String>,
+
+    /// Directory of translations: `<language>.ftl` files with the messages of src/locales/en.ftl
+    /// (default: `locales` next to the config file)
+    pub locales_dir: Option<PathBuf>,
+
+    /// Fill for masked regions and blanked output: "black" or "chroma_key" (green / transparent)
+    pub mask_fill: MaskFill,
+
+    /// When rendering falls behind the capture: "drop_oldest" (lowest latency), `{ queue = N }`
+    /// (smoothest) or "block_capture" (capture waits; nothing is skipped)
+    pub capture_frame_policy: FramePolicy,
+
+    /// Same choice for recordings, which may rather queue frames than skip them while the
+    /// encoder catches up ("block_capture" slows the mirror down to the encoder's pace)
+    pub recording_frame_policy: FramePolicy,
+
+    /// Seconds without content changes before encoders/streamers drop to a low-bitrate mode
+    pub static_after_secs: u64,
+
+    /// Fraction of the screen (0.0-1.0) that must change in one frame to raise a change event
+    pub change_threshold: f32,
+
+    /// Layers composited into the output, bottom first (`[[scene]]` tables; empty = capture only)
+    pub scene: Vec<Layer>,
+
+    /// Camera for webcam layers, by name (see `--list-cameras`); None = system default
+    pub webcam_device: Option<String>,
+
+    /// Named presets, switchable with `--profile <name>` or Cmd+Shift+N
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// Profile applied at startup when `--profile` isn't given
+    pub default_profile: Option<String>,
+
+    /// Screenshot image format ("png" or "webp")
+    pub screenshot_format: ScreenshotFormat,
+
+    /// Encode WebP screenshots losslessly
+    pub screenshot_lossless: bool,
+
+    /// WebP lossy quality (0-100)
+    pub screenshot_quality: f32,
+
+    /// Organization policy the config was merged under (not read from config.toml)
+    #[serde(skip)]
+    pub policy: Policy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            private_spaces: Vec::new(),
+            idle_pause_minutes: None,
+            low_power_on_battery: true,
+            redactions: Vec::new(),
+            follow_cursor: false,
+            follow_cursor_debounce_ms: 500,
+            preview_scale: 1,
+            capture_scale: CaptureScale::Native,
+            capture_backend: None,
+            allow_recording: true,
+            sign_recordings: false,
+            recording_signing_key: None,
+            recording_redaction_log: false,
+            lock_frame_memory: false,
+            no_disk: false,
+            stream_key: None,
+            adaptive_bitrate: true,
+            require_encrypted_streams: true,
+            allow_remote_input: false,
+            check_for_updates: true,
+            update_feed_url: "https://api.github.com/repos/cloak-share/CloakShare/releases/latest"
+                .to_string(),
+            compliance_log: None,
+            plugins: Vec::new(),
+            gpu_adapter: None,
+            gpu_software_fallback: false,
+            surface_format: None,
+            surface_alpha_mode: None,
+            float_pipeline: false,
+            gpu_memory_budget_mb: None,
+            integer_scaling: false,
+            letterbox: false,
+            high_refresh: true,
+            frame_rate: None,
+            remember_window: true,
+            pace_to_display: true,
+            low_latency: false,
+            sharpen: false,
+            sharpen_strength: 0.5,
+            effects_dir: None,
+            theme: Theme::default(),
+            language: None,
+            locales_dir: None,
+            mask_fill: MaskFill::Black,
+            capture_frame_policy: FramePolicy::DropOldest,
+            recording_frame_policy: FramePolicy::DropOldest,
+            static_after_secs: 5,
+            change_threshold: 0.25,
+            scene: Vec::new(),
+            webcam_device: None,
+            profiles: BTreeMap::new(),
+            default_profile: None,
+            screenshot_format: ScreenshotFormat::Png,
+            screenshot_lossless: false,
+            screenshot_quality: 90.0,
+            policy: Policy::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Screenshot encoding options from the config
+    pub fn screenshot_options(&self) -> ScreenshotOptions {
+        ScreenshotOptions {
+            format: self.screenshot_format,
+            lossless: self.screenshot_lossless,
+            quality: self.screenshot_quality,
+        }
+    }
+
+    /// GPU selection settings from the config
+    pub fn gpu_settings(&self) -> GpuSettings {
+        GpuSettings {
+            adapter: self.gpu_adapter.clone(),
+            surface_format: self.surface_format,
+            alpha_mode: self.surface_alpha_mode,
+            software_fallback: self.gpu_software_fallback,
+            mailbox: false,
+            float_pipeline: self.float_pipeline,
+            low_latency: self.low_latency,
+            low_power: false,
+            memory_budget_mb: self.gpu_memory_budget_mb,
+            theme: self.theme.clone(),
+            preview_scale: self.preview_scale,
+        }
+    }
+
+    /// Output scene from the config
+    pub fn scene(&self) -> Scene {
+        Scene::new(self.scene.clone())
+    }
+
+    /// Time without input before capture and outputs pause (`idle_pause_minutes`)
+    pub fn idle_pause_after(&self) -> Option<Duration> {
+        self.idle_pause_minutes
+            .map(|minutes| Duration::from_secs(u64::from(minutes.max(1)) * 60))
+    }
+
+    /// Signing key for recording manifests: `recording_signing_key`, or next to the config file
+    pub fn recording_signing_key_path(&self) -> PathBuf {
+        self.recording_signing_key
+            .clone()
+            .or_else(|| Some(Self::path()?.parent()?.join("recording-signing.key")))
+            .unwrap_or_else(|| PathBuf::from("recording-signing.key"))
+    }
+
+    /// Effects directory: `effects_dir`, or `effects` next to the config file
+    pub fn effects_dir_path(&self) -> PathBuf {
+        self.effects_dir
+            .clone()
+            .or_else(|| Some(Self::path()?.parent()?.join("effects")))
+            .unwrap_or_else(|| PathBuf::from("effects"))
+    }
+
+    /// Translations directory: `locales_dir`, or `locales` next to the config file
+    pub fn locales_dir_path(&self) -> PathBuf {
+        self.locales_dir
+            .clone()
+            .or_else(|| Some(Self::path()?.parent()?.join("locales")))
+            .unwrap_or_else(|| PathBuf::from("locales"))
+    }
+
+    /// Location of the config file: `$CLOAK_SHARE_CONFIG` or `~/.config/cloakshare/config.toml`
+    pub fn path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("CLOAK_SHARE_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let home = std::env::var("HOME").ok()?;
+        Some(
+            PathBuf::from(home)
+                .join(".config")
+                .join("cloakshare")
+                .join("config.toml"),
+        )
+    }
+
+    /// Load the config file, with managed preferences on top and the organization policy
+    /// (see `Policy`) over both
+    /// A missing or invalid config falls back to defaults, but a policy that can't be
+    /// enforced is an error: the app must not start without it
+    pub fn load() -> Result<Self, String> {
+        let policy = Policy::load()?;
+        let mut table = Self::load_table();
+
+        // Settings managed by the organization (MDM) replace the user's
+        let managed = managed_settings();
+        if !managed.is_empty() {
+            Config::deserialize(toml::Value::Table(managed.clone()))
+                .map_err(|e| format!("Invalid managed preferences: {}", e))?;
+            println!("Applying {} managed setting(s)", managed.len());
+            for (key, value) in managed {
+                if table.get(&key).is_some_and(|user| *user != value) {
+                    eprintln!(
+                        "Config setting {} is managed by your organization; ignoring your value",
+                        key
+                    );
+                }
+                table.insert(key, value);
+            }
+        }
+
+        policy.check_profiles(&table)?;
+        policy.lock_settings(&mut table)?;
+
+        let mut config: Config = toml::Value::Table(table).try_into().map_err(|e| {
+            // The user's and managed parts were already validated, so this comes from the policy
+            format!("Policy locked settings don't form a valid config: {}", e)
+        })?;
+        config.redactions.extend(&policy.required_redactions);
+        for space in &policy.required_private_spaces {
+            if !config.private_spaces.contains(space) {
+                config.private_spaces.push(*space);
+            }
+        }
+        config.policy = policy;
+        if config.low_latency {
+            println!("Low-latency mode: newest frame only, no pacing, sharpening or effects");
+            config.prefer_latency();
+        }
+        Ok(config)
+    }
+
+    /// Turn off everything that trades latency for smoothness or quality (`low_latency`)
+    pub(crate) fn prefer_latency(&mut self) {
+        self.capture_frame_policy = FramePolicy::DropOldest;
+        self.pace_to_display = false;
+        self.sharpen = false;
+        self.float_pipeline = false;
+    }
+
+    /// The user's config file as a TOML table; empty if it is missing or invalid
+    fn load_table() -> toml::Table {
+        let Some(path) = Self::path() else {
+            return toml::Table::new();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .parse::<toml::Table>()
+                .map_err(|e| e.to_string())
+                .and_then(|table| {
+                    // Check the whole config so a bad value doesn't fail only after merging
+                    Config::deserialize(toml::Value::Table(table.clone()))
+                        .map(|_| table)
+                        .map_err(|e| e.to_string())
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Invalid config file {}: {}, using defaults",
+                        path.display(),
+                        e
+                    );
+                    toml::Table::new()
+                }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => {
+                eprintln!(
+                    "Failed to read config file {}: {}, using defaults",
+                    path.display(),
+                    e
+                );
+                toml::Table::new()
+            }
+        }
+    }
+}
+
+/// Settings from MDM configuration profiles (macOS managed preferences)
+#[cfg(target_os = "macos")]
+fn managed_settings() -> toml::Table {
+    crate::platform::macos_managed_preferences::managed_preferences()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn managed_settings() -> toml::Table {
+    toml::Table::new()
+}
@@ -0,0 +1,292 @@
+use crate::config::Config;
+use crate::platform_detector::PlatformDetector;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::time::Duration;
+
+/// Lines of output kept for the report
+const LOG_LINES: usize = 200;
+
+/// How long the panic hook waits for a lock another thread holds before leaving its section out
+const LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Report sections the app keeps up to date as it runs (config, GPU adapter, pipeline state)
+static CONTEXT: Mutex<BTreeMap<&'static str, String>> = Mutex::new(BTreeMap::new());
+
+/// The newest lines written to stdout/stderr, oldest first
+static LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Write a crash report when CloakShare panics, after the usual panic message, and keep the
+/// last lines of output for it. `capture_stdout` is false when stdout carries frame data
+/// (`--pipe`); stderr is always kept
+pub fn install(capture_stdout: bool) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        report_panic(info);
+    }));
+    capture_output(capture_stdout);
+}
+
+/// Replace a section of the report; never put pixel data here
+pub fn set_context(section: &'static str, contents: String) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.insert(section, contents);
+    }
+}
+
+/// Put the config in the report, without the stream key and plugin settings (which may hold
+/// credentials)
+pub fn set_config(config: &Config) {
+    let mut config = config.clone();
+    if config.stream_key.is_some() {
+        config.stream_key = Some("<redacted>".to_string());
+    }
+    for plugin in &mut config.plugins {
+        if !plugin.settings.is_empty() {
+            plugin.settings = "<redacted>".to_string();
+        }
+    }
+    set_context("config", format!("{:#?}", config));
+}
+
+fn report_panic(info: &std::panic::PanicHookInfo) {
+    let path = report_path();
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, build_report(info)));
+    match written {
+        Ok(()) => {
+            eprintln!(
+                "CloakShare crashed. A report (without screen content) was written to {}",
+                path.display()
+            );
+            show_crash_dialog(&path);
+        }
+        Err(e) => eprintln!("Failed to write crash report {}: {}", path.display(), e),
+    }
+}
+
+fn build_report(info: &std::panic::PanicHookInfo) -> String {
+    let mut report = String::new();
+    // Writing to a String can't fail
+    let _ = write_report(&mut report, info);
+    report
+}
+
+fn write_report(out: &mut String, info: &std::panic::PanicHookInfo) -> std::fmt::Result {
+    writeln!(out, "CloakShare {} crash report", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "Time: {} (unix)", unix_time())?;
+    writeln!(out, "OS: {}", PlatformDetector::os_version())?;
+    writeln!(
+        out,
+        "Thread: {}",
+        std::thread::current().name().unwrap_or("unnamed")
+    )?;
+    writeln!(
+        out,
+        "Panic: {}",
+        info.payload_as_str().unwrap_or("(non-string payload)")
+    )?;
+    if let Some(location) = info.location() {
+        writeln!(out, "Location: {}:{}", location.file(), location.line())?;
+    }
+    writeln!(
+        out,
+        "\nBacktrace:\n{}",
+        std::backtrace::Backtrace::force_capture()
+    )?;
+
+    match lock_briefly(&CONTEXT) {
+        Some(context) => {
+            for (section, contents) in context.iter() {
+                writeln!(out, "\n== {} ==\n{}", section, contents)?;
+            }
+        }
+        None => writeln!(out, "\n(state unavailable: locked by another thread)")?,
+    }
+
+    writeln!(out, "\n== Last output ==")?;
+    match lock_briefly(&LOG) {
+        Some(log) if !log.is_empty() => {
+            for line in log.iter() {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        Some(_) => writeln!(out, "(none captured)")?,
+        None => writeln!(out, "(unavailable: locked by another thread)")?,
+    }
+    Ok(())
+}
+
+/// Lock `mutex` unless another thread keeps holding it (the panicking thread itself may)
+fn lock_briefly<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    let deadline = std::time::Instant::now() + LOCK_TIMEOUT;
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Some(guard),
+            Err(TryLockError::Poisoned(e)) => return Some(e.into_inner()),
+            Err(TryLockError::WouldBlock) if std::time::Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(TryLockError::WouldBlock) => return None,
+        }
+    }
+}
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// `~/Library/Logs/CloakShare/crash-<unix time>.txt` on macOS, the temp directory elsewhere
+fn report_path() -> PathBuf {
+    let directory = match std::env::var("HOME") {
+        Ok(home) if cfg!(target_os = "macos") => {
+            PathBuf::from(home).join("Library/Logs/CloakShare")
+        }
+        _ => std::env::temp_dir(),
+    };
+    directory.join(format!("cloakshare-crash-{}.txt", unix_time()))
+}
+
+/// Tell the user where the report is; AppKit dialogs only work on the main thread
+#[cfg(target_os = "macos")]
+fn show_crash_dialog(path: &std::path::Path) {
+    if std::thread::current().name() == Some("main") {
+        use crate::l10n::{tr, tr_args};
+        crate::platform::macos_alert::show_alert(
+            &tr("crash-title"),
+            &tr_args("crash-message", &[("path", &path.display().to_string())]),
+            &[&tr("button-ok")],
+        );
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_crash_dialog(_path: &std::path::Path) {
+    // The path is printed to stderr; there is no native dialog on this platform yet
+}
+
+fn remember_line(line: String) {
+    if let Ok(mut log) = LOG.lock() {
+        if log.len() == LOG_LINES {
+            log.pop_front();
+        }
+        log.push_back(line);
+    }
+}
+
+#[cfg(unix)]
+mod tee {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::fd::FromRawFd;
+    use std::sync::Mutex;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// How long exit waits for output still in a pipe to be forwarded
+    const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// A stream routed through a pipe: its fd, where it originally went, and a signal that the
+    /// reader forwarded everything
+    struct Tee {
+        fd: i32,
+        original: i32,
+        done: mpsc::Receiver<()>,
+    }
+
+    static TEES: Mutex<Vec<Tee>> = Mutex::new(Vec::new());
+
+    /// Route `fd` through a pipe whose reader forwards everything to the original destination
+    /// and keeps each line for the report
+    pub(super) fn tee(fd: i32) -> Result<(), String> {
+        let _ = std::io::stdout().flush();
+        let _ = std::io::stderr().flush();
+        let mut pipe = [0; 2];
+        unsafe {
+            if libc::pipe(pipe.as_mut_ptr()) != 0 {
+                return Err("Failed to create a pipe".to_string());
+            }
+            let original = libc::dup(fd);
+            if original < 0 || libc::dup2(pipe[1], fd) < 0 {
+                libc::close(pipe[0]);
+                libc::close(pipe[1]);
+                if original >= 0 {
+                    libc::close(original);
+                }
+                return Err(format!("Failed to redirect fd {}", fd));
+            }
+            libc::close(pipe[1]);
+
+            let reader = BufReader::new(File::from_raw_fd(pipe[0]));
+            // A separate fd for the reader, so restoring `fd` at exit can't close it underneath
+            let mut forward = File::from_raw_fd(libc::dup(original));
+            let (done_tx, done) = mpsc::channel();
+            let spawned = std::thread::Builder::new()
+                .name("output-tee".to_string())
+                .spawn(move || {
+                    for line in reader.split(b'\n').map_while(Result::ok) {
+                        let _ = forward.write_all(&line);
+                        let _ = forward.write_all(b"\n");
+                        super::remember_line(String::from_utf8_lossy(&line).into_owned());
+                    }
+                    let _ = done_tx.send(());
+                });
+            if let Err(e) = spawned {
+                // Nothing reads the pipe: put the stream back
+                libc::dup2(original, fd);
+                libc::close(original);
+                return Err(format!("Failed to start output capture: {}", e));
+            }
+            if let Ok(mut tees) = TEES.lock() {
+                if tees.is_empty() {
+                    libc::atexit(restore_output);
+                }
+                tees.push(Tee { fd, original, done });
+            }
+        }
+        Ok(())
+    }
+
+    /// At exit: point the streams back at their destinations, which closes the pipes, and
+    /// give the readers a moment to forward what's still in them
+    extern "C" fn restore_output() {
+        let _ = std::io::stdout().flush();
+        let _ = std::io::stderr().flush();
+        let Ok(mut tees) = TEES.lock() else {
+            return;
+        };
+        for tee in tees.drain(..) {
+            unsafe {
+                libc::dup2(tee.original, tee.fd);
+                libc::close(tee.original);
+            }
+            let _ = tee.done.recv_timeout(DRAIN_TIMEOUT);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn capture_output(capture_stdout: bool) {
+    let mut streams = vec![libc::STDERR_FILENO];
+    if capture_stdout {
+        streams.push(libc::STDOUT_FILENO);
+    }
+    for fd in streams {
+        if let Err(e) = tee::tee(fd) {
+            eprintln!("Crash reports won't include recent output: {}", e);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn capture_output(_capture_stdout: bool) {
+    // Redirecting the console isn't implemented here; reports carry the state sections only
+}
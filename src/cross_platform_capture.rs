@@ -1,76 +1,152 @@
-use crate::platform::{DisplayResolution, PixelConverter, Platform, ScreenCapture};
-use std::sync::{Arc, Mutex};
+use crate::frame_bus::FramePolicy;
+use crate::frame_channel::FrameChannelStats;
+use crate::platform::{
+    CaptureCapabilities, CaptureEventCallback, CaptureScale, CaptureSource, DisplayInfo,
+    DisplayResolution, Frame, PixelConverter, Platform, ScreenCapture, SourceInfo, SourceThumbnail,
+    WindowInfo,
+};
+use std::sync::Arc;
+
+/// Builds a backend's capture and matching pixel converter
+pub type CaptureConstructor = Arc<
+    dyn Fn() -> Result<(Box<dyn ScreenCapture>, Box<dyn PixelConverter>), String> + Send + Sync,
+>;
+
+/// A capture implementation that can be picked at runtime
+/// Built-in backends are registered by the platform modules, others come from plugins
+#[derive(Clone)]
+pub struct CaptureBackend {
+    /// Name for the `capture_backend` config option and `--capture-backend`
+    pub name: String,
+    /// Backends are tried highest priority first
+    pub priority: i32,
+    /// Whether the backend can work on this machine (OS version, session type, ...)
+    pub is_available: Arc<dyn Fn() -> bool + Send + Sync>,
+    pub create: CaptureConstructor,
+}
+
+/// Capture backends known to this build; each platform module registers its own
+#[derive(Default)]
+pub struct CaptureBackendRegistry {
+    backends: Vec<CaptureBackend>,
+}
+
+impl CaptureBackendRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry holding every backend compiled in for the current platform
+    pub fn with_platform_backends() -> Self {
+        let mut registry = Self::new();
+        crate::platform::register_capture_backends(&mut registry);
+        registry
+    }
+
+    pub fn register(&mut self, backend: CaptureBackend) {
+        self.backends.push(backend);
+        self.backends
+            .sort_by_key(|backend| std::cmp::Reverse(backend.priority));
+    }
+
+    /// Registered backends, highest priority first
+    pub fn backends(&self) -> &[CaptureBackend] {
+        &self.backends
+    }
+
+    /// The backend named `name` (case-insensitive) if given, otherwise the highest priority
+    /// backend that is available
+    pub fn select(&self, name: Option<&str>) -> Result<&CaptureBackend, String> {
+        let names = || {
+            self.backends
+                .iter()
+                .map(|backend| backend.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match name {
+            Some(name) => {
+                let backend = self
+                    .backends
+                    .iter()
+                    .find(|backend| backend.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| {
+                        format!("Unknown capture backend {:?} (known: {})", name, names())
+                    })?;
+                if !(backend.is_available)() {
+                    return Err(format!(
+                        "Capture backend {} isn't available on this system",
+                        backend.name
+                    ));
+                }
+                Ok(backend)
+            }
+            None => self
+                .backends
+                .iter()
+                .find(|backend| (backend.is_available)())
+                .ok_or_else(|| {
+                    format!(
+                        "No capture backend available on {:?} (known: {})",
+                        Platform::current(),
+                        names()
+                    )
+                }),
+        }
+    }
+}
 
 /// Cross-platform screen capture manager that abstracts over platform-specific implementations
 pub struct CrossPlatformScreenCapture {
     capture: Box<dyn ScreenCapture>,
     converter: Box<dyn PixelConverter>,
     platform: Platform,
+    /// Name of the backend in use
+    backend: String,
+    /// Set with `set_source`; None captures the platform's default display
+    source: Option<CaptureSource>,
 }
 
 impl CrossPlatformScreenCapture {
-    /// Create a new cross-platform screen capture instance
+    /// Create a screen capture with the best available backend
     pub fn new() -> Result<Self, String> {
-        let platform = Platform::current();
-
-        if !platform.is_supported() {
-            return Err(format!("Platform {:?} is not yet supported", platform));
-        }
-
-        let (capture, converter): (Box<dyn ScreenCapture>, Box<dyn PixelConverter>) = match platform
-        {
-            Platform::MacOS => {
-                #[cfg(target_os = "macos")]
-                {
-                    use crate::platform::ScreenCaptureFactory;
-                    use crate::platform::macos::{MacOSPixelConverter, MacOSScreenCaptureFactory};
-                    (
-                        Box::new(MacOSScreenCaptureFactory::create()),
-                        Box::new(MacOSPixelConverter),
-                    )
-                }
-                #[cfg(not(target_os = "macos"))]
-                return Err("macOS platform code not available on this system".to_string());
-            }
+        Self::with_backend(None)
+    }
 
-            Platform::Windows => {
-                #[cfg(target_os = "windows")]
-                {
-                    use crate::platform::ScreenCaptureFactory;
-                    use crate::platform::windows::{
-                        WindowsPixelConverter, WindowsScreenCaptureFactory,
-                    };
-                    (
-                        Box::new(WindowsScreenCaptureFactory::create()),
-                        Box::new(WindowsPixelConverter),
-                    )
-                }
-                #[cfg(not(target_os = "windows"))]
-                return Err("Windows platform code not available on this system".to_string());
-            }
+    /// Create a screen capture with the named backend, or the best available one if None
+    pub fn with_backend(name: Option<&str>) -> Result<Self, String> {
+        Self::from_registry(&CaptureBackendRegistry::with_platform_backends(), name)
+    }
 
-            Platform::Linux => {
-                #[cfg(target_os = "linux")]
-                {
-                    use crate::platform::ScreenCaptureFactory;
-                    use crate::platform::linux::{LinuxPixelConverter, LinuxScreenCaptureFactory};
-                    (
-                        Box::new(LinuxScreenCaptureFactory::create()),
-                        Box::new(LinuxPixelConverter),
-                    )
-                }
-                #[cfg(not(target_os = "linux"))]
-                return Err("Linux platform code not available on this system".to_string());
-            }
-        };
+    /// Create a screen capture with a backend from `registry` (e.g. one extended by plugins)
+    pub fn from_registry(
+        registry: &CaptureBackendRegistry,
+        name: Option<&str>,
+    ) -> Result<Self, String> {
+        let backend = registry.select(name)?;
+        let (capture, converter) = (backend.create)()
+            .map_err(|e| format!("Failed to create capture backend {}: {}", backend.name, e))?;
+        println!("Using capture backend {}", backend.name);
 
         Ok(Self {
             capture,
             converter,
-            platform,
+            platform: Platform::current(),
+            backend: backend.name.clone(),
+            source: None,
         })
     }
 
+    /// Name of the capture backend in use
+    pub fn backend_name(&self) -> &str {
+        &self.backend
+    }
+
+    /// What the platform's capture supports
+    pub fn capabilities(&self) -> CaptureCapabilities {
+        self.capture.capabilities()
+    }
+
     /// Get the display resolution
     pub fn get_display_resolution(&self) -> Result<DisplayResolution, String> {
         self.capture.get_display_resolution()
@@ -84,9 +160,26 @@ impl CrossPlatformScreenCapture {
         self.capture.start_capture(exclude_window)
     }
 
-    /// Get the latest captured frame
-    pub fn get_latest_frame(&self) -> Option<Vec<u8>> {
-        self.capture.get_latest_frame()
+    /// Take the next captured frame out of the frame channel so it is only processed once
+    /// Raw frames are unredacted - only the redaction stage may read them
+    pub(crate) fn take_latest_frame(&self) -> Option<Frame> {
+        self.capture.frame_channel().try_recv()
+    }
+
+    /// Make the capture thread wait for room instead of dropping frames, while a
+    /// `BlockCapture` output is behind
+    pub(crate) fn set_frame_held(&self, held: bool) {
+        self.capture.frame_channel().set_held(held);
+    }
+
+    /// What happens to captured frames when the redaction stage falls behind
+    pub fn set_frame_policy(&self, policy: FramePolicy) {
+        self.capture.frame_channel().set_policy(policy);
+    }
+
+    /// Frames delivered, taken and dropped by the capture's frame channel
+    pub fn frame_stats(&self) -> FrameChannelStats {
+        self.capture.frame_channel().stats()
     }
 
     /// Stop screen capture
@@ -99,9 +192,71 @@ impl CrossPlatformScreenCapture {
         self.platform
     }
 
-    /// Get frame buffer for direct access (useful for testing)
-    pub fn get_frame_buffer(&self) -> Arc<Mutex<Option<Vec<u8>>>> {
-        self.capture.get_frame_buffer()
+    /// Register a callback fired on the capture thread for new frames and stream errors
+    pub fn set_event_callback(&mut self, callback: CaptureEventCallback) {
+        self.capture.set_event_callback(callback)
+    }
+
+    /// Platform identifier of the display being captured
+    pub fn captured_display_id(&self) -> Option<u32> {
+        self.capture.captured_display_id()
+    }
+
+    /// Platform identifiers of every display in the capture
+    pub fn captured_display_ids(&self) -> Vec<u32> {
+        self.capture.captured_display_ids()
+    }
+
+    /// For multi-display sources: the display shown at output pixel (x, y)
+    pub fn display_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.capture.display_at(x, y)
+    }
+
+    /// List every window on the desktop, visible or not
+    pub fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        self.capture.list_windows()
+    }
+
+    /// List connected displays
+    pub fn list_displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        self.capture.list_displays()
+    }
+
+    /// List displays and windows that can be captured
+    pub fn list_sources(&self) -> Result<Vec<SourceInfo>, String> {
+        self.capture.list_sources()
+    }
+
+    /// Select the source used by the next `start_capture`
+    pub fn set_source(&mut self, source: CaptureSource) {
+        self.source = Some(source);
+        self.capture.set_source(source)
+    }
+
+    /// The source set with `set_source`, if any
+    pub fn source(&self) -> Option<CaptureSource> {
+        self.source
+    }
+
+    /// Capture at backing pixels or points from the next `start_capture`
+    pub fn set_capture_scale(&mut self, scale: CaptureScale) {
+        self.capture.set_capture_scale(scale)
+    }
+
+    /// Deliver up to `fps` frames per second from the next `start_capture`
+    pub fn set_frame_rate(&mut self, fps: u32) {
+        self.capture.set_frame_rate(fps)
+    }
+
+    /// Capture a small preview image of a source (raw pixels - for the local picker only)
+    pub fn capture_thumbnail(
+        &self,
+        source: CaptureSource,
+        max_width: u32,
+        max_height: u32,
+    ) -> Result<SourceThumbnail, String> {
+        self.capture
+            .capture_thumbnail(source, max_width, max_height)
     }
 
     /// Get the pixel converter for manual conversions
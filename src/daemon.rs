@@ -0,0 +1,389 @@
+use crate::config::Config;
+use crate::encoding::VideoCodec;
+use crate::headless::HeadlessPipeline;
+use crate::screenshot::{default_screenshot_path, save_screenshot};
+use crate::sinks::recording::{RecordingControl, RecordingSink};
+use crate::sinks::{SinkId, SinkManager, SinkStats};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::Duration;
+
+/// How long to wait before restarting a capture that stopped on its own
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// `control.sock` next to the config file, where `cloakshare daemon` listens by default
+pub fn default_socket_path() -> Option<PathBuf> {
+    Some(Config::path()?.parent()?.join("control.sock"))
+}
+
+/// A control command with the channel its JSON reply goes back on
+type Request = (String, Sender<Value>);
+
+/// Set by SIGINT/SIGTERM; the daemon then shuts down as for `quit`
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// `cloakshare daemon`: capture and redact without a window, starting and stopping outputs
+/// when asked over a Unix control socket (one command per line, one JSON reply per line):
+///
+/// - `status`: capture size and running outputs
+/// - `record <file> [h264|av1|vp9]`, `pause <output id>`, `resume <output id>` (recordings),
+///   `chapter [title]` (marks a chapter in every running recording), `screenshot [file]`
+/// - `stream <host:port>` (QUIC), `whip <url> [token]`, `send-to <receiver name>`
+/// - `stop <output id>`, `stop-all`, `quit`
+///
+/// The socket is owner-only, so only the user running the daemon can start outputs.
+/// SIGINT and SIGTERM stop it like `quit`, so running recordings are finalized
+pub fn run(socket: PathBuf, display: Option<u32>, config: &Config) -> Result<(), String> {
+    let mut pipeline = HeadlessPipeline::new(display, config)?;
+    let mut daemon = Daemon {
+        sinks: SinkManager::new(pipeline.bus().clone()),
+        config,
+        output_size: pipeline.output_size(),
+        recordings: HashMap::new(),
+    };
+    daemon
+        .sinks
+        .set_static_after(Duration::from_secs(config.static_after_secs));
+    daemon.sinks.set_no_disk(config.no_disk);
+
+    let requests = listen(&socket)?;
+    catch_stop_signals();
+    pipeline.start()?;
+    println!(
+        "CloakShare daemon capturing {}x{}; control socket at {}",
+        daemon.output_size.0,
+        daemon.output_size.1,
+        socket.display()
+    );
+
+    while !STOP_REQUESTED.load(Ordering::Relaxed) {
+        if let Err(e) = pipeline.poll() {
+            // Keep serving: a display that comes back (or a granted permission) resumes it
+            eprintln!("{}; restarting capture", e);
+            std::thread::sleep(RESTART_DELAY);
+            if let Err(e) = pipeline.start() {
+                eprintln!("Failed to restart capture: {}", e);
+            }
+        }
+        while let Ok((command, reply)) = requests.try_recv() {
+            let quit = command.trim() == "quit";
+            let response = match daemon.handle(&command, &pipeline) {
+                Ok(value) => json!({ "ok": true, "result": value }),
+                Err(e) => json!({ "ok": false, "error": e }),
+            };
+            let _ = reply.send(response);
+            if quit {
+                STOP_REQUESTED.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    }
+    daemon.sinks.detach_all();
+    pipeline.finish(config);
+    let _ = std::fs::remove_file(&socket);
+    Ok(())
+}
+
+/// Have SIGINT and SIGTERM request a clean shutdown instead of killing the process
+#[cfg(unix)]
+fn catch_stop_signals() {
+    extern "C" fn request_stop(_signal: libc::c_int) {
+        STOP_REQUESTED.store(true, Ordering::Relaxed);
+    }
+    let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(not(unix))]
+fn catch_stop_signals() {}
+
+struct Daemon<'a> {
+    sinks: SinkManager,
+    config: &'a Config,
+    output_size: (u32, u32),
+    /// Pause and resume handles of the running recordings
+    recordings: HashMap<SinkId, RecordingControl>,
+}
+
+impl Daemon<'_> {
+    fn handle(&mut self, command: &str, pipeline: &HeadlessPipeline) -> Result<Value, String> {
+        let mut words = command.split_whitespace();
+        let name = words.next().ok_or("Empty command")?;
+        let args: Vec<&str> = words.collect();
+        match (name, args.as_slice()) {
+            ("status", []) => Ok(json!({
+                "width": self.output_size.0,
+                "height": self.output_size.1,
+                "outputs": self.outputs(),
+            })),
+            ("record", [path, codec @ ..]) if codec.len() <= 1 => {
+                let codec = match codec.first() {
+                    Some(name) => {
+                        VideoCodec::parse(name).ok_or_else(|| format!("Unknown codec: {}", name))?
+                    }
+                    None => VideoCodec::H264,
+                };
+                self.record(PathBuf::from(path), codec)
+            }
+            (command @ ("pause" | "resume"), [id]) => {
+                let control = self
+                    .find_output(id)
+                    .and_then(|id| self.recordings.get(&id))
+                    .ok_or_else(|| format!("No recording with ID {}", id))?;
+                control.set_paused(command == "pause");
+                Ok(json!({ "paused": control.is_paused() }))
+            }
+            ("chapter", title) => {
+                // Titles may contain spaces
+                let title = (!title.is_empty()).then(|| title.join(" "));
+                let mut marked = 0;
+                for (id, control) in &self.recordings {
+                    if self.sinks.is_running(*id) {
+                        control.add_chapter(title.clone());
+                        marked += 1;
+                    }
+                }
+                if marked == 0 {
+                    return Err("No recording is running".to_string());
+                }
+                Ok(json!({ "recordings": marked }))
+            }
+            ("screenshot", path) if path.len() <= 1 => {
+                if self.config.no_disk {
+                    return Err("Screenshots are disabled in no-disk mode".to_string());
+                }
+                let frame = pipeline
+                    .bus()
+                    .latest()
+                    .ok_or("No frame has been captured yet")?;
+                let options = self.config.screenshot_options();
+                let path = path
+                    .first()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| default_screenshot_path(options.format));
+                save_screenshot(&frame, &path, &options)?;
+                Ok(json!({ "path": path }))
+            }
+            ("stream", [address]) => self.start_quic(address.to_string()),
+            ("whip", [url, token @ ..]) if token.len() <= 1 => self.start_whip(
+                url.to_string(),
+                token.first().map(|token| token.to_string()),
+            ),
+            ("send-to", [_, ..]) => {
+                // Receiver names may contain spaces
+                let receiver = crate::discovery::find_receiver(&args.join(" "))?;
+                receiver.check_paired(self.config.stream_key.as_deref())?;
+                match receiver.quic_address().filter(|_| cfg!(feature = "quic")) {
+                    Some(address) => self.start_quic(address),
+                    None => {
+                        let url = receiver
+                            .whip_url()
+                            .ok_or_else(|| format!("Receiver {} has no address", receiver.name))?;
+                        self.start_whip(url, None)
+                    }
+                }
+            }
+            ("stop", [id]) => {
+                let id = self
+                    .find_output(id)
+                    .ok_or_else(|| format!("No output with ID {}", id))?;
+                self.sinks.detach(id);
+                self.recordings.remove(&id);
+                Ok(json!({ "outputs": self.outputs() }))
+            }
+            ("stop-all", []) => {
+                self.sinks.detach_all();
+                self.recordings.clear();
+                Ok(json!({ "outputs": self.outputs() }))
+            }
+            ("quit", []) => Ok(Value::Null),
+            _ => Err(format!("Unknown command: {}", command.trim())),
+        }
+    }
+
+    fn outputs(&self) -> Vec<Value> {
+        let mut outputs: Vec<(SinkId, &str)> = self.sinks.sinks().collect();
+        outputs.sort_by_key(|(id, _)| id.as_u64());
+        outputs
+            .into_iter()
+            .map(|(id, name)| {
+                json!({
+                    "id": id.as_u64(),
+                    "name": name,
+                    "running": self.sinks.is_running(id),
+                    "stats": self.sinks.stats(id).map(stats_json),
+                    "paused": self.recordings.get(&id).map(RecordingControl::is_paused),
+                })
+            })
+            .collect()
+    }
+
+    fn find_output(&self, id: &str) -> Option<SinkId> {
+        let id: u64 = id.parse().ok()?;
+        self.sinks
+            .sinks()
+            .map(|(sink, _)| sink)
+            .find(|sink| sink.as_u64() == id)
+    }
+
+    /// Reply for a newly attached output
+    fn started(id: SinkId) -> Value {
+        json!({ "id": id.as_u64() })
+    }
+
+    fn record(&mut self, path: PathBuf, codec: VideoCodec) -> Result<Value, String> {
+        if !self.config.allow_recording {
+            return Err("Recording is disabled by your organization".to_string());
+        }
+        let (width, height) = self.output_size;
+        let signing_key = self
+            .config
+            .sign_recordings
+            .then(|| self.config.recording_signing_key_path());
+        let sink = RecordingSink::create(path, codec, width, height, signing_key.as_deref())?
+            .with_frame_policy(self.config.recording_frame_policy)
+            .with_redaction_log(self.config.recording_redaction_log);
+        let control = sink.control();
+        let id = self.sinks.attach(Box::new(sink))?;
+        self.recordings.insert(id, control);
+        Ok(Self::started(id))
+    }
+
+    #[cfg(feature = "quic")]
+    fn start_quic(&mut self, address: String) -> Result<Value, String> {
+        use crate::encoding::{EncoderSettings, create_encoder};
+        // QUIC is always encrypted, but only the stream key proves who is on the other end
+        if self.config.require_encrypted_streams && self.config.stream_key.is_none() {
+            return Err(format!(
+                "Not streaming to {}: require_encrypted_streams needs a stream_key for QUIC",
+                address
+            ));
+        }
+        let (width, height) = self.output_size;
+        let settings = EncoderSettings::new(width, height);
+        let encoder = create_encoder(VideoCodec::H264, &settings)?;
+        let sink =
+            crate::sinks::quic::QuicSink::new(address, self.config.stream_key.clone(), encoder)
+                .with_frame_rate(settings.fps);
+        self.sinks.attach(Box::new(sink)).map(Self::started)
+    }
+
+    #[cfg(not(feature = "quic"))]
+    fn start_quic(&mut self, _address: String) -> Result<Value, String> {
+        Err("QUIC output isn't in this build (enable the quic cargo feature)".to_string())
+    }
+
+    #[cfg(feature = "whip")]
+    fn start_whip(&mut self, url: String, token: Option<String>) -> Result<Value, String> {
+        use crate::encoding::{EncoderSettings, create_encoder};
+        crate::stream_auth::check_endpoint(
+            &url,
+            self.config.require_encrypted_streams,
+            self.config.stream_key.as_deref(),
+        )?;
+        let (width, height) = self.output_size;
+        let settings = EncoderSettings::new(width, height);
+        let encoder = create_encoder(VideoCodec::H264, &settings)?;
+        let mut sink =
+            crate::sinks::whip::WhipSink::new(url, token, self.config.stream_key.clone(), encoder)
+                .with_frame_rate(settings.fps);
+        if self.config.adaptive_bitrate {
+            sink = sink.with_adaptive_bitrate(settings);
+        }
+        self.sinks.attach(Box::new(sink)).map(Self::started)
+    }
+
+    #[cfg(not(feature = "whip"))]
+    fn start_whip(&mut self, _url: String, _token: Option<String>) -> Result<Value, String> {
+        Err("WHIP output isn't in this build (enable the whip cargo feature)".to_string())
+    }
+}
+
+/// An output's counters in `status` replies
+fn stats_json(stats: SinkStats) -> Value {
+    json!({
+        "frames": stats.frames,
+        "skipped": stats.skipped,
+        "queue_depth": stats.queue_depth,
+        "encoder": stats.encoder.map(|encoder| json!({
+            "bitrate_kbps": encoder.bitrate_kbps,
+            "keyframe_interval_ms": encoder
+                .keyframe_interval
+                .map(|interval| interval.as_millis() as u64),
+            "dropped_packets": encoder.dropped_packets,
+        })),
+    })
+}
+
+/// Accept control connections on `path` in the background; each command line arrives on the
+/// returned channel
+#[cfg(unix)]
+fn listen(path: &std::path::Path) -> Result<Receiver<Request>, String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // A socket file left over from a previous run would make bind fail; anything else at the
+    // path isn't ours to delete
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!(
+                "{} already exists and isn't a socket",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale {}: {}", path.display(), e))?;
+    }
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    // Create the socket owner-only, so there's no moment another user could connect
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let bound = UnixListener::bind(path);
+    unsafe { libc::umask(previous_umask) };
+    let listener = bound.map_err(|e| format!("Failed to bind {}: {}", path.display(), e))?;
+
+    let (sender, requests) = channel::<Request>();
+    std::thread::Builder::new()
+        .name("daemon-control".to_string())
+        .spawn(move || {
+            for client in listener.incoming().flatten() {
+                let sender = sender.clone();
+                let _ = std::thread::Builder::new()
+                    .name("daemon-client".to_string())
+                    .spawn(move || {
+                        let Ok(mut writer) = client.try_clone() else {
+                            return;
+                        };
+                        for line in BufReader::new(client).lines() {
+                            let Ok(line) = line else { break };
+                            if line.trim().is_empty() {
+                                continue;
+                            }
+                            let (reply, response) = channel();
+                            if sender.send((line, reply)).is_err() {
+                                break; // The daemon is shutting down
+                            }
+                            let Ok(response) = response.recv() else { break };
+                            if writeln!(writer, "{}", response).is_err() {
+                                break;
+                            }
+                        }
+                    });
+            }
+        })
+        .map_err(|e| format!("Failed to start the control socket thread: {}", e))?;
+    Ok(requests)
+}
+
+#[cfg(not(unix))]
+fn listen(_path: &std::path::Path) -> Result<Receiver<Request>, String> {
+    Err("The daemon's control socket needs a Unix platform".to_string())
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// mDNS/Bonjour service type receivers advertise
+pub const SERVICE_TYPE: &str = "_cloakshare._tcp.local.";
+
+/// How long `discover` listens for answers
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A CloakShare receiver found on the local network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredReceiver {
+    /// Instance name shown to the user (e.g. "Meeting Room TV")
+    pub name: String,
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+    /// HTTP path of the receiver's WHIP endpoint (TXT record `path`)
+    pub path: String,
+    /// Also accepts QUIC on the same port number over UDP (TXT record `quic`)
+    pub quic: bool,
+}
+
+impl DiscoveredReceiver {
+    /// Refuse to stream to this receiver without a stream key: anyone on the network can
+    /// advertise a receiver under any name, and only the key's handshake proves it's this one
+    pub fn check_paired(&self, stream_key: Option<&str>) -> Result<(), String> {
+        match stream_key {
+            Some(_) => Ok(()),
+            None => Err(format!(
+                "Not streaming to receiver {}: receivers found on the network need a stream_key \
+                 shared with them (--stream-key)",
+                self.name
+            )),
+        }
+    }
+
+    /// WHIP URL to stream to
+    pub fn whip_url(&self) -> Option<String> {
+        Some(format!(
+            "http://{}:{}{}",
+            self.host_address()?,
+            self.port,
+            self.path
+        ))
+    }
+
+    /// `host:port` to stream to over QUIC, if the receiver accepts it
+    pub fn quic_address(&self) -> Option<String> {
+        if !self.quic {
+            return None;
+        }
+        Some(format!("{}:{}", self.host_address()?, self.port))
+    }
+
+    /// Address for URLs, preferring IPv4 (link-local IPv6 needs a scope ID)
+    fn host_address(&self) -> Option<String> {
+        let address = self
+            .addresses
+            .iter()
+            .find(|address| address.is_ipv4())
+            .or_else(|| self.addresses.first())?;
+        Some(match address {
+            IpAddr::V4(address) => address.to_string(),
+            IpAddr::V6(address) => format!("[{}]", address),
+        })
+    }
+}
+
+type Receivers = Arc<Mutex<BTreeMap<String, DiscoveredReceiver>>>;
+
+/// Keeps an up-to-date list of receivers on the local network while it exists
+pub struct ReceiverBrowser {
+    receivers: Receivers,
+    #[cfg(feature = "discovery")]
+    daemon: mdns_sd::ServiceDaemon,
+}
+
+impl ReceiverBrowser {
+    /// Start browsing in the background; receivers appear as they answer
+    #[cfg(feature = "discovery")]
+    pub fn start() -> Result<Self, String> {
+        let daemon = mdns_sd::ServiceDaemon::new()
+            .map_err(|e| format!("Failed to start mDNS discovery: {}", e))?;
+        let events = daemon
+            .browse(SERVICE_TYPE)
+            .map_err(|e| format!("Failed to browse for receivers: {}", e))?;
+
+        let receivers = Receivers::default();
+        let found = receivers.clone();
+        std::thread::Builder::new()
+            .name("receiver-discovery".to_string())
+            .spawn(move || {
+                // Ends when the daemon shuts down and closes the channel
+                while let Ok(event) = events.recv() {
+                    let Ok(mut found) = found.lock() else {
+                        break;
+                    };
+                    match event {
+                        mdns_sd::ServiceEvent::ServiceResolved(info) => {
+                            let receiver = DiscoveredReceiver {
+                                name: instance_name(info.get_fullname()),
+                                host: info.get_hostname().trim_end_matches('.').to_string(),
+                                addresses: info.get_addresses().iter().copied().collect(),
+                                port: info.get_port(),
+                                path: info
+                                    .get_property_val_str("path")
+                                    .unwrap_or("/whip")
+                                    .to_string(),
+                                quic: info.get_property_val_str("quic") == Some("1"),
+                            };
+                            found.insert(info.get_fullname().to_string(), receiver);
+                        }
+                        mdns_sd::ServiceEvent::ServiceRemoved(_, fullname) => {
+                            found.remove(&fullname);
+                        }
+                        _ => {}
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to spawn discovery thread: {}", e))?;
+
+        Ok(Self { receivers, daemon })
+    }
+
+    #[cfg(not(feature = "discovery"))]
+    pub fn start() -> Result<Self, String> {
+        Err("LAN discovery isn't in this build (enable the discovery cargo feature)".to_string())
+    }
+
+    /// Receivers currently on the network, sorted by service name
+    pub fn receivers(&self) -> Vec<DiscoveredReceiver> {
+        self.receivers
+            .lock()
+            .map(|receivers| receivers.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "discovery")]
+impl Drop for ReceiverBrowser {
+    fn drop(&mut self) {
+        let _ = self.daemon.stop_browse(SERVICE_TYPE);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Browse for `DISCOVERY_TIMEOUT` and return whatever answered
+pub fn discover() -> Result<Vec<DiscoveredReceiver>, String> {
+    let browser = ReceiverBrowser::start()?;
+    std::thread::sleep(DISCOVERY_TIMEOUT);
+    Ok(browser.receivers())
+}
+
+/// The discovered receiver called `name` (case-insensitive)
+pub fn find_receiver(name: &str) -> Result<DiscoveredReceiver, String> {
+    let receiver = discover()?
+        .into_iter()
+        .find(|receiver| receiver.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No receiver called {} on the local network", name))?;
+    if receiver.addresses.is_empty() {
+        return Err(format!("Receiver {} has no address", receiver.name));
+    }
+    Ok(receiver)
+}
+
+/// Advertises this machine as a receiver until dropped
+pub struct ReceiverAdvertisement {
+    #[cfg(feature = "discovery")]
+    daemon: mdns_sd::ServiceDaemon,
+    #[cfg(feature = "discovery")]
+    fullname: String,
+}
+
+impl ReceiverAdvertisement {
+    /// Announce a receiver called `name` whose WHIP endpoint is `path` on `port`
+    #[cfg(feature = "discovery")]
+    pub fn start(name: &str, port: u16, path: &str) -> Result<Self, String> {
+        let daemon = mdns_sd::ServiceDaemon::new()
+            .map_err(|e| format!("Failed to start mDNS advertisement: {}", e))?;
+        let host = format!("{}.local.", local_hostname());
+        let mut properties = vec![("path", path), ("version", env!("CARGO_PKG_VERSION"))];
+        if cfg!(feature = "quic") {
+            properties.push(("quic", "1"));
+        }
+        let info = mdns_sd::ServiceInfo::new(SERVICE_TYPE, name, &host, "", port, &properties[..])
+            .map_err(|e| format!("Invalid receiver advertisement: {}", e))?
+            .enable_addr_auto();
+        let fullname = info.get_fullname().to_string();
+        daemon
+            .register(info)
+            .map_err(|e| format!("Failed to advertise receiver: {}", e))?;
+        println!("Advertising receiver \"{}\" on the local network", name);
+        Ok(Self { daemon, fullname })
+    }
+
+    #[cfg(not(feature = "discovery"))]
+    pub fn start(_name: &str, _port: u16, _path: &str) -> Result<Self, String> {
+        Err("LAN discovery isn't in this build (enable the discovery cargo feature)".to_string())
+    }
+}
+
+#[cfg(feature = "discovery")]
+impl Drop for ReceiverAdvertisement {
+    fn drop(&mut self) {
+        // Say goodbye so senders drop us right away instead of when the record expires
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// "Meeting Room TV._cloakshare._tcp.local." -> "Meeting Room TV"
+#[cfg(feature = "discovery")]
+fn instance_name(fullname: &str) -> String {
+    fullname
+        .strip_suffix(SERVICE_TYPE)
+        .map(|name| name.trim_end_matches('.'))
+        .unwrap_or(fullname)
+        .to_string()
+}
+
+/// This machine's host name, for the advertised address records
+#[cfg(feature = "discovery")]
+fn local_hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } == 0 {
+            let end = buffer
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(buffer.len());
+            let name = String::from_utf8_lossy(&buffer[..end]);
+            // macOS reports "name.local"; the suffix is added back by the caller
+            let name = name.trim_end_matches(".local");
+            if !name.is_empty() {
+                return name.to_string();
+            }
+        }
+    }
+    "cloakshare".to_string()
+}
@@ -0,0 +1,131 @@
+use crate::config::Config;
+use crate::cross_platform_capture::{CaptureBackendRegistry, CrossPlatformScreenCapture};
+use crate::gpu_renderer::GpuRenderer;
+use crate::locked_memory::memlock_limit;
+use crate::platform::CaptureSource;
+use crate::platform_detector::PlatformDetector;
+use crate::plugin::load_plugins;
+use crate::policy::Policy;
+use std::fmt::Write;
+
+/// Check the environment CloakShare depends on and describe it as plain text for bug reports
+/// Problems are reported in the text rather than as errors, so the report is always complete
+pub fn report() -> String {
+    let mut report = String::new();
+    // Writing to a String can't fail
+    let _ = write_report(&mut report);
+    report
+}
+
+fn write_report(out: &mut String) -> std::fmt::Result {
+    writeln!(out, "CloakShare {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "OS: {}", PlatformDetector::os_version())?;
+    writeln!(
+        out,
+        "Platform support: {}",
+        match PlatformDetector::check_support() {
+            Ok(_) => "supported".to_string(),
+            Err(e) => e.lines().next().unwrap_or_default().to_string(),
+        }
+    )?;
+
+    let config = match Config::load() {
+        Ok(config) => {
+            let policy = if config.policy.is_empty() {
+                "none".to_string()
+            } else {
+                format!("enforced from {}", Policy::path().display())
+            };
+            writeln!(out, "Policy: {}", policy)?;
+            config
+        }
+        Err(e) => {
+            writeln!(out, "Policy: {} (the app refuses to start)", e)?;
+            Config::default()
+        }
+    };
+
+    writeln!(
+        out,
+        "Frame memory locking: {} (locked memory limit: {})",
+        if config.lock_frame_memory {
+            "on"
+        } else {
+            "off"
+        },
+        match memlock_limit() {
+            Some(limit) => format!("{} KiB", limit / 1024),
+            None => "unlimited".to_string(),
+        }
+    )?;
+
+    // Capture backends, including ones added by configured plugins
+    let plugins = load_plugins(&config.plugins);
+    let mut registry = CaptureBackendRegistry::with_platform_backends();
+    for plugin in &plugins {
+        plugin.register_capture_backend(&mut registry);
+    }
+    writeln!(out, "\nCapture backends:")?;
+    for backend in registry.backends() {
+        writeln!(
+            out,
+            "  {} (priority {}): {}",
+            backend.name,
+            backend.priority,
+            if (backend.is_available)() {
+                "available"
+            } else {
+                "unavailable"
+            }
+        )?;
+    }
+
+    let capture =
+        CrossPlatformScreenCapture::from_registry(&registry, config.capture_backend.as_deref());
+    writeln!(out, "\nScreen capture:")?;
+    writeln!(
+        out,
+        "  Screen Recording permission: {:?}",
+        PlatformDetector::screen_recording_permission()
+    )?;
+    match &capture {
+        Ok(capture) => {
+            writeln!(out, "  Backend in use: {}", capture.backend_name())?;
+            match capture.list_sources() {
+                Ok(sources) => {
+                    writeln!(out, "  Displays:")?;
+                    for source in &sources {
+                        if let CaptureSource::Display(id) = source.source {
+                            writeln!(
+                                out,
+                                "    {} {} ({}x{})",
+                                id, source.title, source.width, source.height
+                            )?;
+                        }
+                    }
+                }
+                Err(e) => writeln!(out, "  Listing displays failed: {}", e)?,
+            }
+        }
+        Err(e) => writeln!(out, "  No capture backend: {}", e)?,
+    }
+
+    writeln!(out, "\nGPU adapters:")?;
+    let adapters = GpuRenderer::enumerate_adapters_with_limits();
+    if adapters.is_empty() {
+        writeln!(out, "  none found")?;
+    }
+    for (info, limits) in adapters {
+        writeln!(
+            out,
+            "  {} ({:?}, {:?}), driver {} {}, max texture size {}",
+            info.name,
+            info.backend,
+            info.device_type,
+            info.driver,
+            info.driver_info,
+            limits.max_texture_dimension_2d
+        )?;
+    }
+    Ok(())
+}
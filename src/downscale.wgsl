@@ -0,0 +1,32 @@
+// Downscale pass: the reduced-resolution preview (Config::preview_scale)
+//
+// Frames are uploaded at full resolution and this pass writes the smaller capture texture;
+// each output pixel is the average of the `factor` x `factor` block of capture pixels it
+// covers, the same box filter scaling.rs applies on the CPU.
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+override factor: u32 = 2u;
+
+// Fullscreen triangle, as in shader.wgsl
+@vertex
+fn vs_downscale(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let x = select(-1.0, 3.0, vertex_index == 1u);
+    let y = select(-1.0, 3.0, vertex_index == 2u);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+@fragment
+fn fs_downscale(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // Blocks at the right and bottom edges may be cut short; repeat the last pixel
+    let last = textureDimensions(t_source) - vec2<u32>(1u);
+    let origin = vec2<u32>(position.xy) * factor;
+    var sum = vec4<f32>(0.0);
+    for (var dy = 0u; dy < factor; dy += 1u) {
+        for (var dx = 0u; dx < factor; dx += 1u) {
+            sum += textureLoad(t_source, min(origin + vec2<u32>(dx, dy), last), 0);
+        }
+    }
+    return sum / f32(factor * factor);
+}
@@ -0,0 +1,47 @@
+// Effect prelude: prepended to every user effect in the effects directory
+//
+// An effect is a fragment shader run over the whole output after the scene is drawn;
+// effects are chained in file name order, each one reading what the previous one wrote.
+// An effect file only defines the fragment entry point, for example a vignette:
+//
+//     @fragment
+//     fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+//         let color = textureSample(t_input, s_input, input.tex_coords);
+//         let distance = length(input.tex_coords - vec2<f32>(0.5));
+//         return vec4<f32>(color.rgb * (1.0 - smoothstep(0.4, 0.8, distance)), color.a);
+//     }
+
+struct EffectUniforms {
+    // Output size in pixels
+    resolution: vec2<f32>,
+    // Seconds since the effects were loaded (advances with each rendered frame)
+    time: f32,
+    _padding: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // 0-1 across the output, top-left origin
+    @location(0) tex_coords: vec2<f32>,
+}
+
+// Output of the scene or the previous effect
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_input: sampler;
+
+@group(0) @binding(2)
+var<uniform> effect: EffectUniforms;
+
+// Fullscreen triangle, as in shader.wgsl
+@vertex
+fn vs_effect(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = select(-1.0, 3.0, vertex_index == 1u);
+    let y = select(-1.0, 3.0, vertex_index == 2u);
+    return VertexOutput(
+        vec4<f32>(x, y, 0.0, 1.0),
+        vec2<f32>((x + 1.0) / 2.0, 1.0 - (y + 1.0) / 2.0),
+    );
+}
@@ -0,0 +1,311 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Declarations every effect is compiled with (see effect.wgsl)
+const PRELUDE: &str = include_str!("effect.wgsl");
+
+/// Bytes in the effect uniform block (resolution, time, padding)
+const UNIFORM_SIZE: u64 = 16;
+
+/// A post-processing pass from the effects directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Effect {
+    /// File name without the extension
+    pub name: String,
+    /// WGSL defining `fs_main`; compiled after the prelude
+    pub source: String,
+}
+
+/// Read every `.wgsl` file in `dir`, in file name order (so `10-blur.wgsl` runs before
+/// `20-vignette.wgsl`); a missing directory means no effects
+pub fn load_effects(dir: &Path) -> Vec<Effect> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "wgsl")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| match std::fs::read_to_string(&path) {
+            Ok(source) => Some(Effect {
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                source,
+            }),
+            Err(e) => {
+                eprintln!("Skipping effect {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Ping-pong render targets for one output size
+struct Targets {
+    width: u32,
+    height: u32,
+    views: [wgpu::TextureView; 2],
+    /// `bind_groups[i]` reads `views[i]`
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+/// Compiled effects, run as fullscreen passes after the scene is drawn
+/// The scene goes into an offscreen texture; each effect reads the previous result and the
+/// last one writes to the real target
+pub struct EffectChain {
+    passes: Vec<wgpu::RenderPipeline>,
+    format: wgpu::TextureFormat,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform: wgpu::Buffer,
+    started: Instant,
+    /// Recreated when the output size changes (window resize, offscreen export)
+    targets: Mutex<Option<Targets>>,
+}
+
+impl EffectChain {
+    /// Compile `effects` for targets of `format`; effects that don't compile are skipped
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, effects: &[Effect]) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(UNIFORM_SIZE),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("effect_bind_group_layout"),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Effect Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let passes = effects
+            .iter()
+            .filter_map(|effect| match compile(device, &layout, format, effect) {
+                Ok(pipeline) => {
+                    println!("Loaded effect {}", effect.name);
+                    Some(pipeline)
+                }
+                Err(e) => {
+                    eprintln!("Skipping effect {}: {}", effect.name, e);
+                    None
+                }
+            })
+            .collect();
+
+        // Effects sample between pixels (blurs, distortion), so filter both ways
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Effect Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Effect Uniforms"),
+            size: UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            passes,
+            format,
+            bind_group_layout,
+            sampler,
+            uniform,
+            started: Instant::now(),
+            targets: Mutex::new(None),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Offscreen view the scene must be drawn into before `encode` for a `width`x`height` output
+    pub fn input_view(&self, device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+        let mut targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        let targets = match targets.take() {
+            Some(existing) if existing.width == width && existing.height == height => {
+                targets.insert(existing)
+            }
+            _ => targets.insert(self.create_targets(device, width, height)),
+        };
+        targets.views[0].clone()
+    }
+
+    /// Run every effect over the scene drawn into `input_view`, ending in `output`
+    pub fn encode(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+    ) {
+        let targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(targets) = targets.as_ref() else {
+            return;
+        };
+
+        let mut uniforms = [0u8; UNIFORM_SIZE as usize];
+        uniforms[0..4].copy_from_slice(&(targets.width as f32).to_le_bytes());
+        uniforms[4..8].copy_from_slice(&(targets.height as f32).to_le_bytes());
+        uniforms[8..12].copy_from_slice(&self.started.elapsed().as_secs_f32().to_le_bytes());
+        queue.write_buffer(&self.uniform, 0, &uniforms);
+
+        for (index, pipeline) in self.passes.iter().enumerate() {
+            let read = index % 2;
+            let target = if index + 1 == self.passes.len() {
+                output
+            } else {
+                &targets.views[1 - read]
+            };
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Effect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &targets.bind_groups[read], &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn create_targets(&self, device: &wgpu::Device, width: u32, height: u32) -> Targets {
+        let view = || {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Effect Target"),
+                    size: wgpu::Extent3d {
+                        width: width.max(1),
+                        height: height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    // Same format as the real target, so the scene and effect pipelines fit both
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let views = [view(), view()];
+        let bind_group = |view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("effect_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.uniform.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [bind_group(&views[0]), bind_group(&views[1])];
+        Targets {
+            width,
+            height,
+            views,
+            bind_groups,
+        }
+    }
+}
+
+/// Build one effect's pipeline, catching WGSL and validation errors instead of letting them
+/// reach the device's error handler
+fn compile(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    effect: &Effect,
+) -> Result<wgpu::RenderPipeline, String> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(&effect.name),
+        source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", PRELUDE, effect.source).into()),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(&effect.name),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &module,
+            entry_point: Some("vs_effect"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &module,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error.to_string()),
+        None => Ok(pipeline),
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+/// Lowest bitrate the controller backs off to
+pub const MIN_BITRATE_KBPS: u32 = 300;
+
+/// Largest downscale factor; below that the stream just gets blurrier
+const MAX_DOWNSCALE: u32 = 4;
+
+/// Loss above this counts as congestion
+const CONGESTED_LOSS: f32 = 0.1;
+
+/// Loss below this allows probing for more bandwidth
+const CLEAR_LOSS: f32 = 0.02;
+
+/// RTT this far above the best seen means queues are building up along the path
+const RTT_QUEUEING_MARGIN: Duration = Duration::from_millis(100);
+
+/// Wait after a change before backing off again, so one loss burst isn't punished twice
+const DECREASE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wait after a change before probing upwards; longer than backing off to avoid oscillating
+const INCREASE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Network conditions reported by a streaming output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetworkFeedback {
+    /// Round-trip time, if the transport measures it (e.g. RTCP receiver reports)
+    pub rtt: Option<Duration>,
+    /// Fraction of packets lost since the last report (0.0-1.0)
+    pub loss: f32,
+    /// Frames the output skipped since the last report because it couldn't keep up
+    pub skipped_frames: u64,
+}
+
+/// What the encoder should produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderTarget {
+    pub bitrate_kbps: u32,
+    /// Frames are downscaled by this factor before encoding (1, 2 or 4)
+    pub downscale: u32,
+}
+
+/// Congestion controller for streaming outputs: backs the bitrate off multiplicatively on
+/// loss, rising RTT or backpressure and probes back up slowly once the network is clear
+/// When the bitrate gets too low for the full resolution, the resolution drops instead of
+/// every frame turning to mush
+#[derive(Debug, Clone)]
+pub struct AdaptiveBitrate {
+    max_kbps: u32,
+    target: EncoderTarget,
+    /// Lowest RTT seen; the path's RTT without queueing
+    base_rtt: Option<Duration>,
+    last_change: Instant,
+}
+
+impl AdaptiveBitrate {
+    /// Start at full quality: `max_kbps` at full resolution
+    pub fn new(max_kbps: u32) -> Self {
+        let max_kbps = max_kbps.max(MIN_BITRATE_KBPS);
+        Self {
+            max_kbps,
+            target: EncoderTarget {
+                bitrate_kbps: max_kbps,
+                downscale: 1,
+            },
+            base_rtt: None,
+            last_change: Instant::now(),
+        }
+    }
+
+    pub fn target(&self) -> EncoderTarget {
+        self.target
+    }
+
+    /// Take a feedback report; returns the new target when the encoder should change
+    pub fn update(&mut self, feedback: NetworkFeedback) -> Option<EncoderTarget> {
+        let queueing = match (feedback.rtt, self.base_rtt) {
+            (Some(rtt), Some(base)) => rtt > base + RTT_QUEUEING_MARGIN,
+            _ => false,
+        };
+        if let Some(rtt) = feedback.rtt {
+            self.base_rtt = Some(self.base_rtt.map_or(rtt, |base| base.min(rtt)));
+        }
+        let congested = feedback.loss > CONGESTED_LOSS || feedback.skipped_frames > 0 || queueing;
+
+        let since_change = self.last_change.elapsed();
+        let bitrate = self.target.bitrate_kbps;
+        let bitrate = if congested && since_change >= DECREASE_INTERVAL {
+            (bitrate * 7 / 10).max(MIN_BITRATE_KBPS)
+        } else if !congested && feedback.loss < CLEAR_LOSS && since_change >= INCREASE_INTERVAL {
+            (bitrate + bitrate / 10).min(self.max_kbps)
+        } else {
+            return None;
+        };
+
+        let target = EncoderTarget {
+            bitrate_kbps: bitrate,
+            downscale: self.downscale_for(bitrate),
+        };
+        if target == self.target {
+            return None;
+        }
+        self.target = target;
+        self.last_change = Instant::now();
+        Some(target)
+    }
+
+    /// Halve the resolution each time the bitrate falls below a quarter of what the current
+    /// resolution started with (a quarter of the pixels); go back up with some headroom
+    fn downscale_for(&self, bitrate_kbps: u32) -> u32 {
+        let mut downscale = self.target.downscale;
+        while downscale < MAX_DOWNSCALE
+            && bitrate_kbps < self.max_kbps / (downscale * downscale * 4)
+        {
+            downscale *= 2;
+        }
+        while downscale > 1 && bitrate_kbps > self.max_kbps / (downscale * downscale / 4) * 3 / 8 {
+            downscale /= 2;
+        }
+        downscale
+    }
+}
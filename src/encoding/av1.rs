@@ -0,0 +1,116 @@
+use crate::encoding::yuv::rgba_to_i420;
+use crate::encoding::{EncodedPacket, Encoder, EncoderSettings, VideoCodec};
+use crate::frame_bus::ProcessedFrame;
+use rav1e::prelude::*;
+
+/// rav1e's fastest preset; screen content encodes well even at high speed
+const SPEED_PRESET: u8 = 10;
+
+/// Software AV1 encoder backed by rav1e
+pub struct Av1Encoder {
+    context: Context<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Av1Encoder {
+    pub fn new(settings: &EncoderSettings) -> Result<Self, String> {
+        let mut config = EncoderConfig::with_speed_preset(SPEED_PRESET);
+        config.width = settings.width as usize;
+        config.height = settings.height as usize;
+        config.time_base = Rational::new(1, settings.fps.max(1) as u64);
+        config.bitrate = (settings.bitrate_kbps * 1000) as i32;
+        config.max_key_frame_interval = settings.keyframe_interval.max(1) as u64;
+        // No frame reordering, so each packet maps to the frame just sent
+        config.low_latency = true;
+
+        let context = Config::new()
+            .with_encoder_config(config)
+            .new_context()
+            .map_err(|e| format!("Invalid AV1 encoder config: {}", e))?;
+
+        Ok(Self {
+            context,
+            width: settings.width,
+            height: settings.height,
+        })
+    }
+
+    /// Collect every packet the encoder has ready
+    fn receive_packets(&mut self) -> Result<Vec<EncodedPacket>, String> {
+        let mut packets = Vec::new();
+        loop {
+            match self.context.receive_packet() {
+                Ok(packet) => {
+                    let pts_ms = packet
+                        .opaque
+                        .and_then(|opaque| opaque.downcast::<u64>().ok())
+                        .map(|pts| *pts)
+                        .unwrap_or_default();
+                    packets.push(EncodedPacket {
+                        data: packet.data,
+                        pts_ms,
+                        keyframe: packet.frame_type == FrameType::KEY,
+                    });
+                }
+                Err(EncoderStatus::Encoded) => continue,
+                Err(EncoderStatus::NeedMoreData) | Err(EncoderStatus::LimitReached) => break,
+                Err(e) => return Err(format!("AV1 encoding failed: {}", e)),
+            }
+        }
+        Ok(packets)
+    }
+}
+
+impl Encoder for Av1Encoder {
+    fn name(&self) -> &str {
+        "rav1e"
+    }
+
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::Av1
+    }
+
+    fn encode(
+        &mut self,
+        frame: &ProcessedFrame,
+        pts_ms: u64,
+        force_keyframe: bool,
+    ) -> Result<Vec<EncodedPacket>, String> {
+        if frame.width() != self.width || frame.height() != self.height {
+            return Err(format!(
+                "Frame size {}x{} doesn't match encoder size {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            ));
+        }
+
+        let yuv = rgba_to_i420(frame.data(), frame.width(), frame.height());
+        let mut input = self.context.new_frame();
+        input.planes[0].copy_from_raw_u8(&yuv.y, yuv.width as usize, 1);
+        input.planes[1].copy_from_raw_u8(&yuv.u, yuv.chroma_width() as usize, 1);
+        input.planes[2].copy_from_raw_u8(&yuv.v, yuv.chroma_width() as usize, 1);
+
+        let params = FrameParameters {
+            frame_type_override: if force_keyframe {
+                FrameTypeOverride::Key
+            } else {
+                FrameTypeOverride::No
+            },
+            opaque: Some(Opaque::new(pts_ms)),
+            ..Default::default()
+        };
+
+        self.context
+            .send_frame((input, params))
+            .map_err(|e| format!("AV1 encoder rejected frame: {}", e))?;
+        self.receive_packets()
+    }
+
+    fn finish(&mut self) -> Result<Vec<EncodedPacket>, String> {
+        self.context.flush();
+        self.receive_packets()
+    }
+}
@@ -0,0 +1,133 @@
+use crate::encoding::{EncodedPacket, Encoder, EncoderSettings, VideoCodec, even_size};
+use crate::frame_bus::ProcessedFrame;
+use openh264::OpenH264API;
+use openh264::decoder::{Decoder as OpenH264Decoder, DecoderConfig};
+use openh264::encoder::{Encoder as OpenH264Encoder, EncoderConfig, FrameType};
+use openh264::formats::{RgbaSliceU8, YUVBuffer, YUVSource};
+
+/// Software H.264 encoder backed by OpenH264
+/// Produces an Annex B byte stream
+pub struct H264Encoder {
+    encoder: OpenH264Encoder,
+    /// Encoded size: the frame size rounded down to even, as 4:2:0 needs
+    width: u32,
+    height: u32,
+    /// Frames with an odd width or height are cropped into this buffer
+    cropped: Vec<u8>,
+}
+
+impl H264Encoder {
+    /// Encode frames of `settings.width`x`settings.height`; an odd last column or row is
+    /// dropped
+    pub fn new(settings: &EncoderSettings) -> Result<Self, String> {
+        let config = EncoderConfig::new()
+            .set_bitrate_bps(settings.bitrate_kbps * 1000)
+            .max_frame_rate(settings.fps.max(1) as f32)
+            // Never silently drop frames; static content is handled by the sinks
+            .enable_skip_frame(false);
+
+        let encoder = OpenH264Encoder::with_api_config(OpenH264API::from_source(), config)
+            .map_err(|e| format!("Failed to create OpenH264 encoder: {}", e))?;
+
+        Ok(Self {
+            encoder,
+            width: even_size(settings.width),
+            height: even_size(settings.height),
+            cropped: Vec::new(),
+        })
+    }
+}
+
+impl Encoder for H264Encoder {
+    fn name(&self) -> &str {
+        "openh264"
+    }
+
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::H264
+    }
+
+    fn encode(
+        &mut self,
+        frame: &ProcessedFrame,
+        pts_ms: u64,
+        force_keyframe: bool,
+    ) -> Result<Vec<EncodedPacket>, String> {
+        if even_size(frame.width()) != self.width || even_size(frame.height()) != self.height {
+            return Err(format!(
+                "Frame size {}x{} doesn't match encoder size {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            ));
+        }
+
+        if force_keyframe {
+            self.encoder.force_intra_frame();
+        }
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let data = if frame.width() == self.width && frame.height() == self.height {
+            frame.data()
+        } else {
+            let frame_row = frame.width() as usize * 4;
+            self.cropped.clear();
+            for row in frame.data().chunks_exact(frame_row).take(height) {
+                self.cropped.extend_from_slice(&row[..width * 4]);
+            }
+            &self.cropped
+        };
+        let rgba = RgbaSliceU8::new(data, (width, height));
+        let yuv = YUVBuffer::from_rgb_source(rgba);
+        let bitstream = self
+            .encoder
+            .encode(&yuv)
+            .map_err(|e| format!("H.264 encoding failed: {}", e))?;
+
+        let data = bitstream.to_vec();
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![EncodedPacket {
+            data,
+            pts_ms,
+            keyframe: matches!(bitstream.frame_type(), FrameType::IDR | FrameType::I),
+        }])
+    }
+
+    fn finish(&mut self) -> Result<Vec<EncodedPacket>, String> {
+        // OpenH264 doesn't buffer frames, so there is nothing to flush
+        Ok(Vec::new())
+    }
+}
+
+/// Software H.264 decoder backed by OpenH264, for receiving streams
+pub struct H264Decoder {
+    decoder: OpenH264Decoder,
+}
+
+impl H264Decoder {
+    pub fn new() -> Result<Self, String> {
+        let decoder =
+            OpenH264Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::new())
+                .map_err(|e| format!("Failed to create OpenH264 decoder: {}", e))?;
+        Ok(Self { decoder })
+    }
+
+    /// Decode an Annex B access unit to tightly packed RGBA with its size
+    /// None until the decoder has a picture (e.g. while waiting for the first keyframe)
+    pub fn decode(&mut self, data: &[u8]) -> Result<Option<(Vec<u8>, u32, u32)>, String> {
+        let Some(yuv) = self
+            .decoder
+            .decode(data)
+            .map_err(|e| format!("H.264 decoding failed: {}", e))?
+        else {
+            return Ok(None);
+        };
+        let (width, height) = yuv.dimensions();
+        let mut rgba = vec![0u8; width * height * 4];
+        yuv.write_rgba8(&mut rgba);
+        Ok(Some((rgba, width as u32, height as u32)))
+    }
+}
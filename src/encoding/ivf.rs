@@ -0,0 +1,64 @@
+use crate::encoding::{EncodedPacket, VideoCodec};
+use std::io::{Seek, SeekFrom, Write};
+
+/// Timestamps in the file are milliseconds
+const TIMEBASE_DENOMINATOR: u32 = 1000;
+
+/// Minimal IVF container writer (AV1/VP9 elementary streams)
+pub struct IvfWriter<W: Write + Seek> {
+    out: W,
+    frame_count: u32,
+}
+
+impl<W: Write + Seek> IvfWriter<W> {
+    /// Write the file header; the frame count is patched in by `finish`
+    pub fn new(mut out: W, codec: VideoCodec, width: u32, height: u32) -> Result<Self, String> {
+        let mut header = Vec::with_capacity(32);
+        header.extend_from_slice(b"DKIF");
+        header.extend_from_slice(&0u16.to_le_bytes()); // version
+        header.extend_from_slice(&32u16.to_le_bytes()); // header size
+        header.extend_from_slice(&codec.fourcc());
+        header.extend_from_slice(&(width as u16).to_le_bytes());
+        header.extend_from_slice(&(height as u16).to_le_bytes());
+        header.extend_from_slice(&TIMEBASE_DENOMINATOR.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes()); // timebase numerator
+        header.extend_from_slice(&0u32.to_le_bytes()); // frame count
+        header.extend_from_slice(&0u32.to_le_bytes()); // unused
+        out.write_all(&header)
+            .map_err(|e| format!("Failed to write IVF header: {}", e))?;
+
+        Ok(Self {
+            out,
+            frame_count: 0,
+        })
+    }
+
+    pub fn write_packet(&mut self, packet: &EncodedPacket) -> Result<(), String> {
+        self.out
+            .write_all(&(packet.data.len() as u32).to_le_bytes())
+            .and_then(|_| self.out.write_all(&packet.pts_ms.to_le_bytes()))
+            .and_then(|_| self.out.write_all(&packet.data))
+            .map_err(|e| format!("Failed to write IVF frame: {}", e))?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Push buffered packets to the underlying writer
+    #[cfg(feature = "signed-recordings")]
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.out
+            .flush()
+            .map_err(|e| format!("Failed to flush IVF file: {}", e))
+    }
+
+    /// Patch the frame count into the header and flush
+    pub fn finish(mut self) -> Result<W, String> {
+        self.out
+            .seek(SeekFrom::Start(24))
+            .and_then(|_| self.out.write_all(&self.frame_count.to_le_bytes()))
+            .and_then(|_| self.out.seek(SeekFrom::End(0)))
+            .and_then(|_| self.out.flush())
+            .map_err(|e| format!("Failed to finalize IVF file: {}", e))?;
+        Ok(self.out)
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "whip")]
+pub mod adaptive;
+#[cfg(feature = "av1")]
+pub mod av1;
+#[cfg(feature = "h264")]
+pub mod h264;
+pub mod ivf;
+pub mod mp4;
+pub mod yuv;
+
+use crate::frame_bus::ProcessedFrame;
+use serde::Deserialize;
+
+/// Video codecs produced by the encoder backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoCodec {
+    H264,
+    Av1,
+    Vp9,
+}
+
+impl VideoCodec {
+    /// Codec by name, as in the config file ("h264", "av1", "vp9")
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "h264" => Some(VideoCodec::H264),
+            "av1" => Some(VideoCodec::Av1),
+            "vp9" => Some(VideoCodec::Vp9),
+            _ => None,
+        }
+    }
+
+    /// FourCC used in container headers (IVF, ...)
+    pub fn fourcc(&self) -> [u8; 4] {
+        match self {
+            VideoCodec::H264 => *b"H264",
+            VideoCodec::Av1 => *b"AV01",
+            VideoCodec::Vp9 => *b"VP90",
+        }
+    }
+}
+
+/// Settings shared by every encoder backend
+#[derive(Debug, Clone)]
+pub struct EncoderSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Nominal frame rate used for rate control
+    pub fps: u32,
+    pub bitrate_kbps: u32,
+    /// Maximum number of frames between keyframes
+    pub keyframe_interval: u32,
+}
+
+impl EncoderSettings {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            fps: 30,
+            bitrate_kbps: 6000,
+            keyframe_interval: 60,
+        }
+    }
+}
+
+/// One encoded frame
+#[derive(Debug, Clone)]
+pub struct EncodedPacket {
+    pub data: Vec<u8>,
+    /// Presentation timestamp in milliseconds since the start of the stream
+    pub pts_ms: u64,
+    pub keyframe: bool,
+}
+
+/// A video encoder backend
+/// Encoders only accept ProcessedFrames, so nothing unredacted can ever be encoded
+pub trait Encoder: Send {
+    /// Human-readable backend name for logs
+    fn name(&self) -> &str;
+
+    fn codec(&self) -> VideoCodec;
+
+    /// Encode one frame; returns whatever packets are ready (encoders may buffer frames)
+    fn encode(
+        &mut self,
+        frame: &ProcessedFrame,
+        pts_ms: u64,
+        force_keyframe: bool,
+    ) -> Result<Vec<EncodedPacket>, String>;
+
+    /// Flush buffered frames at the end of the stream
+    fn finish(&mut self) -> Result<Vec<EncodedPacket>, String>;
+}
+
+/// Largest even size not above `size`; H.264 (4:2:0) frames are cropped to it
+pub fn even_size(size: u32) -> u32 {
+    size & !1
+}
+
+/// Create an encoder for the codec, if its backend was compiled in
+/// All backends are software (OpenH264, rav1e); there is no VideoToolbox or other hardware
+/// encoder yet
+pub fn create_encoder(
+    codec: VideoCodec,
+    settings: &EncoderSettings,
+) -> Result<Box<dyn Encoder>, String> {
+    match codec {
+        #[cfg(feature = "av1")]
+        VideoCodec::Av1 => Ok(Box::new(av1::Av1Encoder::new(settings)?)),
+        #[cfg(feature = "h264")]
+        VideoCodec::H264 => Ok(Box::new(h264::H264Encoder::new(settings)?)),
+        _ => {
+            let _ = settings;
+            Err(format!(
+                "No software encoder for {:?} in this build (enable the matching cargo feature)",
+                codec
+            ))
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::encoding::EncodedPacket;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Timestamps in the file are milliseconds
+const TIMESCALE: u32 = 1000;
+
+/// Duration of the last sample, which has no next timestamp to end it (one 30 fps frame)
+const DEFAULT_SAMPLE_DURATION_MS: u32 = 33;
+
+/// Identity transform in tkhd and mvhd (16.16 and 2.30 fixed point)
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+/// NAL unit types handled by the writer
+const NAL_IDR: u8 = 5;
+const NAL_SPS: u8 = 7;
+const NAL_PPS: u8 = 8;
+const NAL_ACCESS_UNIT_DELIMITER: u8 = 9;
+
+/// Whether `path` names an MP4 file (.mp4, .m4v or .mov)
+pub fn is_mp4_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["mp4", "m4v", "mov"]
+                .iter()
+                .any(|mp4| extension.eq_ignore_ascii_case(mp4))
+        })
+}
+
+/// One access unit in the mdat box
+struct Sample {
+    offset: u64,
+    size: u32,
+    pts_ms: u64,
+    keyframe: bool,
+}
+
+/// Minimal MP4 writer for H.264: access units go into one mdat box as they arrive (Annex B
+/// start codes replaced by lengths), the sample tables are written after it by `finish`
+pub struct Mp4Writer<W: Write + Seek> {
+    out: W,
+    width: u32,
+    height: u32,
+    /// Offset of the mdat box, whose size is patched in by `finish`
+    mdat_start: u64,
+    /// End of the data written so far
+    position: u64,
+    /// First parameter sets in the stream, for the avcC box
+    sps: Option<Vec<u8>>,
+    pps: Option<Vec<u8>>,
+    samples: Vec<Sample>,
+}
+
+impl<W: Write + Seek> Mp4Writer<W> {
+    /// Write the file type and the start of the media data
+    pub fn new(mut out: W, width: u32, height: u32) -> Result<Self, String> {
+        let mut header = Vec::with_capacity(48);
+        write_box(&mut header, b"ftyp", |body| {
+            body.extend_from_slice(b"isom");
+            body.extend_from_slice(&0x200u32.to_be_bytes()); // minor version
+            for brand in [b"isom", b"iso2", b"avc1", b"mp41"] {
+                body.extend_from_slice(brand);
+            }
+        });
+        let mdat_start = header.len() as u64;
+        // 64-bit size (patched by `finish`), so recordings may exceed 4 GB
+        header.extend_from_slice(&1u32.to_be_bytes());
+        header.extend_from_slice(b"mdat");
+        header.extend_from_slice(&0u64.to_be_bytes());
+        out.write_all(&header)
+            .map_err(|e| format!("Failed to write MP4 header: {}", e))?;
+
+        Ok(Self {
+            out,
+            width,
+            height,
+            mdat_start,
+            position: header.len() as u64,
+            sps: None,
+            pps: None,
+            samples: Vec::new(),
+        })
+    }
+
+    /// Append an Annex B access unit; parameter sets move to the avcC box
+    pub fn write_packet(&mut self, packet: &EncodedPacket) -> Result<(), String> {
+        let mut sample = Vec::with_capacity(packet.data.len());
+        let mut keyframe = false;
+        for nal in nal_units(&packet.data) {
+            match nal[0] & 0x1f {
+                NAL_SPS => {
+                    self.sps.get_or_insert_with(|| nal.to_vec());
+                }
+                NAL_PPS => {
+                    self.pps.get_or_insert_with(|| nal.to_vec());
+                }
+                NAL_ACCESS_UNIT_DELIMITER => {}
+                kind => {
+                    keyframe |= kind == NAL_IDR;
+                    sample.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+                    sample.extend_from_slice(nal);
+                }
+            }
+        }
+        if sample.is_empty() {
+            return Ok(());
+        }
+
+        self.out
+            .write_all(&sample)
+            .map_err(|e| format!("Failed to write MP4 sample: {}", e))?;
+        self.samples.push(Sample {
+            offset: self.position,
+            size: sample.len() as u32,
+            pts_ms: packet.pts_ms,
+            keyframe,
+        });
+        self.position += sample.len() as u64;
+        Ok(())
+    }
+
+    /// Push buffered samples to the underlying writer
+    #[cfg(feature = "signed-recordings")]
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.out
+            .flush()
+            .map_err(|e| format!("Failed to flush MP4 file: {}", e))
+    }
+
+    /// Patch the media data size and write the sample tables (moov) after it
+    pub fn finish(mut self) -> Result<W, String> {
+        let (Some(sps), Some(pps)) = (&self.sps, &self.pps) else {
+            return Err("No H.264 parameter sets were recorded".to_string());
+        };
+        if self.samples.is_empty() {
+            return Err("No H.264 frames were recorded".to_string());
+        }
+        let moov = self.moov(sps, pps);
+        self.out
+            .seek(SeekFrom::Start(self.mdat_start + 8))
+            .and_then(|_| {
+                self.out
+                    .write_all(&(self.position - self.mdat_start).to_be_bytes())
+            })
+            .and_then(|_| self.out.seek(SeekFrom::End(0)))
+            .and_then(|_| self.out.write_all(&moov))
+            .and_then(|_| self.out.flush())
+            .map_err(|e| format!("Failed to finalize MP4 file: {}", e))?;
+        Ok(self.out)
+    }
+
+    fn moov(&self, sps: &[u8], pps: &[u8]) -> Vec<u8> {
+        // Each sample lasts until the next one starts
+        let mut durations: Vec<u32> = self
+            .samples
+            .windows(2)
+            .map(|pair| pair[1].pts_ms.saturating_sub(pair[0].pts_ms).max(1) as u32)
+            .collect();
+        durations.push(
+            durations
+                .last()
+                .copied()
+                .unwrap_or(DEFAULT_SAMPLE_DURATION_MS),
+        );
+        let duration = durations.iter().map(|&d| d as u64).sum::<u64>() as u32;
+        let (width, height) = (self.width, self.height);
+
+        let mut moov = Vec::new();
+        write_box(&mut moov, b"moov", |moov| {
+            write_full_box(moov, b"mvhd", 0, |mvhd| {
+                put_u32s(mvhd, &[0, 0, TIMESCALE, duration]); // creation, modification
+                put_u32s(mvhd, &[0x0001_0000]); // rate 1.0
+                mvhd.extend_from_slice(&0x0100u16.to_be_bytes()); // volume 1.0
+                mvhd.extend_from_slice(&[0; 10]);
+                put_u32s(mvhd, &UNITY_MATRIX);
+                mvhd.extend_from_slice(&[0; 24]);
+                put_u32s(mvhd, &[2]); // next track ID
+            });
+            write_box(moov, b"trak", |trak| {
+                // Enabled, in the movie
+                write_full_box(trak, b"tkhd", 3, |tkhd| {
+                    put_u32s(tkhd, &[0, 0, 1, 0, duration, 0, 0]); // track 1
+                    tkhd.extend_from_slice(&[0; 8]); // layer, group, volume, reserved
+                    put_u32s(tkhd, &UNITY_MATRIX);
+                    put_u32s(tkhd, &[width << 16, height << 16]);
+                });
+                write_box(trak, b"mdia", |mdia| {
+                    write_full_box(mdia, b"mdhd", 0, |mdhd| {
+                        put_u32s(mdhd, &[0, 0, TIMESCALE, duration]);
+                        mdhd.extend_from_slice(&0x55c4u16.to_be_bytes()); // "und"
+                        mdhd.extend_from_slice(&[0; 2]);
+                    });
+                    write_full_box(mdia, b"hdlr", 0, |hdlr| {
+                        put_u32s(hdlr, &[0]);
+                        hdlr.extend_from_slice(b"vide");
+                        hdlr.extend_from_slice(&[0; 12]);
+                        hdlr.extend_from_slice(b"VideoHandler\0");
+                    });
+                    write_box(mdia, b"minf", |minf| {
+                        write_full_box(minf, b"vmhd", 1, |vmhd| vmhd.extend_from_slice(&[0; 8]));
+                        write_box(minf, b"dinf", |dinf| {
+                            write_full_box(dinf, b"dref", 0, |dref| {
+                                put_u32s(dref, &[1]);
+                                // The media is in this file
+                                write_full_box(dref, b"url ", 1, |_| {});
+                            });
+                        });
+                        write_box(minf, b"stbl", |stbl| {
+                            self.write_sample_tables(stbl, &durations, sps, pps)
+                        });
+                    });
+                });
+            });
+        });
+        moov
+    }
+
+    fn write_sample_tables(&self, stbl: &mut Vec<u8>, durations: &[u32], sps: &[u8], pps: &[u8]) {
+        write_full_box(stbl, b"stsd", 0, |stsd| {
+            put_u32s(stsd, &[1]);
+            write_box(stsd, b"avc1", |avc1| {
+                avc1.extend_from_slice(&[0; 6]);
+                avc1.extend_from_slice(&1u16.to_be_bytes()); // data reference index
+                avc1.extend_from_slice(&[0; 16]);
+                avc1.extend_from_slice(&(self.width as u16).to_be_bytes());
+                avc1.extend_from_slice(&(self.height as u16).to_be_bytes());
+                put_u32s(avc1, &[0x0048_0000, 0x0048_0000, 0]); // 72 dpi
+                avc1.extend_from_slice(&1u16.to_be_bytes()); // frames per sample
+                avc1.extend_from_slice(&[0; 32]); // compressor name
+                avc1.extend_from_slice(&0x0018u16.to_be_bytes()); // depth
+                avc1.extend_from_slice(&(-1i16).to_be_bytes());
+                write_box(avc1, b"avcC", |avcc| {
+                    // Version, then profile, compatibility and level from the SPS
+                    let sps_byte = |index: usize| sps.get(index).copied().unwrap_or(0);
+                    avcc.extend_from_slice(&[1, sps_byte(1), sps_byte(2), sps_byte(3)]);
+                    avcc.push(0xff); // 4-byte NAL lengths
+                    avcc.push(0xe1); // one SPS
+                    avcc.extend_from_slice(&(sps.len() as u16).to_be_bytes());
+                    avcc.extend_from_slice(sps);
+                    avcc.push(1); // one PPS
+                    avcc.extend_from_slice(&(pps.len() as u16).to_be_bytes());
+                    avcc.extend_from_slice(pps);
+                });
+            });
+        });
+
+        // Runs of equal durations
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for &duration in durations {
+            match runs.last_mut() {
+                Some((count, last)) if *last == duration => *count += 1,
+                _ => runs.push((1, duration)),
+            }
+        }
+        write_full_box(stbl, b"stts", 0, |stts| {
+            put_u32s(stts, &[runs.len() as u32]);
+            for (count, duration) in &runs {
+                put_u32s(stts, &[*count, *duration]);
+            }
+        });
+        write_full_box(stbl, b"stss", 0, |stss| {
+            let keyframes: Vec<u32> = (1..)
+                .zip(&self.samples)
+                .filter(|(_, sample)| sample.keyframe)
+                .map(|(number, _)| number)
+                .collect();
+            put_u32s(stss, &[keyframes.len() as u32]);
+            put_u32s(stss, &keyframes);
+        });
+        // One sample per chunk
+        write_full_box(stbl, b"stsc", 0, |stsc| put_u32s(stsc, &[1, 1, 1, 1]));
+        write_full_box(stbl, b"stsz", 0, |stsz| {
+            put_u32s(stsz, &[0, self.samples.len() as u32]);
+            for sample in &self.samples {
+                put_u32s(stsz, &[sample.size]);
+            }
+        });
+        write_full_box(stbl, b"co64", 0, |co64| {
+            put_u32s(co64, &[self.samples.len() as u32]);
+            for sample in &self.samples {
+                co64.extend_from_slice(&sample.offset.to_be_bytes());
+            }
+        });
+    }
+}
+
+/// Whether an Annex B access unit contains an IDR slice, which decodes without earlier frames
+pub fn has_idr(access_unit: &[u8]) -> bool {
+    nal_units(access_unit)
+        .iter()
+        .any(|nal| nal[0] & 0x1f == NAL_IDR)
+}
+
+/// NAL units of an Annex B stream, without their start codes
+fn nal_units(stream: &[u8]) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+    while i + 3 <= stream.len() {
+        if stream[i..i + 3] == [0, 0, 1] {
+            if let Some(start) = start {
+                units.push(&stream[start..i]);
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(start) = start {
+        units.push(&stream[start..]);
+    }
+    // A 4-byte start code leaves a zero at the end of the unit before it
+    units
+        .into_iter()
+        .map(|unit| {
+            let end = unit
+                .iter()
+                .rposition(|&byte| byte != 0)
+                .map_or(0, |last| last + 1);
+            &unit[..end]
+        })
+        .filter(|unit| !unit.is_empty())
+        .collect()
+}
+
+/// Append a box: size, type, then what `body` writes
+fn write_box(out: &mut Vec<u8>, kind: &[u8; 4], body: impl FnOnce(&mut Vec<u8>)) {
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(kind);
+    body(out);
+    let size = (out.len() - start) as u32;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+/// Append a box that starts with a version (0) and `flags`
+fn write_full_box(out: &mut Vec<u8>, kind: &[u8; 4], flags: u32, body: impl FnOnce(&mut Vec<u8>)) {
+    write_box(out, kind, |out| {
+        put_u32s(out, &[flags & 0x00ff_ffff]);
+        body(out);
+    });
+}
+
+fn put_u32s(out: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
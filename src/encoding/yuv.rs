@@ -0,0 +1,149 @@
+/// Planar 8-bit YUV 4:2:0 frame (BT.709, limited range)
+pub struct I420Frame {
+    pub width: u32,
+    pub height: u32,
+    pub y: Vec<u8>,
+    pub u: Vec<u8>,
+    pub v: Vec<u8>,
+}
+
+impl I420Frame {
+    /// Width of the chroma planes (rounded up for odd sizes)
+    pub fn chroma_width(&self) -> u32 {
+        self.width.div_ceil(2)
+    }
+}
+
+/// Convert tightly packed RGBA to I420 for software encoders
+pub fn rgba_to_i420(rgba: &[u8], width: u32, height: u32) -> I420Frame {
+    let (w, h) = (width as usize, height as usize);
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+    let mut y = vec![0u8; w * h];
+    let mut u = vec![0u8; cw * ch];
+    let mut v = vec![0u8; cw * ch];
+
+    for row in 0..h {
+        for col in 0..w {
+            let i = (row * w + col) * 4;
+            let (r, g, b) = (rgba[i] as f32, rgba[i + 1] as f32, rgba[i + 2] as f32);
+            y[row * w + col] = (16.0 + 0.1826 * r + 0.6142 * g + 0.0620 * b) as u8;
+        }
+    }
+
+    // Each chroma sample averages a 2x2 block of source pixels
+    for crow in 0..ch {
+        for ccol in 0..cw {
+            let mut sum = [0f32; 3];
+            let mut count = 0.0;
+            for row in (crow * 2)..(crow * 2 + 2).min(h) {
+                for col in (ccol * 2)..(ccol * 2 + 2).min(w) {
+                    let i = (row * w + col) * 4;
+                    sum[0] += rgba[i] as f32;
+                    sum[1] += rgba[i + 1] as f32;
+                    sum[2] += rgba[i + 2] as f32;
+                    count += 1.0;
+                }
+            }
+            let (r, g, b) = (sum[0] / count, sum[1] / count, sum[2] / count);
+            u[crow * cw + ccol] = (128.0 - 0.1006 * r - 0.3386 * g + 0.4392 * b) as u8;
+            v[crow * cw + ccol] = (128.0 + 0.4392 * r - 0.3989 * g - 0.0403 * b) as u8;
+        }
+    }
+
+    I420Frame {
+        width,
+        height,
+        y,
+        u,
+        v,
+    }
+}
+
+/// Y'CbCr matrix: how much red and blue contribute to luma
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YuvMatrix {
+    /// SD video and some webcams
+    Bt601,
+    /// HD video; what ScreenCaptureKit tags its Y'CbCr frames with
+    #[default]
+    Bt709,
+    /// UHD/HDR video
+    Bt2020,
+}
+
+impl YuvMatrix {
+    /// Luma weights of red and blue (Kr, Kb); green's is what's left
+    fn weights(self) -> (f32, f32) {
+        match self {
+            Self::Bt601 => (0.299, 0.114),
+            Self::Bt709 => (0.2126, 0.0722),
+            Self::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// Which code values a Y'CbCr signal uses for black to white
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YuvRange {
+    /// Luma 16..235, chroma 16..240 (broadcast convention)
+    #[default]
+    Video,
+    /// Luma and chroma use all of 0..255
+    Full,
+}
+
+impl YuvRange {
+    /// Code value of black and the luma/chroma spans above it and around 128
+    fn levels(self) -> (f32, f32, f32) {
+        match self {
+            Self::Video => (16.0, 219.0, 224.0),
+            Self::Full => (0.0, 255.0, 255.0),
+        }
+    }
+}
+
+/// How a Y'CbCr frame's values map back to RGB
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YuvColorSpace {
+    pub matrix: YuvMatrix,
+    pub range: YuvRange,
+}
+
+/// Convert 8-bit NV12 (a luma plane and a half-size plane of interleaved Cb/Cr, rows
+/// `y_stride`/`uv_stride` bytes apart) to tightly packed RGBA
+pub fn nv12_to_rgba(
+    y_plane: &[u8],
+    y_stride: usize,
+    uv_plane: &[u8],
+    uv_stride: usize,
+    width: u32,
+    height: u32,
+    color_space: YuvColorSpace,
+) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    let (kr, kb) = color_space.matrix.weights();
+    let kg = 1.0 - kr - kb;
+    // Stretch video range so its black and white land on 0 and 255 rather than 16 and 235
+    let (black, luma_span, chroma_span) = color_space.range.levels();
+    let (luma_scale, chroma_scale) = (255.0 / luma_span, 255.0 / chroma_span);
+    let to_u8 = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+
+    let mut rgba = vec![255u8; w * h * 4];
+    for row in 0..h {
+        for col in 0..w {
+            let luma = (y_plane[row * y_stride + col] as f32 - black) * luma_scale;
+            let uv = (row / 2) * uv_stride + (col / 2) * 2;
+            let cb = (uv_plane[uv] as f32 - 128.0) * chroma_scale;
+            let cr = (uv_plane[uv + 1] as f32 - 128.0) * chroma_scale;
+
+            let r = luma + 2.0 * (1.0 - kr) * cr;
+            let b = luma + 2.0 * (1.0 - kb) * cb;
+            let g = (luma - kr * r - kb * b) / kg;
+            let i = (row * w + col) * 4;
+            rgba[i] = to_u8(r);
+            rgba[i + 1] = to_u8(g);
+            rgba[i + 2] = to_u8(b);
+        }
+    }
+    rgba
+}
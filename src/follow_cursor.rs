@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+/// Follow-cursor mode: switches capture to whichever display the cursor is on
+/// The cursor must stay on a new display for the debounce period before switching,
+/// so quickly crossing a screen edge doesn't flip the mirror back and forth
+pub struct FollowCursor {
+    enabled: bool,
+    debounce: Duration,
+    /// Display the cursor moved to, and when it first got there
+    candidate: Option<(u32, Instant)>,
+}
+
+impl FollowCursor {
+    pub fn new(enabled: bool, debounce: Duration) -> Self {
+        Self {
+            enabled,
+            debounce,
+            candidate: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.candidate = None;
+    }
+
+    /// Check the cursor position; returns a display to switch to once the debounce has elapsed
+    /// `captured_display` is None when capturing a window, which disables following
+    pub fn poll(&mut self, captured_display: Option<u32>) -> Option<u32> {
+        if !self.enabled {
+            return None;
+        }
+        let captured = captured_display?;
+
+        let cursor_display = display_under_cursor()?;
+
+        if cursor_display == captured {
+            self.candidate = None;
+            return None;
+        }
+
+        match self.candidate {
+            Some((display, since)) if display == cursor_display => {
+                if since.elapsed() >= self.debounce {
+                    self.candidate = None;
+                    return Some(display);
+                }
+            }
+            _ => self.candidate = Some((cursor_display, Instant::now())),
+        }
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn display_under_cursor() -> Option<u32> {
+    crate::platform::macos_cursor::display_under_cursor()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn display_under_cursor() -> Option<u32> {
+    // Cursor tracking not implemented on this platform yet
+    None
+}
@@ -0,0 +1,135 @@
+use xxhash_rust::xxh3::Xxh3;
+
+/// Channel values at or below this count as black (allows for encoder/scaling noise)
+const BLACK_LEVEL: u8 = 10;
+
+/// Only every Nth pixel is inspected; a prime stride avoids lining up with UI patterns
+const SAMPLE_STRIDE: usize = 97;
+
+/// Consecutive black frames required before the source is reported as black
+const BLACK_FRAME_THRESHOLD: u32 = 3;
+
+/// Only every Nth row is hashed; thin changes are picked up by the next frame that touches a sampled row
+const ROW_SAMPLE_STRIDE: usize = 4;
+
+/// Per-channel difference above which a sampled pixel counts as changed
+const PIXEL_CHANGE_LEVEL: u8 = 24;
+
+/// Whether a tightly packed RGBA frame is (practically) entirely black
+/// Samples a sparse grid of pixels so it stays cheap at full resolution
+pub fn is_black_frame(rgba: &[u8]) -> bool {
+    rgba.chunks_exact(4)
+        .step_by(SAMPLE_STRIDE)
+        .all(|pixel| pixel[..3].iter().all(|&c| c <= BLACK_LEVEL))
+}
+
+/// Detects captures that only deliver black frames
+/// (missing Screen Recording permission, DRM-protected content, sleeping display)
+#[derive(Debug, Default)]
+pub struct BlackFrameDetector {
+    black_streak: u32,
+    is_black: bool,
+}
+
+impl BlackFrameDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inspect a raw frame; returns the new state when the source turns black or recovers
+    pub fn observe(&mut self, rgba: &[u8]) -> Option<bool> {
+        if is_black_frame(rgba) {
+            self.black_streak = self.black_streak.saturating_add(1);
+        } else {
+            self.black_streak = 0;
+        }
+
+        // Recover on the first frame with content, but require a streak to report black
+        // so fades and transitions don't flap the state
+        let is_black = self.black_streak >= BLACK_FRAME_THRESHOLD;
+        if is_black != self.is_black {
+            self.is_black = is_black;
+            return Some(is_black);
+        }
+        None
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.is_black
+    }
+}
+
+/// Cheap content hash of a frame, used to tell whether anything on screen changed
+/// Hashes every ROW_SAMPLE_STRIDE-th row with xxh3; not cryptographic
+pub fn frame_hash(rgba: &[u8], width: u32) -> u64 {
+    let row_bytes = (width as usize * 4).max(1);
+    let mut hasher = Xxh3::new();
+    for row in rgba.chunks(row_bytes).step_by(ROW_SAMPLE_STRIDE) {
+        hasher.update(row);
+    }
+    hasher.digest()
+}
+
+/// Tracks frame hashes to detect when the content actually changes
+#[derive(Debug, Default)]
+pub struct ContentChangeTracker {
+    last_hash: Option<u64>,
+}
+
+impl ContentChangeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the hash differs from the previously observed one
+    pub fn observe(&mut self, hash: u64) -> bool {
+        let changed = self.last_hash != Some(hash);
+        self.last_hash = Some(hash);
+        changed
+    }
+}
+
+/// Detects significant screen changes by comparing a sparse pixel sample between frames
+#[derive(Debug)]
+pub struct ChangeDetector {
+    /// Fraction of sampled pixels (0.0-1.0) that must change to count as significant
+    threshold: f32,
+    previous: Vec<[u8; 3]>,
+}
+
+impl ChangeDetector {
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold: threshold.clamp(0.0, 1.0),
+            previous: Vec::new(),
+        }
+    }
+
+    /// Compare a frame with the previous one; returns the changed fraction when it meets the threshold
+    pub fn observe(&mut self, rgba: &[u8]) -> Option<f32> {
+        let samples: Vec<[u8; 3]> = rgba
+            .chunks_exact(4)
+            .step_by(SAMPLE_STRIDE)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+
+        // A resolution change makes the samples incomparable; treat it as a fresh start
+        let previous = std::mem::replace(&mut self.previous, samples);
+        if previous.len() != self.previous.len() || previous.is_empty() {
+            return None;
+        }
+
+        let changed = previous
+            .iter()
+            .zip(&self.previous)
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(x, y)| x.abs_diff(*y) > PIXEL_CHANGE_LEVEL)
+            })
+            .count();
+        let fraction = changed as f32 / previous.len() as f32;
+
+        (fraction >= self.threshold).then_some(fraction)
+    }
+}
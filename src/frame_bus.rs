@@ -0,0 +1,400 @@
+use crate::locked_memory::FrameBuffer;
+use crate::platform::DirtyRect;
+use crate::redaction::RedactionRect;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest a `BlockCapture` consumer holds up the capture before frames are skipped anyway,
+/// so a stuck output slows the pipeline down but can't freeze it
+pub const BLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Most frames a `Queue` consumer may fall behind by (bounds the memory frames are kept in)
+pub const MAX_QUEUED_FRAMES: usize = 60;
+
+/// What happens to frames a consumer hasn't taken when newer ones arrive
+/// In config files: `"drop_oldest"`, `{ queue = 8 }` or `"block_capture"`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FramePolicy {
+    /// Only the newest frame is kept; missed frames are skipped (lowest latency, live mirror)
+    #[default]
+    DropOldest,
+    /// Up to N frames wait to be taken in order; older ones are skipped only beyond that
+    /// (smoothest playback for recordings that can catch up)
+    Queue(usize),
+    /// Nothing is skipped: the capture thread waits until the consumer took the previous frame
+    /// (up to BLOCK_TIMEOUT), slowing every output down to this consumer's pace
+    BlockCapture,
+}
+
+impl FramePolicy {
+    /// Frames a consumer with this policy can have waiting
+    pub fn depth(self) -> usize {
+        match self {
+            Self::Queue(frames) => frames.clamp(1, MAX_QUEUED_FRAMES),
+            Self::DropOldest | Self::BlockCapture => 1,
+        }
+    }
+}
+
+/// A frame that has already passed through the redaction stage
+/// Only the redaction stage can construct these, so holding one means the pixels are safe to export
+#[derive(Debug, Clone)]
+pub struct ProcessedFrame {
+    data: Arc<FrameBuffer>,
+    width: u32,
+    height: u32,
+    sequence: u64,
+    /// Content hash assigned by the redaction stage (equal hashes = identical content)
+    content_hash: u64,
+    /// When the source frame was captured
+    captured_at: Instant,
+    /// Regions changed since the previous published frame (None = treat the whole frame as changed)
+    dirty_rects: Option<Arc<Vec<DirtyRect>>>,
+    /// Re-sent by a paced sink because no new frame arrived in time
+    repeat: bool,
+    /// Regions the redaction stage masked in this frame, clipped to it
+    masked_regions: Arc<Vec<RedactionRect>>,
+    /// The whole frame is the blanking fill instead of the capture
+    blanked: bool,
+}
+
+impl ProcessedFrame {
+    pub(crate) fn new(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Self {
+            data: Arc::new(FrameBuffer::new(data)),
+            width,
+            height,
+            sequence: 0,
+            content_hash: 0,
+            captured_at: Instant::now(),
+            dirty_rects: None,
+            repeat: false,
+            masked_regions: Arc::default(),
+            blanked: false,
+        }
+    }
+
+    /// Carry over capture time and damage from the raw frame this was produced from
+    pub(crate) fn with_capture_info(
+        mut self,
+        captured_at: Instant,
+        dirty_rects: Option<Vec<DirtyRect>>,
+    ) -> Self {
+        self.captured_at = captured_at;
+        self.dirty_rects = dirty_rects.map(Arc::new);
+        self
+    }
+
+    /// The same frame again, for sinks fed at a constant rate; nothing changed since this frame
+    pub(crate) fn repeated(&self) -> Self {
+        Self {
+            dirty_rects: Some(Arc::new(Vec::new())),
+            repeat: true,
+            ..self.clone()
+        }
+    }
+
+    /// Record what the redaction stage hid in this frame
+    pub(crate) fn with_redactions(
+        mut self,
+        masked_regions: Vec<RedactionRect>,
+        blanked: bool,
+    ) -> Self {
+        self.masked_regions = Arc::new(masked_regions);
+        self.blanked = blanked;
+        self
+    }
+
+    pub(crate) fn with_content_hash(mut self, content_hash: u64) -> Self {
+        self.content_hash = content_hash;
+        self
+    }
+
+    /// Redacted RGBA pixels (width*height*4 bytes)
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Hash of the frame content; consumers can skip work when it matches the previous frame
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// Monotonic sequence number assigned by the bus when the frame was published
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// When the source frame was captured; `captured_at().elapsed()` is the pipeline latency
+    pub fn captured_at(&self) -> Instant {
+        self.captured_at
+    }
+
+    /// Regions that changed since the previous frame on the bus (None = everything)
+    /// Subscribers that skipped frames get None, since they missed the skipped frames' changes
+    pub fn dirty_rects(&self) -> Option<&[DirtyRect]> {
+        self.dirty_rects.as_deref().map(Vec::as_slice)
+    }
+
+    /// Where content was masked in this frame (positions only, never what was there)
+    pub fn masked_regions(&self) -> &[RedactionRect] {
+        &self.masked_regions
+    }
+
+    /// Whether the output was blanked for this frame (see `BlankingController`)
+    pub fn is_blanked(&self) -> bool {
+        self.blanked
+    }
+
+    /// Whether this is a previous frame re-sent to keep a constant rate (see `FrameSink::frame_rate`)
+    pub fn is_repeat(&self) -> bool {
+        self.repeat
+    }
+}
+
+/// What the bus knows about a subscriber: its policy and the last frame it took
+struct SubscriberCursor {
+    policy: FramePolicy,
+    last_sequence: u64,
+}
+
+#[derive(Default)]
+struct BusState {
+    /// Recent frames, oldest first; as many as the deepest subscriber queue (at least one)
+    history: VecDeque<ProcessedFrame>,
+    next_sequence: u64,
+    /// When the newest frame was published
+    published_at: Option<Instant>,
+    /// When the published content last differed from the previous frame
+    last_content_change: Option<Instant>,
+    subscribers: HashMap<u64, SubscriberCursor>,
+    next_subscriber_id: u64,
+}
+
+impl BusState {
+    fn history_limit(&self) -> usize {
+        self.subscribers
+            .values()
+            .map(|cursor| cursor.policy.depth())
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Whether a `BlockCapture` subscriber hasn't taken the newest frame yet
+    fn blocked(&self) -> bool {
+        self.subscribers.values().any(|cursor| {
+            cursor.policy == FramePolicy::BlockCapture && cursor.last_sequence < self.next_sequence
+        })
+    }
+}
+
+#[derive(Default)]
+struct BusShared {
+    state: Mutex<BusState>,
+    /// Signalled on every publish so sink threads can sleep until a frame arrives
+    frame_published: Condvar,
+}
+
+/// ProcessedFrameBus: single distribution point for redacted frames
+/// Every output (renderer, recorder, streamer, virtual camera, screenshot) subscribes here
+/// instead of touching the raw capture buffer
+#[derive(Clone, Default)]
+pub struct ProcessedFrameBus {
+    shared: Arc<BusShared>,
+}
+
+impl ProcessedFrameBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish a processed frame (only callable from inside the crate's redaction stage)
+    /// Never waits: the redaction stage checks `holds_capture` before taking a raw frame
+    pub(crate) fn publish(&self, mut frame: ProcessedFrame) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.published_at = Some(Instant::now());
+            state.next_sequence += 1;
+            frame.sequence = state.next_sequence;
+            state.history.push_back(frame);
+            let limit = state.history_limit();
+            while state.history.len() > limit {
+                state.history.pop_front();
+            }
+        }
+        self.shared.frame_published.notify_all();
+    }
+
+    /// Whether a `BlockCapture` subscriber still hasn't taken the newest frame, published less
+    /// than BLOCK_TIMEOUT ago; the next raw frame should wait until it has
+    pub(crate) fn holds_capture(&self) -> bool {
+        self.shared.state.lock().is_ok_and(|state| {
+            state.blocked()
+                && state
+                    .published_at
+                    .is_some_and(|published| published.elapsed() < BLOCK_TIMEOUT)
+        })
+    }
+
+    /// Record that the content changed (called by the redaction stage after hashing the frame)
+    pub(crate) fn mark_content_changed(&self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.last_content_change = Some(Instant::now());
+        }
+    }
+
+    /// How long the published content has stayed unchanged (None before the first frame)
+    /// Keeps growing while no frames arrive, since capture stops delivering on a static screen
+    pub fn unchanged_for(&self) -> Option<Duration> {
+        let change = self.shared.state.lock().ok()?.last_content_change?;
+        Some(change.elapsed())
+    }
+
+    /// Get the most recently published frame
+    pub fn latest(&self) -> Option<ProcessedFrame> {
+        self.shared.state.lock().ok()?.history.back().cloned()
+    }
+
+    /// Create a subscriber that only ever gets the newest frame (`FramePolicy::DropOldest`)
+    pub fn subscribe(&self) -> FrameSubscriber {
+        self.subscribe_with_policy(FramePolicy::DropOldest)
+    }
+
+    /// Create a subscriber that tracks which frames it has already seen, skipping frames it
+    /// falls behind on according to `policy`
+    pub fn subscribe_with_policy(&self, policy: FramePolicy) -> FrameSubscriber {
+        let (mut id, mut last_sequence) = (0, 0);
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.next_subscriber_id += 1;
+            id = state.next_subscriber_id;
+            // The frame already on the bus is the first one handed out; older history isn't
+            last_sequence = state.next_sequence.saturating_sub(1);
+            // Level with the bus, so a blocking subscriber doesn't hold up the next frame
+            let cursor = SubscriberCursor {
+                policy,
+                last_sequence: state.next_sequence,
+            };
+            state.subscribers.insert(id, cursor);
+        }
+        FrameSubscriber {
+            bus: self.clone(),
+            id,
+            policy,
+            last_sequence,
+            fresh: true,
+            skipped: 0,
+        }
+    }
+}
+
+/// Per-consumer view of the bus
+pub struct FrameSubscriber {
+    bus: ProcessedFrameBus,
+    /// Key of this subscriber's cursor in the bus state
+    id: u64,
+    policy: FramePolicy,
+    last_sequence: u64,
+    /// No frame returned yet (the first one has no damage relative to anything this saw)
+    fresh: bool,
+    /// Frames published after this subscriber's first frame that it never got
+    skipped: u64,
+}
+
+impl FrameSubscriber {
+    /// Get the next frame this subscriber hasn't seen, if one is waiting
+    pub fn next_frame(&mut self) -> Option<ProcessedFrame> {
+        let bus = self.bus.clone();
+        let mut state = bus.shared.state.lock().ok()?;
+        self.take(&mut state)
+    }
+
+    /// Pick the frame to return from the bus history according to the policy and remember
+    /// it as the last one returned; damage is only meaningful relative to the frame right
+    /// before it
+    fn take(&mut self, state: &mut BusState) -> Option<ProcessedFrame> {
+        let waiting = state
+            .history
+            .iter()
+            .filter(|frame| frame.sequence() > self.last_sequence)
+            .count();
+        let behind = waiting.saturating_sub(self.policy.depth());
+        let mut frame = state
+            .history
+            .iter()
+            .filter(|frame| frame.sequence() > self.last_sequence)
+            .nth(behind)?
+            .clone();
+
+        if self.fresh || frame.sequence() != self.last_sequence + 1 {
+            frame.dirty_rects = None;
+        }
+        if !self.fresh {
+            self.skipped += frame.sequence() - self.last_sequence - 1;
+        }
+        self.fresh = false;
+        self.last_sequence = frame.sequence();
+        if let Some(cursor) = state.subscribers.get_mut(&self.id) {
+            cursor.last_sequence = self.last_sequence;
+        }
+        Some(frame)
+    }
+
+    /// Block until a frame this subscriber hasn't seen is published, or the timeout expires
+    pub fn wait_next_frame(&mut self, timeout: Duration) -> Option<ProcessedFrame> {
+        let bus = self.bus.clone();
+        let state = bus.shared.state.lock().ok()?;
+        let last_sequence = self.last_sequence;
+        let (mut state, _) = bus
+            .shared
+            .frame_published
+            .wait_timeout_while(state, timeout, |s| s.next_sequence == last_sequence)
+            .ok()?;
+        self.take(&mut state)
+    }
+
+    /// Frames published that this subscriber hasn't taken yet (at most what the bus keeps)
+    pub fn pending(&self) -> usize {
+        let Ok(state) = self.bus.shared.state.lock() else {
+            return 0;
+        };
+        state
+            .history
+            .iter()
+            .filter(|frame| frame.sequence() > self.last_sequence)
+            .count()
+    }
+
+    /// Frames this subscriber missed because it fell further behind than its policy allows
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Get the latest frame regardless of whether it was seen before
+    pub fn latest(&self) -> Option<ProcessedFrame> {
+        self.bus.latest()
+    }
+
+    /// How long the published content has stayed unchanged
+    pub fn unchanged_for(&self) -> Option<Duration> {
+        self.bus.unchanged_for()
+    }
+}
+
+impl Drop for FrameSubscriber {
+    fn drop(&mut self) {
+        // A gone subscriber must not hold up the capture or keep frames in the history
+        if let Ok(mut state) = self.bus.shared.state.lock() {
+            state.subscribers.remove(&self.id);
+        }
+    }
+}
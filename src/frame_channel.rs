@@ -0,0 +1,138 @@
+use crate::frame_bus::{BLOCK_TIMEOUT, FramePolicy};
+use crate::platform::Frame;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Frame counts since the channel was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameChannelStats {
+    /// Frames the capture delivered
+    pub sent: u64,
+    /// Frames the consumer took
+    pub received: u64,
+    /// Frames dropped because the consumer fell behind
+    pub dropped: u64,
+}
+
+struct ChannelQueue {
+    frames: VecDeque<Frame>,
+    policy: FramePolicy,
+    /// A `BlockCapture` output is behind, so the consumer leaves frames queued until it
+    /// catches up; the capture thread waits for room meanwhile, whatever the policy
+    held: bool,
+}
+
+struct ChannelShared {
+    queue: Mutex<ChannelQueue>,
+    /// Signalled when the consumer takes a frame, for senders waiting on `BlockCapture`
+    frame_taken: Condvar,
+    sent: AtomicU64,
+    received: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Bounded single-producer, single-consumer handoff of raw frames from a capture thread
+/// The policy sets the queue length (one frame unless `Queue`); when the queue is full the
+/// oldest frame is dropped (and counted) and its damage carried into the frame after it.
+/// With `BlockCapture`, or while an output that uses it holds the channel, the capture thread
+/// first waits for the consumer to make room
+#[derive(Clone)]
+pub struct FrameChannel {
+    shared: Arc<ChannelShared>,
+}
+
+impl Default for FrameChannel {
+    fn default() -> Self {
+        Self::new(FramePolicy::default())
+    }
+}
+
+impl FrameChannel {
+    pub fn new(policy: FramePolicy) -> Self {
+        Self {
+            shared: Arc::new(ChannelShared {
+                queue: Mutex::new(ChannelQueue {
+                    frames: VecDeque::new(),
+                    policy,
+                    held: false,
+                }),
+                frame_taken: Condvar::new(),
+                sent: AtomicU64::new(0),
+                received: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Change what happens when the consumer falls behind; applies from the next frame
+    pub fn set_policy(&self, policy: FramePolicy) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.policy = policy;
+        }
+        // Senders waiting under the old policy re-check against the new one
+        self.shared.frame_taken.notify_all();
+    }
+
+    /// Hold the capture thread while a `BlockCapture` output is behind (see `FramePolicy`)
+    pub fn set_held(&self, held: bool) {
+        let changed = self.shared.queue.lock().is_ok_and(|mut queue| {
+            let changed = queue.held != held;
+            queue.held = held;
+            changed
+        });
+        if changed {
+            self.shared.frame_taken.notify_all();
+        }
+    }
+
+    /// Queue a frame from the capture thread, dropping the oldest one if the queue is full
+    pub fn send(&self, mut frame: Frame) {
+        self.shared.sent.fetch_add(1, Ordering::Relaxed);
+        let waited = self.shared.queue.lock().ok().and_then(|queue| {
+            self.shared
+                .frame_taken
+                .wait_timeout_while(queue, BLOCK_TIMEOUT, |queue| {
+                    (queue.policy == FramePolicy::BlockCapture || queue.held)
+                        && queue.frames.len() >= queue.policy.depth()
+                })
+                .ok()
+        });
+        let Some((mut queue, _)) = waited else {
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        while queue.frames.len() >= queue.policy.depth()
+            && let Some(dropped) = queue.frames.pop_front()
+        {
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            // Whatever follows the dropped frame now has to report its changes too
+            match queue.frames.front_mut() {
+                Some(next) => next.merge_dirty_rects(&dropped),
+                None => frame.merge_dirty_rects(&dropped),
+            }
+        }
+        queue.frames.push_back(frame);
+    }
+
+    /// Take the oldest queued frame (raw and unredacted: only the redaction stage calls this)
+    pub fn try_recv(&self) -> Option<Frame> {
+        let frame = self.shared.queue.lock().ok()?.frames.pop_front()?;
+        self.shared.received.fetch_add(1, Ordering::Relaxed);
+        self.shared.frame_taken.notify_all();
+        Some(frame)
+    }
+
+    /// The newest queued frame, left in the queue
+    pub fn peek_latest(&self) -> Option<Frame> {
+        self.shared.queue.lock().ok()?.frames.back().cloned()
+    }
+
+    pub fn stats(&self) -> FrameChannelStats {
+        FrameChannelStats {
+            sent: self.shared.sent.load(Ordering::Relaxed),
+            received: self.shared.received.load(Ordering::Relaxed),
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
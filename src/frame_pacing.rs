@@ -0,0 +1,121 @@
+#[cfg(target_os = "macos")]
+use crate::platform::macos_display_link::DisplayLink;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use winit::window::Window;
+
+/// Frames per second captured and presented on ordinary displays
+pub const DEFAULT_FRAME_RATE: u32 = 60;
+
+/// Highest rate followed on high refresh displays (ProMotion)
+pub const MAX_FRAME_RATE: u32 = 120;
+
+/// Rate to capture at for a display refreshing at `refresh_hz`: the display's own rate when
+/// `high_refresh` is on and it is faster than 60 Hz, otherwise 60
+pub fn capture_frame_rate(high_refresh: bool, refresh_hz: Option<u32>) -> u32 {
+    match refresh_hz {
+        Some(hz) if high_refresh && hz > DEFAULT_FRAME_RATE => hz.min(MAX_FRAME_RATE),
+        _ => DEFAULT_FRAME_RATE,
+    }
+}
+
+/// Holds back redraws for new frames until the mirror display's next refresh, so frames are
+/// uploaded and presented at even intervals instead of whenever the capture thread wakes the
+/// loop. Without a display link (other platforms, or CoreVideo refusing) frames draw at once
+pub struct FramePacer {
+    /// A frame is waiting for the next refresh
+    pending: Arc<AtomicBool>,
+    #[cfg(target_os = "macos")]
+    link: Option<DisplayLink>,
+}
+
+impl FramePacer {
+    /// Pace to `display` (a CGDirectDisplayID, the main display for None); `wake` is called on
+    /// the display link thread at the refresh after each `frame_ready`
+    pub fn new(display: Option<u32>, wake: impl Fn() + Send + Sync + 'static) -> Self {
+        let pending = Arc::new(AtomicBool::new(false));
+        #[cfg(target_os = "macos")]
+        {
+            let tick_pending = pending.clone();
+            let display = display.unwrap_or_else(crate::platform::macos_display::main_display_id);
+            let link = DisplayLink::new(display, move || {
+                if tick_pending.swap(false, Ordering::AcqRel) {
+                    wake();
+                }
+            });
+            let link = match link {
+                Ok(link) => {
+                    if let Some(rate) = link.refresh_rate() {
+                        println!("Presenting in step with the display ({:.0} Hz)", rate);
+                    }
+                    Some(link)
+                }
+                Err(e) => {
+                    eprintln!("{}; presenting frames as they arrive", e);
+                    None
+                }
+            };
+            Self { pending, link }
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (display, wake);
+            Self { pending }
+        }
+    }
+
+    /// A new frame arrived; true if the caller should redraw now (unpaced), false if the
+    /// redraw will come from the next refresh
+    pub fn frame_ready(&self) -> bool {
+        if !self.is_paced() {
+            return true;
+        }
+        self.pending.store(true, Ordering::Release);
+        false
+    }
+
+    pub fn is_paced(&self) -> bool {
+        #[cfg(target_os = "macos")]
+        return self.link.is_some();
+        #[cfg(not(target_os = "macos"))]
+        false
+    }
+
+    /// Follow the display the mirror window is on
+    pub fn set_display(&self, display: u32) {
+        #[cfg(target_os = "macos")]
+        if let Some(link) = &self.link
+            && let Err(e) = link.set_display(display)
+        {
+            eprintln!("{}", e);
+        }
+        #[cfg(not(target_os = "macos"))]
+        let _ = display;
+    }
+
+    /// Refresh rate presentation is paced to, in Hz
+    pub fn refresh_rate(&self) -> Option<f64> {
+        #[cfg(target_os = "macos")]
+        return self.link.as_ref().and_then(DisplayLink::refresh_rate);
+        #[cfg(not(target_os = "macos"))]
+        None
+    }
+}
+
+/// Refresh rate of the monitor `window` is on, rounded to whole Hz (119.88 reports 120)
+pub fn window_refresh_rate(window: &Window) -> Option<u32> {
+    let millihertz = window.current_monitor()?.refresh_rate_millihertz()?;
+    Some((millihertz + 500) / 1000)
+}
+
+/// CGDirectDisplayID of the monitor `window` is on
+#[cfg(target_os = "macos")]
+pub fn window_display(window: &Window) -> Option<u32> {
+    use winit::platform::macos::MonitorHandleExtMacOS;
+    window.current_monitor().map(|monitor| monitor.native_id())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn window_display(_window: &Window) -> Option<u32> {
+    None
+}
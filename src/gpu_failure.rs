@@ -0,0 +1,57 @@
+use crate::gpu_renderer::RendererError;
+use crate::l10n::{tr, tr_args};
+
+/// What to do after the renderer failed to start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuFailureChoice {
+    /// Try the same settings again (after plugging in a display, unlocking the session, ...)
+    Retry,
+    /// Retry with a software adapter
+    SoftwareFallback,
+    Quit,
+}
+
+impl GpuFailureChoice {
+    fn label(self) -> String {
+        tr(match self {
+            Self::Retry => "button-retry",
+            Self::SoftwareFallback => "button-software-renderer",
+            Self::Quit => "button-quit",
+        })
+    }
+}
+
+/// Explain why the renderer couldn't start and ask how to continue
+/// The software option is left out once it has been tried
+pub fn ask_after_gpu_failure(error: &RendererError, software_tried: bool) -> GpuFailureChoice {
+    let mut message = tr_args("gpu-failure-message", &[("error", &error.to_string())]);
+    let choices: &[GpuFailureChoice] = if software_tried {
+        &[GpuFailureChoice::Retry, GpuFailureChoice::Quit]
+    } else {
+        message.push(' ');
+        message.push_str(&tr("gpu-failure-software-hint"));
+        &[
+            GpuFailureChoice::Retry,
+            GpuFailureChoice::SoftwareFallback,
+            GpuFailureChoice::Quit,
+        ]
+    };
+
+    let labels: Vec<String> = choices.iter().map(|choice| choice.label()).collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    show_dialog(&message, &labels)
+        .and_then(|index| choices.get(index).copied())
+        .unwrap_or(GpuFailureChoice::Quit)
+}
+
+#[cfg(target_os = "macos")]
+fn show_dialog(message: &str, buttons: &[&str]) -> Option<usize> {
+    crate::platform::macos_alert::show_alert(&tr("gpu-failure-title"), message, buttons)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_dialog(message: &str, _buttons: &[&str]) -> Option<usize> {
+    // No native dialog on this platform yet; the error goes to the terminal and the app quits
+    eprintln!("{}: {}", tr("gpu-failure-title"), message);
+    None
+}
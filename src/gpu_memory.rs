@@ -0,0 +1,26 @@
+/// GPU memory a renderer holds, by what it's for (bytes)
+/// The window surface's own buffers belong to the compositor and aren't counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuMemoryUsage {
+    /// The capture texture
+    pub capture: u64,
+    /// One staging chunk frames are uploaded through (more are added while uploads queue up)
+    pub staging: u64,
+    /// Float pipeline scene target
+    pub float_target: u64,
+    /// Scene layer atlas
+    pub atlas: u64,
+    /// Effect ping-pong targets
+    pub effects: u64,
+    /// Error card and stats HUD
+    pub cards: u64,
+}
+
+impl GpuMemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.capture + self.staging + self.float_target + self.atlas + self.effects + self.cards
+    }
+}
+
+/// Bytes in a megabyte, for budgets in the config and messages
+pub const MEGABYTE: u64 = 1024 * 1024;
@@ -1,9 +1,115 @@
+use crate::atlas;
+use crate::crash_report;
+use crate::downscale::DownscalePass;
+use crate::effects::{Effect, EffectChain};
+use crate::gpu_memory::{GpuMemoryUsage, MEGABYTE};
+use crate::scaling::{downscale_rgba, strip_row_padding};
+use crate::scene::{LayerSource, Scene, load_image};
+use crate::text::TextRenderer;
+use crate::theme::Theme;
+use crate::tone_map::{SCENE_FORMAT, SCENE_TEXEL_SIZE, ToneMapPass, write_scene_texels};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use winit::window::Window;
 
+/// Graphics APIs the renderer uses (Metal for best macOS performance)
+const BACKENDS: wgpu::Backends = wgpu::Backends::METAL;
+
+/// Most scene layers drawn at once (must match MAX_LAYERS in layer.wgsl)
+const MAX_LAYERS: usize = 64;
+
+/// Largest factor scene layer sources are downscaled by to fit the memory budget
+const MAX_ATLAS_FACTOR: u32 = 8;
+
+/// f32s per layer in the layer uniform array (rect, uv_rect, params)
+const LAYER_FLOATS: usize = 12;
+
+/// User-tunable GPU selection and surface settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GpuSettings {
+    /// Use the first adapter whose name contains this (case-insensitive) instead of the default
+    /// high-performance pick - avoids cross-GPU copies on multi-GPU and eGPU systems
+    pub adapter: Option<String>,
+    /// Surface color format instead of the first sRGB format the surface offers
+    pub surface_format: Option<SurfaceFormat>,
+    /// Surface alpha mode instead of opaque (or the first mode the surface offers)
+    pub alpha_mode: Option<SurfaceAlphaMode>,
+    /// Ask for a software (CPU) adapter on any backend instead of a GPU - slow, but works on
+    /// machines whose GPU can't be used
+    pub software_fallback: bool,
+    /// Present with Mailbox (a newer frame replaces one still waiting for the display) when
+    /// the surface offers it, so high refresh frames aren't queued behind each other
+    pub mailbox: bool,
+    /// Keep the capture and scene in linear RGBA16F textures and tone-map to the surface at
+    /// the end, so gradients don't band and EDR highlights keep their hue instead of clipping
+    /// (twice the upload bandwidth and texture memory)
+    pub float_pipeline: bool,
+    /// Present with Immediate (or Mailbox) and keep at most one frame queued for the display,
+    /// trading possible tearing for the lowest glass-to-glass latency
+    pub low_latency: bool,
+    /// Try the low-power (integrated) GPU before the high-performance one, e.g. on battery
+    pub low_power: bool,
+    /// Most GPU memory (MB) the renderer's textures may take; beyond it effects are turned
+    /// off and scene layers drawn at lower resolution (None = no limit)
+    pub memory_budget_mb: Option<u32>,
+    /// Font and corner radius of text layers that don't set their own
+    pub theme: Theme,
+    /// Show the capture at 1/n resolution (`preview_scale`; 0 or 1 = native)
+    pub preview_scale: u32,
+}
+
+/// Window surface color formats that can be forced from the config
+/// Limited to 8-bit formats so offscreen readback stays plain RGBA/BGRA
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceFormat {
+    Bgra8UnormSrgb,
+    Bgra8Unorm,
+    Rgba8UnormSrgb,
+    Rgba8Unorm,
+}
+
+impl SurfaceFormat {
+    fn to_wgpu(self) -> wgpu::TextureFormat {
+        match self {
+            SurfaceFormat::Bgra8UnormSrgb => wgpu::TextureFormat::Bgra8UnormSrgb,
+            SurfaceFormat::Bgra8Unorm => wgpu::TextureFormat::Bgra8Unorm,
+            SurfaceFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            SurfaceFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+/// Window surface alpha modes that can be forced from the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceAlphaMode {
+    Opaque,
+    PreMultiplied,
+    PostMultiplied,
+    Inherit,
+}
+
+impl SurfaceAlphaMode {
+    fn to_wgpu(self) -> wgpu::CompositeAlphaMode {
+        match self {
+            SurfaceAlphaMode::Opaque => wgpu::CompositeAlphaMode::Opaque,
+            SurfaceAlphaMode::PreMultiplied => wgpu::CompositeAlphaMode::PreMultiplied,
+            SurfaceAlphaMode::PostMultiplied => wgpu::CompositeAlphaMode::PostMultiplied,
+            SurfaceAlphaMode::Inherit => wgpu::CompositeAlphaMode::Inherit,
+        }
+    }
+}
+
 /// GPU renderer that handles all wgpu operations for screen mirroring
 pub struct GpuRenderer {
-    pub surface: wgpu::Surface<'static>,
+    /// The window's surface; None for a renderer that only draws offscreen (headless record)
+    pub surface: Option<wgpu::Surface<'static>>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
@@ -13,36 +119,243 @@ pub struct GpuRenderer {
     pub texture: wgpu::Texture,
     pub capture_width: u32,
     pub capture_height: u32,
+    /// The capture texture is this factor smaller than the capture: the preview scale, or
+    /// more when the capture exceeds the device's maximum texture size (1 = full resolution)
+    upload_factor: u32,
+    /// Reduces uploaded frames by `upload_factor` on the GPU; None when the factor is 1, or
+    /// the full-resolution frame wouldn't fit in a texture and is downscaled on the CPU
+    downscale: Option<DownscalePass>,
+    /// Reused mapped buffers frames are staged in before being copied into textures, so
+    /// an upload doesn't wait for the GPU to finish reading the previous frame
+    staging_belt: wgpu::util::StagingBelt,
+    /// Set from the device-lost callback (driver reset, eGPU unplugged)
+    device_lost: Arc<AtomicBool>,
+    sampler: wgpu::Sampler,
+    /// Same as `bind_group`, but sampling the capture with nearest filtering (integer scaling)
+    nearest_bind_group: wgpu::BindGroup,
+    /// Show the capture at a whole-number scale, letterboxed, instead of stretched to the window
+    integer_scaling: bool,
+    /// Fit the capture inside the window at its own aspect ratio instead of stretching it
+    letterbox: bool,
+    /// Capture sharpening strength (0 = off, up to 1; see sharpen.wgsl)
+    sharpen: f32,
+    /// Sharpening strength for the fullscreen capture pipeline (one vec4)
+    sharpen_uniform: wgpu::Buffer,
+    /// Draws scene layers as positioned, alpha-blended rectangles
+    layer_pipeline: wgpu::RenderPipeline,
+    layer_bind_group_layout: wgpu::BindGroupLayout,
+    /// Placement, source region and opacity of every visible layer (MAX_LAYERS entries)
+    layer_uniform: wgpu::Buffer,
+    /// Holds every non-capture layer source (images, colors, text, webcam) side by side
+    atlas: wgpu::Texture,
+    /// Layer sources are downscaled by this before packing, to fit the memory budget
+    atlas_factor: u32,
+    /// Most bytes `memory_usage` may reach (None = no limit); lowered after running out
+    memory_budget: Option<u64>,
+    /// Capture texture + atlas + sampler + layer uniforms: one bind group for the whole scene
+    layer_bind_group: wgpu::BindGroup,
+    /// Visible layers drawn, bottom first (0 = plain fullscreen capture)
+    layer_count: u32,
+    /// Decoded image layers by path, so showing/hiding a layer doesn't re-decode it
+    images: HashMap<PathBuf, (Vec<u8>, u32, u32)>,
+    /// Camera resolution (None until the first frame arrives)
+    webcam_size: Option<(u32, u32)>,
+    /// Atlas region camera frames are written to
+    webcam_origin: Option<(u32, u32)>,
+    /// Capture layers with an `fps` cap, drawn from a copy in the atlas
+    held_captures: Vec<HeldCapture>,
+    /// Scene the layers were built from, kept to rebuild them when the webcam or window size changes
+    scene: Scene,
+    /// Rasterizes text layers
+    text_renderer: TextRenderer,
+    /// Fills in the font and corners text layers leave unset
+    theme: Theme,
+    /// User post-processing passes from the effects directory (None = draw straight to the target)
+    effects: Option<EffectChain>,
+    /// Layout of `bind_group`, reused for the overlay
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    /// Card drawn centered over the output in the window only (error states): bind group,
+    /// width and height. Offscreen renders, and so every output, never include it
+    overlay: Option<(wgpu::BindGroup, u32, u32)>,
+    /// Stats HUD drawn in the window's top left corner, window only like `overlay`
+    hud: Option<(wgpu::BindGroup, u32, u32)>,
+    /// `render_pipeline` blending over the output instead of replacing it, for cards with
+    /// transparent (rounded) corners
+    card_pipeline: wgpu::RenderPipeline,
+    /// Format the scene, overlay and effects are drawn in (the surface's, or RGBA16F)
+    scene_format: wgpu::TextureFormat,
+    /// Last pass of the float pipeline (None = draw straight to the target)
+    tone_map: Option<ToneMapPass>,
+    /// Output readbacks the GPU is still working on, oldest first (at most `MAX_READBACKS`)
+    readbacks: VecDeque<Readback>,
+    /// Readback buffers that were collected, reused while the output size stays the same
+    free_readback_buffers: Vec<wgpu::Buffer>,
+}
+
+/// Tightly packed RGBA pixels with their width and height
+pub type RgbaImage = (Vec<u8>, u32, u32);
+
+/// Output readbacks in flight at once: one being filled while the previous one is collected
+const MAX_READBACKS: usize = 2;
+
+/// The output copied into a mappable buffer, collected once the GPU has finished with it
+struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    /// Receives the result of mapping the buffer
+    mapped: std::sync::mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+/// Atlas copy of the capture for a layer with an `fps` cap, refreshed at that rate while the
+/// capture itself keeps running at full rate for everything else
+#[derive(Debug)]
+struct HeldCapture {
+    /// Atlas region the copy is written to
+    origin: (u32, u32),
+    /// Capture texture size the region was laid out for
+    source_size: (u32, u32),
+    interval: Duration,
+    /// None until the first capture frame after the atlas was (re)built
+    refreshed: Option<Instant>,
+}
+
+/// Why the renderer couldn't start
+#[derive(Debug)]
+pub enum RendererError {
+    /// The window can't be drawn to (no display attached, some remote sessions)
+    Surface(wgpu::CreateSurfaceError),
+    /// No GPU can present to the window (headless Mac, unsupported or disabled GPU)
+    NoAdapter(wgpu::RequestAdapterError),
+    /// The GPU refused to create a device (driver problem, limits not met)
+    Device(wgpu::RequestDeviceError),
+    /// The GPU reports no formats or present modes for the window surface
+    IncompatibleSurface(String),
+}
+
+impl std::fmt::Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Surface(e) => write!(
+                f,
+                "Can't draw to the window ({}). Is a display attached and the session unlocked?",
+                e
+            ),
+            Self::NoAdapter(e) => write!(
+                f,
+                "No usable GPU found ({}). Check that Metal is available, or unset gpu_adapter",
+                e
+            ),
+            Self::Device(e) => write!(f, "The GPU failed to initialize ({})", e),
+            Self::IncompatibleSurface(adapter) => write!(
+                f,
+                "GPU {} can't present to this window; try another gpu_adapter (see --list-gpus)",
+                adapter
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Surface(e) => Some(e),
+            Self::NoAdapter(e) => Some(e),
+            Self::Device(e) => Some(e),
+            Self::IncompatibleSurface(_) => None,
+        }
+    }
 }
 
 impl GpuRenderer {
-    pub async fn new(window: Arc<Window>, capture_width: u32, capture_height: u32) -> Self {
+    pub async fn new(
+        window: Arc<Window>,
+        capture_width: u32,
+        capture_height: u32,
+        settings: &GpuSettings,
+    ) -> Result<Self, RendererError> {
         let size = window.inner_size();
+        Self::create(Some(window), size, capture_width, capture_height, settings).await
+    }
+
+    /// A renderer without a window, for `render_to_texture` and output readbacks of a
+    /// capture-sized (`capture_width`x`capture_height`) output
+    pub async fn new_offscreen(
+        capture_width: u32,
+        capture_height: u32,
+        settings: &GpuSettings,
+    ) -> Result<Self, RendererError> {
+        let size = winit::dpi::PhysicalSize::new(capture_width.max(1), capture_height.max(1));
+        Self::create(None, size, capture_width, capture_height, settings).await
+    }
 
+    async fn create(
+        window: Option<Arc<Window>>,
+        size: winit::dpi::PhysicalSize<u32>,
+        capture_width: u32,
+        capture_height: u32,
+        settings: &GpuSettings,
+    ) -> Result<Self, RendererError> {
         // STEP 1: Create wgpu instance - this is our entry point to GPU programming
         // wgpu is a Rust library that provides safe access to GPU APIs (Metal, Vulkan, DirectX)
         // We specify Metal backend because we're on macOS and want direct access to Apple's GPU API
+        // Software adapters aren't Metal devices, so the fallback looks at every backend
+        let backends = if settings.software_fallback {
+            wgpu::Backends::all()
+        } else {
+            BACKENDS
+        };
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::METAL, // Use Apple's Metal API for best macOS performance
+            backends, // Use Apple's Metal API for best macOS performance
             ..Default::default()
         });
 
         // STEP 2: Create surface - this connects our GPU rendering to the actual window
         // The surface is where our final rendered pixels will appear
         // Think of it as the "screen" that the GPU draws onto
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = window
+            .map(|window| instance.create_surface(window))
+            .transpose()
+            .map_err(RendererError::Surface)?;
 
         // STEP 3: Request adapter - this finds the best GPU for our needs
         // An adapter represents a physical GPU device on the system
-        // We ask for high performance GPU (discrete if available, integrated otherwise)
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance, // Prefer faster GPU over power saving
-                compatible_surface: Some(&surface), // Must be able to draw to our window
-                force_fallback_adapter: false,      // Don't force software rendering
-            })
-            .await
-            .unwrap();
+        // A pinned adapter from the config wins; otherwise we walk a fallback chain from the
+        // high performance GPU (discrete if available) down to a software adapter
+        let pinned = settings
+            .adapter
+            .as_deref()
+            .filter(|_| !settings.software_fallback);
+        let pinned = pinned.and_then(|name| {
+            let wanted = name.to_lowercase();
+            let adapter = instance.enumerate_adapters(BACKENDS).into_iter().find(|a| {
+                a.get_info().name.to_lowercase().contains(&wanted)
+                    && surface
+                        .as_ref()
+                        .is_none_or(|surface| a.is_surface_supported(surface))
+            });
+            if adapter.is_none() {
+                eprintln!("GPU adapter matching \"{}\" not found, using default", name);
+            }
+            adapter
+        });
+        let adapter = match pinned {
+            Some(adapter) => adapter,
+            None => Self::request_adapter(&instance, surface.as_ref(), settings).await?,
+        };
+        let info = adapter.get_info();
+        println!(
+            "GPU adapter: {} ({:?}, {:?})",
+            info.name, info.backend, info.device_type
+        );
+        crash_report::set_context(
+            "gpu",
+            format!(
+                "{} ({:?}, {:?}), driver {} {}",
+                info.name, info.backend, info.device_type, info.driver, info.driver_info
+            ),
+        );
 
         // STEP 4: Request device and queue from the adapter
         // Device: Our handle to the GPU for creating resources (textures, shaders, etc.)
@@ -50,60 +363,205 @@ impl GpuRenderer {
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 required_features: wgpu::Features::empty(), // No special GPU features needed
-                required_limits: wgpu::Limits::default(),   // Use standard GPU limits
-                label: None,                                // Optional debug name
+                // Standard GPU limits, except for the largest texture the adapter supports
+                // (6K/8K displays and multi-display captures exceed the 8192 default)
+                required_limits: wgpu::Limits::default().using_resolution(adapter.limits()),
+                label: None, // Optional debug name
                 memory_hints: wgpu::MemoryHints::Performance,
                 trace: wgpu::Trace::Off,
             })
             .await
-            .unwrap();
+            .map_err(RendererError::Device)?;
+
+        // Remember device loss so the owner can rebuild everything on a new device
+        // (Destroyed is reported when we drop the device ourselves)
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            if !matches!(reason, wgpu::DeviceLostReason::Destroyed) {
+                eprintln!("GPU device lost: {}", message);
+                lost_flag.store(true, Ordering::SeqCst);
+            }
+        });
+        // The default handler panics; errors after a device loss are expected, so just log them
+        device.on_uncaptured_error(Box::new(|error| eprintln!("GPU error: {}", error)));
 
         // STEP 5: Configure the surface for drawing
         // Get capabilities: What color formats, present modes the GPU supports
-        let surface_caps = surface.get_capabilities(&adapter);
+        // Offscreen, the output is drawn in the format readbacks expect
+        let surface_caps = match &surface {
+            Some(surface) => surface.get_capabilities(&adapter),
+            None => wgpu::SurfaceCapabilities {
+                formats: vec![wgpu::TextureFormat::Rgba8UnormSrgb],
+                present_modes: vec![wgpu::PresentMode::Fifo],
+                alpha_modes: vec![wgpu::CompositeAlphaMode::Opaque],
+                usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            },
+        };
+        if surface_caps.formats.is_empty()
+            || surface_caps.alpha_modes.is_empty()
+            || surface_caps.present_modes.is_empty()
+        {
+            return Err(RendererError::IncompatibleSurface(info.name));
+        }
 
         // Choose sRGB color format if available (standard for displays)
         // sRGB ensures colors look correct on most monitors
-        let surface_format = surface_caps
+        let default_format = surface_caps
             .formats
             .iter()
             .copied()
             .find(|f| f.is_srgb()) // Prefer sRGB for correct color display
             .unwrap_or(surface_caps.formats[0]); // Fallback to first available format
 
+        // A configured format overrides that, as long as the surface supports it
+        let surface_format = match settings.surface_format.map(SurfaceFormat::to_wgpu) {
+            Some(format) if surface_caps.formats.contains(&format) => format,
+            Some(format) => {
+                eprintln!(
+                    "Surface format {:?} not supported (available: {:?}), using {:?}",
+                    format, surface_caps.formats, default_format
+                );
+                default_format
+            }
+            None => default_format,
+        };
+
+        // The mirror is opaque; a premultiplied/transparent mode can wash it out
+        let default_alpha_mode = if surface_caps
+            .alpha_modes
+            .contains(&wgpu::CompositeAlphaMode::Opaque)
+        {
+            wgpu::CompositeAlphaMode::Opaque
+        } else {
+            surface_caps.alpha_modes[0]
+        };
+        let alpha_mode = match settings.alpha_mode.map(SurfaceAlphaMode::to_wgpu) {
+            Some(mode) if surface_caps.alpha_modes.contains(&mode) => mode,
+            Some(mode) => {
+                eprintln!(
+                    "Surface alpha mode {:?} not supported (available: {:?}), using {:?}",
+                    mode, surface_caps.alpha_modes, default_alpha_mode
+                );
+                default_alpha_mode
+            }
+            None => default_alpha_mode,
+        };
+        // Frames, layers and cards are straight alpha; a premultiplied surface gets its color
+        // multiplied by alpha in the fragment shaders, or translucent pixels show dark fringes
+        // and a chroma key fill glows green instead of being transparent
+        let premultiplied = alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied;
+        // The float pipeline draws the scene into RGBA16F; its tone map pass premultiplies
+        let (scene_format, tone_map) = if settings.float_pipeline {
+            (
+                SCENE_FORMAT,
+                Some(ToneMapPass::new(&device, surface_format, premultiplied)),
+            )
+        } else {
+            (surface_format, None)
+        };
+        let premultiply_scene = premultiplied && tone_map.is_none();
+        let output_constants = [(
+            "premultiply_output",
+            if premultiply_scene { 1.0 } else { 0.0 },
+        )];
+
+        // Immediate for low latency, Mailbox for high refresh capture (or as the low latency
+        // fallback), otherwise the surface's default
+        let supports = |mode| surface_caps.present_modes.contains(&mode);
+        let present_mode = if settings.low_latency && supports(wgpu::PresentMode::Immediate) {
+            wgpu::PresentMode::Immediate
+        } else if (settings.mailbox || settings.low_latency) && supports(wgpu::PresentMode::Mailbox)
+        {
+            wgpu::PresentMode::Mailbox
+        } else {
+            if settings.mailbox || settings.low_latency {
+                eprintln!(
+                    "Mailbox presentation not supported (available: {:?}), using {:?}",
+                    surface_caps.present_modes, surface_caps.present_modes[0]
+                );
+            }
+            surface_caps.present_modes[0]
+        };
+
         // Surface configuration: How the GPU should draw to our window
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT, // We'll draw directly to this surface
             format: surface_format,                        // Color format (usually RGBA or BGRA)
             width: size.width,                             // Window width in pixels
             height: size.height,                           // Window height in pixels
-            present_mode: surface_caps.present_modes[0],   // How to sync with display refresh
-            alpha_mode: surface_caps.alpha_modes[0],       // How to handle transparency
+            present_mode,                                  // How to sync with display refresh
+            alpha_mode,                                    // How to handle transparency
             view_formats: vec![],                          // Additional formats (none needed)
-            desired_maximum_frame_latency: 2,              // Buffer 2 frames max for responsiveness
+            // Buffer 2 frames max for responsiveness, 1 when latency matters most
+            desired_maximum_frame_latency: if settings.low_latency { 1 } else { 2 },
         };
-        surface.configure(&device, &config);
+        if let Some(surface) = &surface {
+            surface.configure(&device, &config);
+        }
 
         // STEP 6: Create texture to hold screen capture data
         // This is GPU memory where we'll store the captured screen image
         // Think of this as a bitmap/image that lives on the GPU
+        // Captures larger than the device allows are downscaled rather than failing here
+        let max_dimension = device.limits().max_texture_dimension_2d.max(1);
+        let limit_factor = capture_width
+            .max(capture_height)
+            .div_ceil(max_dimension)
+            .max(1);
+        if limit_factor > 1 {
+            eprintln!(
+                "Capture {}x{} exceeds the GPU's {}px texture limit; mirroring at 1/{} resolution",
+                capture_width, capture_height, max_dimension, limit_factor
+            );
+        }
+        let upload_factor = limit_factor.max(settings.preview_scale);
+        let capture_format = if settings.float_pipeline {
+            SCENE_FORMAT
+        } else {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        };
+        // A reduced preview of a capture that fits is uploaded whole and downscaled on the GPU
+        let downscale = (limit_factor == 1 && upload_factor > 1).then(|| {
+            DownscalePass::new(
+                &device,
+                capture_width,
+                capture_height,
+                capture_format,
+                upload_factor,
+            )
+        });
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
-                width: capture_width, // Dynamic resolution based on actual display
-                height: capture_height,
+                width: (capture_width / upload_factor).max(1), // Dynamic resolution based on actual display
+                height: (capture_height / upload_factor).max(1),
                 depth_or_array_layers: 1, // 2D texture (not 3D or array)
             },
             mip_level_count: 1, // No mipmaps (smaller versions for distance rendering)
             sample_count: 1,    // No anti-aliasing
             dimension: wgpu::TextureDimension::D2, // 2D texture (has width and height)
-            format: wgpu::TextureFormat::Rgba8UnormSrgb, // 8-bit RGBA in sRGB color space
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            // 8-bit RGBA in sRGB color space, or linear half floats in the float pipeline
+            format: capture_format,
+            usage: if downscale.is_some() {
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT
+            } else {
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+            },
             // TEXTURE_BINDING: Shaders can read from this texture
             // COPY_DST: We can write screen capture data into this texture
+            // RENDER_ATTACHMENT: The downscale pass draws the preview into it instead
             label: Some("Screen Capture Texture"), // Debug name
             view_formats: &[],                     // No additional view formats needed
         });
 
+        // Chunks hold one padded uploaded frame, so a frame never spans several staging buffers
+        let uploaded = downscale.as_ref().map_or(&texture, DownscalePass::source);
+        let staging_belt = wgpu::util::StagingBelt::new(padded_upload_size(
+            uploaded.width(),
+            uploaded.height(),
+            texel_size(uploaded.format()),
+        ));
+
         // STEP 7: Create texture view - this is how shaders access the texture
         // A "view" is like a window into the texture data that shaders can read from
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
@@ -147,10 +605,29 @@ impl GpuRenderer {
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering), // Can filter/interpolate
                         count: None, // Single sampler
                     },
+                    // Binding 2: Sharpening strength
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("texture_bind_group_layout"),
             });
 
+        // Sharpening starts off (set_sharpen turns it on)
+        let sharpen_uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sharpen Uniforms"),
+            size: 4 * std::mem::size_of::<f32>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         // STEP 10: Create the actual bind group - connects real resources to the layout
         // This binds our actual texture and sampler to the slots defined in the layout
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -166,17 +643,52 @@ impl GpuRenderer {
                     binding: 1, // Corresponds to @binding(1) in shader
                     resource: wgpu::BindingResource::Sampler(&sampler),
                 },
+                // Bind the sharpening strength to slot 2
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: sharpen_uniform.as_entire_binding(),
+                },
             ],
             label: Some("texture_bind_group"),
         });
 
+        // Integer scaling maps every capture pixel to a block of whole output pixels, so
+        // nearest filtering keeps the edges crisp instead of blending neighbours
+        let nearest_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Nearest Sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let nearest_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&nearest_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: sharpen_uniform.as_entire_binding(),
+                },
+            ],
+            label: Some("nearest_texture_bind_group"),
+        });
+
         // STEP 11: Load and compile shaders
         // Shaders are small programs that run on the GPU
         // - Vertex shader: Positions geometry (where to draw)
         // - Fragment shader: Colors pixels (what color each pixel should be)
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Screen Mirror Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+            // sharpen.wgsl holds the sharpening function both capture shaders share
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("sharpen.wgsl"), include_str!("shader.wgsl")).into(),
+            ),
         });
 
         // STEP 12: Create pipeline layout - defines the "interface" for the entire pipeline
@@ -210,11 +722,14 @@ impl GpuRenderer {
                 module: &shader,              // Use our compiled shader
                 entry_point: Some("fs_main"), // Function name in shader.wgsl
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,                  // Must match surface format
+                    format: scene_format, // Surface format, or RGBA16F in the float pipeline
                     blend: Some(wgpu::BlendState::REPLACE), // Don't blend, just replace pixels
-                    write_mask: wgpu::ColorWrites::ALL,     // Write to all color channels (RGBA)
+                    write_mask: wgpu::ColorWrites::ALL, // Write to all color channels (RGBA)
                 })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &output_constants,
+                    ..Default::default()
+                },
             }),
 
             // PRIMITIVE SETTINGS: How to interpret vertex data
@@ -243,7 +758,139 @@ impl GpuRenderer {
             cache: None,
         });
 
-        Self {
+        // Cards (HUD, error card) use the same shader, but blend so rounded corners show the
+        // output underneath
+        let card_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Card Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: scene_format,
+                    blend: Some(if premultiply_scene {
+                        wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
+                    } else {
+                        wgpu::BlendState::ALPHA_BLENDING
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &output_constants,
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        // Scene layers: the capture texture, an atlas with every other source, the sampler
+        // and a uniform array with each layer's placement - shared by all layers
+        let layer_texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let layer_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    layer_texture_entry(0),
+                    layer_texture_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("layer_bind_group_layout"),
+            });
+        let layer_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Scene Layer Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("sharpen.wgsl"), include_str!("layer.wgsl")).into(),
+            ),
+        });
+        let layer_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Layer Pipeline Layout"),
+                bind_group_layouts: &[&layer_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let layer_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Layer Pipeline"),
+            layout: Some(&layer_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &layer_shader,
+                entry_point: Some("vs_layer"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &layer_shader,
+                entry_point: Some("fs_layer"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: scene_format,
+                    // Layers are stacked, so upper ones blend over lower ones
+                    blend: Some(if premultiply_scene {
+                        wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
+                    } else {
+                        wgpu::BlendState::ALPHA_BLENDING
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &output_constants,
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None, // Negative sizes (mirrored layers) stay visible
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let layer_uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Layer Uniforms"),
+            size: (MAX_LAYERS * LAYER_FLOATS * std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Placeholders until the first set_scene
+        let atlas = texture.clone();
+        let layer_bind_group = bind_group.clone();
+
+        let mut renderer = Self {
             surface,
             device,
             queue,
@@ -254,7 +901,446 @@ impl GpuRenderer {
             texture,
             capture_width,
             capture_height,
+            upload_factor,
+            downscale,
+            staging_belt,
+            device_lost,
+            sampler,
+            nearest_bind_group,
+            integer_scaling: false,
+            letterbox: false,
+            sharpen: 0.0,
+            sharpen_uniform,
+            layer_pipeline,
+            layer_bind_group_layout,
+            layer_uniform,
+            atlas,
+            atlas_factor: 1,
+            memory_budget: settings
+                .memory_budget_mb
+                .map(|megabytes| u64::from(megabytes) * MEGABYTE),
+            layer_bind_group,
+            layer_count: 0,
+            images: HashMap::new(),
+            webcam_size: None,
+            webcam_origin: None,
+            held_captures: Vec::new(),
+            scene: Scene::default(),
+            text_renderer: TextRenderer::new(),
+            theme: settings.theme.clone(),
+            effects: None,
+            texture_bind_group_layout,
+            overlay: None,
+            hud: None,
+            card_pipeline,
+            scene_format,
+            tone_map,
+            readbacks: VecDeque::new(),
+            free_readback_buffers: Vec::new(),
+        };
+        renderer.set_scene(&Scene::default());
+        Ok(renderer)
+    }
+
+    /// Ask for an adapter that can draw to `surface` (any adapter offscreen), falling back
+    /// step by step: high-performance GPU → low-power GPU (the other way round with
+    /// `low_power`) → software adapter → error
+    async fn request_adapter(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'static>>,
+        settings: &GpuSettings,
+    ) -> Result<wgpu::Adapter, RendererError> {
+        let mut gpu_preferences = [
+            ("high-performance", wgpu::PowerPreference::HighPerformance), // Discrete GPU if any
+            ("low-power", wgpu::PowerPreference::LowPower),               // Integrated GPU
+        ];
+        if settings.low_power {
+            gpu_preferences.reverse();
+        }
+        if !settings.software_fallback {
+            for (kind, power_preference) in gpu_preferences {
+                match instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference,
+                        compatible_surface: surface, // Must be able to draw to our window
+                        force_fallback_adapter: false,
+                    })
+                    .await
+                {
+                    Ok(adapter) => {
+                        println!("Selected {} GPU adapter", kind);
+                        return Ok(adapter);
+                    }
+                    Err(e) => eprintln!("No {} GPU adapter: {}", kind, e),
+                }
+            }
+        }
+
+        // Last resort: a software adapter, if the platform has one
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::None,
+                compatible_surface: surface,
+                force_fallback_adapter: true,
+            })
+            .await
+            .map_err(RendererError::NoAdapter)?;
+        println!("Selected software fallback adapter");
+        Ok(adapter)
+    }
+
+    /// Pack the visible layers of a scene into the atlas and layer uniforms (an empty scene
+    /// restores the plain fullscreen capture); layers whose source can't be loaded are skipped
+    pub fn set_scene(&mut self, scene: &Scene) {
+        self.scene = scene.clone();
+
+        // Decode images up front (once per path); hidden layers are loaded too so
+        // showing them later is instant
+        for layer in scene.layers() {
+            if let LayerSource::Image(path) = &layer.source
+                && !self.images.contains_key(path)
+            {
+                match load_image(path) {
+                    Ok(image) => {
+                        self.images.insert(path.clone(), image);
+                    }
+                    Err(e) => eprintln!("Skipping layer {}: {}", layer.name, e),
+                }
+            }
         }
+
+        // Pixels and size of each visible layer's atlas region (None = capture texture, or
+        // the webcam or held capture region that the next frame fills in)
+        let mut layers = Vec::new();
+        for layer in scene.layers().iter().filter(|layer| layer.visible) {
+            let source = match &layer.source {
+                LayerSource::Capture if layer.fps.is_some() => {
+                    Some((None, self.texture.width(), self.texture.height()))
+                }
+                LayerSource::Capture => None,
+                LayerSource::Image(path) => match self.images.get(path) {
+                    Some((rgba, width, height)) => Some((Some(rgba.clone()), *width, *height)),
+                    None => continue,
+                },
+                LayerSource::Color(color) => Some((Some(color.to_vec()), 1, 1)),
+                // Skipped until the camera delivers its first frame
+                LayerSource::Webcam => match self.webcam_size {
+                    Some((width, height)) => Some((None, width, height)),
+                    None => continue,
+                },
+                LayerSource::Text(style) => {
+                    match self.text_renderer.render(&self.theme.text_layer(style)) {
+                        Ok((rgba, width, height)) => Some((Some(rgba), width, height)),
+                        Err(e) => {
+                            eprintln!("Skipping layer {}: {}", layer.name, e);
+                            continue;
+                        }
+                    }
+                }
+            };
+            layers.push((layer, source));
+        }
+        if layers.len() > MAX_LAYERS {
+            eprintln!(
+                "Scene has {} visible layers; only the bottom {} are drawn",
+                layers.len(),
+                MAX_LAYERS
+            );
+            layers.truncate(MAX_LAYERS);
+        }
+
+        // Under a memory budget, sources are downscaled until the atlas fits what's left
+        let usage = self.memory_usage();
+        let available = self
+            .memory_budget
+            .map(|budget| budget.saturating_sub(usage.total() - usage.atlas));
+        let mut factor = 1;
+        let layout = loop {
+            let sizes: Vec<(u32, u32)> = layers
+                .iter()
+                .filter_map(|(_, source)| {
+                    source
+                        .as_ref()
+                        .map(|(_, width, height)| scaled_size(*width, *height, factor))
+                })
+                .collect();
+            let layout = atlas::pack(&sizes, self.device.limits().max_texture_dimension_2d);
+            let bytes = layout.width as u64 * layout.height as u64 * 4;
+            if available.is_none_or(|available| bytes <= available) || factor >= MAX_ATLAS_FACTOR {
+                break layout;
+            }
+            factor *= 2;
+        };
+        if factor > 1 {
+            eprintln!(
+                "Scene layers drawn at 1/{} resolution to fit the GPU memory budget",
+                factor
+            );
+        }
+        self.atlas_factor = factor;
+        self.atlas = self.create_atlas(layout.width, layout.height);
+        self.webcam_origin = None;
+        self.held_captures.clear();
+
+        let mut uniform_values = vec![0.0f32; MAX_LAYERS * LAYER_FLOATS];
+        let mut positions = layout.positions.into_iter();
+        let mut count = 0;
+        for (layer, source) in &layers {
+            // (u, v, width, height) of the source and whether it lives in the atlas
+            let (uv_rect, in_atlas, aspect) = match source {
+                None => ([0.0, 0.0, 1.0, 1.0], 0.0, 1.0),
+                Some((pixels, source_width, source_height)) => {
+                    let Some((x, y)) = positions.next().flatten() else {
+                        eprintln!("Skipping layer {}: too large for the GPU", layer.name);
+                        continue;
+                    };
+                    let (width, height) = scaled_size(*source_width, *source_height, factor);
+                    match pixels {
+                        Some(pixels) if factor > 1 => {
+                            let (pixels, ..) =
+                                downscale_rgba(pixels, *source_width, *source_height, factor);
+                            self.write_atlas(&pixels, x, y, width, height);
+                        }
+                        Some(pixels) => self.write_atlas(pixels, x, y, width, height),
+                        None if layer.source == LayerSource::Capture => {
+                            self.held_captures.push(HeldCapture {
+                                origin: (x, y),
+                                source_size: (*source_width, *source_height),
+                                interval: Duration::from_secs(1) / layer.fps.unwrap_or(1).max(1),
+                                refreshed: None,
+                            })
+                        }
+                        None => self.webcam_origin = Some((x, y)),
+                    }
+                    // Inset by half a texel so filtering never samples a neighbour
+                    let (atlas_width, atlas_height) = (layout.width as f32, layout.height as f32);
+                    let uv_rect = [
+                        (x as f32 + 0.5) / atlas_width,
+                        (y as f32 + 0.5) / atlas_height,
+                        (width as f32 - 1.0) / atlas_width,
+                        (height as f32 - 1.0) / atlas_height,
+                    ];
+                    (uv_rect, 1.0, width as f32 / height as f32)
+                }
+            };
+
+            let t = layer.transform;
+            let mut width = t.width;
+            if let LayerSource::Text(_) = layer.source {
+                // Text keeps its aspect ratio: the layer height sets the scale and the
+                // width follows from the rendered text and the output's aspect ratio
+                width = t.height * aspect * self.size.height as f32 / self.size.width.max(1) as f32;
+            }
+
+            let values = [t.x, t.y, width, t.height]
+                .into_iter()
+                .chain(uv_rect)
+                .chain([t.opacity, in_atlas, self.sharpen, 0.0]);
+            for (slot, value) in uniform_values[count * LAYER_FLOATS..]
+                .iter_mut()
+                .zip(values)
+            {
+                *slot = value;
+            }
+            count += 1;
+        }
+
+        let uniform_bytes: Vec<u8> = uniform_values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        self.queue
+            .write_buffer(&self.layer_uniform, 0, &uniform_bytes);
+        self.layer_count = count as u32;
+        // One bind group for the whole scene, rebuilt only when the atlas is
+        self.layer_bind_group = self.create_layer_bind_group();
+    }
+
+    /// Show the capture at whole-number scales (1x, 2x, ... or 1/2, 1/3, ... when it's larger
+    /// than the window) with nearest sampling, letterboxing the rest, for pixel-perfect text
+    /// Only applies while the output shows the capture alone (no scene layers)
+    pub fn set_integer_scaling(&mut self, enabled: bool) {
+        self.integer_scaling = enabled;
+    }
+
+    /// Show the capture at its own aspect ratio, centered with black bars, instead of
+    /// stretching it to the window; only while the output shows the capture alone
+    pub fn set_letterbox(&mut self, enabled: bool) {
+        self.letterbox = enabled;
+    }
+
+    /// Where the capture is drawn in the window: (x, y, width, height) in physical pixels
+    pub fn capture_rect(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = (self.size.width, self.size.height);
+        self.capture_viewport(width, height)
+            .unwrap_or((0, 0, width, height))
+    }
+
+    /// Where the capture goes in a `width`x`height` output under integer scaling or
+    /// letterboxing: the centered (x, y, width, height) rectangle, or None when it fills the
+    /// output
+    fn capture_viewport(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        if !(self.integer_scaling || self.letterbox) || self.layer_count > 0 {
+            return None;
+        }
+        let (texture_width, texture_height) = (self.texture.width(), self.texture.height());
+        let fit = (width / texture_width).min(height / texture_height);
+        let (scaled_width, scaled_height) = if !self.integer_scaling {
+            let scale =
+                (width as f64 / texture_width as f64).min(height as f64 / texture_height as f64);
+            (
+                ((texture_width as f64 * scale).round() as u32).max(1),
+                ((texture_height as f64 * scale).round() as u32).max(1),
+            )
+        } else if fit >= 1 {
+            (texture_width * fit, texture_height * fit)
+        } else {
+            // Larger than the output: the smallest whole-number divisor that fits
+            let divisor = texture_width
+                .div_ceil(width.max(1))
+                .max(texture_height.div_ceil(height.max(1)));
+            (
+                (texture_width / divisor).max(1),
+                (texture_height / divisor).max(1),
+            )
+        };
+        Some((
+            (width.saturating_sub(scaled_width)) / 2,
+            (height.saturating_sub(scaled_height)) / 2,
+            scaled_width.min(width),
+            scaled_height.min(height),
+        ))
+    }
+
+    /// Sharpen the capture (not overlays) with the given strength, 0 = off, up to 1
+    pub fn set_sharpen(&mut self, strength: f32) {
+        self.sharpen = strength.clamp(0.0, 1.0);
+        let values = [self.sharpen, 0.0, 0.0, 0.0];
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        self.queue.write_buffer(&self.sharpen_uniform, 0, &bytes);
+        // Scene layers carry the strength in their uniforms
+        let scene = std::mem::take(&mut self.scene);
+        self.set_scene(&scene);
+    }
+
+    /// Upload a camera frame for the webcam layers
+    pub fn update_webcam(&mut self, rgba: &[u8], width: u32, height: u32) {
+        if self.webcam_size != Some((width, height)) {
+            // First frame or a new camera resolution: the atlas needs a region this size
+            self.webcam_size = Some((width, height));
+            let scene = std::mem::take(&mut self.scene);
+            self.set_scene(&scene);
+        }
+        if let Some((x, y)) = self.webcam_origin {
+            let atlas = self.atlas.clone();
+            if self.atlas_factor > 1 {
+                let (rgba, width, height) = downscale_rgba(rgba, width, height, self.atlas_factor);
+                self.upload(&atlas, (x, y), &rgba, width * 4, width, height);
+            } else {
+                self.upload(&atlas, (x, y), rgba, width * 4, width, height);
+            }
+        }
+    }
+
+    /// Texture shared by every non-capture layer (zero-initialized, i.e. transparent)
+    fn create_atlas(&self, width: u32, height: u32) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb, // Same as the capture texture
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Scene Layer Atlas"),
+            view_formats: &[],
+        })
+    }
+
+    /// Copy RGBA pixels into the atlas region at (x, y)
+    fn write_atlas(&self, rgba: &[u8], x: u32, y: u32, width: u32, height: u32) {
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn create_layer_bind_group(&self) -> wgpu::BindGroup {
+        let capture_view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let atlas_view = self
+            .atlas
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layer_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&capture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.layer_uniform.as_entire_binding(),
+                },
+            ],
+            label: Some("layer_bind_group"),
+        })
+    }
+
+    /// Whether the GPU device was lost; the renderer must then be recreated with `new`
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::SeqCst)
+    }
+
+    /// List the GPUs the renderer can use (name, backend, device type, ...)
+    pub fn enumerate_adapters() -> Vec<wgpu::AdapterInfo> {
+        Self::enumerate_adapters_with_limits()
+            .into_iter()
+            .map(|(info, _)| info)
+            .collect()
+    }
+
+    /// Like `enumerate_adapters`, with each adapter's limits (max texture size, ...)
+    pub fn enumerate_adapters_with_limits() -> Vec<(wgpu::AdapterInfo, wgpu::Limits)> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: BACKENDS,
+            ..Default::default()
+        });
+        instance
+            .enumerate_adapters(BACKENDS)
+            .iter()
+            .map(|adapter| (adapter.get_info(), adapter.limits()))
+            .collect()
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -262,32 +1348,311 @@ impl GpuRenderer {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+
+            // Text layer widths depend on the output's aspect ratio
+            if self
+                .scene
+                .layers()
+                .iter()
+                .any(|layer| matches!(layer.source, LayerSource::Text(_)))
+            {
+                let scene = std::mem::take(&mut self.scene);
+                self.set_scene(&scene);
+            }
+            // Effect and float targets follow the window size
+            self.enforce_memory_budget();
+        }
+    }
+
+    /// GPU memory held for textures and staging, by purpose
+    /// Effect and float targets are counted at the window size they're drawn at
+    pub fn memory_usage(&self) -> GpuMemoryUsage {
+        let texel = texel_size(self.texture.format()) as u64;
+        let output = self.size.width as u64 * self.size.height as u64;
+        let cards = [&self.overlay, &self.hud]
+            .into_iter()
+            .flatten()
+            .map(|(_, width, height)| *width as u64 * *height as u64 * 4)
+            .sum();
+        let uploaded = self
+            .downscale
+            .as_ref()
+            .map_or(&self.texture, DownscalePass::source);
+        let full_resolution = if self.downscale.is_some() {
+            uploaded.width() as u64 * uploaded.height() as u64 * texel
+        } else {
+            0
+        };
+        GpuMemoryUsage {
+            capture: self.texture.width() as u64 * self.texture.height() as u64 * texel
+                + full_resolution,
+            staging: padded_upload_size(uploaded.width(), uploaded.height(), texel as u32),
+            float_target: if self.tone_map.is_some() {
+                output * SCENE_TEXEL_SIZE as u64
+            } else {
+                0
+            },
+            atlas: self.atlas.width() as u64 * self.atlas.height() as u64 * 4,
+            effects: if self.effects.is_some() {
+                2 * output * texel_size(self.scene_format) as u64
+            } else {
+                0
+            },
+            cards,
         }
     }
 
+    /// Give memory back until the renderer fits its budget: effects are turned off first,
+    /// then scene layers are drawn from a lower resolution atlas
+    fn enforce_memory_budget(&mut self) {
+        let Some(budget) = self.memory_budget else {
+            return;
+        };
+        if self.memory_usage().total() <= budget {
+            return;
+        }
+        if self.effects.take().is_some() {
+            eprintln!(
+                "Effects turned off to stay within the GPU memory budget ({} MB)",
+                budget / MEGABYTE
+            );
+        }
+        if self.memory_usage().total() > budget && self.layer_count > 0 {
+            let scene = std::mem::take(&mut self.scene);
+            self.set_scene(&scene);
+        }
+    }
+
+    /// The GPU ran out of memory: lower the budget to three quarters of what's in use and
+    /// give back what that takes. False if nothing could be given back
+    pub fn reduce_memory(&mut self) -> bool {
+        let before = self.memory_usage().total();
+        let budget = before / 4 * 3;
+        self.memory_budget = Some(
+            self.memory_budget
+                .map_or(budget, |current| current.min(budget)),
+        );
+        self.enforce_memory_budget();
+        let after = self.memory_usage().total();
+        eprintln!(
+            "GPU out of memory: now using {} MB (was {} MB)",
+            after / MEGABYTE,
+            before / MEGABYTE
+        );
+        after < before
+    }
+
     pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
         self.size
     }
 
-    pub fn update_texture(&self, texture_data: &[u8]) {
-        self.queue.write_texture(
-            self.texture.as_image_copy(),
-            texture_data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(self.capture_width * 4),
-                rows_per_image: Some(self.capture_height),
+    /// Upload a capture frame (capture_width x capture_height RGBA, tightly packed)
+    pub fn update_texture(&mut self, texture_data: &[u8]) {
+        self.update_texture_with_stride(texture_data, self.capture_width * 4);
+    }
+
+    /// Upload a capture frame whose rows are `bytes_per_row` apart, which may be more than
+    /// capture_width*4 when the source pads its rows (e.g. CVPixelBuffers, mapped GPU buffers)
+    pub fn update_texture_with_stride(&mut self, texture_data: &[u8], bytes_per_row: u32) {
+        let texture = self.texture.clone();
+        let (width, height) = (texture.width(), texture.height());
+
+        if let Some(downscale) = self.downscale.take() {
+            let source = downscale.source();
+            self.upload(
+                source,
+                (0, 0),
+                texture_data,
+                bytes_per_row,
+                source.width(),
+                source.height(),
+            );
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Downscale Encoder"),
+                });
+            downscale.encode(
+                &mut encoder,
+                &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+            self.downscale = Some(downscale);
+        } else if self.upload_factor > 1 {
+            let packed;
+            let texture_data = if bytes_per_row != self.capture_width * 4 {
+                packed = strip_row_padding(
+                    texture_data,
+                    self.capture_width,
+                    self.capture_height,
+                    bytes_per_row,
+                );
+                &packed
+            } else {
+                texture_data
+            };
+            let (downscaled, _, _) = downscale_rgba(
+                texture_data,
+                self.capture_width,
+                self.capture_height,
+                self.upload_factor,
+            );
+            self.upload(&texture, (0, 0), &downscaled, width * 4, width, height);
+        } else {
+            self.upload(&texture, (0, 0), texture_data, bytes_per_row, width, height);
+        }
+        self.refresh_held_captures(texture_data, bytes_per_row);
+    }
+
+    /// Copy the capture into the atlas for the capture layers whose `fps` cap is due
+    fn refresh_held_captures(&mut self, texture_data: &[u8], bytes_per_row: u32) {
+        let now = Instant::now();
+        let due = |held: &HeldCapture| {
+            held.refreshed
+                .is_none_or(|refreshed| now.duration_since(refreshed) >= held.interval)
+        };
+        if !self.held_captures.iter().any(due) {
+            return;
+        }
+        let size = (self.texture.width(), self.texture.height());
+        if self
+            .held_captures
+            .iter()
+            .any(|held| held.source_size != size)
+        {
+            // The capture changed size: the atlas needs regions this size
+            let scene = std::mem::take(&mut self.scene);
+            self.set_scene(&scene);
+        }
+
+        // Scaled to the capture texture, then like every other atlas source
+        let mut pixels = if bytes_per_row != self.capture_width * 4 {
+            Cow::Owned(strip_row_padding(
+                texture_data,
+                self.capture_width,
+                self.capture_height,
+                bytes_per_row,
+            ))
+        } else {
+            Cow::Borrowed(texture_data)
+        };
+        let (mut width, mut height) = (self.capture_width, self.capture_height);
+        for factor in [self.upload_factor, self.atlas_factor] {
+            if factor > 1 {
+                let downscaled;
+                (downscaled, width, height) = downscale_rgba(&pixels, width, height, factor);
+                pixels = Cow::Owned(downscaled);
+            }
+        }
+
+        let atlas = self.atlas.clone();
+        let origins: Vec<(u32, u32)> = self
+            .held_captures
+            .iter_mut()
+            .filter(|held| due(held))
+            .map(|held| {
+                held.refreshed = Some(now);
+                held.origin
+            })
+            .collect();
+        for origin in origins {
+            self.upload(&atlas, origin, &pixels, width * 4, width, height);
+        }
+    }
+
+    /// Copy RGBA pixels (rows `source_bytes_per_row` apart) into `texture` at `origin` through
+    /// the staging belt
+    ///
+    /// Rows are re-laid out to wgpu's 256-byte bytes_per_row alignment in the staging buffer
+    /// (and converted to linear half floats for an RGBA16F texture), then a buffer-to-texture
+    /// copy is submitted; the belt's buffers are reused once the GPU is done
+    fn upload(
+        &mut self,
+        texture: &wgpu::Texture,
+        origin: (u32, u32),
+        rgba: &[u8],
+        source_bytes_per_row: u32,
+        width: u32,
+        height: u32,
+    ) {
+        let row_bytes = width as usize * 4;
+        let source_bytes_per_row = source_bytes_per_row as usize;
+        let texel_size = texel_size(texture.format());
+        let padded_row_bytes = padded_bytes_per_row(width, texel_size);
+        let Some(size) = wgpu::BufferSize::new(padded_upload_size(width, height, texel_size))
+        else {
+            return;
+        };
+        // The last row needs no padding after it
+        let needed = source_bytes_per_row * (height as usize - 1) + row_bytes;
+        if source_bytes_per_row < row_bytes || rgba.len() < needed {
+            eprintln!(
+                "Skipping upload: {} bytes with {} bytes per row can't hold a {}x{} frame",
+                rgba.len(),
+                source_bytes_per_row,
+                width,
+                height
+            );
+            return;
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Upload Encoder"),
+            });
+        let alignment = wgpu::BufferSize::new(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64)
+            .expect("alignment is non-zero");
+        let slice = self.staging_belt.allocate(size, alignment, &self.device);
+        {
+            let mut staged = slice.get_mapped_range_mut();
+            for (source, destination) in rgba
+                .chunks(source_bytes_per_row)
+                .zip(staged.chunks_exact_mut(padded_row_bytes as usize))
+            {
+                if texture.format() == SCENE_FORMAT {
+                    write_scene_texels(&source[..row_bytes], destination);
+                } else {
+                    destination[..row_bytes].copy_from_slice(&source[..row_bytes]);
+                }
+            }
+        }
+        encoder.copy_buffer_to_texture(
+            wgpu::TexelCopyBufferInfo {
+                buffer: slice.buffer(),
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: slice.offset(),
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: origin.0,
+                    y: origin.1,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
             },
             wgpu::Extent3d {
-                width: self.capture_width,
-                height: self.capture_height,
+                width,
+                height,
                 depth_or_array_layers: 1,
             },
         );
+
+        self.staging_belt.finish();
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.staging_belt.recall();
     }
 
     /// Renders one frame to the screen
+    /// Offscreen renderers have nothing to present and draw only through `render_to_texture`
     ///
     /// THE RENDERING PROCESS:
     /// 1. Get the next frame buffer from the surface (where pixels will go)
@@ -298,9 +1663,12 @@ impl GpuRenderer {
     /// 6. Submit commands to GPU
     /// 7. Present frame to screen
     pub fn render(&self) -> Result<(), wgpu::SurfaceError> {
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
         // STEP 1: Get the next frame buffer to draw into
         // This is the actual memory where our pixels will go
-        let output = self.surface.get_current_texture()?;
+        let output = surface.get_current_texture()?;
 
         // Create a view of the frame buffer for rendering
         // Views define how we want to interpret the texture data
@@ -308,6 +1676,199 @@ impl GpuRenderer {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        // STEP 2-5: Record the drawing commands into the frame buffer (with any overlay card)
+        let encoder =
+            self.encode_render_pass(&view, output.texture.width(), output.texture.height(), true);
+
+        // STEP 6: Submit commands to GPU for execution
+        // All the commands we recorded are sent to GPU as a batch
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // STEP 7: Present the frame to the screen
+        // This makes our rendered pixels visible in the window
+        output.present();
+
+        Ok(())
+    }
+
+    /// Compile post-processing effects run over every rendered frame, replacing any loaded
+    /// before; effects that fail to compile are skipped
+    pub fn set_effects(&mut self, effects: &[Effect]) {
+        if effects.is_empty() {
+            self.effects = None;
+            return;
+        }
+        let chain = EffectChain::new(&self.device, self.scene_format, effects);
+        self.effects = (!chain.is_empty()).then_some(chain);
+        self.enforce_memory_budget();
+    }
+
+    /// Show `card` (tightly packed RGBA, width, height) over the output in the window, or
+    /// remove it with None
+    pub fn set_overlay(&mut self, card: Option<(&[u8], u32, u32)>) {
+        self.overlay = card.map(|(rgba, width, height)| {
+            (self.card_bind_group(rgba, width, height), width, height)
+        });
+    }
+
+    /// Show the stats HUD (tightly packed RGBA, width, height) in the window's top left
+    /// corner, or remove it with None
+    pub fn set_hud(&mut self, hud: Option<(&[u8], u32, u32)>) {
+        self.hud = hud.map(|(rgba, width, height)| {
+            (self.card_bind_group(rgba, width, height), width, height)
+        });
+    }
+
+    /// Upload a card drawn over the output and bind it for the capture pipeline
+    fn card_bind_group(&self, rgba: &[u8], width: u32, height: u32) -> wgpu::BindGroup {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Overlay Texture"),
+            view_formats: &[],
+        });
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        // Zero strength: the card is drawn with the capture pipeline but never sharpened
+        let uniform = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Uniforms"),
+            size: 4 * std::mem::size_of::<f32>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: true,
+        });
+        uniform.unmap();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform.as_entire_binding(),
+                },
+            ],
+            label: Some("overlay_bind_group"),
+        })
+    }
+
+    /// Where the overlay card is drawn in the window: (x, y, width, height) in pixels,
+    /// centered and shrunk to fit small windows
+    pub fn overlay_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let (_, width, height) = self.overlay.as_ref()?;
+        let (window_width, window_height) = (self.size.width as f32, self.size.height as f32);
+        let fit = (window_width / *width as f32)
+            .min(window_height / *height as f32)
+            .min(1.0);
+        let (card_width, card_height) = (*width as f32 * fit, *height as f32 * fit);
+        Some((
+            (window_width - card_width) / 2.0,
+            (window_height - card_height) / 2.0,
+            card_width,
+            card_height,
+        ))
+    }
+
+    /// Where the stats HUD is drawn in the window: the top left corner, shrunk to fit small windows
+    fn hud_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let (_, width, height) = self.hud.as_ref()?;
+        let (window_width, window_height) = (self.size.width as f32, self.size.height as f32);
+        let fit = (window_width / *width as f32)
+            .min(window_height / *height as f32)
+            .min(1.0);
+        Some((0.0, 0.0, *width as f32 * fit, *height as f32 * fit))
+    }
+
+    /// Draw the stats HUD and the overlay card over whatever `view` already holds
+    fn encode_overlay(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        if let (Some((bind_group, _, _)), Some(rect)) = (&self.hud, self.hud_rect()) {
+            self.encode_card(encoder, view, bind_group, rect);
+        }
+        if let (Some((bind_group, _, _)), Some(rect)) = (&self.overlay, self.overlay_rect()) {
+            self.encode_card(encoder, view, bind_group, rect);
+        }
+    }
+
+    /// Draw one card into the (x, y, width, height) pixel rectangle of `view`
+    fn encode_card(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+        (x, y, card_width, card_height): (f32, f32, f32, f32),
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load, // Keep the mirror underneath
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.card_pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.set_viewport(x, y, card_width, card_height, 0.0, 1.0);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Records the full mirror render pass (clear + fullscreen textured triangle) into `view`,
+    /// a `width`x`height` target, followed by any effects, the overlay card (window only) and
+    /// the float pipeline's tone map
+    /// Shared by on-screen and offscreen rendering so both produce identical output
+    fn encode_render_pass(
+        &self,
+        view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+        overlay: bool,
+    ) -> wgpu::CommandEncoder {
         // STEP 2: Create command encoder - this records GPU commands
         // Instead of executing immediately, we record commands and submit them all at once
         // This is more efficient and allows the GPU to optimize execution
@@ -317,6 +1878,33 @@ impl GpuRenderer {
                 label: Some("Render Encoder"),
             });
 
+        // The float pipeline draws everything into an RGBA16F target that is tone-mapped to
+        // `view` at the end
+        let float_view = self
+            .tone_map
+            .as_ref()
+            .map(|tone_map| tone_map.input_view(&self.device, width, height));
+        let target = float_view.as_ref().unwrap_or(view);
+
+        // With effects, the scene goes to an offscreen texture the first effect reads
+        let scene_view = self
+            .effects
+            .as_ref()
+            .map(|effects| effects.input_view(&self.device, width, height));
+
+        let capture_viewport = self.capture_viewport(width, height);
+        // Integer scaling and letterboxing fill the bars with black rather than the usual
+        // background
+        let clear = match capture_viewport {
+            Some(_) => wgpu::Color::BLACK,
+            None => wgpu::Color {
+                r: 0.1, // Dark red
+                g: 0.2, // Dark green
+                b: 0.3, // Dark blue
+                a: 1.0, // Fully opaque
+            },
+        };
+
         // STEP 3: Begin render pass - the actual drawing phase
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -324,18 +1912,13 @@ impl GpuRenderer {
 
                 // Color attachments: Where we draw pixels (the screen)
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view, // Draw to our frame buffer
+                    view: scene_view.as_ref().unwrap_or(target), // Draw to the target frame buffer
                     depth_slice: None,
                     resolve_target: None, // No multisampling, so no resolve needed
                     ops: wgpu::Operations {
-                        // Clear the screen to dark blue before drawing
+                        // Clear the screen to dark blue (black when the capture has bars) before drawing
                         // This ensures we start with a known background color
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1, // Dark red
-                            g: 0.2, // Dark green
-                            b: 0.3, // Dark blue
-                            a: 1.0, // Fully opaque
-                        }),
+                        load: wgpu::LoadOp::Clear(clear),
                         store: wgpu::StoreOp::Store, // Save the results to memory
                     },
                 })],
@@ -346,29 +1929,236 @@ impl GpuRenderer {
                 timestamp_writes: None,    // Not measuring GPU timing
             });
 
-            // STEP 4: Set up the render pass for drawing
-            render_pass.set_pipeline(&self.render_pipeline); // Use our screen mirror pipeline
-            render_pass.set_bind_group(0, &self.bind_group, &[]); // Bind texture+sampler
+            if self.layer_count == 0 {
+                // STEP 4: Set up the render pass for drawing
+                render_pass.set_pipeline(&self.render_pipeline); // Use our screen mirror pipeline
+                render_pass.set_bind_group(0, &self.bind_group, &[]); // Bind texture+sampler
+
+                // Integer scaling and letterboxing draw into the centered rectangle only
+                if let Some((x, y, viewport_width, viewport_height)) = capture_viewport {
+                    render_pass.set_viewport(
+                        x as f32,
+                        y as f32,
+                        viewport_width as f32,
+                        viewport_height as f32,
+                        0.0,
+                        1.0,
+                    );
+                }
+                // Whole-number scales sample nearest so pixels stay sharp
+                if self.integer_scaling {
+                    render_pass.set_bind_group(0, &self.nearest_bind_group, &[]);
+                }
 
-            // STEP 5: Draw the geometry
-            // draw(vertices, instances) - we draw 3 vertices (1 large triangle), 1 instance
-            // The vertex shader generates positions for a fullscreen triangle
-            // Single triangle covers entire screen (fullscreen triangle trick)
-            render_pass.draw(0..3, 0..1);
+                // STEP 5: Draw the geometry
+                // draw(vertices, instances) - we draw 3 vertices (1 large triangle), 1 instance
+                // The vertex shader generates positions for a fullscreen triangle
+                // Single triangle covers entire screen (fullscreen triangle trick)
+                render_pass.draw(0..3, 0..1);
+            } else {
+                // Scene: one rectangle (6 vertices) per layer as instances, bottom layer first
+                // (instances are blended in order)
+                render_pass.set_pipeline(&self.layer_pipeline);
+                render_pass.set_bind_group(0, &self.layer_bind_group, &[]);
+                render_pass.draw(0..6, 0..self.layer_count);
+            }
         } // render_pass is automatically ended here
 
-        // STEP 6: Submit commands to GPU for execution
-        // All the commands we recorded are sent to GPU as a batch
+        if let Some(effects) = &self.effects {
+            effects.encode(&self.queue, &mut encoder, target);
+        }
+        if overlay {
+            self.encode_overlay(&mut encoder, target, width, height);
+        }
+        if let Some(tone_map) = &self.tone_map {
+            tone_map.encode(&mut encoder, view);
+        }
+
+        encoder
+    }
+
+    /// Renders one frame into a new offscreen texture instead of the window surface
+    /// The texture uses the surface format, so the output matches what the window shows
+    pub fn render_to_texture(&self, width: u32, height: u32) -> wgpu::Texture {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format, // What the last pass (scene, effect or tone map) writes
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            // RENDER_ATTACHMENT: We draw into it like the surface
+            // COPY_SRC: We can copy its pixels into a buffer for readback
+            label: Some("Offscreen Render Texture"),
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let encoder = self.encode_render_pass(&view, texture.width(), texture.height(), false);
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        // STEP 7: Present the frame to the screen
-        // This makes our rendered pixels visible in the window
-        output.present();
+        texture
+    }
 
-        Ok(())
+    /// Copies a rendered texture back to the CPU as tightly packed RGBA bytes (width*height*4)
+    pub async fn read_texture_rgba(&self, texture: &wgpu::Texture) -> Result<Vec<u8>, String> {
+        let buffer = self.create_readback_buffer(texture.width(), texture.height());
+        let readback = self.start_readback(texture, buffer);
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| format!("Failed to wait for readback: {:?}", e))?;
+        readback
+            .mapped
+            .recv()
+            .map_err(|_| "Readback buffer was never mapped".to_string())?
+            .map_err(|e| format!("Failed to map readback buffer: {:?}", e))?;
+        Ok(Self::finish_readback(&readback))
+    }
+
+    /// Render the output offscreen and start reading it back without waiting for the GPU;
+    /// `take_output_readback` collects it on a later frame. While `MAX_READBACKS` are still
+    /// in flight this frame is skipped rather than stalling the render loop
+    pub fn start_output_readback(&mut self) {
+        if self.readbacks.len() >= MAX_READBACKS {
+            return;
+        }
+        let texture = self.render_to_texture(self.size.width, self.size.height);
+        let size = padded_upload_size(texture.width(), texture.height(), 4);
+        // Buffers from before a resize no longer fit
+        self.free_readback_buffers
+            .retain(|buffer| buffer.size() == size);
+        let buffer = self
+            .free_readback_buffers
+            .pop()
+            .unwrap_or_else(|| self.create_readback_buffer(texture.width(), texture.height()));
+        let readback = self.start_readback(&texture, buffer);
+        self.readbacks.push_back(readback);
+    }
+
+    /// The oldest output readback, if the GPU has finished it; never waits
+    pub fn take_output_readback(&mut self) -> Option<Result<RgbaImage, String>> {
+        // Runs the map callbacks of copies the GPU has finished
+        let _ = self.device.poll(wgpu::PollType::Poll);
+        let mapped = match self.readbacks.front()?.mapped.try_recv() {
+            Ok(mapped) => mapped,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return None,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err(wgpu::BufferAsyncError),
+        };
+        let readback = self.readbacks.pop_front()?;
+        if let Err(e) = mapped {
+            return Some(Err(format!("Failed to map readback buffer: {:?}", e)));
+        }
+        let rgba = Self::finish_readback(&readback);
+        self.free_readback_buffers.push(readback.buffer);
+        Some(Ok((rgba, readback.width, readback.height)))
+    }
+
+    /// Whether output readbacks are still in flight or waiting to be taken
+    pub fn has_output_readbacks(&self) -> bool {
+        !self.readbacks.is_empty()
+    }
+
+    fn create_readback_buffer(&self, width: u32, height: u32) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: padded_upload_size(width, height, 4),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Copy `texture` into `buffer` and ask for the buffer to be mapped once that's done; on
+    /// native backends the map callback fires while polling the device
+    fn start_readback(&self, texture: &wgpu::Texture, buffer: wgpu::Buffer) -> Readback {
+        let (width, height) = (texture.width(), texture.height());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    // Buffer rows must be aligned to 256 bytes for texture-to-buffer copies
+                    bytes_per_row: Some(padded_bytes_per_row(width, 4)),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, mapped) = std::sync::mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        Readback {
+            buffer,
+            width,
+            height,
+            format: texture.format(),
+            mapped,
+        }
+    }
+
+    /// Tightly packed RGBA from a mapped readback, which is unmapped for reuse
+    fn finish_readback(readback: &Readback) -> Vec<u8> {
+        // Strip row padding and convert BGRA surface formats to RGBA
+        let swap_red_blue = matches!(
+            readback.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let mut rgba = strip_row_padding(
+            &readback.buffer.slice(..).get_mapped_range(),
+            readback.width,
+            readback.height,
+            padded_bytes_per_row(readback.width, 4),
+        );
+        readback.buffer.unmap();
+
+        if swap_red_blue {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        rgba
     }
 
     pub fn create_test_pattern(&self) -> Vec<u8> {
         vec![64u8; (self.capture_width * self.capture_height * 4) as usize] // Dark gray fallback
     }
 }
+
+/// Size of a `width`x`height` image downscaled by `factor` (as `downscale_rgba` does)
+fn scaled_size(width: u32, height: u32, factor: u32) -> (u32, u32) {
+    ((width / factor).max(1), (height / factor).max(1))
+}
+
+/// Bytes per pixel of the textures frames are uploaded to (8-bit RGBA or RGBA16F)
+fn texel_size(format: wgpu::TextureFormat) -> u32 {
+    if format == SCENE_FORMAT {
+        SCENE_TEXEL_SIZE
+    } else {
+        4
+    }
+}
+
+/// Bytes per row of an image with `texel_size`-byte pixels, padded to wgpu's buffer copy
+/// alignment (256)
+fn padded_bytes_per_row(width: u32, texel_size: u32) -> u32 {
+    (width * texel_size).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Buffer space for a width x height image with padded rows
+fn padded_upload_size(width: u32, height: u32, texel_size: u32) -> u64 {
+    padded_bytes_per_row(width, texel_size) as u64 * height as u64
+}
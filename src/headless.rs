@@ -0,0 +1,335 @@
+use crate::blanking::BlankReason;
+use crate::cli::{RecordOptions, ScreenshotCommand};
+use crate::config::Config;
+use crate::cross_platform_capture::CrossPlatformScreenCapture;
+use crate::effects::load_effects;
+use crate::encoding::VideoCodec;
+use crate::frame_analysis::{ContentChangeTracker, frame_hash};
+use crate::frame_bus::{ProcessedFrame, ProcessedFrameBus};
+use crate::gpu_renderer::GpuRenderer;
+use crate::idle::IdleWatcher;
+use crate::platform::{CaptureEvent, CaptureSource};
+use crate::power::{self, BATTERY_FRAME_RATE};
+use crate::private_spaces::PrivateSpaceWatcher;
+use crate::redaction::RedactionStage;
+use crate::screenshot::{ScreenshotFormat, default_screenshot_path, save_screenshot};
+use crate::sinks::{SinkManager, recording::RecordingSink};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long to wait before polling the capture again when no new frame had arrived
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+/// How long `screenshot` waits for the capture's first frame
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Capture and redaction without a window: the part of the mirror's pipeline the headless
+/// commands share
+pub(crate) struct HeadlessPipeline {
+    stage: RedactionStage,
+    private_spaces: PrivateSpaceWatcher,
+    /// Blanks the output while nobody uses the machine (`idle_pause_minutes`)
+    idle: Option<IdleWatcher>,
+    /// Set from the capture thread when the stream ends on its own
+    stopped: Arc<Mutex<Option<String>>>,
+}
+
+impl HeadlessPipeline {
+    /// Set up capture of `display` (an ID from list-displays; None = the main display) with the
+    /// config's redaction rules; capture starts with `start`
+    pub(crate) fn new(display: Option<u32>, config: &Config) -> Result<Self, String> {
+        let mut screen_capture =
+            CrossPlatformScreenCapture::with_backend(config.capture_backend.as_deref())?;
+        if let Some(display) = display {
+            if !screen_capture
+                .list_displays()?
+                .iter()
+                .any(|info| info.id == display)
+            {
+                return Err(format!(
+                    "No display with ID {} (see cloakshare list-displays)",
+                    display
+                ));
+            }
+            let source = CaptureSource::Display(display);
+            config.policy.check_source(&source, None)?;
+            screen_capture.set_source(source);
+        }
+        config.policy.check_default_display(&screen_capture)?;
+
+        let resolution = screen_capture.get_display_resolution()?;
+        let (width, height) = resolution.capture_size(config.capture_scale);
+        screen_capture.set_capture_scale(config.capture_scale);
+        screen_capture.set_frame_policy(config.capture_frame_policy);
+        let mut frame_rate = config.frame_rate.map(|fps| fps.max(1));
+        if config.low_power_on_battery && power::on_battery() {
+            println!("On battery: capturing at most {} fps", BATTERY_FRAME_RATE);
+            frame_rate =
+                Some(frame_rate.map_or(BATTERY_FRAME_RATE, |fps| fps.min(BATTERY_FRAME_RATE)));
+        }
+        if let Some(fps) = frame_rate {
+            screen_capture.set_frame_rate(fps);
+        }
+
+        // The stream can end on its own (display unplugged, permission revoked)
+        let stopped = Arc::new(Mutex::new(None));
+        let on_stopped = stopped.clone();
+        screen_capture.set_event_callback(Arc::new(move |event| {
+            if let CaptureEvent::Stopped(error) = event
+                && let Ok(mut stopped) = on_stopped.lock()
+            {
+                *stopped = Some(error);
+            }
+        }));
+
+        let mut stage = RedactionStage::new(screen_capture, width, height);
+        stage.redactor_mut().set_fill(config.mask_fill);
+        stage.redactor_mut().set_rules(config.redactions.clone());
+        Ok(Self {
+            stage,
+            private_spaces: PrivateSpaceWatcher::new(config.private_spaces.iter().copied()),
+            // Polled continuously below, so the watcher needn't wake anything
+            idle: config
+                .idle_pause_after()
+                .map(|after| IdleWatcher::new(after, || {})),
+            stopped,
+        })
+    }
+
+    pub(crate) fn start(&mut self) -> Result<(), String> {
+        self.stage.screen_capture_mut().start_capture(None)
+    }
+
+    /// Redacted frames, for outputs
+    pub(crate) fn bus(&self) -> &ProcessedFrameBus {
+        self.stage.bus()
+    }
+
+    pub(crate) fn output_size(&self) -> (u32, u32) {
+        self.stage.output_size()
+    }
+
+    /// Redact and publish the newest frame, if any; true if one was published
+    pub(crate) fn poll(&mut self) -> Result<bool, String> {
+        // Blank private Spaces before their frames are processed, as the mirror does
+        let display_ids = self.stage.screen_capture().captured_display_ids();
+        let private = self.private_spaces.poll(&display_ids);
+        self.stage
+            .blanking_mut()
+            .set(BlankReason::PrivateSpace, private);
+        let idle = self.idle.as_ref().is_some_and(IdleWatcher::is_idle);
+        self.stage.blanking_mut().set(BlankReason::Idle, idle);
+
+        let published = self.stage.process();
+        if let Some(error) = self
+            .stopped
+            .lock()
+            .ok()
+            .and_then(|mut stopped| stopped.take())
+        {
+            return Err(format!("Capture stopped: {}", error));
+        }
+        if !published {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        Ok(published)
+    }
+
+    /// Stop capturing and write the compliance log if one is configured
+    pub(crate) fn finish(mut self, config: &Config) {
+        self.stage.screen_capture_mut().stop_capture();
+        if let Some(path) = &config.compliance_log {
+            match self.stage.compliance().export(path) {
+                Ok(()) => println!("Compliance log written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write compliance log: {}", e),
+            }
+        }
+    }
+}
+
+/// `cloakshare record`: capture, redact and encode a display to a file for a fixed time,
+/// without a window. Redacted (and blanked) frames are drawn offscreen with the config's
+/// scene and effects, so the file shows what the mirror's output would
+pub fn record(options: &RecordOptions, config: &Config) -> Result<(), String> {
+    if !config.allow_recording {
+        return Err("Recording is disabled by your organization".to_string());
+    }
+    let codec = codec_for(&options.output, options.codec)?;
+    let mut pipeline = HeadlessPipeline::new(options.display, config)?;
+    let (width, height) = pipeline.output_size();
+
+    let mut renderer = pollster::block_on(GpuRenderer::new_offscreen(
+        width,
+        height,
+        &config.gpu_settings(),
+    ))
+    .map_err(|e| e.to_string())?;
+    renderer.set_scene(&config.scene());
+    if !config.low_latency {
+        renderer.set_effects(&load_effects(&config.effects_dir_path()));
+    }
+    let mut frames = pipeline.bus().subscribe();
+    let composite = ProcessedFrameBus::new();
+    let mut composite_changes = ContentChangeTracker::new();
+
+    let mut sinks = SinkManager::new(composite.clone());
+    sinks.set_static_after(Duration::from_secs(config.static_after_secs));
+    sinks.set_no_disk(config.no_disk);
+    let signing_key = config
+        .sign_recordings
+        .then(|| config.recording_signing_key_path());
+    let sink = RecordingSink::create(
+        options.output.clone(),
+        codec,
+        width,
+        height,
+        signing_key.as_deref(),
+    )?
+    .with_frame_policy(config.recording_frame_policy)
+    .with_redaction_log(config.recording_redaction_log);
+    let recording = sinks.attach(Box::new(sink))?;
+
+    pipeline.start()?;
+    println!(
+        "Recording {}x{} for {}s to {}",
+        width,
+        height,
+        options.duration.as_secs(),
+        options.output.display()
+    );
+
+    let started = Instant::now();
+    let mut result = Ok(());
+    while started.elapsed() < options.duration {
+        if let Err(e) = pipeline.poll() {
+            result = Err(e);
+            break;
+        }
+        // Rendered frames are collected on later iterations instead of waiting for the GPU
+        if let Some(frame) = frames.next_frame() {
+            renderer.update_texture(frame.data());
+            renderer.start_output_readback();
+        }
+        publish_rendered(&mut renderer, &composite, &mut composite_changes);
+        if renderer.is_device_lost() {
+            result = Err("GPU device lost while recording".to_string());
+            break;
+        }
+        if !sinks.is_running(recording) {
+            result = Err("The recording stopped early".to_string());
+            break;
+        }
+    }
+    // The last frames still on the GPU
+    while result.is_ok() && renderer.has_output_readbacks() && !renderer.is_device_lost() {
+        publish_rendered(&mut renderer, &composite, &mut composite_changes);
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    // Detaching finishes the file (container header, manifest)
+    sinks.detach_all();
+    pipeline.finish(config);
+    result?;
+    println!("Recording saved to {}", options.output.display());
+    Ok(())
+}
+
+/// Publish the frames `renderer` has finished reading back to `composite`
+fn publish_rendered(
+    renderer: &mut GpuRenderer,
+    composite: &ProcessedFrameBus,
+    changes: &mut ContentChangeTracker,
+) {
+    while let Some(readback) = renderer.take_output_readback() {
+        match readback {
+            Ok((rgba, width, height)) => {
+                let frame = ProcessedFrame::new(rgba, width, height);
+                let hash = frame_hash(frame.data(), frame.width());
+                if changes.observe(hash) {
+                    composite.mark_content_changed();
+                }
+                composite.publish(frame.with_content_hash(hash));
+            }
+            Err(e) => eprintln!("Failed to read back a rendered frame: {}", e),
+        }
+    }
+}
+
+/// `cloakshare screenshot`: save the first redacted frame of a display and return its path
+/// PNG or WebP by the file's extension, otherwise the configured screenshot format
+pub fn screenshot(options: &ScreenshotCommand, config: &Config) -> Result<PathBuf, String> {
+    if config.no_disk {
+        return Err("Screenshots are disabled in no-disk mode".to_string());
+    }
+    let mut screenshot_options = config.screenshot_options();
+    let path = match &options.output {
+        Some(path) => {
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(str::to_ascii_lowercase);
+            match extension.as_deref() {
+                Some("png") => screenshot_options.format = ScreenshotFormat::Png,
+                Some("webp") => screenshot_options.format = ScreenshotFormat::Webp,
+                _ => {}
+            }
+            path.clone()
+        }
+        None => default_screenshot_path(screenshot_options.format),
+    };
+
+    let mut pipeline = HeadlessPipeline::new(options.display, config)?;
+    let mut frames = pipeline.bus().subscribe();
+    pipeline.start()?;
+
+    let started = Instant::now();
+    let frame = loop {
+        if let Err(e) = pipeline.poll() {
+            pipeline.finish(config);
+            return Err(e);
+        }
+        if let Some(frame) = frames.next_frame() {
+            break frame;
+        }
+        if started.elapsed() >= FIRST_FRAME_TIMEOUT {
+            pipeline.finish(config);
+            return Err(crate::l10n::tr_args(
+                "permission-no-frame",
+                &[("seconds", &FIRST_FRAME_TIMEOUT.as_secs().to_string())],
+            ));
+        }
+    };
+    pipeline.finish(config);
+    save_screenshot(&frame, &path, &screenshot_options)?;
+    Ok(path)
+}
+
+/// The codec to record `path` with: `codec` if given, otherwise AV1 for .ivf files and H.264
+/// for anything else. H.264 goes into MP4 (.mp4, .m4v, .mov) or a raw .h264 stream, AV1 and
+/// VP9 into IVF; other containers are refused rather than written with the wrong contents
+fn codec_for(path: &Path, codec: Option<VideoCodec>) -> Result<VideoCodec, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let ivf = extension.as_deref() == Some("ivf");
+    if let Some(extension @ ("mkv" | "webm")) = extension.as_deref() {
+        return Err(format!(
+            ".{} files aren't supported; record to .mp4, .mov or .h264 (H.264) or .ivf \
+             (AV1, VP9)",
+            extension
+        ));
+    }
+    match codec {
+        None if ivf => Ok(VideoCodec::Av1),
+        None => Ok(VideoCodec::H264),
+        Some(VideoCodec::H264) if ivf => {
+            Err("H.264 is recorded to .mp4, .mov or .h264 files, not .ivf".to_string())
+        }
+        Some(codec @ (VideoCodec::Av1 | VideoCodec::Vp9)) if !ivf => {
+            Err(format!("{:?} recordings need an .ivf file", codec))
+        }
+        Some(codec) => Ok(codec),
+    }
+}
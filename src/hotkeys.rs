@@ -0,0 +1,109 @@
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::{Key, KeyCode, ModifiersState, PhysicalKey};
+
+/// Actions that can be triggered from the keyboard while the mirror window is focused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// Cmd+Shift+P: open the capture source picker
+    OpenSourcePicker,
+    /// Cmd+Shift+A: drag a rectangle over the display to capture just that area
+    SelectRegion,
+    /// Cmd+Shift+D: move the capture to the next connected display (wrapping around)
+    NextDisplay,
+    /// Cmd+Shift+F: toggle follow-cursor display switching
+    ToggleFollowCursor,
+    /// Cmd+Shift+S: save a screenshot of the mirror output
+    Screenshot,
+    /// Cmd+Shift+H: hold (freeze) the output on the current frame, or resume
+    TogglePause,
+    /// Cmd+Shift+N: switch to the next configured profile
+    NextProfile,
+    /// Cmd+Shift+G: show all displays in a live grid (click one to mirror it)
+    ToggleOverview,
+    /// Cmd+Shift+K: turn capture sharpening on or off
+    ToggleSharpen,
+    /// Cmd+Shift+I: show or hide the stats HUD (per-output bitrate, queue depth, ...)
+    ToggleStatsHud,
+    /// Cmd+Shift+M: mark a chapter in the running recordings
+    AddChapter,
+    /// Cmd+Shift+B: mark the Space on screen as private (never mirrored), or unmark it
+    ToggleSpacePrivate,
+    /// Cmd+Shift+R: stream to the next receiver found on the local network (stop after the last)
+    NextReceiver,
+    /// Cmd+Shift+1..9: show/hide the nth image layer of the scene (0-based index)
+    ToggleImageLayer(usize),
+}
+
+/// Maps key presses (plus current modifier state) to hotkey actions
+#[derive(Debug, Default)]
+pub struct HotkeyHandler {
+    modifiers: ModifiersState,
+}
+
+impl HotkeyHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track modifier keys (fed from WindowEvent::ModifiersChanged)
+    pub fn update_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// Resolve a key event to an action, ignoring releases and key repeat
+    pub fn action_for(&self, event: &KeyEvent) -> Option<HotkeyAction> {
+        if event.state != ElementState::Pressed || event.repeat {
+            return None;
+        }
+
+        let command = self.modifiers.super_key() || self.modifiers.control_key();
+        let shift = self.modifiers.shift_key();
+
+        // Digits are matched by physical key: with Shift held the logical key is the
+        // shifted symbol, which depends on the keyboard layout
+        if command
+            && shift
+            && let PhysicalKey::Code(code) = event.physical_key
+            && let Some(index) = digit_index(code)
+        {
+            return Some(HotkeyAction::ToggleImageLayer(index));
+        }
+
+        let Key::Character(ref text) = event.logical_key else {
+            return None;
+        };
+
+        match text.to_lowercase().as_str() {
+            "p" if command && shift => Some(HotkeyAction::OpenSourcePicker),
+            "a" if command && shift => Some(HotkeyAction::SelectRegion),
+            "d" if command && shift => Some(HotkeyAction::NextDisplay),
+            "f" if command && shift => Some(HotkeyAction::ToggleFollowCursor),
+            "s" if command && shift => Some(HotkeyAction::Screenshot),
+            "h" if command && shift => Some(HotkeyAction::TogglePause),
+            "n" if command && shift => Some(HotkeyAction::NextProfile),
+            "g" if command && shift => Some(HotkeyAction::ToggleOverview),
+            "r" if command && shift => Some(HotkeyAction::NextReceiver),
+            "k" if command && shift => Some(HotkeyAction::ToggleSharpen),
+            "i" if command && shift => Some(HotkeyAction::ToggleStatsHud),
+            "m" if command && shift => Some(HotkeyAction::AddChapter),
+            "b" if command && shift => Some(HotkeyAction::ToggleSpacePrivate),
+            _ => None,
+        }
+    }
+}
+
+/// 0-based index for the digit keys 1-9
+fn digit_index(code: KeyCode) -> Option<usize> {
+    let digits = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+    digits.iter().position(|&digit| digit == code)
+}
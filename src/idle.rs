@@ -0,0 +1,63 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often the watcher thread checks the time since the last input
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notices the user walking away: idle once there was no keyboard or mouse input for a while,
+/// active again at the next input
+/// A thread does the checking and wakes the mirror when the state flips, since an unattended
+/// (static) screen delivers no frames that would
+pub struct IdleWatcher {
+    idle: Arc<AtomicBool>,
+    /// Cleared on drop to end the thread
+    running: Arc<AtomicBool>,
+}
+
+impl IdleWatcher {
+    /// Idle after `after` without input; `wake` is called whenever that changes
+    pub fn new(after: Duration, wake: impl Fn() + Send + Sync + 'static) -> Self {
+        let idle = Arc::new(AtomicBool::new(false));
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_idle = idle.clone();
+        let thread_running = running.clone();
+        let spawned = std::thread::Builder::new()
+            .name("idle-watcher".to_string())
+            .spawn(move || {
+                while thread_running.load(Ordering::Acquire) {
+                    let now_idle = time_since_input().is_some_and(|elapsed| elapsed >= after);
+                    if thread_idle.swap(now_idle, Ordering::AcqRel) != now_idle {
+                        wake();
+                    }
+                    std::thread::sleep(CHECK_INTERVAL);
+                }
+            });
+        if let Err(e) = spawned {
+            eprintln!("Failed to start idle detection: {}", e);
+        }
+        Self { idle, running }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for IdleWatcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn time_since_input() -> Option<Duration> {
+    Some(crate::platform::macos_idle::time_since_input())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn time_since_input() -> Option<Duration> {
+    // GetLastInputInfo (Windows) and the X11/Wayland idle protocols aren't wired up yet, so
+    // other platforms never count as idle
+    None
+}
@@ -0,0 +1,172 @@
+use crate::config::Config;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The built-in English messages, also shown for anything a translation leaves out
+const ENGLISH: &str = include_str!("locales/en.ftl");
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// User-facing messages by ID: English with the chosen translation on top
+#[derive(Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// English plus `<language>.ftl` from `dir` (e.g. "pt-BR", falling back to "pt")
+    pub fn load(language: Option<&str>, dir: &Path) -> Self {
+        let mut messages = parse(ENGLISH);
+        let Some(language) = language else {
+            return Self { messages };
+        };
+        let mut candidates = vec![language.to_string()];
+        if let Some((base, _)) = language.split_once('-') {
+            candidates.push(base.to_string());
+        }
+        for candidate in candidates {
+            let path = dir.join(format!("{}.ftl", candidate));
+            match std::fs::read_to_string(&path) {
+                Ok(source) => {
+                    println!("Using translation {}", path.display());
+                    messages.extend(parse(&source));
+                    break;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => eprintln!("Failed to read translation {}: {}", path.display(), e),
+            }
+        }
+        Self { messages }
+    }
+
+    /// Message `id` with each `{ $name }` replaced by its value from `args`
+    /// Unknown IDs come back as the ID, so a typo shows up instead of an empty string
+    pub fn format(&self, id: &str, args: &[(&str, &str)]) -> String {
+        let Some(message) = self.messages.get(id) else {
+            return id.to_string();
+        };
+        let mut out = String::with_capacity(message.len());
+        let mut rest = message.as_str();
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}') else {
+                rest = &rest[start..];
+                break;
+            };
+            let placeable = rest[start + 1..start + end].trim();
+            match placeable
+                .strip_prefix('$')
+                .and_then(|name| args.iter().find(|(arg, _)| *arg == name))
+            {
+                Some((_, value)) => out.push_str(value),
+                // Left as written so a missing argument is visible
+                None => out.push_str(&rest[start..=start + end]),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Choose the language for this run: `language` from the config, otherwise the system's
+/// The first call wins; messages shown before it are in the system language
+pub fn init(config: &Config) {
+    let language = config
+        .language
+        .as_deref()
+        .and_then(normalize)
+        .or_else(system_language);
+    let _ = CATALOG.set(Catalog::load(
+        language.as_deref(),
+        &config.locales_dir_path(),
+    ));
+}
+
+/// Message `id` in the user's language
+pub fn tr(id: &str) -> String {
+    tr_args(id, &[])
+}
+
+/// Message `id` in the user's language, with `{ $name }` placeables filled from `args`
+pub fn tr_args(id: &str, args: &[(&str, &str)]) -> String {
+    CATALOG
+        .get_or_init(|| {
+            Catalog::load(
+                system_language().as_deref(),
+                &Config::default().locales_dir_path(),
+            )
+        })
+        .format(id, args)
+}
+
+/// Messages of an `.ftl` file: `id = text`, with indented lines continuing the text
+fn parse(source: &str) -> HashMap<String, String> {
+    fn finish(messages: &mut HashMap<String, String>, message: Option<(String, Vec<&str>)>) {
+        if let Some((id, lines)) = message {
+            let text = lines.join("\n");
+            messages.insert(id, text.trim_matches('\n').to_string());
+        }
+    }
+
+    let mut messages = HashMap::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    for line in source.lines() {
+        if line.starts_with([' ', '\t']) || line.trim().is_empty() {
+            if let Some((_, lines)) = &mut current {
+                lines.push(line.trim());
+            }
+            continue;
+        }
+        finish(&mut messages, current.take());
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((id, value)) = line.split_once('=') {
+            current = Some((id.trim().to_string(), vec![value.trim()]));
+        }
+    }
+    finish(&mut messages, current);
+    messages
+}
+
+/// "pt_BR.UTF-8" -> "pt-BR"; None for the "C"/"POSIX" locales, which name no language
+fn normalize(locale: &str) -> Option<String> {
+    let language = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace('_', "-");
+    (!language.is_empty() && language != "C" && language != "POSIX").then_some(language)
+}
+
+/// The locale environment (as set in a terminal), then the system's preferred language
+fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|var| normalize(&std::env::var(var).ok()?))
+        .or_else(preferred_language)
+}
+
+#[cfg(target_os = "macos")]
+fn preferred_language() -> Option<String> {
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send, msg_send_id};
+    use std::ffi::{CStr, c_char};
+
+    let language = unsafe {
+        let languages: Retained<AnyObject> = msg_send_id![class!(NSLocale), preferredLanguages];
+        let first: Option<Retained<AnyObject>> = msg_send_id![&*languages, firstObject];
+        let utf8: *const c_char = msg_send![&*first?, UTF8String];
+        (!utf8.is_null()).then(|| CStr::from_ptr(utf8).to_string_lossy().into_owned())
+    };
+    normalize(&language?)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn preferred_language() -> Option<String> {
+    None
+}
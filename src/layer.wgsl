@@ -0,0 +1,83 @@
+// Scene layer shader: draws one textured rectangle per layer, alpha blended in order
+//
+// Unlike shader.wgsl (one fullscreen triangle), each layer covers only the rectangle
+// given by its transform, so several sources can be arranged in one output.
+// All layers share one bind group: the capture texture, an atlas holding every other
+// source (images, colors, text, webcam), and a uniform array with one entry per layer.
+// Layer i is drawn as instance i.
+
+// Must match MAX_LAYERS in gpu_renderer.rs
+const MAX_LAYERS: u32 = 64u;
+
+struct Layer {
+    // x, y, width, height as fractions of the output (top-left origin)
+    rect: vec4<f32>,
+    // x, y, width, height of the source within its texture (0-1)
+    uv_rect: vec4<f32>,
+    // x: opacity (0-1); y: 0 = capture texture, 1 = atlas; z: capture sharpening (0 = off,
+    // up to 1; see sharpen.wgsl); w unused padding
+    params: vec4<f32>,
+}
+
+struct Layers {
+    layers: array<Layer, MAX_LAYERS>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) layer: u32,
+}
+
+@group(0) @binding(0)
+var t_capture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var t_atlas: texture_2d<f32>;
+
+@group(0) @binding(2)
+var s_layer: sampler;
+
+@group(0) @binding(3)
+var<uniform> scene: Layers;
+
+// Set when the window surface is premultiplied (see shader.wgsl); the pipeline then blends
+// premultiplied colors too
+override premultiply_output: bool = false;
+
+@vertex
+fn vs_layer(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    // Two triangles covering the unit square
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let layer = scene.layers[instance_index];
+
+    // Place the square in the output, then convert to NDC (y up)
+    let position = layer.rect.xy + corner * layer.rect.zw;
+    return VertexOutput(
+        vec4<f32>(position.x * 2.0 - 1.0, 1.0 - position.y * 2.0, 0.0, 1.0),
+        layer.uv_rect.xy + corner * layer.uv_rect.zw,
+        instance_index,
+    );
+}
+
+@fragment
+fn fs_layer(input: VertexOutput) -> @location(0) vec4<f32> {
+    let layer = scene.layers[input.layer];
+    // Sample both (sampling must stay in uniform control flow) and pick the layer's source
+    let capture = sharpen(t_capture, s_layer, input.tex_coords, layer.params.z);
+    let atlas = textureSample(t_atlas, s_layer, input.tex_coords);
+    let color = select(capture, atlas, layer.params.y > 0.5);
+    let alpha = color.a * layer.params.x;
+    return vec4<f32>(select(color.rgb, color.rgb * alpha, premultiply_output), alpha);
+}
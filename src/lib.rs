@@ -1,7 +1,65 @@
+pub mod atlas;
+pub mod blanking;
+pub mod cli;
+pub mod compliance;
+pub mod config;
+pub mod crash_report;
 pub mod cross_platform_capture;
+pub mod daemon;
+pub mod discovery;
+pub mod doctor;
+pub mod downscale;
+pub mod effects;
+pub mod encoding;
+pub mod follow_cursor;
+pub mod frame_analysis;
+pub mod frame_bus;
+pub mod frame_channel;
+pub mod frame_pacing;
+pub mod gpu_failure;
+pub mod gpu_memory;
 pub mod gpu_renderer;
+pub mod headless;
+pub mod hotkeys;
+pub mod idle;
+pub mod l10n;
+pub mod locked_memory;
+pub mod mirror_error;
+#[cfg(target_os = "macos")]
 pub mod pixel_conversion;
 pub mod platform;
 pub mod platform_detector;
+pub mod plugin;
+pub mod policy;
+pub mod power;
+pub mod private_spaces;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "receiver")]
+pub mod receiver;
+#[cfg(feature = "signed-recordings")]
+pub mod recording_manifest;
+pub mod redaction;
+pub mod region_select;
+#[cfg(feature = "quic")]
+pub mod remote_input;
 pub mod safe_mirror;
+pub mod scaling;
+pub mod scene;
+#[cfg(target_os = "macos")]
 pub mod screen_capture;
+pub mod screenshot;
+pub mod sinks;
+pub mod source_list;
+pub mod source_picker;
+pub mod stats_hud;
+#[cfg(any(feature = "whip", feature = "quic"))]
+pub mod stream_auth;
+pub mod text;
+pub mod theme;
+pub mod tone_map;
+#[cfg(feature = "update-check")]
+pub mod update_check;
+pub mod webcam;
+pub mod webvtt;
+pub mod window_state;
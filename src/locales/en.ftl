@@ -0,0 +1,86 @@
+# CloakShare user interface messages (English)
+#
+# Translations are `<language>.ftl` files with the same message IDs in the locales directory
+# (`locales_dir`, default `locales` next to config.toml), e.g. `de.ftl` or `pt-BR.ftl`.
+# Messages a translation leaves out are shown in English.
+#
+# Syntax (a subset of Fluent): `id = text`; indented lines continue the text on a new line and
+# a blank line inside a message starts a new paragraph; `{ $name }` is replaced with a value.
+
+## Errors keeping the mirror from showing the screen (dialog and card in the window)
+
+error-permission-title = Screen Recording permission is off
+error-permission-message = Allow CloakShare in System Settings > Privacy & Security > Screen Recording, then retry or restart CloakShare.
+error-capture-failed-title = Screen capture couldn't start
+error-capture-failed-message = { $error }. Check the source is still available.
+error-capture-stopped-title = Screen capture stopped
+error-capture-stopped-message = { $error }. The mirror shows the last frame.
+error-gpu-title = The GPU stopped responding
+error-gpu-message = { $error }. Reconnecting the display may help.
+
+## Renderer failing to start
+
+gpu-failure-title = CloakShare can't start rendering
+gpu-failure-message =
+    { $error }
+
+    Connecting a display, unlocking the session or quitting other GPU-heavy apps may help before retrying.
+gpu-failure-software-hint = The software renderer works without a GPU but is much slower.
+
+## Remote input consent
+
+remote-input-title = Allow remote control?
+remote-input-message =
+    The CloakShare receiver at { $receiver } wants to control this computer's mouse and keyboard through the shared screen.
+
+    Only allow this for someone you trust; it lasts until this connection ends.
+
+## Crash dialog
+
+crash-title = CloakShare crashed
+crash-message = A crash report without any screen content was written to { $path }
+
+## Dialog and card buttons
+
+button-retry = Retry
+button-open-settings = Open System Settings
+button-dismiss = Dismiss
+button-software-renderer = Try Software Renderer
+button-quit = Quit
+button-deny = Deny
+button-allow = Allow
+button-ok = OK
+
+## Permission guidance
+
+permission-denied = Screen Recording permission denied; allow CloakShare in System Settings > Privacy & Security > Screen Recording, then restart it
+permission-no-frame = No frame captured within { $seconds }s; check Screen Recording permission
+permission-accessibility = CloakShare needs the Accessibility permission to inject input (System Settings > Privacy & Security > Accessibility)
+
+## Window title: the source and what keeps the output from being live
+
+title-default-source = Safe Mirror
+title-stopped = stopped
+title-paused = paused
+title-idle = idle
+title-low-power = on battery - low power
+title-blanked = blanked
+title-no-picture = no picture - check Screen Recording permission
+title-memory-unlocked = frame memory not locked
+title-update-available = update { $version } available
+title-choose-source = Choose Source
+
+## Platform support
+
+unsupported-windows =
+    Windows support is not implemented yet.
+    To add Windows support:
+    1. Implement Windows-specific screen capture using DXGI or GDI+
+    2. Add pixel format conversion for Windows capture formats
+    3. Update platform::windows module with real implementation
+unsupported-linux =
+    Linux support is not implemented yet.
+    To add Linux support:
+    1. Implement X11/Wayland screen capture using xrandr or wlroots
+    2. Add pixel format conversion for Linux capture formats
+    3. Update platform::linux module with real implementation
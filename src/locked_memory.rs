@@ -0,0 +1,169 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether new frame buffers are locked into RAM (`lock_frame_memory`)
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static STATUS: Mutex<FrameMemoryStatus> = Mutex::new(FrameMemoryStatus::Disabled);
+
+/// Whether frame pixels are kept out of swap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameMemoryStatus {
+    /// Locking wasn't asked for
+    Disabled,
+    /// Every frame buffer so far has been locked
+    Locked,
+    /// Locking failed (usually the locked memory rlimit); frames use ordinary memory from then on
+    Unlocked(String),
+}
+
+/// Turn frame memory locking on or off for buffers created from now on
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    set_status(if enabled {
+        FrameMemoryStatus::Locked
+    } else {
+        FrameMemoryStatus::Disabled
+    });
+}
+
+pub fn status() -> FrameMemoryStatus {
+    STATUS
+        .lock()
+        .map(|status| status.clone())
+        .unwrap_or(FrameMemoryStatus::Disabled)
+}
+
+fn set_status(status: FrameMemoryStatus) {
+    if let Ok(mut current) = STATUS.lock() {
+        *current = status;
+    }
+}
+
+/// Pixel memory that is locked into RAM while locking is enabled, so raw screen content
+/// can't be paged out to the swap file
+/// The first failed lock switches locking off for the rest of the session rather than
+/// retrying (and failing) on every frame. Only frame buffers are covered: copies made along
+/// the way (pixel format conversion, GPU readbacks, encoder input) are ordinary memory
+pub struct FrameBuffer {
+    data: Vec<u8>,
+    locked: bool,
+}
+
+impl FrameBuffer {
+    pub fn new(data: Vec<u8>) -> Self {
+        let locked = ENABLED.load(Ordering::Relaxed) && !data.is_empty() && lock(&data);
+        Self { data, locked }
+    }
+
+    /// Take the pixels back, unlocked: nothing would unlock them once the Vec is freed, and
+    /// the allocator may hand those pages to anything. Wrapping them in a new buffer locks
+    /// them again
+    pub fn into_vec(mut self) -> Vec<u8> {
+        if std::mem::take(&mut self.locked) {
+            unlock(&self.data);
+        }
+        std::mem::take(&mut self.data)
+    }
+}
+
+impl Clone for FrameBuffer {
+    fn clone(&self) -> Self {
+        Self::new(self.data.clone())
+    }
+}
+
+impl Drop for FrameBuffer {
+    fn drop(&mut self) {
+        if self.locked {
+            unlock(&self.data);
+        }
+    }
+}
+
+impl Deref for FrameBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl DerefMut for FrameBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl std::fmt::Debug for FrameBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameBuffer")
+            .field("len", &self.data.len())
+            .field("locked", &self.locked)
+            .finish()
+    }
+}
+
+/// Lock `data`'s pages; on failure record why and stop locking new buffers
+#[cfg(unix)]
+fn lock(data: &[u8]) -> bool {
+    if unsafe { libc::mlock(data.as_ptr().cast(), data.len()) } == 0 {
+        return true;
+    }
+    let error = std::io::Error::last_os_error();
+    let reason = match memlock_limit() {
+        Some(limit) => format!(
+            "mlock failed: {} (locked memory limit is {} KiB; raise it with `ulimit -l`)",
+            error,
+            limit / 1024
+        ),
+        None => format!("mlock failed: {}", error),
+    };
+    fail(reason);
+    false
+}
+
+#[cfg(not(unix))]
+fn lock(_data: &[u8]) -> bool {
+    fail("Locking frame memory isn't supported on this platform".to_string());
+    false
+}
+
+fn fail(reason: String) {
+    // Only the first failure is reported; buffers created meanwhile may fail too
+    if ENABLED.swap(false, Ordering::Relaxed) {
+        eprintln!("Frame memory is not locked: {}", reason);
+        set_status(FrameMemoryStatus::Unlocked(reason));
+    }
+}
+
+#[cfg(unix)]
+fn unlock(data: &[u8]) {
+    unsafe {
+        libc::munlock(data.as_ptr().cast(), data.len());
+    }
+}
+
+#[cfg(not(unix))]
+fn unlock(_data: &[u8]) {}
+
+/// The process's locked memory limit in bytes (None = unlimited or unknown)
+#[cfg(unix)]
+pub fn memlock_limit() -> Option<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0
+        || limit.rlim_cur == libc::RLIM_INFINITY
+    {
+        return None;
+    }
+    Some(limit.rlim_cur)
+}
+
+#[cfg(not(unix))]
+pub fn memlock_limit() -> Option<u64> {
+    None
+}
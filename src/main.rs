@@ -1,19 +1,39 @@
-mod cross_platform_capture;
-mod gpu_renderer;
-mod pixel_conversion;
-mod platform;
-mod safe_mirror;
-mod screen_capture;
-
-use crate::safe_mirror::SafeMirror;
-use std::sync::Arc;
+use cloak_share::cli::{CliOptions, PipeOptions, USAGE};
+use cloak_share::gpu_failure::{GpuFailureChoice, ask_after_gpu_failure};
+use cloak_share::gpu_renderer::GpuRenderer;
+use cloak_share::hotkeys::{HotkeyAction, HotkeyHandler};
+use cloak_share::mirror_error::{
+    ErrorChoice, MirrorError, ask_after_error, open_permission_settings,
+};
+use cloak_share::platform::CaptureEvent;
+use cloak_share::platform_detector::{PermissionStatus, PlatformDetector};
+use cloak_share::plugin::{Plugin, load_plugins};
+use cloak_share::region_select::{RegionAction, RegionSelectWindow};
+use cloak_share::safe_mirror::{MirrorEvent, SafeMirror};
+use cloak_share::sinks::pipe::{PipeSink, take_stdout};
+use cloak_share::sinks::socket::SocketSink;
+use cloak_share::source_picker::{PickerAction, PickerWindow};
+use cloak_share::{crash_report, doctor, l10n, webcam};
+use std::sync::{Arc, Mutex};
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
-    event_loop::{ActiveEventLoop, EventLoop},
+    event::{ElementState, MouseButton, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
     window::{Window, WindowId},
 };
 
+/// Mirror window title (must contain "CloakShare" so capture excludes the window)
+const WINDOW_TITLE: &str = "CloakShare - Safe Mirror";
+
+/// Events delivered to the winit loop from other threads
+#[derive(Debug)]
+enum AppEvent {
+    /// Sent by the capture thread (new frame, stream stopped, ...)
+    Capture(CaptureEvent),
+    /// The mirror display refreshed with a frame waiting (see FramePacer)
+    DisplayRefresh,
+}
+
 /// App: Main application structure using winit's ApplicationHandler pattern
 /// This handles window lifecycle events (creation, resize, close, etc.)
 struct App {
@@ -22,113 +42,894 @@ struct App {
     /// The window handle (None until created)
     window: Option<Arc<Window>>,
     /// Screen capture (created early for resolution detection)
-    screen_capture: Option<crate::cross_platform_capture::CrossPlatformScreenCapture>,
+    screen_capture: Option<cloak_share::cross_platform_capture::CrossPlatformScreenCapture>,
+    /// Source picker window (only while open)
+    picker: Option<PickerWindow>,
+    /// Region selection overlay (only while selecting)
+    region_select: Option<RegionSelectWindow>,
+    /// Keyboard shortcut state
+    hotkeys: HotkeyHandler,
+    /// Lets other threads wake the event loop with an AppEvent
+    proxy: EventLoopProxy<AppEvent>,
+    /// Original stdout for `--pipe`, attached as a sink once the mirror exists
+    pipe: Option<(std::fs::File, PipeOptions)>,
+    /// Unix socket path for `--socket`
+    socket: Option<std::path::PathBuf>,
+    /// WHIP URL and bearer token from `--whip`
+    whip: Option<(String, Option<String>)>,
+    /// Receiver `host:port` from `--quic`
+    quic: Option<String>,
+    /// Receiver found on the network for `--send-to`
+    send_to: Option<cloak_share::discovery::DiscoveredReceiver>,
+    /// Where to export the compliance log on exit (`--compliance-log` or the config file)
+    compliance_log: Option<std::path::PathBuf>,
+    /// `--no-disk`, on top of the config's `no_disk`
+    no_disk: bool,
+    /// `--stream-key`, overriding the config's `stream_key`
+    stream_key: Option<String>,
+    /// Profile from `--profile`, applied once the mirror exists
+    profile: Option<String>,
+    /// Capture backend from `--capture-backend` or the config file (None = best available)
+    capture_backend: Option<String>,
+    /// Receivers on the local network, offered by Cmd+Shift+R
+    receivers: Option<cloak_share::discovery::ReceiverBrowser>,
+    /// Plugins from the config file, loaded once on the first resume
+    plugins: Vec<Arc<Plugin>>,
+    /// Last cursor position over the mirror window (for clicks in the overview grid)
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    /// Times redraws for new frames to the display's refresh (None with `pace_to_display` off)
+    pacer: Option<cloak_share::frame_pacing::FramePacer>,
+    /// Save the window geometry on exit (`remember_window`)
+    remember_window: bool,
+    /// Last windowed geometry, kept while fullscreen so it can be saved
+    window_state: Option<cloak_share::window_state::WindowState>,
+}
+
+impl App {
+    /// Create a screen capture that forwards its notifications into the event loop
+    fn create_screen_capture(
+        &self,
+    ) -> cloak_share::cross_platform_capture::CrossPlatformScreenCapture {
+        // Plugin backends compete with the built-in ones on priority
+        let mut registry =
+            cloak_share::cross_platform_capture::CaptureBackendRegistry::with_platform_backends();
+        for plugin in &self.plugins {
+            plugin.register_capture_backend(&mut registry);
+        }
+        let mut screen_capture =
+            cloak_share::cross_platform_capture::CrossPlatformScreenCapture::from_registry(
+                &registry,
+                self.capture_backend.as_deref(),
+            )
+            .expect("Failed to create screen capture");
+
+        // Forward capture thread notifications into the event loop (see user_event)
+        // The proxy isn't Sync on every platform, so it is shared behind a mutex
+        let proxy = Mutex::new(self.proxy.clone());
+        screen_capture.set_event_callback(Arc::new(move |event| {
+            if let Ok(proxy) = proxy.lock() {
+                // Fails only once the event loop has exited
+                let _ = proxy.send_event(AppEvent::Capture(event));
+            }
+        }));
+        screen_capture
+    }
+
+    /// Note the mirror window's geometry for saving on exit
+    fn remember_window_state(&mut self) {
+        if !self.remember_window {
+            return;
+        }
+        if let Some(window) = &self.window
+            && let Some(state) = cloak_share::window_state::WindowState::of_window(
+                window,
+                self.window_state.as_ref(),
+            )
+        {
+            self.window_state = Some(state);
+        }
+    }
+
+    /// Schedule a redraw of the mirror window
+    fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<AppEvent> for App {
     /// Called when the app starts up or resumes
     /// This is where we create our window and initialize GPU rendering
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Load user configuration (private Spaces, capture backend, ...) under the policy
+        let mut config = match cloak_share::config::Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Refusing to start: {}", e);
+                event_loop.exit();
+                return;
+            }
+        };
+        l10n::init(&config);
+        if self.capture_backend.is_none() {
+            self.capture_backend = config.capture_backend.clone();
+        }
+        if self.plugins.is_empty() {
+            self.plugins = load_plugins(&config.plugins);
+        }
+        if self.compliance_log.is_none() {
+            self.compliance_log = config.compliance_log.clone();
+        }
+        // Browse from the start so receivers are known by the time Cmd+Shift+R is pressed
+        if cfg!(feature = "discovery") && self.receivers.is_none() {
+            match cloak_share::discovery::ReceiverBrowser::start() {
+                Ok(browser) => self.receivers = Some(browser),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        config.no_disk |= self.no_disk;
+        if self.stream_key.is_some() {
+            config.stream_key = self.stream_key.clone();
+        }
+        // Before capture starts, so the first frame is already locked
+        cloak_share::locked_memory::set_enabled(config.lock_frame_memory);
+
+        // Prompt up front instead of letting the first capture fail with a stream error
+        let permission = PlatformDetector::screen_recording_permission();
+        match permission {
+            PermissionStatus::Granted => {}
+            PermissionStatus::NotDetermined => {
+                PlatformDetector::request_screen_recording_permission();
+            }
+            PermissionStatus::Denied => eprintln!("{}", l10n::tr("permission-denied")),
+        }
+
         // Create screen capture first for resolution detection
-        let mut screen_capture = crate::cross_platform_capture::CrossPlatformScreenCapture::new()
-            .expect("Failed to create screen capture");
+        let screen_capture = self.create_screen_capture();
 
         // Get display resolution for window sizing
         let resolution = screen_capture.get_display_resolution().unwrap_or_else(|e| {
             eprintln!("Failed to get display resolution: {}, using fallback", e);
-            crate::platform::DisplayResolution {
+            cloak_share::platform::DisplayResolution {
                 width: 1280,
                 height: 720,
+                scale_factor: 1.0,
             }
         });
 
-        // Create the main window sized to match display resolution
-        let window = Arc::new(
-            event_loop
-                .create_window(
-                    Window::default_attributes()
-                        .with_title("CloakShare - Safe Mirror") // Window title
-                        .with_inner_size(winit::dpi::LogicalSize::new(
-                            resolution.width,
-                            resolution.height,
-                        )),
-                ) // Size to match display
-                .unwrap(),
-        );
+        // Create the main window sized to match display resolution, or where it was last time
+        let mut attributes = Window::default_attributes()
+            .with_title(WINDOW_TITLE) // Window title
+            .with_inner_size(winit::dpi::LogicalSize::new(
+                resolution.width,
+                resolution.height,
+            )); // Size to match display
+        self.remember_window = config.remember_window;
+        if config.remember_window
+            && let Some(state) = cloak_share::window_state::WindowState::load()
+        {
+            attributes = state.apply(attributes, event_loop);
+            self.window_state = Some(state);
+        }
+        let window = Arc::new(event_loop.create_window(attributes).unwrap());
+
+        // Present new frames on the refresh of the display the window is on
+        if config.pace_to_display {
+            let proxy = Mutex::new(self.proxy.clone());
+            self.pacer = Some(cloak_share::frame_pacing::FramePacer::new(
+                cloak_share::frame_pacing::window_display(&window),
+                move || {
+                    if let Ok(proxy) = proxy.lock() {
+                        let _ = proxy.send_event(AppEvent::DisplayRefresh);
+                    }
+                },
+            ));
+        }
 
         // Store references and initialize GPU rendering
         self.window = Some(window.clone());
         self.screen_capture = Some(screen_capture);
 
-        // pollster::block_on converts async function to sync (required for this context)
-        self.safe_mirror = Some(pollster::block_on(SafeMirror::new(
-            window,
-            self.screen_capture.take().unwrap(),
-        )));
+        // If the renderer can't start, explain why and let the user retry (or switch to the
+        // software renderer) instead of giving up
+        let mut software_tried = config.gpu_software_fallback;
+        let mut safe_mirror = loop {
+            // A failed attempt consumes the capture; later attempts start a fresh one
+            let screen_capture = match self.screen_capture.take() {
+                Some(screen_capture) => screen_capture,
+                None => self.create_screen_capture(),
+            };
+
+            // pollster::block_on converts async function to sync (required for this context)
+            match pollster::block_on(SafeMirror::new(window.clone(), screen_capture, &config)) {
+                Ok(safe_mirror) => break safe_mirror,
+                Err(e) => {
+                    eprintln!("Failed to start the GPU renderer: {}", e);
+                    match ask_after_gpu_failure(&e, software_tried) {
+                        GpuFailureChoice::Retry => {}
+                        GpuFailureChoice::SoftwareFallback => {
+                            config.gpu_software_fallback = true;
+                            software_tried = true;
+                        }
+                        GpuFailureChoice::Quit => {
+                            event_loop.exit();
+                            return;
+                        }
+                    }
+                }
+            }
+        };
+        // Shown in a dialog on the first redraw and as a card until it is fixed
+        if permission == PermissionStatus::Denied {
+            safe_mirror.set_error(MirrorError::PermissionDenied);
+        }
+
+        // Stream frames to stdout alongside the mirror window
+        if let Some((output, options)) = self.pipe.take() {
+            let sink = PipeSink::new(output, options.format, options.fps);
+            if let Err(e) = safe_mirror.attach_sink(Box::new(sink)) {
+                eprintln!("Failed to start pipe output: {}", e);
+            }
+        }
+
+        // Serve frames to local companion processes
+        if let Some(path) = self.socket.take()
+            && let Err(e) = safe_mirror.attach_sink(Box::new(SocketSink::new(path)))
+        {
+            eprintln!("Failed to start frame socket: {}", e);
+        }
+
+        // Stream to a WHIP ingest server
+        if let Some((url, token)) = self.whip.take()
+            && let Err(e) = safe_mirror.start_whip(url, token)
+        {
+            eprintln!("Failed to start WHIP output: {}", e);
+        }
+
+        // Stream to a CloakShare receiver over QUIC
+        if let Some(address) = self.quic.take()
+            && let Err(e) = safe_mirror.start_quic(address)
+        {
+            eprintln!("Failed to start QUIC output: {}", e);
+        }
+
+        // Stream to the receiver picked with --send-to
+        if let Some(receiver) = self.send_to.take()
+            && let Err(e) = safe_mirror.send_to(&receiver)
+        {
+            eprintln!("Failed to stream to {}: {}", receiver.name, e);
+        }
+
+        // Feed redacted frames to plugin outputs
+        for plugin in &self.plugins {
+            let attached = match plugin.create_sink() {
+                Some(Ok(sink)) => safe_mirror.attach_sink(Box::new(sink)).map(|_| ()),
+                Some(Err(e)) => Err(e),
+                None => Ok(()),
+            };
+            if let Err(e) = attached {
+                eprintln!("Failed to start plugin {}: {}", plugin.name(), e);
+            }
+        }
+
+        // The command line wins over the config's default profile
+        if let Some(name) = self.profile.take().or(config.default_profile.clone())
+            && let Err(e) = safe_mirror.apply_profile(&name)
+        {
+            eprintln!("Failed to apply profile: {}", e);
+        }
+
+        self.safe_mirror = Some(safe_mirror);
     }
 
     /// Handles all window events (resize, close, redraw, etc.)
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        _window_id: WindowId,
+        window_id: WindowId,
         event: WindowEvent,
     ) {
+        // Events for the source picker window are handled separately
+        if let Some(picker) = &mut self.picker
+            && picker.window_id() == window_id
+        {
+            let action = picker.handle_event(&event);
+            if let PickerAction::Select(source) = action
+                && let Some(safe_mirror) = &mut self.safe_mirror
+                && let Err(e) = safe_mirror.select_source(source)
+            {
+                eprintln!("Failed to switch capture source: {}", e);
+            }
+            if action != PickerAction::None {
+                self.picker = None;
+                if let Some(safe_mirror) = &mut self.safe_mirror {
+                    safe_mirror.set_source_picker_open(false);
+                }
+                self.request_redraw();
+            }
+            return;
+        }
+
+        // Likewise for the region selection overlay
+        if let Some(region_select) = &mut self.region_select
+            && region_select.window_id() == window_id
+        {
+            let action = region_select.handle_event(&event);
+            if let RegionAction::Select(source) = action
+                && let Some(safe_mirror) = &mut self.safe_mirror
+                && let Err(e) = safe_mirror.select_source(source)
+            {
+                eprintln!("Failed to capture the selected region: {}", e);
+            }
+            if action != RegionAction::None {
+                self.region_select = None;
+                if let Some(safe_mirror) = &mut self.safe_mirror {
+                    safe_mirror.set_region_selection_open(false);
+                }
+                self.request_redraw();
+            }
+            return;
+        }
+
+        if matches!(event, WindowEvent::Moved(_) | WindowEvent::Resized(_)) {
+            self.remember_window_state();
+        }
+
         if let Some(safe_mirror) = &mut self.safe_mirror {
             match event {
                 // User clicked X button or pressed Cmd+Q
                 WindowEvent::CloseRequested => event_loop.exit(),
 
+                // Track modifier keys for hotkeys
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.hotkeys.update_modifiers(modifiers.state());
+                }
+
+                // Keyboard shortcuts
+                WindowEvent::KeyboardInput { event, .. } => match self.hotkeys.action_for(&event) {
+                    Some(HotkeyAction::OpenSourcePicker) if self.picker.is_none() => {
+                        safe_mirror.set_source_picker_open(true);
+                        match safe_mirror
+                            .source_picker()
+                            .and_then(|picker| PickerWindow::open(event_loop, picker))
+                        {
+                            Ok(picker) => self.picker = Some(picker),
+                            Err(e) => {
+                                safe_mirror.set_source_picker_open(false);
+                                eprintln!("Failed to open source picker: {}", e);
+                            }
+                        }
+                        self.request_redraw();
+                    }
+                    Some(HotkeyAction::SelectRegion) if self.region_select.is_none() => {
+                        // The background is taken from the output before it is blanked
+                        match safe_mirror.region_selection().and_then(|selection| {
+                            safe_mirror.set_region_selection_open(true);
+                            RegionSelectWindow::open(event_loop, selection)
+                        }) {
+                            Ok(region_select) => self.region_select = Some(region_select),
+                            Err(e) => {
+                                safe_mirror.set_region_selection_open(false);
+                                eprintln!("Failed to start region selection: {}", e);
+                            }
+                        }
+                        self.request_redraw();
+                    }
+                    Some(HotkeyAction::NextDisplay) => match safe_mirror.next_display() {
+                        Ok(Some(_)) => self.request_redraw(),
+                        Ok(None) => println!("Only one display is connected"),
+                        Err(e) => eprintln!("Failed to switch display: {}", e),
+                    },
+                    Some(HotkeyAction::ToggleFollowCursor) => {
+                        safe_mirror.toggle_follow_cursor();
+                    }
+                    Some(HotkeyAction::TogglePause) => {
+                        safe_mirror.toggle_pause();
+                        // Resuming should show the current screen right away, and the redraw
+                        // updates the title
+                        self.request_redraw();
+                    }
+                    Some(HotkeyAction::ToggleImageLayer(index)) => {
+                        match safe_mirror.toggle_image_layer(index) {
+                            Some((name, true)) => println!("Showing layer {}", name),
+                            Some((name, false)) => println!("Hiding layer {}", name),
+                            None => println!("No image layer {} in the scene", index + 1),
+                        }
+                    }
+                    Some(HotkeyAction::ToggleSharpen) => {
+                        safe_mirror.toggle_sharpen();
+                        // Show the change even while the screen is static
+                        self.request_redraw();
+                    }
+                    Some(HotkeyAction::ToggleStatsHud) => {
+                        safe_mirror.toggle_stats_hud();
+                        self.request_redraw();
+                    }
+                    Some(HotkeyAction::AddChapter) => match safe_mirror.add_chapter(None) {
+                        0 => println!("No recording is running to mark a chapter in"),
+                        count => println!("Chapter marked in {} recording(s)", count),
+                    },
+                    Some(HotkeyAction::ToggleSpacePrivate) => {
+                        match safe_mirror.toggle_current_space_private() {
+                            Ok((space, true)) => println!("Space {} marked private", space),
+                            Ok((space, false)) => {
+                                println!("Space {} no longer marked private", space)
+                            }
+                            Err(e) => eprintln!("Failed to mark the Space private: {}", e),
+                        }
+                        self.request_redraw();
+                    }
+                    Some(HotkeyAction::ToggleOverview) => {
+                        if let Err(e) = safe_mirror.toggle_overview() {
+                            eprintln!("Failed to toggle display overview: {}", e);
+                        }
+                    }
+                    Some(HotkeyAction::NextProfile) => match safe_mirror.next_profile() {
+                        Ok(Some(_)) => self.request_redraw(),
+                        Ok(None) => println!("No profiles configured"),
+                        Err(e) => eprintln!("Failed to switch profile: {}", e),
+                    },
+                    Some(HotkeyAction::NextReceiver) => {
+                        let receivers = self
+                            .receivers
+                            .as_ref()
+                            .map(cloak_share::discovery::ReceiverBrowser::receivers)
+                            .unwrap_or_default();
+                        match safe_mirror.next_receiver(&receivers) {
+                            Ok(Some(name)) => println!("Streaming to receiver {}", name),
+                            Ok(None) if receivers.is_empty() => {
+                                println!("No receivers found on the local network")
+                            }
+                            Ok(None) => println!("Stopped streaming to receivers"),
+                            Err(e) => eprintln!("Failed to stream to receiver: {}", e),
+                        }
+                    }
+                    Some(HotkeyAction::Screenshot) => match safe_mirror.save_screenshot(None) {
+                        Ok(path) => println!("Screenshot saved to {}", path.display()),
+                        Err(e) => eprintln!("Failed to save screenshot: {}", e),
+                    },
+                    _ => {}
+                },
+
+                WindowEvent::CursorMoved { position, .. } => self.cursor_position = position,
+
+                // The error card's Retry button
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                } if safe_mirror.hits_retry_button(self.cursor_position) => {
+                    safe_mirror.retry();
+                    if let Some(window) = &self.window {
+                        window.set_title(&safe_mirror.window_title());
+                        window.request_redraw();
+                    }
+                }
+
+                // Clicking a display in the overview grid mirrors it
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                } if safe_mirror.is_overview() => {
+                    if let Err(e) = safe_mirror.select_overview_display(self.cursor_position) {
+                        eprintln!("Failed to select display: {}", e);
+                    }
+                }
+
+                // The window may have moved to a display with a different refresh
+                WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    if let Some(window) = &self.window {
+                        if let Some(pacer) = &self.pacer
+                            && let Some(display) = cloak_share::frame_pacing::window_display(window)
+                        {
+                            pacer.set_display(display);
+                        }
+                        safe_mirror.set_display_refresh(
+                            cloak_share::frame_pacing::window_refresh_rate(window),
+                        );
+                    }
+                }
+
                 // User resized the window
                 WindowEvent::Resized(physical_size) => {
                     safe_mirror.resize(physical_size);
+                    // The new surface is empty until we draw into it
+                    if let Some(window) = &self.window {
+                        window.request_redraw();
+                    }
                 }
 
-                // System requests a redraw (60fps or when window needs updating)
+                // System requests a redraw (new frame arrived or window needs updating)
                 WindowEvent::RedrawRequested => {
                     // Render the frame to the screen
                     match safe_mirror.update_and_render() {
                         Ok(_) => {} // Successful render
 
                         // Handle common GPU errors gracefully
-                        Err(wgpu::SurfaceError::Lost) => {
-                            // GPU lost surface, try to recreate it
-                            safe_mirror.resize(safe_mirror.size())
+                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                            // Surface lost or out of date, reconfigure it and draw again
+                            safe_mirror.resize(safe_mirror.size());
+                            if let Some(window) = &self.window {
+                                window.request_redraw();
+                            }
                         }
+                        // The compositor didn't hand out a frame in time; the next frame retries
+                        Err(wgpu::SurfaceError::Timeout) => {}
                         Err(wgpu::SurfaceError::OutOfMemory) => {
-                            // GPU out of memory, exit app
-                            event_loop.exit()
+                            // Give back effects and layer resolution and try again; exit only
+                            // when there is nothing left to give back
+                            if safe_mirror.reduce_gpu_memory() {
+                                if let Some(window) = &self.window {
+                                    window.request_redraw();
+                                }
+                            } else {
+                                eprintln!("GPU out of memory");
+                                event_loop.exit()
+                            }
                         }
                         Err(e) => eprintln!("Render error: {e:?}"),
                     }
+
+                    // Show the source and pipeline state changes in the window title
+                    let mut title_changed = false;
+                    for event in safe_mirror.drain_events() {
+                        match event {
+                            MirrorEvent::SignificantChange { changed_fraction } => {
+                                println!(
+                                    "Significant screen change ({:.0}% of the screen)",
+                                    changed_fraction * 100.0
+                                );
+                            }
+                            MirrorEvent::SourceBlack
+                            | MirrorEvent::SourceRestored
+                            | MirrorEvent::FrameMemoryUnlocked
+                            | MirrorEvent::SourceChanged { .. }
+                            | MirrorEvent::PausedChanged { .. }
+                            | MirrorEvent::BlankingChanged { .. }
+                            | MirrorEvent::LowPowerChanged { .. }
+                            | MirrorEvent::CaptureStopped => title_changed = true,
+                            MirrorEvent::Error(error) => {
+                                match ask_after_error(&error) {
+                                    ErrorChoice::Retry => safe_mirror.retry(),
+                                    ErrorChoice::OpenSettings => open_permission_settings(),
+                                    ErrorChoice::Dismiss => {}
+                                }
+                                title_changed = true;
+                                // Draw the card, or whatever the retry changed
+                                if let Some(window) = &self.window {
+                                    window.request_redraw();
+                                }
+                            }
+                            #[cfg(feature = "quic")]
+                            MirrorEvent::RemoteInputRequested { session } => {
+                                let allowed =
+                                    cloak_share::remote_input::ask_consent(&session.receiver);
+                                safe_mirror.set_remote_input_allowed(&session, allowed);
+                            }
+                            #[cfg(feature = "update-check")]
+                            MirrorEvent::UpdateAvailable(release) => {
+                                println!(
+                                    "CloakShare {} is available: {}",
+                                    release.version, release.url
+                                );
+                                title_changed = true;
+                            }
+                        }
+                    }
+                    if title_changed && let Some(window) = &self.window {
+                        window.set_title(&safe_mirror.window_title());
+                    }
                 }
                 _ => {} // Ignore other events
             }
         }
+    }
 
-        // Request continuous redraws for smooth animation
-        // This creates our 60fps render loop
-        if let Some(window) = &self.window {
-            window.request_redraw();
+    /// Handles events sent from other threads through the EventLoopProxy
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: AppEvent) {
+        match event {
+            // Redraw only when a new frame has arrived, on the next refresh if paced
+            AppEvent::Capture(CaptureEvent::FrameReady) => {
+                if self.pacer.as_ref().is_none_or(|pacer| pacer.frame_ready()) {
+                    self.request_redraw();
+                }
+            }
+            AppEvent::DisplayRefresh => self.request_redraw(),
+            AppEvent::Capture(CaptureEvent::Stopped(error)) => {
+                eprintln!("Capture stopped: {}", error);
+                if let Some(safe_mirror) = &mut self.safe_mirror {
+                    safe_mirror.capture_stopped(&error);
+                }
+                // Redraw so the mirror reflects whatever the pipeline shows without frames
+                self.request_redraw();
+            }
+        }
+    }
+
+    /// Called once when the event loop is shutting down
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.remember_window_state();
+        if let Some(state) = &self.window_state
+            && let Err(e) = state.save()
+        {
+            eprintln!("Failed to save the window state: {}", e);
+        }
+        if let Some(safe_mirror) = &self.safe_mirror {
+            let stats = safe_mirror.capture_stats();
+            println!(
+                "Capture frames: {} delivered, {} processed, {} dropped",
+                stats.sent, stats.received, stats.dropped
+            );
+        }
+        if let (Some(path), Some(safe_mirror)) = (&self.compliance_log, &self.safe_mirror) {
+            match safe_mirror.export_compliance_log(path) {
+                Ok(()) => println!("Compliance log written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write compliance log: {}", e),
+            }
+        }
+    }
+}
+
+/// `cloakshare verify`: report whether a recording matches its signed manifest
+#[cfg(feature = "signed-recordings")]
+fn verify_recording(recording: &std::path::Path) {
+    match cloak_share::recording_manifest::verify(recording) {
+        Ok(verification) => {
+            println!(
+                "{}: {} segments, {} bytes, {:.1}s verified",
+                recording.display(),
+                verification.segments,
+                verification.bytes,
+                verification.duration_ms as f64 / 1000.0
+            );
+            println!("Signed by {}", verification.public_key);
+            if !verification.complete {
+                println!("Recording didn't finish; only the part above is covered");
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: verification failed: {}", recording.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "signed-recordings"))]
+fn verify_recording(_recording: &std::path::Path) {
+    eprintln!(
+        "Signed recordings aren't in this build (enable the signed-recordings cargo feature)"
+    );
+    std::process::exit(2);
+}
+
+/// `cloakshare receive`: show incoming streams until the window is closed
+#[cfg(feature = "receiver")]
+fn run_receiver(
+    options: cloak_share::cli::ReceiveOptions,
+    token: Option<String>,
+    stream_key: Option<String>,
+) {
+    let mut config = match cloak_share::config::Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+    };
+    if stream_key.is_some() {
+        config.stream_key = stream_key;
+    }
+    crash_report::set_config(&config);
+    l10n::init(&config);
+    if let Err(e) = cloak_share::receiver::run(options, token, &config) {
+        eprintln!("Receiver failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "receiver"))]
+fn run_receiver(
+    _options: cloak_share::cli::ReceiveOptions,
+    _token: Option<String>,
+    _stream_key: Option<String>,
+) {
+    eprintln!("Receiver mode isn't in this build (enable the receiver cargo feature)");
+    std::process::exit(2);
+}
+
+/// Config for the windowless commands, with the command-line overrides applied; exits if the
+/// config can't be loaded
+fn headless_config(options: &CliOptions) -> cloak_share::config::Config {
+    let mut config = match cloak_share::config::Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Refusing to start: {}", e);
+            std::process::exit(1);
+        }
+    };
+    crash_report::set_config(&config);
+    l10n::init(&config);
+    config.no_disk |= options.no_disk;
+    if options.capture_backend.is_some() {
+        config.capture_backend = options.capture_backend.clone();
+    }
+    if options.compliance_log.is_some() {
+        config.compliance_log = options.compliance_log.clone();
+    }
+    config
+}
+
+/// `cloakshare record`: record without a window, exiting with an error if the recording fails
+fn run_record(record: &cloak_share::cli::RecordOptions, options: &CliOptions) {
+    let config = headless_config(options);
+    if let Err(e) = cloak_share::headless::record(record, &config) {
+        eprintln!("Recording failed: {}", e);
+        std::process::exit(1);
     }
 }
 
 /// Main function: Entry point of the application
 fn main() {
+    let options = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    // Stdout carries frame data with --pipe, so only stderr is kept for crash reports then
+    crash_report::install(options.pipe.is_none());
+    if options.doctor {
+        print!("{}", doctor::report());
+        return;
+    }
+    if let Some(recording) = &options.verify {
+        verify_recording(recording);
+        return;
+    }
+    if let Some(record) = &options.record {
+        run_record(record, &options);
+        return;
+    }
+    if let Some(daemon) = &options.daemon {
+        let config = headless_config(&options);
+        let socket = daemon
+            .control_socket
+            .clone()
+            .or_else(cloak_share::daemon::default_socket_path);
+        let result = match socket {
+            Some(socket) => cloak_share::daemon::run(socket, daemon.display, &config),
+            None => Err("No config directory for the control socket; pass --control-socket".into()),
+        };
+        if let Err(e) = result {
+            eprintln!("Daemon failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(screenshot) = &options.screenshot {
+        let config = headless_config(&options);
+        match cloak_share::headless::screenshot(screenshot, &config) {
+            Ok(path) => println!("Screenshot saved to {}", path.display()),
+            Err(e) => {
+                eprintln!("Screenshot failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(receive) = options.receive {
+        run_receiver(receive, options.whip_token, options.stream_key);
+        return;
+    }
+    if options.list_cameras {
+        for camera in webcam::list_cameras() {
+            println!("{}", camera);
+        }
+        return;
+    }
+    if let Some(listing) = options.list {
+        match cloak_share::source_list::report(listing, options.json) {
+            Ok(report) => print!("{}", report),
+            Err(e) => {
+                eprintln!("Cannot list sources: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.list_receivers {
+        match cloak_share::discovery::discover() {
+            Ok(receivers) => {
+                for receiver in receivers {
+                    println!(
+                        "{}\t{}",
+                        receiver.name,
+                        receiver.whip_url().unwrap_or_default()
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("Cannot list receivers: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.list_gpus {
+        for adapter in GpuRenderer::enumerate_adapters() {
+            println!(
+                "{} ({:?}, {:?})",
+                adapter.name, adapter.backend, adapter.device_type
+            );
+        }
+        return;
+    }
+
+    // A receiver picked by name is found over mDNS; the mirror streams to it once it's up
+    let send_to = options
+        .send_to
+        .map(|name| match cloak_share::discovery::find_receiver(&name) {
+            Ok(receiver) => receiver,
+            Err(e) => {
+                eprintln!("Cannot use --send-to: {}", e);
+                std::process::exit(1);
+            }
+        });
+    let whip = options.whip.map(|url| (url, options.whip_token));
+
+    // Claim stdout for frame data before anything is logged to it
+    let pipe = options.pipe.map(|pipe_options| match take_stdout() {
+        Ok(output) => (output, pipe_options),
+        Err(e) => {
+            eprintln!("Cannot use --pipe: {}", e);
+            std::process::exit(1);
+        }
+    });
+
     println!("Starting CloakShare Safe Mirror...");
 
     // Create the main event loop (handles window events, user input, etc.)
-    let event_loop = EventLoop::new().unwrap();
+    // AppEvent lets the capture thread wake the loop when a frame arrives
+    let event_loop = EventLoop::<AppEvent>::with_user_event().build().unwrap();
+
+    // Sleep until an event arrives instead of spinning
+    event_loop.set_control_flow(ControlFlow::Wait);
 
     // Create our app instance
     let mut app = App {
         safe_mirror: None,    // Will be initialized when window is created
         window: None,         // Will be created in resumed()
         screen_capture: None, // Will be created in resumed()
+        picker: None,         // Opened with Cmd+Shift+P
+        region_select: None,  // Opened with Cmd+Shift+A
+        hotkeys: HotkeyHandler::new(),
+        proxy: event_loop.create_proxy(),
+        pipe,
+        socket: options.socket,
+        whip,
+        quic: options.quic,
+        send_to,
+        compliance_log: options.compliance_log,
+        no_disk: options.no_disk,
+        stream_key: options.stream_key,
+        profile: options.profile,
+        capture_backend: options.capture_backend,
+        receivers: None,
+        plugins: Vec::new(),
+        cursor_position: Default::default(),
+        pacer: None,
+        remember_window: false,
+        window_state: None,
     };
 
     // Start the event loop - this runs until the app closes
-    // The event loop continuously calls our window_event handler
+    // Redraws are driven by new frames from the capture thread, not a continuous loop
     event_loop.run_app(&mut app).unwrap();
 }
@@ -0,0 +1,210 @@
+use crate::l10n::{tr, tr_args};
+use crate::scaling::blit_rgba;
+use crate::text::{TextRenderer, TextStyle};
+use crate::theme::{Theme, flatten, round_corners};
+
+/// Card layout in points; multiplied by the window's scale factor
+const MARGIN: f32 = 24.0;
+const SPACING: f32 = 14.0;
+const TITLE_SIZE: f32 = 22.0;
+const MESSAGE_SIZE: f32 = 15.0;
+const BUTTON_PADDING: f32 = 10.0;
+
+/// Messages are wrapped to lines of about this many characters
+const WRAP_COLUMNS: usize = 56;
+
+/// Something that keeps the mirror from showing the screen, shown in a native dialog when it
+/// happens and as a card in the mirror window until it is resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MirrorError {
+    /// Screen Recording permission was refused
+    PermissionDenied,
+    /// The capture stream couldn't be started
+    CaptureFailed(String),
+    /// The capture stream stopped on its own (display unplugged, window closed, ...)
+    CaptureStopped(String),
+    /// The GPU was lost and the renderer couldn't be recreated
+    Gpu(String),
+}
+
+impl MirrorError {
+    pub fn title(&self) -> String {
+        tr(match self {
+            Self::PermissionDenied => "error-permission-title",
+            Self::CaptureFailed(_) => "error-capture-failed-title",
+            Self::CaptureStopped(_) => "error-capture-stopped-title",
+            Self::Gpu(_) => "error-gpu-title",
+        })
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::PermissionDenied => tr("error-permission-message"),
+            Self::CaptureFailed(e) => tr_args("error-capture-failed-message", &[("error", e)]),
+            Self::CaptureStopped(e) => tr_args("error-capture-stopped-message", &[("error", e)]),
+            Self::Gpu(e) => tr_args("error-gpu-message", &[("error", e)]),
+        }
+    }
+
+    /// Whether the permission settings can help (offered as an extra dialog button)
+    pub fn needs_permission(&self) -> bool {
+        matches!(self, Self::PermissionDenied)
+    }
+}
+
+/// What to do after an error dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorChoice {
+    Retry,
+    /// Open the Screen Recording privacy settings
+    OpenSettings,
+    /// Close the dialog; the card stays in the window
+    Dismiss,
+}
+
+impl ErrorChoice {
+    fn label(self) -> String {
+        tr(match self {
+            Self::Retry => "button-retry",
+            Self::OpenSettings => "button-open-settings",
+            Self::Dismiss => "button-dismiss",
+        })
+    }
+}
+
+/// Tell the user about `error` in a native dialog and ask how to continue
+pub fn ask_after_error(error: &MirrorError) -> ErrorChoice {
+    let choices: &[ErrorChoice] = if error.needs_permission() {
+        &[
+            ErrorChoice::OpenSettings,
+            ErrorChoice::Retry,
+            ErrorChoice::Dismiss,
+        ]
+    } else {
+        &[ErrorChoice::Retry, ErrorChoice::Dismiss]
+    };
+    let labels: Vec<String> = choices.iter().map(|choice| choice.label()).collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    show_dialog(&error.title(), &error.message(), &labels)
+        .and_then(|index| choices.get(index).copied())
+        .unwrap_or(ErrorChoice::Dismiss)
+}
+
+#[cfg(target_os = "macos")]
+fn show_dialog(title: &str, message: &str, buttons: &[&str]) -> Option<usize> {
+    crate::platform::macos_alert::show_alert(title, message, buttons)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_dialog(title: &str, message: &str, _buttons: &[&str]) -> Option<usize> {
+    // No native dialog on this platform yet; the card in the window still offers a retry
+    eprintln!("{}: {}", title, message);
+    None
+}
+
+/// Open System Settings at the Screen Recording privacy pane
+#[cfg(target_os = "macos")]
+pub fn open_permission_settings() {
+    let url = "x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture";
+    if let Err(e) = std::process::Command::new("open").arg(url).spawn() {
+        eprintln!("Failed to open System Settings: {}", e);
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn open_permission_settings() {}
+
+/// An error rendered as a card: title, message and a Retry button, in RGBA
+pub struct ErrorCard {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Retry button in card pixels: (x, y, width, height)
+    retry_button: (u32, u32, u32, u32),
+}
+
+impl ErrorCard {
+    /// Lay out the card for `error` at `scale` pixels per point in `theme`'s colors
+    pub fn new(error: &MirrorError, scale: f32, theme: &Theme) -> Result<Self, String> {
+        let mut text = TextRenderer::new();
+        let radius = theme.corner_radius as f32 * scale;
+        let style = |text: String, size: f32, color, background, padding: f32| TextStyle {
+            text,
+            font: theme.font.clone(),
+            size: size * scale,
+            color,
+            background,
+            padding: padding * scale,
+            corner_radius: None,
+        };
+        let title = text.render(&style(
+            error.title(),
+            TITLE_SIZE,
+            theme.text,
+            theme.background,
+            0.0,
+        ))?;
+        let message = text.render(&style(
+            wrap(&error.message(), WRAP_COLUMNS),
+            MESSAGE_SIZE,
+            theme.secondary_text,
+            theme.background,
+            0.0,
+        ))?;
+        let mut button = text.render(&TextStyle {
+            corner_radius: Some(radius),
+            ..style(
+                tr("button-retry"),
+                MESSAGE_SIZE,
+                theme.text,
+                theme.accent,
+                BUTTON_PADDING,
+            )
+        })?;
+        flatten(&mut button.0, theme.background);
+
+        let margin = (MARGIN * scale).round() as u32;
+        let spacing = (SPACING * scale).round() as u32;
+        let width = title.1.max(message.1).max(button.1) + 2 * margin;
+        let height = title.2 + message.2 + button.2 + 2 * spacing + 2 * margin;
+        let mut rgba = theme.background.repeat((width * height) as usize);
+
+        let mut y = margin;
+        for (pixels, part_width, part_height) in [&title, &message] {
+            blit_rgba(&mut rgba, width, height, pixels, *part_width, margin, y);
+            y += part_height + spacing;
+        }
+        // Button in the bottom right corner, like a dialog's default button
+        let button_x = width - margin - button.1;
+        blit_rgba(&mut rgba, width, height, &button.0, button.1, button_x, y);
+        round_corners(&mut rgba, width, height, radius);
+
+        Ok(Self {
+            rgba,
+            width,
+            height,
+            retry_button: (button_x, y, button.1, button.2),
+        })
+    }
+
+    /// Whether card pixel (x, y) is on the Retry button
+    pub fn hits_retry(&self, x: f64, y: f64) -> bool {
+        let (bx, by, bw, bh) = self.retry_button;
+        x >= bx as f64 && y >= by as f64 && x < (bx + bw) as f64 && y < (by + bh) as f64
+    }
+}
+
+/// Break `text` into lines of at most `columns` characters at spaces
+fn wrap(text: &str, columns: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= columns => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines.join("\n")
+}
@@ -1,20 +1,113 @@
+use crate::encoding::yuv::{YuvColorSpace, YuvMatrix, YuvRange, nv12_to_rgba};
+use crate::platform::{AlphaMode, GpuFrameSource};
+use crate::scaling::unpremultiply_alpha;
 use core_foundation::base::TCFType;
+use core_foundation::string::CFString;
 use core_video_sys::{
-    CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow, CVPixelBufferGetHeight,
-    CVPixelBufferGetPixelFormatType, CVPixelBufferGetWidth, CVPixelBufferLockBaseAddress,
-    CVPixelBufferRef, CVPixelBufferUnlockBaseAddress, kCVPixelBufferLock_ReadOnly,
-    kCVPixelFormatType_32BGRA,
+    CVPixelBufferGetBaseAddress, CVPixelBufferGetBaseAddressOfPlane, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetBytesPerRowOfPlane, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+    CVPixelBufferGetWidth, CVPixelBufferLockBaseAddress, CVPixelBufferRef, CVPixelBufferRelease,
+    CVPixelBufferRetain, CVPixelBufferUnlockBaseAddress, kCVPixelBufferLock_ReadOnly,
+    kCVPixelFormatType_32BGRA, kCVPixelFormatType_420YpCbCr8BiPlanarFullRange,
+    kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange,
 };
 use screencapturekit::output::CMSampleBuffer;
+use std::ffi::c_void;
 
-/// Converts ScreenCaptureKit CMSampleBuffer (chunky BGRA) -> RGBA at native resolution.
-/// Returns None if the buffer isn't BGRA or if locking/base address fails.
-pub fn convert_sample_buffer_to_rgba(sample_buffer: &CMSampleBuffer) -> Option<Vec<u8>> {
+#[link(name = "CoreVideo", kind = "framework")]
+unsafe extern "C" {
+    static kCVImageBufferAlphaChannelModeKey: *const c_void;
+    static kCVImageBufferAlphaChannelMode_StraightAlpha: *const c_void;
+    static kCVImageBufferYCbCrMatrixKey: *const c_void;
+    static kCVImageBufferYCbCrMatrix_ITU_R_601_4: *const c_void;
+    static kCVImageBufferYCbCrMatrix_ITU_R_2020: *const c_void;
+    fn CVBufferGetAttachment(
+        buffer: CVPixelBufferRef,
+        key: *const c_void,
+        attachment_mode: *mut u32,
+    ) -> *const c_void;
+}
+
+/// A ScreenCaptureKit frame left in its IOSurface-backed CVPixelBuffer, so nothing is copied
+/// until a CPU consumer reads it (as BGRA rows `bytes_per_row` apart)
+#[derive(Debug)]
+pub struct RetainedPixelBuffer {
+    buffer: CVPixelBufferRef,
+    pub width: u32,
+    pub height: u32,
+    pub bytes_per_row: u32,
+    pub alpha_mode: AlphaMode,
+}
+
+// SAFETY: CVPixelBuffers are reference-counted CF objects usable from any thread; pixel
+// access goes through CVPixelBufferLockBaseAddress
+unsafe impl Send for RetainedPixelBuffer {}
+unsafe impl Sync for RetainedPixelBuffer {}
+
+impl RetainedPixelBuffer {
+    /// Keep the sample buffer's pixel buffer alive past the callback; None unless it's BGRA
+    pub fn from_sample_buffer(sample_buffer: &CMSampleBuffer) -> Option<Self> {
+        let pixel_buffer = sample_buffer.get_pixel_buffer().ok()?;
+        let buffer: CVPixelBufferRef = pixel_buffer.as_concrete_TypeRef().cast();
+        unsafe {
+            if CVPixelBufferGetPixelFormatType(buffer) != kCVPixelFormatType_32BGRA {
+                return None;
+            }
+            Some(Self {
+                buffer: CVPixelBufferRetain(buffer),
+                width: CVPixelBufferGetWidth(buffer) as u32,
+                height: CVPixelBufferGetHeight(buffer) as u32,
+                bytes_per_row: CVPixelBufferGetBytesPerRow(buffer) as u32,
+                alpha_mode: alpha_mode(buffer),
+            })
+        }
+    }
+}
+
+impl GpuFrameSource for RetainedPixelBuffer {
+    fn read_pixels(&self) -> Option<Vec<u8>> {
+        unsafe {
+            if CVPixelBufferLockBaseAddress(self.buffer, kCVPixelBufferLock_ReadOnly) != 0 {
+                eprintln!("Failed to lock CVPixelBuffer");
+                return None;
+            }
+            let base_ptr = CVPixelBufferGetBaseAddress(self.buffer) as *const u8;
+            let pixels = (!base_ptr.is_null()).then(|| {
+                let len = self.bytes_per_row as usize * self.height as usize;
+                std::slice::from_raw_parts(base_ptr, len).to_vec()
+            });
+            CVPixelBufferUnlockBaseAddress(self.buffer, kCVPixelBufferLock_ReadOnly);
+            pixels
+        }
+    }
+}
+
+impl Drop for RetainedPixelBuffer {
+    fn drop(&mut self) {
+        unsafe { CVPixelBufferRelease(self.buffer) };
+    }
+}
+
+/// Converts ScreenCaptureKit CMSampleBuffer (chunky BGRA or NV12) -> RGBA at native
+/// resolution; returns (rgba, width, height).
+/// Returns None for other formats or if locking/base address fails.
+pub fn convert_sample_buffer_to_rgba(
+    sample_buffer: &CMSampleBuffer,
+) -> Option<(Vec<u8>, u32, u32)> {
     // 1) Get CVPixelBuffer
     let pixel_buffer = sample_buffer.get_pixel_buffer().ok()?;
     let pixel_buffer_rs = pixel_buffer.as_concrete_TypeRef(); // *mut __CVPixelBufferRef (rs)
     let pixel_buffer_ref = pixel_buffer_rs.cast(); // We cast __CVPixelBufferRef to *mut __CVBuffer (sys)
 
+    convert_pixel_buffer_to_rgba(pixel_buffer_ref)
+}
+
+/// Converts a BGRA or NV12 CVPixelBuffer (e.g. a camera frame) -> straight-alpha RGBA;
+/// returns (rgba, width, height).
+/// Returns None for other formats or if locking/base address fails.
+pub fn convert_pixel_buffer_to_rgba(
+    pixel_buffer_ref: CVPixelBufferRef,
+) -> Option<(Vec<u8>, u32, u32)> {
     // 2) Lock for read
     let lock_flags = kCVPixelBufferLock_ReadOnly;
     let lock_result = unsafe { CVPixelBufferLockBaseAddress(pixel_buffer_ref, lock_flags) };
@@ -45,7 +138,9 @@ pub fn convert_sample_buffer_to_rgba(sample_buffer: &CMSampleBuffer) -> Option<V
     let height = unsafe { CVPixelBufferGetHeight(pixel_buffer_ref) } as usize;
     let bytes_per_row = unsafe { CVPixelBufferGetBytesPerRow(pixel_buffer_ref) } as usize;
     let pixel_format = unsafe { CVPixelBufferGetPixelFormatType(pixel_buffer_ref) };
-    println!("{pixel_format}");
+    if let Some(range) = ycbcr_range(pixel_format) {
+        return nv12_buffer_to_rgba(pixel_buffer_ref, width, height, range); // still locked
+    }
     if pixel_format != kCVPixelFormatType_32BGRA {
         eprintln!(
             "Unexpected pixel format: {}, expected kCVPixelFormatType_32BGRA",
@@ -95,6 +190,121 @@ pub fn convert_sample_buffer_to_rgba(sample_buffer: &CMSampleBuffer) -> Option<V
             dst_row[di + 3] = a;
         }
     }
+    if alpha_mode(pixel_buffer_ref) == AlphaMode::Premultiplied {
+        unpremultiply_alpha(&mut dst);
+    }
+
+    Some((dst, width as u32, height as u32))
+}
+
+/// The Y'CbCr range of a sample buffer's pixels; None for BGRA (or a buffer without pixels)
+pub fn sample_buffer_range(sample_buffer: &CMSampleBuffer) -> Option<YuvRange> {
+    let pixel_buffer = sample_buffer.get_pixel_buffer().ok()?;
+    let buffer: CVPixelBufferRef = pixel_buffer.as_concrete_TypeRef().cast();
+    ycbcr_range(unsafe { CVPixelBufferGetPixelFormatType(buffer) })
+}
+
+/// Whether a BGRA buffer's color is premultiplied: what it's tagged with, otherwise
+/// premultiplied, which is what ScreenCaptureKit and Core Graphics deliver
+fn alpha_mode(pixel_buffer_ref: CVPixelBufferRef) -> AlphaMode {
+    unsafe {
+        let value = CVBufferGetAttachment(
+            pixel_buffer_ref,
+            kCVImageBufferAlphaChannelModeKey,
+            std::ptr::null_mut(),
+        );
+        if !value.is_null()
+            && CFString::wrap_under_get_rule(value.cast())
+                == CFString::wrap_under_get_rule(
+                    kCVImageBufferAlphaChannelMode_StraightAlpha.cast(),
+                )
+        {
+            AlphaMode::Straight
+        } else {
+            AlphaMode::Premultiplied
+        }
+    }
+}
 
-    Some(dst)
+/// The range of a bi-planar Y'CbCr pixel format; None for other formats
+fn ycbcr_range(pixel_format: u32) -> Option<YuvRange> {
+    match pixel_format {
+        kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange => Some(YuvRange::Video),
+        kCVPixelFormatType_420YpCbCr8BiPlanarFullRange => Some(YuvRange::Full),
+        _ => None,
+    }
+}
+
+/// Convert a locked bi-planar Y'CbCr buffer with the matrix it's tagged with
+fn nv12_buffer_to_rgba(
+    pixel_buffer_ref: CVPixelBufferRef,
+    width: usize,
+    height: usize,
+    range: YuvRange,
+) -> Option<(Vec<u8>, u32, u32)> {
+    let plane = |index: usize| {
+        let base_ptr =
+            unsafe { CVPixelBufferGetBaseAddressOfPlane(pixel_buffer_ref, index) } as *const u8;
+        let bytes_per_row =
+            unsafe { CVPixelBufferGetBytesPerRowOfPlane(pixel_buffer_ref, index) } as usize;
+        let rows = if index == 0 {
+            height
+        } else {
+            height.div_ceil(2)
+        };
+        let min_bpr = if index == 0 {
+            width
+        } else {
+            width.div_ceil(2) * 2
+        };
+        if base_ptr.is_null() || bytes_per_row < min_bpr {
+            eprintln!("CVPixelBuffer plane {index} is missing or too narrow");
+            return None;
+        }
+        let len = bytes_per_row.checked_mul(rows)?;
+        Some((
+            unsafe { std::slice::from_raw_parts(base_ptr, len) },
+            bytes_per_row,
+        ))
+    };
+    let (y_plane, y_stride) = plane(0)?;
+    let (uv_plane, uv_stride) = plane(1)?;
+
+    let rgba = nv12_to_rgba(
+        y_plane,
+        y_stride,
+        uv_plane,
+        uv_stride,
+        width as u32,
+        height as u32,
+        YuvColorSpace {
+            matrix: ycbcr_matrix(pixel_buffer_ref),
+            range,
+        },
+    );
+    Some((rgba, width as u32, height as u32))
+}
+
+/// The Y'CbCr matrix a buffer is tagged with; untagged buffers are taken as BT.709, which
+/// ScreenCaptureKit and HD cameras use
+fn ycbcr_matrix(pixel_buffer_ref: CVPixelBufferRef) -> YuvMatrix {
+    unsafe {
+        let value = CVBufferGetAttachment(
+            pixel_buffer_ref,
+            kCVImageBufferYCbCrMatrixKey,
+            std::ptr::null_mut(),
+        );
+        if value.is_null() {
+            return YuvMatrix::Bt709;
+        }
+        let value = CFString::wrap_under_get_rule(value.cast());
+        let matches = |key: *const c_void| value == CFString::wrap_under_get_rule(key.cast());
+        if matches(kCVImageBufferYCbCrMatrix_ITU_R_601_4) {
+            YuvMatrix::Bt601
+        } else if matches(kCVImageBufferYCbCrMatrix_ITU_R_2020) {
+            YuvMatrix::Bt2020
+        } else {
+            YuvMatrix::Bt709
+        }
+    }
 }
@@ -1,36 +1,258 @@
-use crate::pixel_conversion::convert_sample_buffer_to_rgba;
+use crate::cross_platform_capture::{CaptureBackend, CaptureBackendRegistry};
+use crate::frame_channel::FrameChannel;
+use crate::frame_pacing::DEFAULT_FRAME_RATE;
+use crate::pixel_conversion::{
+    RetainedPixelBuffer, convert_sample_buffer_to_rgba, sample_buffer_range,
+};
+use crate::platform::macos_display::{display_scale_factor, main_display_id};
 use crate::platform::traits::{
-    DisplayResolution, PixelConverter, ScreenCapture, ScreenCaptureFactory,
+    AlphaMode, CaptureCapabilities, CaptureEvent, CaptureEventCallback, CaptureScale,
+    CaptureSource, DirtyRect, DisplayArrangement, DisplayInfo, DisplayResolution, Frame, FrameData,
+    PixelConverter, PixelFormat as FramePixelFormat, RawCaptureBuffer, ScreenCapture,
+    ScreenCaptureFactory, SourceInfo, SourceThumbnail,
 };
+use crate::scaling::blit_rgba;
+use core_foundation::error::CFError;
+use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+use core_media_rs::cm_time::CMTime;
+use objc2::runtime::AnyClass;
 use screencapturekit::{
-    output::CMSampleBuffer,
-    shareable_content::SCShareableContent,
+    output::{CMSampleBuffer, sc_stream_frame_info::SCStreamFrameInfo},
+    shareable_content::{SCShareableContent, SCWindow},
     stream::{
         SCStream, configuration::SCStreamConfiguration, configuration::pixel_format::PixelFormat,
-        content_filter::SCContentFilter, output_trait::SCStreamOutputTrait,
-        output_type::SCStreamOutputType,
+        content_filter::SCContentFilter, delegate_trait::SCStreamDelegateTrait,
+        output_trait::SCStreamOutputTrait, output_type::SCStreamOutputType, screenshot_manager,
     },
 };
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// macOS implementation using ScreenCaptureKit
 pub struct MacOSScreenCapture {
-    latest_frame: Arc<Mutex<Option<Vec<u8>>>>,
-    stream: Option<SCStream>,
+    /// Frames from the ScreenCaptureKit thread, waiting for the redaction stage
+    frames: FrameChannel,
+    /// Running streams (several when compositing all displays)
+    streams: Vec<SCStream>,
     display_resolution: Option<DisplayResolution>,
+    display_id: Option<u32>,
+    /// Source selected for the next capture (None = primary display)
+    source: Option<CaptureSource>,
+    /// Notified from the ScreenCaptureKit thread of new frames and stream errors
+    event_callback: Option<CaptureEventCallback>,
+    /// Backing pixels or points
+    capture_scale: CaptureScale,
+    /// Highest frames per second each stream delivers
+    frame_rate: u32,
+    /// Output rectangles (x, y, width, height) of each display in a multi-display composite
+    display_cells: Vec<(u32, [u32; 4])>,
+    /// Generation of the stream allowed to publish frames; bumped on every source switch so
+    /// a replaced stream can't overwrite frames from its successor while it shuts down
+    generation: Arc<AtomicU64>,
 }
 
 impl MacOSScreenCapture {
     pub fn new() -> Self {
         Self {
-            latest_frame: Arc::new(Mutex::new(None)),
-            stream: None,
+            frames: FrameChannel::default(),
+            streams: Vec::new(),
             display_resolution: None,
+            display_id: None,
+            source: None,
+            event_callback: None,
+            capture_scale: CaptureScale::Native,
+            frame_rate: DEFAULT_FRAME_RATE,
+            display_cells: Vec::new(),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+/// One stream to start for the selected source
+struct StreamPlan {
+    filter: SCContentFilter,
+    width: u32,
+    height: u32,
+    source_rect: Option<CGRect>,
+    /// Where the stream's frames go in a composited multi-display frame
+    tile: Option<Tile>,
+    /// Display shown in `tile`
+    display_id: Option<u32>,
+}
+
+/// A stream's area in the shared multi-display canvas
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    canvas: Arc<Mutex<Vec<u8>>>,
+    canvas_width: u32,
+    canvas_height: u32,
+}
+
+/// Plan one stream per display, each letterboxed into its cell of the output frame
+fn all_display_plans(
+    shareable: &SCShareableContent,
+    arrangement: DisplayArrangement,
+    width: u32,
+    height: u32,
+    excluded_windows: &[&SCWindow],
+) -> Result<Vec<StreamPlan>, String> {
+    let mut displays = shareable.displays();
+    if displays.is_empty() {
+        return Err("No displays found".to_string());
+    }
+    // Follow the desktop arrangement so the composite matches what users see
+    match arrangement {
+        DisplayArrangement::SideBySide | DisplayArrangement::Grid => {
+            displays.sort_by(|a, b| a.frame().origin.x.total_cmp(&b.frame().origin.x))
+        }
+        DisplayArrangement::Stacked => {
+            displays.sort_by(|a, b| a.frame().origin.y.total_cmp(&b.frame().origin.y))
+        }
+    }
+
+    let count = displays.len() as u32;
+    // Smallest square-ish grid that fits every display
+    let columns = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    let canvas = Arc::new(Mutex::new(
+        [0u8, 0, 0, 255].repeat((width * height) as usize),
+    ));
+    let plans = displays
+        .iter()
+        .enumerate()
+        .map(|(index, display)| {
+            let index = index as u32;
+            let (cell_x, cell_y, cell_width, cell_height) = match arrangement {
+                DisplayArrangement::SideBySide => (index * width / count, 0, width / count, height),
+                DisplayArrangement::Stacked => (0, index * height / count, width, height / count),
+                DisplayArrangement::Grid => (
+                    (index % columns) * width / columns,
+                    (index / columns) * height / rows,
+                    width / columns,
+                    height / rows,
+                ),
+            };
+
+            // Fit the display into its cell without distorting it
+            let scale = (cell_width as f64 / display.width().max(1) as f64)
+                .min(cell_height as f64 / display.height().max(1) as f64);
+            let tile_width = ((display.width() as f64 * scale) as u32).clamp(1, cell_width.max(1));
+            let tile_height =
+                ((display.height() as f64 * scale) as u32).clamp(1, cell_height.max(1));
+
+            StreamPlan {
+                filter: SCContentFilter::new()
+                    .with_display_excluding_windows(display, excluded_windows),
+                width: tile_width,
+                height: tile_height,
+                source_rect: None,
+                tile: Some(Tile {
+                    x: cell_x + cell_width.saturating_sub(tile_width) / 2,
+                    y: cell_y + cell_height.saturating_sub(tile_height) / 2,
+                    width: tile_width,
+                    height: tile_height,
+                    canvas: canvas.clone(),
+                    canvas_width: width,
+                    canvas_height: height,
+                }),
+                display_id: Some(display.display_id()),
+            }
+        })
+        .collect();
+    Ok(plans)
+}
+
+/// Convert a display-relative region in points to a CGRect
+fn region_rect(x: u32, y: u32, width: u32, height: u32) -> CGRect {
+    CGRect::new(
+        &CGPoint::new(x as f64, y as f64),
+        &CGSize::new(width.max(1) as f64, height.max(1) as f64),
+    )
+}
+
+impl MacOSScreenCapture {
+    /// Configure and start one stream; its frames are published once `generation` is current
+    fn start_stream(&self, plan: StreamPlan, generation: u64) -> Result<SCStream, String> {
+        let mut config = SCStreamConfiguration::new()
+            .set_width(plan.width)
+            .map_err(|e| format!("Failed to set width: {:?}", e))?
+            .set_height(plan.height)
+            .map_err(|e| format!("Failed to set height: {:?}", e))?
+            .set_scales_to_fit(true)
+            .map_err(|e| format!("Failed to set scales to fit: {:?}", e))?
+            .set_captures_audio(false)
+            .map_err(|e| format!("Failed to set audio: {:?}", e))?
+            .set_pixel_format(PixelFormat::BGRA)
+            .map_err(|e| format!("Failed to set pixel format: {:?}", e))?;
+        if let Some(rect) = plan.source_rect {
+            // The region is scaled to the output size like any other source
+            config = config
+                .set_source_rect(rect)
+                .map_err(|e| format!("Failed to set source rect: {:?}", e))?;
+        }
+        if self.frame_rate != DEFAULT_FRAME_RATE {
+            // The default minimum interval caps streams at 60 fps; ProMotion displays go to 120
+            let interval = CMTime {
+                value: 1,
+                timescale: self.frame_rate as i32,
+                flags: 1, // kCMTimeFlags_Valid
+                epoch: 0,
+            };
+            config = match config.clone().set_minimum_frame_interval(&interval) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to capture at {} fps: {:?}; using the default rate",
+                        self.frame_rate, e
+                    );
+                    config
+                }
+            };
         }
+
+        // Create output handler
+        let output_handler = MacOSScreenCaptureOutputHandler {
+            frames: self.frames.clone(),
+            event_callback: self.event_callback.clone(),
+            generation,
+            current_generation: self.generation.clone(),
+            tile: plan.tile,
+        };
+
+        // The delegate reports streams that stop on their own (e.g. the captured window closed)
+        let delegate = MacOSScreenCaptureDelegate {
+            event_callback: self.event_callback.clone(),
+            generation,
+            current_generation: self.generation.clone(),
+        };
+
+        let mut stream = SCStream::new_with_delegate(&plan.filter, &config, delegate);
+        stream.add_output_handler(output_handler, SCStreamOutputType::Screen);
+        stream
+            .start_capture()
+            .map_err(|e| format!("Failed to start capture: {:?}", e))?;
+        Ok(stream)
     }
 }
 
 impl ScreenCapture for MacOSScreenCapture {
+    fn capabilities(&self) -> CaptureCapabilities {
+        CaptureCapabilities {
+            window_capture: true,
+            region_capture: true,
+            // Streams use ScreenCaptureKit's defaults: cursor shown, no audio, 8-bit BGRA
+            cursor_toggle: false,
+            audio: false,
+            hdr: false,
+            // 60 (the default minimum frame interval of an SCStream) unless raised for ProMotion
+            max_fps: Some(self.frame_rate),
+        }
+    }
+
     fn get_display_resolution(&self) -> Result<DisplayResolution, String> {
         let shareable = SCShareableContent::get()
             .map_err(|e| format!("Failed to get SCShareableContent: {:?}", e))?;
@@ -38,10 +260,11 @@ impl ScreenCapture for MacOSScreenCapture {
         let displays = shareable.displays();
         let display = displays.first().ok_or("No displays found")?;
 
-        let width = display.width();
-        let height = display.height();
-
-        Ok(DisplayResolution { width, height })
+        Ok(DisplayResolution {
+            width: display.width(),
+            height: display.height(),
+            scale_factor: display_scale_factor(display.display_id()),
+        })
     }
 
     fn start_capture(
@@ -52,22 +275,30 @@ impl ScreenCapture for MacOSScreenCapture {
         let shareable = SCShareableContent::get()
             .map_err(|e| format!("Failed to get SCShareableContent: {:?}", e))?;
 
-        let display = shareable
+        let primary = shareable
             .displays()
             .first()
             .ok_or("No displays found")?
             .clone();
 
-        // Get actual display resolution
+        // Output frames always use the primary display resolution so the pipeline size stays
+        // fixed; other sources are scaled to fit by ScreenCaptureKit
         let resolution = DisplayResolution {
-            width: display.width(),
-            height: display.height(),
+            width: primary.width(),
+            height: primary.height(),
+            scale_factor: display_scale_factor(primary.display_id()),
         };
         self.display_resolution = Some(resolution);
+        let (width, height) = resolution.capture_size(self.capture_scale);
 
         println!(
-            "Capturing display at {}x{}",
-            resolution.width, resolution.height
+            "Capturing {:?} at {}x{} ({:?}, scale factor {})",
+            self.source
+                .unwrap_or(CaptureSource::Display(primary.display_id())),
+            width,
+            height,
+            self.capture_scale,
+            resolution.scale_factor
         );
 
         // Build a content filter for the display, excluding our app window if provided
@@ -87,52 +318,385 @@ impl ScreenCapture for MacOSScreenCapture {
         }
 
         let excluded_refs: Vec<&_> = excluded_windows.iter().collect();
-        let filter =
-            SCContentFilter::new().with_display_excluding_windows(&display, &excluded_refs);
-
-        // Configure the stream with actual display resolution
-        let config = SCStreamConfiguration::new()
-            .set_width(resolution.width)
-            .map_err(|e| format!("Failed to set width: {:?}", e))?
-            .set_height(resolution.height)
-            .map_err(|e| format!("Failed to set height: {:?}", e))?
-            .set_captures_audio(false)
-            .map_err(|e| format!("Failed to set audio: {:?}", e))?
-            .set_pixel_format(PixelFormat::BGRA)
-            .map_err(|e| format!("Failed to set pixel format: {:?}", e))?;
-
-        // Create output handler
-        let output_handler = MacOSScreenCaptureOutputHandler {
-            frame_data: self.latest_frame.clone(),
-            converter: MacOSPixelConverter,
+        let single = |filter, source_rect| {
+            vec![StreamPlan {
+                filter,
+                width,
+                height,
+                source_rect,
+                tile: None,
+                display_id: None,
+            }]
+        };
+        let (plans, display_id) = match self.source {
+            None => (
+                single(
+                    SCContentFilter::new().with_display_excluding_windows(&primary, &excluded_refs),
+                    None,
+                ),
+                Some(primary.display_id()),
+            ),
+            Some(CaptureSource::Display(id)) => {
+                let display = shareable
+                    .displays()
+                    .into_iter()
+                    .find(|d| d.display_id() == id)
+                    .ok_or_else(|| format!("Display {} not found", id))?;
+                (
+                    single(
+                        SCContentFilter::new()
+                            .with_display_excluding_windows(&display, &excluded_refs),
+                        None,
+                    ),
+                    Some(id),
+                )
+            }
+            Some(CaptureSource::Window(id)) => {
+                let window = shareable
+                    .windows()
+                    .into_iter()
+                    .find(|w| w.window_id() == id)
+                    .ok_or_else(|| format!("Window {} not found", id))?;
+                // A single window isn't tied to one display's Space
+                (
+                    single(
+                        SCContentFilter::new().with_desktop_independent_window(&window),
+                        None,
+                    ),
+                    None,
+                )
+            }
+            Some(CaptureSource::Region {
+                display,
+                x,
+                y,
+                width: region_width,
+                height: region_height,
+            }) => {
+                let sc_display = shareable
+                    .displays()
+                    .into_iter()
+                    .find(|d| d.display_id() == display)
+                    .ok_or_else(|| format!("Display {} not found", display))?;
+                (
+                    single(
+                        SCContentFilter::new()
+                            .with_display_excluding_windows(&sc_display, &excluded_refs),
+                        Some(region_rect(x, y, region_width, region_height)),
+                    ),
+                    Some(display),
+                )
+            }
+            Some(CaptureSource::AllDisplays(arrangement)) => {
+                // Several displays have no single Space; private Space checks look at each
+                // display in `display_cells`
+                (
+                    all_display_plans(&shareable, arrangement, width, height, &excluded_refs)?,
+                    None,
+                )
+            }
         };
 
-        // Create stream, add output, start
-        let mut stream = SCStream::new(&filter, &config);
-        stream.add_output_handler(output_handler, SCStreamOutputType::Screen);
-        stream
-            .start_capture()
-            .map_err(|e| format!("Failed to start capture: {:?}", e))?;
+        // The new streams only publish once they become the current generation
+        let generation = self.generation.load(Ordering::SeqCst) + 1;
 
-        self.stream = Some(stream);
-        println!("Screen capture started!");
+        // Start the new streams while the old ones (if any) keep running, so a failed switch
+        // leaves the current capture untouched and a successful one has no gap
+        let display_cells: Vec<(u32, [u32; 4])> = plans
+            .iter()
+            .filter_map(|plan| {
+                let tile = plan.tile.as_ref()?;
+                Some((plan.display_id?, [tile.x, tile.y, tile.width, tile.height]))
+            })
+            .collect();
+        let mut streams = Vec::with_capacity(plans.len());
+        for plan in plans {
+            match self.start_stream(plan, generation) {
+                Ok(stream) => streams.push(stream),
+                Err(e) => {
+                    for stream in streams {
+                        let _ = stream.stop_capture();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        // Atomically hand the frame buffer to the new streams, then retire the old ones
+        self.generation.store(generation, Ordering::SeqCst);
+        self.display_id = display_id;
+        self.display_cells = display_cells;
+        let previous = std::mem::replace(&mut self.streams, streams);
+        if previous.is_empty() {
+            println!("Screen capture started!");
+        } else {
+            for stream in previous {
+                if let Err(e) = stream.stop_capture() {
+                    eprintln!("Failed to stop previous capture: {:?}", e);
+                }
+            }
+            println!("Screen capture switched");
+        }
         Ok(())
     }
 
-    fn get_latest_frame(&self) -> Option<Vec<u8>> {
-        self.latest_frame.lock().ok()?.clone()
+    fn get_latest_frame(&self) -> Option<Frame> {
+        self.frames.peek_latest()
     }
 
     fn stop_capture(&mut self) {
-        if let Some(stream) = self.stream.take() {
+        if self.streams.is_empty() {
+            return;
+        }
+        // Drop any frames still in flight from the stopped streams
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        for stream in self.streams.drain(..) {
             if let Err(e) = stream.stop_capture() {
                 eprintln!("Failed to stop capture: {:?}", e);
             }
         }
     }
 
-    fn get_frame_buffer(&self) -> Arc<Mutex<Option<Vec<u8>>>> {
-        self.latest_frame.clone()
+    fn frame_channel(&self) -> FrameChannel {
+        self.frames.clone()
+    }
+
+    fn set_event_callback(&mut self, callback: CaptureEventCallback) {
+        self.event_callback = Some(callback);
+    }
+
+    fn captured_display_id(&self) -> Option<u32> {
+        self.display_id
+    }
+
+    fn captured_display_ids(&self) -> Vec<u32> {
+        match self.display_id {
+            Some(id) => vec![id],
+            None => self.display_cells.iter().map(|(id, _)| *id).collect(),
+        }
+    }
+
+    fn display_at(&self, x: u32, y: u32) -> Option<u32> {
+        self.display_cells
+            .iter()
+            .find(|(_, [cx, cy, cw, ch])| x >= *cx && x < cx + cw && y >= *cy && y < cy + ch)
+            .map(|(id, _)| *id)
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        let shareable = SCShareableContent::get()
+            .map_err(|e| format!("Failed to get SCShareableContent: {:?}", e))?;
+
+        Ok(shareable
+            .windows()
+            .iter()
+            .map(|w| {
+                let frame = w.get_frame();
+                let app = w.owning_application();
+                WindowInfo {
+                    id: w.window_id(),
+                    title: w.title(),
+                    app_name: app.application_name(),
+                    app_id: app.bundle_identifier(),
+                    frame: WindowFrame {
+                        x: frame.origin.x.round() as i32,
+                        y: frame.origin.y.round() as i32,
+                        width: frame.size.width.round().max(0.0) as u32,
+                        height: frame.size.height.round().max(0.0) as u32,
+                    },
+                    layer: w.window_layer(),
+                    on_screen: w.is_on_screen(),
+                }
+            })
+            .collect())
+    }
+
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        let shareable = SCShareableContent::get()
+            .map_err(|e| format!("Failed to get SCShareableContent: {:?}", e))?;
+
+        // Named in the same order as `list_sources`, so "Display 2" means the same display
+        let main = main_display_id();
+        Ok(shareable
+            .displays()
+            .iter()
+            .enumerate()
+            .map(|(index, display)| DisplayInfo {
+                id: display.display_id(),
+                name: format!("Display {}", index + 1),
+                width: display.width(),
+                height: display.height(),
+                scale_factor: display_scale_factor(display.display_id()),
+                main: display.display_id() == main,
+            })
+            .collect())
+    }
+
+    fn list_sources(&self) -> Result<Vec<SourceInfo>, String> {
+        let shareable = SCShareableContent::get()
+            .map_err(|e| format!("Failed to get SCShareableContent: {:?}", e))?;
+
+        let mut sources: Vec<SourceInfo> = shareable
+            .displays()
+            .iter()
+            .enumerate()
+            .map(|(index, display)| SourceInfo {
+                source: CaptureSource::Display(display.display_id()),
+                title: format!("Display {}", index + 1),
+                width: display.width(),
+                height: display.height(),
+            })
+            .collect();
+
+        // With several displays, offer them composited into one frame as well
+        let displays = shareable.displays();
+        if displays.len() > 1
+            && let Some(primary) = displays.first()
+        {
+            for (arrangement, title) in [
+                (DisplayArrangement::SideBySide, "All displays side by side"),
+                (DisplayArrangement::Stacked, "All displays stacked"),
+                (DisplayArrangement::Grid, "All displays grid"),
+            ] {
+                sources.push(SourceInfo {
+                    source: CaptureSource::AllDisplays(arrangement),
+                    title: title.to_string(),
+                    width: primary.width(),
+                    height: primary.height(),
+                });
+            }
+        }
+
+        // Only offer normal, visible app windows (layer 0) - skip menu bar items, our own windows, etc.
+        sources.extend(
+            self.list_windows()?
+                .into_iter()
+                .filter(|w| w.on_screen && w.layer == 0)
+                .filter(|w| !w.title.is_empty() && !w.title.contains("CloakShare"))
+                .map(|w| SourceInfo {
+                    source: CaptureSource::Window(w.id),
+                    title: format!("{} - {}", w.app_name, w.title),
+                    width: w.frame.width,
+                    height: w.frame.height,
+                }),
+        );
+
+        Ok(sources)
+    }
+
+    fn set_source(&mut self, source: CaptureSource) {
+        self.source = Some(source);
+    }
+
+    fn set_capture_scale(&mut self, scale: CaptureScale) {
+        self.capture_scale = scale;
+    }
+
+    fn set_frame_rate(&mut self, fps: u32) {
+        self.frame_rate = fps.max(1);
+    }
+
+    fn capture_thumbnail(
+        &self,
+        source: CaptureSource,
+        max_width: u32,
+        max_height: u32,
+    ) -> Result<SourceThumbnail, String> {
+        let shareable = SCShareableContent::get()
+            .map_err(|e| format!("Failed to get SCShareableContent: {:?}", e))?;
+
+        let (filter, source_width, source_height, source_rect) = match source {
+            CaptureSource::AllDisplays(_) => {
+                return Err("No preview for combined displays".to_string());
+            }
+            CaptureSource::Display(id) => {
+                let display = shareable
+                    .displays()
+                    .into_iter()
+                    .find(|d| d.display_id() == id)
+                    .ok_or_else(|| format!("Display {} not found", id))?;
+                let (w, h) = (display.width(), display.height());
+                (
+                    SCContentFilter::new().with_display_excluding_windows(&display, &[]),
+                    w,
+                    h,
+                    None,
+                )
+            }
+            CaptureSource::Region {
+                display,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let sc_display = shareable
+                    .displays()
+                    .into_iter()
+                    .find(|d| d.display_id() == display)
+                    .ok_or_else(|| format!("Display {} not found", display))?;
+                (
+                    SCContentFilter::new().with_display_excluding_windows(&sc_display, &[]),
+                    width,
+                    height,
+                    Some(region_rect(x, y, width, height)),
+                )
+            }
+            CaptureSource::Window(id) => {
+                let window = shareable
+                    .windows()
+                    .into_iter()
+                    .find(|w| w.window_id() == id)
+                    .ok_or_else(|| format!("Window {} not found", id))?;
+                let frame = window.get_frame();
+                (
+                    SCContentFilter::new().with_desktop_independent_window(&window),
+                    frame.size.width as u32,
+                    frame.size.height as u32,
+                    None,
+                )
+            }
+        };
+
+        // Scale the source down to fit the thumbnail box, preserving aspect ratio
+        let scale = (max_width as f64 / source_width.max(1) as f64)
+            .min(max_height as f64 / source_height.max(1) as f64)
+            .min(1.0);
+        let width = ((source_width as f64 * scale) as u32).max(1);
+        let height = ((source_height as f64 * scale) as u32).max(1);
+
+        let mut config = SCStreamConfiguration::new()
+            .set_width(width)
+            .map_err(|e| format!("Failed to set width: {:?}", e))?
+            .set_height(height)
+            .map_err(|e| format!("Failed to set height: {:?}", e))?
+            .set_pixel_format(PixelFormat::BGRA)
+            .map_err(|e| format!("Failed to set pixel format: {:?}", e))?;
+        if let Some(rect) = source_rect {
+            config = config
+                .set_source_rect(rect)
+                .map_err(|e| format!("Failed to set source rect: {:?}", e))?;
+        }
+
+        let sample_buffer = screenshot_manager::capture(&filter, &config)
+            .map_err(|e| format!("Failed to capture thumbnail: {:?}", e))?;
+        let rgba = convert_sample_buffer_to_rgba(&sample_buffer)
+            .ok_or("Failed to convert thumbnail pixels")?;
+
+        if rgba.len() != (width * height * 4) as usize {
+            return Err(format!(
+                "Thumbnail has unexpected size: {} bytes for {}x{}",
+                rgba.len(),
+                width,
+                height
+            ));
+        }
+
+        Ok(SourceThumbnail {
+            width,
+            height,
+            rgba,
+        })
     }
 }
 
@@ -153,24 +717,44 @@ impl ScreenCaptureFactory for MacOSScreenCaptureFactory {
     }
 }
 
+/// Register ScreenCaptureKit, which needs macOS 12.3 or later
+pub fn register_capture_backends(registry: &mut CaptureBackendRegistry) {
+    registry.register(CaptureBackend {
+        name: "screencapturekit".to_string(),
+        priority: 100,
+        is_available: Arc::new(|| AnyClass::get("SCShareableContent").is_some()),
+        create: Arc::new(|| {
+            Ok((
+                Box::new(MacOSScreenCaptureFactory::create()) as Box<dyn ScreenCapture>,
+                Box::new(MacOSPixelConverter) as Box<dyn PixelConverter>,
+            ))
+        }),
+    });
+}
+
 /// macOS pixel converter using Core Video
 pub struct MacOSPixelConverter;
 
 impl PixelConverter for MacOSPixelConverter {
-    fn convert_to_rgba(&self, buffer: &dyn std::any::Any) -> Option<Vec<u8>> {
-        // Try to downcast to CMSampleBuffer
-        if let Some(sample_buffer) = buffer.downcast_ref::<CMSampleBuffer>() {
-            convert_sample_buffer_to_rgba(sample_buffer)
-        } else {
-            None
+    fn convert_to_rgba(&self, buffer: RawCaptureBuffer<'_>) -> Option<Vec<u8>> {
+        match buffer {
+            RawCaptureBuffer::SampleBuffer(sample_buffer) => {
+                convert_sample_buffer_to_rgba(sample_buffer).map(|(rgba, _, _)| rgba)
+            }
+            mapped => mapped.mapped_to_rgba(),
         }
     }
 }
 
 /// Output handler for ScreenCaptureKit frames on macOS
 struct MacOSScreenCaptureOutputHandler {
-    frame_data: Arc<Mutex<Option<Vec<u8>>>>,
-    converter: MacOSPixelConverter,
+    frames: FrameChannel,
+    event_callback: Option<CaptureEventCallback>,
+    /// Generation of the stream this handler belongs to
+    generation: u64,
+    current_generation: Arc<AtomicU64>,
+    /// Set when this stream is one display of a multi-display composite
+    tile: Option<Tile>,
 }
 
 impl SCStreamOutputTrait for MacOSScreenCaptureOutputHandler {
@@ -179,13 +763,130 @@ impl SCStreamOutputTrait for MacOSScreenCaptureOutputHandler {
         sample_buffer: CMSampleBuffer,
         output_type: SCStreamOutputType,
     ) {
-        if matches!(output_type, SCStreamOutputType::Screen) {
-            if let Some(rgba_data) = self.converter.convert_to_rgba(&sample_buffer) {
-                if let Ok(mut latest) = self.frame_data.lock() {
-                    *latest = Some(rgba_data);
-                }
+        // Frames from a stream that is starting up or being replaced are not published
+        if self.current_generation.load(Ordering::SeqCst) != self.generation {
+            return;
+        }
+        if !matches!(output_type, SCStreamOutputType::Screen) {
+            return;
+        }
+        let Some(mut frame) = self.frame_from_sample_buffer(&sample_buffer) else {
+            return;
+        };
+        // Damage is reported in the stream's own frame, which is a tile of composites
+        let (width, height) = self
+            .tile
+            .as_ref()
+            .map_or((frame.width, frame.height), |tile| {
+                (tile.width, tile.height)
+            });
+        if let Ok(info) = SCStreamFrameInfo::from_sample_buffer(&sample_buffer) {
+            // Backing scale of the display times the scale applied to fit the output
+            frame.content_scale =
+                info.scale_factor().unwrap_or(1.0) * info.content_scale().unwrap_or(1.0);
+            frame.dirty_rects = info.dirty_rects().map(|rects| {
+                rects
+                    .iter()
+                    .filter_map(|r| dirty_rect(r, width, height))
+                    .collect()
+            });
+        }
+        if let Some(tile) = &self.tile
+            && let Some(rects) = &mut frame.dirty_rects
+        {
+            for rect in rects.iter_mut() {
+                rect.x += tile.x;
+                rect.y += tile.y;
             }
         }
+
+        self.frames.send(frame);
+        // Signal after queueing so the consumer can take the frame immediately
+        if let Some(callback) = &self.event_callback {
+            callback(CaptureEvent::FrameReady);
+        }
+    }
+}
+
+impl MacOSScreenCaptureOutputHandler {
+    /// Wrap a stream frame; single sources stay in their IOSurface until read, composite tiles
+    /// are converted and drawn into the shared canvas, which is published whole
+    fn frame_from_sample_buffer(&self, sample_buffer: &CMSampleBuffer) -> Option<Frame> {
+        let Some(tile) = &self.tile else {
+            let Some(buffer) = RetainedPixelBuffer::from_sample_buffer(sample_buffer) else {
+                // Y'CbCr frames are converted (with their tagged matrix) here instead
+                let (rgba_data, width, height) = convert_sample_buffer_to_rgba(sample_buffer)?;
+                let mut frame = Frame::from_rgba(rgba_data, width, height);
+                frame.color_range = sample_buffer_range(sample_buffer);
+                return Some(frame);
+            };
+            return Some(Frame {
+                width: buffer.width,
+                height: buffer.height,
+                stride: buffer.bytes_per_row,
+                pixel_format: FramePixelFormat::Bgra8,
+                alpha_mode: buffer.alpha_mode,
+                timestamp: Instant::now(),
+                content_scale: 1.0,
+                dirty_rects: None,
+                color_range: None,
+                data: FrameData::Gpu(Arc::new(buffer)),
+            });
+        };
+
+        let (rgba_data, width, height) = convert_sample_buffer_to_rgba(sample_buffer)?;
+        if width != tile.width || height != tile.height {
+            return None;
+        }
+        let mut canvas = tile.canvas.lock().ok()?;
+        blit_rgba(
+            &mut canvas,
+            tile.canvas_width,
+            tile.canvas_height,
+            &rgba_data,
+            tile.width,
+            tile.x,
+            tile.y,
+        );
+        Some(Frame::from_rgba(
+            canvas.clone(),
+            tile.canvas_width,
+            tile.canvas_height,
+        ))
+    }
+}
+
+/// Clip a ScreenCaptureKit dirty rectangle (frame pixels) to the frame; None if it's empty
+fn dirty_rect(rect: &CGRect, width: u32, height: u32) -> Option<DirtyRect> {
+    let x = (rect.origin.x.max(0.0).floor() as u32).min(width);
+    let y = (rect.origin.y.max(0.0).floor() as u32).min(height);
+    let right = ((rect.origin.x + rect.size.width).ceil().max(0.0) as u32).min(width);
+    let bottom = ((rect.origin.y + rect.size.height).ceil().max(0.0) as u32).min(height);
+    (right > x && bottom > y).then_some(DirtyRect {
+        x,
+        y,
+        width: right - x,
+        height: bottom - y,
+    })
+}
+
+/// Stream delegate forwarding ScreenCaptureKit errors to the event callback
+struct MacOSScreenCaptureDelegate {
+    event_callback: Option<CaptureEventCallback>,
+    generation: u64,
+    current_generation: Arc<AtomicU64>,
+}
+
+impl SCStreamDelegateTrait for MacOSScreenCaptureDelegate {
+    fn did_stop_with_error(&self, _stream: SCStream, error: CFError) {
+        // Errors from a stream that was already replaced don't affect the capture
+        if self.current_generation.load(Ordering::SeqCst) != self.generation {
+            return;
+        }
+        eprintln!("Screen capture stopped with error: {}", error);
+        if let Some(callback) = &self.event_callback {
+            callback(CaptureEvent::Stopped(error.to_string()));
+        }
     }
 }
 
@@ -0,0 +1,34 @@
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send, msg_send_id};
+use std::ffi::{CString, c_char};
+
+/// NSAlertStyleCritical
+const ALERT_STYLE_CRITICAL: usize = 2;
+
+/// NSAlertFirstButtonReturn; later buttons count up from here
+const FIRST_BUTTON_RETURN: isize = 1000;
+
+/// Show a modal critical alert and wait for a button; returns the index of the clicked button
+pub fn show_alert(title: &str, message: &str, buttons: &[&str]) -> Option<usize> {
+    unsafe {
+        let alert: Retained<AnyObject> = msg_send_id![class!(NSAlert), new];
+        let _: () = msg_send![&*alert, setAlertStyle: ALERT_STYLE_CRITICAL];
+        let _: () = msg_send![&*alert, setMessageText: &*ns_string(title)];
+        let _: () = msg_send![&*alert, setInformativeText: &*ns_string(message)];
+        for button in buttons {
+            let _: *mut AnyObject = msg_send![&*alert, addButtonWithTitle: &*ns_string(button)];
+        }
+
+        let response: isize = msg_send![&*alert, runModal];
+        let index = usize::try_from(response - FIRST_BUTTON_RETURN).ok()?;
+        (index < buttons.len()).then_some(index)
+    }
+}
+
+fn ns_string(text: &str) -> Retained<AnyObject> {
+    // Interior NULs can't cross into C; drop them rather than the whole string
+    let text = CString::new(text.replace('\0', "")).unwrap_or_default();
+    let utf8: *const c_char = text.as_ptr();
+    unsafe { msg_send_id![class!(NSString), stringWithUTF8String: utf8] }
+}
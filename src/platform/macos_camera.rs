@@ -0,0 +1,258 @@
+use crate::pixel_conversion::convert_pixel_buffer_to_rgba;
+use core_video_sys::{
+    CVPixelBufferRef, kCVPixelBufferPixelFormatTypeKey, kCVPixelFormatType_32BGRA,
+};
+use objc2::rc::Retained;
+use objc2::runtime::{AnyObject, Bool, NSObject};
+use objc2::{ClassType, DeclaredClass, class, declare_class, msg_send, msg_send_id, mutability};
+use std::ffi::{CStr, c_char, c_void};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Called on the camera queue with each frame as (rgba, width, height)
+pub type FrameCallback = Box<dyn Fn(Vec<u8>, u32, u32) + Send + Sync>;
+
+/// AVAuthorizationStatus values that mean the user (or policy) blocked camera access
+const AUTHORIZATION_RESTRICTED: isize = 1;
+const AUTHORIZATION_DENIED: isize = 2;
+
+/// The frame callback and how often it may run
+struct FrameHandler {
+    on_frame: FrameCallback,
+    /// Frames arriving sooner than this after the last delivered one are dropped unconverted
+    min_interval: Option<Duration>,
+    last_delivered: Mutex<Option<Instant>>,
+}
+
+impl FrameHandler {
+    /// Whether a frame arriving now should be converted and delivered
+    fn due(&self) -> bool {
+        let Some(min_interval) = self.min_interval else {
+            return true;
+        };
+        let Ok(mut last) = self.last_delivered.lock() else {
+            return true;
+        };
+        let now = Instant::now();
+        if last.is_some_and(|last| now.duration_since(last) < min_interval) {
+            return false;
+        }
+        *last = Some(now);
+        true
+    }
+}
+
+#[link(name = "AVFoundation", kind = "framework")]
+unsafe extern "C" {
+    static AVMediaTypeVideo: *const AnyObject;
+}
+
+#[link(name = "CoreMedia", kind = "framework")]
+unsafe extern "C" {
+    fn CMSampleBufferGetImageBuffer(sample_buffer: *mut c_void) -> CVPixelBufferRef;
+}
+
+unsafe extern "C" {
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut AnyObject;
+    fn dispatch_release(object: *mut AnyObject);
+}
+
+declare_class!(
+    /// AVCaptureVideoDataOutputSampleBufferDelegate forwarding converted frames to a callback
+    struct FrameDelegate;
+
+    // SAFETY: NSObject has no subclassing requirements and FrameDelegate doesn't implement Drop
+    unsafe impl ClassType for FrameDelegate {
+        type Super = NSObject;
+        type Mutability = mutability::InteriorMutable;
+        const NAME: &'static str = "CloakShareCameraFrameDelegate";
+    }
+
+    impl DeclaredClass for FrameDelegate {
+        type Ivars = FrameHandler;
+    }
+
+    unsafe impl FrameDelegate {
+        #[method(captureOutput:didOutputSampleBuffer:fromConnection:)]
+        fn did_output_sample_buffer(
+            &self,
+            _output: *mut AnyObject,
+            sample_buffer: *mut c_void,
+            _connection: *mut AnyObject,
+        ) {
+            let handler = self.ivars();
+            if !handler.due() {
+                return;
+            }
+            let pixel_buffer = unsafe { CMSampleBufferGetImageBuffer(sample_buffer) };
+            if pixel_buffer.is_null() {
+                return;
+            }
+            if let Some((rgba, width, height)) = convert_pixel_buffer_to_rgba(pixel_buffer) {
+                (handler.on_frame)(rgba, width, height);
+            }
+        }
+    }
+);
+
+impl FrameDelegate {
+    fn new(handler: FrameHandler) -> Retained<Self> {
+        let this = Self::alloc().set_ivars(handler);
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+/// A running AVFoundation capture session delivering BGRA camera frames
+/// Stops when dropped
+pub struct CameraSession {
+    session: Retained<AnyObject>,
+    output: Retained<AnyObject>,
+    _delegate: Retained<FrameDelegate>,
+}
+
+impl CameraSession {
+    /// Open a camera (by name, or the system default) and start delivering frames to `on_frame`,
+    /// at most `frame_rate` per second when set
+    pub fn start(
+        device_name: Option<&str>,
+        frame_rate: Option<u32>,
+        on_frame: FrameCallback,
+    ) -> Result<Self, String> {
+        unsafe {
+            let status: isize = msg_send![
+                class!(AVCaptureDevice),
+                authorizationStatusForMediaType: AVMediaTypeVideo
+            ];
+            if status == AUTHORIZATION_RESTRICTED || status == AUTHORIZATION_DENIED {
+                return Err(
+                    "Camera access denied; allow it in System Settings > Privacy & Security > Camera"
+                        .to_string(),
+                );
+            }
+
+            let device = match device_name {
+                Some(name) => camera_devices()
+                    .into_iter()
+                    .find(|device| device_name_of(device).eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("No camera named {:?}", name))?,
+                None => {
+                    let device: Option<Retained<AnyObject>> = msg_send_id![
+                        class!(AVCaptureDevice),
+                        defaultDeviceWithMediaType: AVMediaTypeVideo
+                    ];
+                    device.ok_or("No camera found")?
+                }
+            };
+            let name = device_name_of(&device);
+
+            let no_error: *mut *mut AnyObject = std::ptr::null_mut();
+            let input: Option<Retained<AnyObject>> = msg_send_id![
+                class!(AVCaptureDeviceInput),
+                deviceInputWithDevice: &*device,
+                error: no_error
+            ];
+            let input = input.ok_or_else(|| format!("Failed to open camera {}", name))?;
+
+            let session: Retained<AnyObject> = msg_send_id![class!(AVCaptureSession), new];
+            let can_add: Bool = msg_send![&*session, canAddInput: &*input];
+            if !can_add.as_bool() {
+                return Err(format!(
+                    "Camera {} can't be added to a capture session",
+                    name
+                ));
+            }
+            let _: () = msg_send![&*session, addInput: &*input];
+
+            // Ask for BGRA so frames go through the same conversion as screen captures
+            let output: Retained<AnyObject> = msg_send_id![class!(AVCaptureVideoDataOutput), new];
+            let format: Retained<AnyObject> = msg_send_id![
+                class!(NSNumber),
+                numberWithUnsignedInt: kCVPixelFormatType_32BGRA
+            ];
+            let format_key = kCVPixelBufferPixelFormatTypeKey as *const AnyObject;
+            let settings: Retained<AnyObject> = msg_send_id![
+                class!(NSDictionary),
+                dictionaryWithObject: &*format,
+                forKey: format_key
+            ];
+            let _: () = msg_send![&*output, setVideoSettings: &*settings];
+            // A slow consumer should see the newest frame, not a growing backlog
+            let _: () = msg_send![&*output, setAlwaysDiscardsLateVideoFrames: Bool::YES];
+
+            let delegate = FrameDelegate::new(FrameHandler {
+                on_frame,
+                min_interval: frame_rate.map(|fps| Duration::from_secs(1) / fps.max(1)),
+                last_delivered: Mutex::new(None),
+            });
+            let queue = dispatch_queue_create(c"cloak-share.camera".as_ptr(), std::ptr::null());
+            let _: () = msg_send![&*output, setSampleBufferDelegate: &*delegate, queue: queue];
+            // The output retains the queue
+            dispatch_release(queue);
+
+            let can_add: Bool = msg_send![&*session, canAddOutput: &*output];
+            if !can_add.as_bool() {
+                return Err(format!("Camera {} can't deliver video frames", name));
+            }
+            let _: () = msg_send![&*session, addOutput: &*output];
+            let _: () = msg_send![&*session, startRunning];
+            println!("Started camera {}", name);
+
+            Ok(Self {
+                session,
+                output,
+                _delegate: delegate,
+            })
+        }
+    }
+}
+
+impl Drop for CameraSession {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![&*self.session, stopRunning];
+            // Detach the delegate so no callback can run after it's released
+            let no_delegate: *mut AnyObject = std::ptr::null_mut();
+            let _: () = msg_send![
+                &*self.output,
+                setSampleBufferDelegate: no_delegate,
+                queue: no_delegate
+            ];
+        }
+    }
+}
+
+/// Names of the cameras available for a webcam layer
+pub fn list_cameras() -> Vec<String> {
+    camera_devices()
+        .iter()
+        .map(|device| device_name_of(device))
+        .collect()
+}
+
+fn camera_devices() -> Vec<Retained<AnyObject>> {
+    unsafe {
+        let devices: Option<Retained<AnyObject>> =
+            msg_send_id![class!(AVCaptureDevice), devicesWithMediaType: AVMediaTypeVideo];
+        let Some(devices) = devices else {
+            return Vec::new();
+        };
+        let count: usize = msg_send![&*devices, count];
+        (0..count)
+            .map(|index| msg_send_id![&*devices, objectAtIndex: index])
+            .collect()
+    }
+}
+
+fn device_name_of(device: &AnyObject) -> String {
+    unsafe {
+        let name: Option<Retained<AnyObject>> = msg_send_id![device, localizedName];
+        let Some(name) = name else {
+            return String::new();
+        };
+        let utf8: *const c_char = msg_send![&*name, UTF8String];
+        if utf8.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(utf8).to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,38 @@
+use core_foundation::base::CFRelease;
+use std::ffi::c_void;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventCreate(source: *const c_void) -> *const c_void;
+    fn CGEventGetLocation(event: *const c_void) -> CGPoint;
+    fn CGGetDisplaysWithPoint(
+        point: CGPoint,
+        max_displays: u32,
+        displays: *mut u32,
+        matching_display_count: *mut u32,
+    ) -> i32;
+}
+
+/// Get the ID of the display the mouse cursor is currently on
+pub fn display_under_cursor() -> Option<u32> {
+    // A null-source event reports the current cursor position in global display coordinates
+    let event = unsafe { CGEventCreate(std::ptr::null()) };
+    if event.is_null() {
+        return None;
+    }
+    let location = unsafe { CGEventGetLocation(event) };
+    unsafe { CFRelease(event) };
+
+    let mut display_id = 0u32;
+    let mut count = 0u32;
+    let result = unsafe { CGGetDisplaysWithPoint(location, 1, &mut display_id, &mut count) };
+
+    (result == 0 && count > 0).then_some(display_id)
+}
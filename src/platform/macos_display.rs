@@ -0,0 +1,32 @@
+use core_foundation::base::CFRelease;
+use std::ffi::c_void;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayCopyDisplayMode(display: u32) -> *const c_void;
+    fn CGDisplayModeGetWidth(mode: *const c_void) -> usize;
+    fn CGDisplayModeGetPixelWidth(mode: *const c_void) -> usize;
+    fn CGMainDisplayID() -> u32;
+}
+
+/// The display with the menu bar
+pub fn main_display_id() -> u32 {
+    unsafe { CGMainDisplayID() }
+}
+
+/// Backing pixels per point for a display (2.0 on Retina, 1.0 on standard displays)
+/// Falls back to 1.0 if the display mode can't be read
+pub fn display_scale_factor(display_id: u32) -> f64 {
+    let mode = unsafe { CGDisplayCopyDisplayMode(display_id) };
+    if mode.is_null() {
+        return 1.0;
+    }
+    let points = unsafe { CGDisplayModeGetWidth(mode) };
+    let pixels = unsafe { CGDisplayModeGetPixelWidth(mode) };
+    unsafe { CFRelease(mode) };
+
+    if points == 0 || pixels == 0 {
+        return 1.0;
+    }
+    pixels as f64 / points as f64
+}
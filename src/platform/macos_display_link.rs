@@ -0,0 +1,113 @@
+use std::ffi::c_void;
+use std::sync::Arc;
+
+/// kCVReturnSuccess
+const CV_RETURN_SUCCESS: i32 = 0;
+
+type CVDisplayLinkRef = *mut c_void;
+
+/// CVDisplayLinkOutputCallback; the timestamps aren't read, so they stay opaque
+type OutputCallback = unsafe extern "C" fn(
+    link: CVDisplayLinkRef,
+    now: *const c_void,
+    output_time: *const c_void,
+    flags_in: u64,
+    flags_out: *mut u64,
+    context: *mut c_void,
+) -> i32;
+
+#[link(name = "CoreVideo", kind = "framework")]
+unsafe extern "C" {
+    fn CVDisplayLinkCreateWithCGDisplay(display: u32, link: *mut CVDisplayLinkRef) -> i32;
+    fn CVDisplayLinkSetOutputCallback(
+        link: CVDisplayLinkRef,
+        callback: OutputCallback,
+        context: *mut c_void,
+    ) -> i32;
+    fn CVDisplayLinkSetCurrentCGDisplay(link: CVDisplayLinkRef, display: u32) -> i32;
+    fn CVDisplayLinkStart(link: CVDisplayLinkRef) -> i32;
+    fn CVDisplayLinkStop(link: CVDisplayLinkRef) -> i32;
+    fn CVDisplayLinkRelease(link: CVDisplayLinkRef);
+    fn CVDisplayLinkGetActualOutputVideoRefreshPeriod(link: CVDisplayLinkRef) -> f64;
+}
+
+type Tick = dyn Fn() + Send + Sync;
+
+/// Calls a closure on CoreVideo's display link thread once per refresh of a display
+pub struct DisplayLink {
+    link: CVDisplayLinkRef,
+    /// Handed to the callback as its context; kept alive until the link is released
+    _tick: Arc<Box<Tick>>,
+}
+
+// SAFETY: CVDisplayLink is thread-safe; the closure is Send + Sync
+unsafe impl Send for DisplayLink {}
+
+impl DisplayLink {
+    /// Start ticking with `display`'s refresh (a CGDirectDisplayID)
+    pub fn new(display: u32, tick: impl Fn() + Send + Sync + 'static) -> Result<Self, String> {
+        let mut link: CVDisplayLinkRef = std::ptr::null_mut();
+        let result = unsafe { CVDisplayLinkCreateWithCGDisplay(display, &mut link) };
+        if result != CV_RETURN_SUCCESS || link.is_null() {
+            return Err(format!("Failed to create display link (error {})", result));
+        }
+
+        let tick: Arc<Box<Tick>> = Arc::new(Box::new(tick));
+        let context = Arc::as_ptr(&tick) as *mut c_void;
+        let display_link = Self { link, _tick: tick };
+        let result = unsafe { CVDisplayLinkSetOutputCallback(link, on_tick, context) };
+        if result != CV_RETURN_SUCCESS {
+            return Err(format!(
+                "Failed to set display link callback (error {})",
+                result
+            ));
+        }
+        let result = unsafe { CVDisplayLinkStart(link) };
+        if result != CV_RETURN_SUCCESS {
+            return Err(format!("Failed to start display link (error {})", result));
+        }
+        Ok(display_link)
+    }
+
+    /// Follow another display's refresh (the window moved to a different monitor)
+    pub fn set_display(&self, display: u32) -> Result<(), String> {
+        match unsafe { CVDisplayLinkSetCurrentCGDisplay(self.link, display) } {
+            CV_RETURN_SUCCESS => Ok(()),
+            error => Err(format!(
+                "Failed to move display link to display {} (error {})",
+                display, error
+            )),
+        }
+    }
+
+    /// Refresh rate the link is ticking at, in Hz (None until the display reports it)
+    pub fn refresh_rate(&self) -> Option<f64> {
+        let period = unsafe { CVDisplayLinkGetActualOutputVideoRefreshPeriod(self.link) };
+        (period > 0.0).then(|| 1.0 / period)
+    }
+}
+
+impl Drop for DisplayLink {
+    fn drop(&mut self) {
+        // Stop waits for a running callback, so the context outlives every call
+        unsafe {
+            CVDisplayLinkStop(self.link);
+            CVDisplayLinkRelease(self.link);
+        }
+    }
+}
+
+unsafe extern "C" fn on_tick(
+    _link: CVDisplayLinkRef,
+    _now: *const c_void,
+    _output_time: *const c_void,
+    _flags_in: u64,
+    _flags_out: *mut u64,
+    context: *mut c_void,
+) -> i32 {
+    // SAFETY: context points into the DisplayLink's closure, alive until the link is released
+    if let Some(tick) = unsafe { (context as *const Box<Tick>).as_ref() } {
+        tick();
+    }
+    CV_RETURN_SUCCESS
+}
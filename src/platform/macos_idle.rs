@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+/// kCGEventSourceStateCombinedSessionState: input from any source in the login session
+const COMBINED_SESSION_STATE: i32 = 0;
+
+/// kCGAnyInputEventType
+const ANY_INPUT_EVENT: u32 = !0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+}
+
+/// Time since the last keyboard, mouse or trackpad input anywhere in the session
+pub fn time_since_input() -> Duration {
+    let seconds =
+        unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT) };
+    Duration::try_from_secs_f64(seconds).unwrap_or_default()
+}
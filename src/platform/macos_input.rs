@@ -0,0 +1,246 @@
+use crate::platform::WindowFrame;
+use crate::remote_input::{PointerButton, RemoteInputEvent, SpecialKey};
+use core_foundation::base::CFRelease;
+use std::ffi::c_void;
+use std::time::{Duration, Instant};
+
+/// kCGHIDEventTap: post as if the events came from the hardware
+const HID_EVENT_TAP: u32 = 0;
+
+/// kCGScrollEventUnitPixel
+const SCROLL_UNIT_PIXEL: u32 = 0;
+
+/// kCGMouseEventClickState
+const MOUSE_EVENT_CLICK_STATE: u32 = 1;
+
+/// Clicks closer together than this count as a double (or triple) click
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// UTF-16 units CGEventKeyboardSetUnicodeString reliably takes per event
+const MAX_UNICODE_PER_EVENT: usize = 20;
+
+// CGEventType values
+const LEFT_MOUSE_DOWN: u32 = 1;
+const LEFT_MOUSE_UP: u32 = 2;
+const RIGHT_MOUSE_DOWN: u32 = 3;
+const RIGHT_MOUSE_UP: u32 = 4;
+const MOUSE_MOVED: u32 = 5;
+const LEFT_MOUSE_DRAGGED: u32 = 6;
+const RIGHT_MOUSE_DRAGGED: u32 = 7;
+const OTHER_MOUSE_DOWN: u32 = 25;
+const OTHER_MOUSE_UP: u32 = 26;
+const OTHER_MOUSE_DRAGGED: u32 = 27;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CGSize {
+    width: f64,
+    height: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CGRect {
+    origin: CGPoint,
+    size: CGSize,
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGMainDisplayID() -> u32;
+    fn CGDisplayBounds(display: u32) -> CGRect;
+    fn CGEventCreateMouseEvent(
+        source: *const c_void,
+        event_type: u32,
+        position: CGPoint,
+        button: u32,
+    ) -> *const c_void;
+    fn CGEventCreateScrollWheelEvent2(
+        source: *const c_void,
+        units: u32,
+        wheel_count: u32,
+        wheel1: i32,
+        wheel2: i32,
+        wheel3: i32,
+    ) -> *const c_void;
+    fn CGEventCreateKeyboardEvent(
+        source: *const c_void,
+        keycode: u16,
+        key_down: bool,
+    ) -> *const c_void;
+    fn CGEventKeyboardSetUnicodeString(event: *const c_void, length: usize, string: *const u16);
+    fn CGEventSetIntegerValueField(event: *const c_void, field: u32, value: i64);
+    fn CGEventPost(tap: u32, event: *const c_void);
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+}
+
+/// Whether macOS lets this process post input events (the Accessibility permission)
+pub fn input_injection_permitted() -> bool {
+    unsafe { AXIsProcessTrusted() }
+}
+
+/// Desktop rectangle of `display` (the main display for None), in global points
+pub fn display_bounds(display: Option<u32>) -> Option<WindowFrame> {
+    let bounds = unsafe { CGDisplayBounds(display.unwrap_or_else(|| CGMainDisplayID())) };
+    (bounds.size.width > 0.0 && bounds.size.height > 0.0).then(|| WindowFrame {
+        x: bounds.origin.x as i32,
+        y: bounds.origin.y as i32,
+        width: bounds.size.width as u32,
+        height: bounds.size.height as u32,
+    })
+}
+
+/// Posts remote input as CGEvents, remembering what macOS needs to make sense of them:
+/// where the pointer is, which buttons are held (moves become drags) and recent clicks
+#[derive(Default)]
+pub struct Injector {
+    position: CGPoint,
+    held: Option<PointerButton>,
+    /// Last press, for double-click detection: button, time and click count
+    last_click: Option<(PointerButton, Instant, i64)>,
+}
+
+impl Injector {
+    pub fn inject(&mut self, event: &RemoteInputEvent, area: &WindowFrame) -> Result<(), String> {
+        match event {
+            RemoteInputEvent::Move { x, y } => {
+                self.position = CGPoint {
+                    x: area.x as f64 + x.clamp(0.0, 1.0) as f64 * area.width as f64,
+                    y: area.y as f64 + y.clamp(0.0, 1.0) as f64 * area.height as f64,
+                };
+                let (event_type, button) = match self.held {
+                    Some(PointerButton::Left) => (LEFT_MOUSE_DRAGGED, PointerButton::Left),
+                    Some(PointerButton::Right) => (RIGHT_MOUSE_DRAGGED, PointerButton::Right),
+                    Some(PointerButton::Middle) => (OTHER_MOUSE_DRAGGED, PointerButton::Middle),
+                    None => (MOUSE_MOVED, PointerButton::Left),
+                };
+                post(unsafe {
+                    CGEventCreateMouseEvent(
+                        std::ptr::null(),
+                        event_type,
+                        self.position,
+                        button_number(button),
+                    )
+                })
+            }
+            RemoteInputEvent::Button { button, pressed } => {
+                let event_type = match (button, pressed) {
+                    (PointerButton::Left, true) => LEFT_MOUSE_DOWN,
+                    (PointerButton::Left, false) => LEFT_MOUSE_UP,
+                    (PointerButton::Right, true) => RIGHT_MOUSE_DOWN,
+                    (PointerButton::Right, false) => RIGHT_MOUSE_UP,
+                    (PointerButton::Middle, true) => OTHER_MOUSE_DOWN,
+                    (PointerButton::Middle, false) => OTHER_MOUSE_UP,
+                };
+                let clicks = if *pressed {
+                    let clicks = match self.last_click {
+                        Some((last, at, count))
+                            if last == *button && at.elapsed() < MULTI_CLICK_INTERVAL =>
+                        {
+                            count + 1
+                        }
+                        _ => 1,
+                    };
+                    self.last_click = Some((*button, Instant::now(), clicks));
+                    self.held = Some(*button);
+                    clicks
+                } else {
+                    self.held = None;
+                    self.last_click.map_or(1, |(_, _, count)| count)
+                };
+                let event = unsafe {
+                    CGEventCreateMouseEvent(
+                        std::ptr::null(),
+                        event_type,
+                        self.position,
+                        button_number(*button),
+                    )
+                };
+                if !event.is_null() {
+                    unsafe { CGEventSetIntegerValueField(event, MOUSE_EVENT_CLICK_STATE, clicks) };
+                }
+                post(event)
+            }
+            RemoteInputEvent::Scroll { x, y } => post(unsafe {
+                CGEventCreateScrollWheelEvent2(
+                    std::ptr::null(),
+                    SCROLL_UNIT_PIXEL,
+                    2,
+                    (y * crate::remote_input::PIXELS_PER_LINE).round() as i32,
+                    (x * crate::remote_input::PIXELS_PER_LINE).round() as i32,
+                    0,
+                )
+            }),
+            RemoteInputEvent::Key { key, pressed } => post(unsafe {
+                CGEventCreateKeyboardEvent(std::ptr::null(), key_code(*key), *pressed)
+            }),
+            RemoteInputEvent::Text { text } => {
+                let units: Vec<u16> = text.encode_utf16().collect();
+                for chunk in units.chunks(MAX_UNICODE_PER_EVENT) {
+                    for key_down in [true, false] {
+                        let event =
+                            unsafe { CGEventCreateKeyboardEvent(std::ptr::null(), 0, key_down) };
+                        if !event.is_null() {
+                            unsafe {
+                                CGEventKeyboardSetUnicodeString(event, chunk.len(), chunk.as_ptr())
+                            };
+                        }
+                        post(event)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Post and release a created event
+fn post(event: *const c_void) -> Result<(), String> {
+    if event.is_null() {
+        return Err("Failed to create input event".to_string());
+    }
+    unsafe {
+        CGEventPost(HID_EVENT_TAP, event);
+        CFRelease(event);
+    }
+    Ok(())
+}
+
+/// CGMouseButton
+fn button_number(button: PointerButton) -> u32 {
+    match button {
+        PointerButton::Left => 0,
+        PointerButton::Right => 1,
+        PointerButton::Middle => 2,
+    }
+}
+
+/// ANSI virtual key codes (kVK_*)
+fn key_code(key: SpecialKey) -> u16 {
+    match key {
+        SpecialKey::Enter => 0x24,
+        SpecialKey::Tab => 0x30,
+        SpecialKey::Backspace => 0x33,
+        SpecialKey::Escape => 0x35,
+        SpecialKey::Delete => 0x75,
+        SpecialKey::Home => 0x73,
+        SpecialKey::PageUp => 0x74,
+        SpecialKey::End => 0x77,
+        SpecialKey::PageDown => 0x79,
+        SpecialKey::Left => 0x7B,
+        SpecialKey::Right => 0x7C,
+        SpecialKey::Down => 0x7D,
+        SpecialKey::Up => 0x7E,
+    }
+}
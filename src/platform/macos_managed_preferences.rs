@@ -0,0 +1,91 @@
+use core_foundation::array::CFArray;
+use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::data::CFData;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::number::CFNumber;
+use core_foundation::propertylist::{CFPropertyList, create_with_data, kCFPropertyListImmutable};
+use core_foundation::string::{CFString, CFStringRef};
+use std::path::PathBuf;
+
+/// Preference domain administrators target with configuration profiles
+pub const MANAGED_DOMAIN: &str = "com.cloakshare.CloakShare";
+
+/// Settings pushed by MDM configuration profiles, as config keys and values
+/// Device-wide settings are read first and the current user's override them
+pub fn managed_preferences() -> toml::Table {
+    let mut paths = vec![PathBuf::from(format!(
+        "/Library/Managed Preferences/{}.plist",
+        MANAGED_DOMAIN
+    ))];
+    if let Ok(user) = std::env::var("USER") {
+        paths.push(PathBuf::from(format!(
+            "/Library/Managed Preferences/{}/{}.plist",
+            user, MANAGED_DOMAIN
+        )));
+    }
+
+    let mut table = toml::Table::new();
+    for path in paths {
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let plist = match create_with_data(CFData::from_buffer(&bytes), kCFPropertyListImmutable) {
+            Ok((plist, _)) => unsafe { CFPropertyList::wrap_under_create_rule(plist) },
+            Err(e) => {
+                eprintln!(
+                    "Failed to parse managed preferences {}: {}",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        match to_toml(&plist) {
+            Some(toml::Value::Table(values)) => table.extend(values),
+            _ => eprintln!(
+                "Ignoring managed preferences {}: not a dictionary",
+                path.display()
+            ),
+        }
+    }
+    table
+}
+
+/// Convert a property list value; dates and data have no config equivalent and are dropped
+fn to_toml(value: &CFPropertyList) -> Option<toml::Value> {
+    if let Some(string) = value.downcast::<CFString>() {
+        return Some(toml::Value::String(string.to_string()));
+    }
+    if let Some(boolean) = value.downcast::<CFBoolean>() {
+        return Some(toml::Value::Boolean(boolean.into()));
+    }
+    if let Some(number) = value.downcast::<CFNumber>() {
+        // Whole numbers stay integers so they fit integer settings (float settings accept them too)
+        return number
+            .to_i64()
+            .map(toml::Value::Integer)
+            .or_else(|| number.to_f64().map(toml::Value::Float));
+    }
+    if let Some(array) = value.downcast::<CFArray>() {
+        let items = array
+            .iter()
+            .filter_map(|item| to_toml(&unsafe { CFPropertyList::wrap_under_get_rule(*item) }))
+            .collect();
+        return Some(toml::Value::Array(items));
+    }
+    if let Some(dictionary) = value.downcast::<CFDictionary>() {
+        let (keys, values) = dictionary.get_keys_and_values();
+        let table = keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| {
+                let key = unsafe { CFString::wrap_under_get_rule(key as CFStringRef) };
+                let value = to_toml(&unsafe { CFPropertyList::wrap_under_get_rule(value) })?;
+                Some((key.to_string(), value))
+            })
+            .collect();
+        return Some(toml::Value::Table(table));
+    }
+    None
+}
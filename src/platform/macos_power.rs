@@ -0,0 +1,28 @@
+use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+use core_foundation::string::{CFString, CFStringRef};
+
+/// kIOPSBatteryPowerValue
+const BATTERY_POWER: &str = "Battery Power";
+
+#[link(name = "IOKit", kind = "framework")]
+unsafe extern "C" {
+    fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+    fn IOPSGetProvidingPowerSourceType(snapshot: CFTypeRef) -> CFStringRef;
+}
+
+/// Whether the Mac currently runs on its battery; None if IOKit can't tell (e.g. a desktop
+/// without power source info)
+pub fn on_battery() -> Option<bool> {
+    unsafe {
+        let snapshot = IOPSCopyPowerSourcesInfo();
+        if snapshot.is_null() {
+            return None;
+        }
+        // The type string belongs to the snapshot
+        let source = IOPSGetProvidingPowerSourceType(snapshot);
+        let on_battery = (!source.is_null())
+            .then(|| CFString::wrap_under_get_rule(source).to_string() == BATTERY_POWER);
+        CFRelease(snapshot);
+        on_battery
+    }
+}
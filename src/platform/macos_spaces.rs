@@ -0,0 +1,50 @@
+use core_foundation::base::{CFRelease, TCFType, kCFAllocatorDefault};
+use core_foundation::string::{CFString, CFStringRef};
+use std::ffi::c_void;
+
+// Space identifiers are only exposed through the (stable, but undocumented) CGS/SkyLight API
+// that CoreGraphics re-exports. There is no public AppKit equivalent for per-display Spaces.
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGSMainConnectionID() -> i32;
+    fn CGSGetActiveSpace(connection: i32) -> u64;
+    fn CGSManagedDisplayGetCurrentSpace(connection: i32, display_uuid: CFStringRef) -> u64;
+    fn CGDisplayCreateUUIDFromDisplayID(display_id: u32) -> *const c_void;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    fn CFUUIDCreateString(allocator: *const c_void, uuid: *const c_void) -> CFStringRef;
+}
+
+/// Get the ID of the Space currently shown on the given display
+/// Falls back to the globally active Space when no display is given or its UUID can't be resolved
+pub fn current_space_id(display_id: Option<u32>) -> Option<u64> {
+    let connection = unsafe { CGSMainConnectionID() };
+
+    let space = match display_id.and_then(display_uuid_string) {
+        Some(uuid) => unsafe {
+            CGSManagedDisplayGetCurrentSpace(connection, uuid.as_concrete_TypeRef())
+        },
+        None => unsafe { CGSGetActiveSpace(connection) },
+    };
+
+    // 0 means the window server couldn't tell us (e.g. display just disconnected)
+    (space != 0).then_some(space)
+}
+
+/// Resolve a CGDirectDisplayID to the UUID string the Space APIs are keyed by
+fn display_uuid_string(display_id: u32) -> Option<CFString> {
+    let uuid = unsafe { CGDisplayCreateUUIDFromDisplayID(display_id) };
+    if uuid.is_null() {
+        return None;
+    }
+
+    let string_ref = unsafe { CFUUIDCreateString(kCFAllocatorDefault as *const c_void, uuid) };
+    unsafe { CFRelease(uuid) };
+
+    if string_ref.is_null() {
+        return None;
+    }
+    Some(unsafe { CFString::wrap_under_create_rule(string_ref) })
+}
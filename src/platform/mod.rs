@@ -3,6 +3,39 @@ pub mod traits;
 #[cfg(target_os = "macos")]
 pub mod macos;
 
+#[cfg(target_os = "macos")]
+pub mod macos_alert;
+
+#[cfg(target_os = "macos")]
+pub mod macos_camera;
+
+#[cfg(target_os = "macos")]
+pub mod macos_cursor;
+
+#[cfg(target_os = "macos")]
+pub mod macos_display;
+
+#[cfg(target_os = "macos")]
+pub mod macos_display_link;
+
+#[cfg(target_os = "macos")]
+pub mod macos_idle;
+
+#[cfg(all(target_os = "macos", feature = "quic"))]
+pub mod macos_input;
+
+#[cfg(target_os = "macos")]
+pub mod macos_managed_preferences;
+
+#[cfg(target_os = "macos")]
+pub mod macos_permission;
+
+#[cfg(target_os = "macos")]
+pub mod macos_power;
+
+#[cfg(target_os = "macos")]
+pub mod macos_spaces;
+
 #[cfg(target_os = "windows")]
 pub mod windows;
 
@@ -19,3 +52,17 @@ pub use windows::*;
 
 #[cfg(target_os = "linux")]
 pub use linux::*;
+
+/// Add this platform's capture backends to `registry`
+pub fn register_capture_backends(
+    registry: &mut crate::cross_platform_capture::CaptureBackendRegistry,
+) {
+    #[cfg(target_os = "macos")]
+    macos::register_capture_backends(registry);
+
+    #[cfg(target_os = "windows")]
+    windows::register_capture_backends(registry);
+
+    #[cfg(target_os = "linux")]
+    linux::register_capture_backends(registry);
+}
@@ -1,31 +1,426 @@
-use std::sync::{Arc, Mutex};
+use crate::encoding::yuv::YuvRange;
+use crate::frame_channel::FrameChannel;
+use crate::locked_memory::FrameBuffer;
+use crate::scaling::{strip_row_padding, unpremultiply_alpha};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
 
 /// Display resolution information
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DisplayResolution {
+    /// Width in points (logical pixels)
+    pub width: u32,
+    /// Height in points (logical pixels)
+    pub height: u32,
+    /// Backing pixels per point (2.0 on Retina displays)
+    pub scale_factor: f64,
+}
+
+impl DisplayResolution {
+    /// Size of captured frames for the given capture scale
+    pub fn capture_size(&self, scale: CaptureScale) -> (u32, u32) {
+        match scale {
+            CaptureScale::Native => (
+                ((self.width as f64 * self.scale_factor).round() as u32).max(1),
+                ((self.height as f64 * self.scale_factor).round() as u32).max(1),
+            ),
+            CaptureScale::Points => (self.width.max(1), self.height.max(1)),
+        }
+    }
+}
+
+/// Byte order of the pixels in a `Frame`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PixelFormat {
+    #[default]
+    Rgba8,
+    Bgra8,
+}
+
+/// How a frame's color channels relate to its alpha
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Color is independent of alpha; what the rest of the pipeline works in
+    #[default]
+    Straight,
+    /// Color is already multiplied by alpha (ScreenCaptureKit's BGRA)
+    Premultiplied,
+}
+
+/// A rectangle of a frame that changed since the previous frame, in frame pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DirtyRect {
+    /// The same region in a frame downscaled by `factor`, grown to whole pixels
+    pub fn scaled_down(&self, factor: u32) -> DirtyRect {
+        let factor = factor.max(1);
+        let (x, y) = (self.x / factor, self.y / factor);
+        DirtyRect {
+            x,
+            y,
+            width: (self.x + self.width).div_ceil(factor) - x,
+            height: (self.y + self.height).div_ceil(factor) - y,
+        }
+    }
+}
+
+/// Pixels that stay in GPU memory (e.g. an IOSurface) until a CPU consumer asks for them
+pub trait GpuFrameSource: Send + Sync + std::fmt::Debug {
+    /// Copy the pixels to CPU memory, laid out as the owning `Frame` describes
+    /// (its `pixel_format`, rows `stride` bytes apart)
+    fn read_pixels(&self) -> Option<Vec<u8>>;
+}
+
+/// Where a frame's pixels live; cloning shares them instead of copying
+#[derive(Debug, Clone)]
+pub enum FrameData {
+    /// CPU memory, shared by every holder of the frame
+    Cpu(Arc<FrameBuffer>),
+    /// GPU memory, read back only when CPU access is needed
+    Gpu(Arc<dyn GpuFrameSource>),
+}
+
+/// A captured frame and what's known about it
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub data: FrameData,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes from the start of one row to the next (at least width*4)
+    pub stride: u32,
+    pub pixel_format: PixelFormat,
+    /// Whether `pixel_format`'s color is premultiplied; `into_rgba` always returns straight alpha
+    pub alpha_mode: AlphaMode,
+    /// When the frame was captured, for latency measurements
+    pub timestamp: Instant,
+    /// Frame pixels per point of captured content (2.0 for Retina captures at native scale)
+    pub content_scale: f64,
+    /// Regions that changed since the previous frame (None = unknown, treat all as changed)
+    pub dirty_rects: Option<Vec<DirtyRect>>,
+    /// Range of the Y'CbCr pixels the source delivered before conversion (None = RGB source),
+    /// for debugging washed-out or crushed blacks
+    pub color_range: Option<YuvRange>,
+}
+
+impl Frame {
+    /// A tightly packed RGBA frame captured now
+    pub fn from_rgba(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Self {
+            data: FrameData::Cpu(Arc::new(FrameBuffer::new(data))),
+            width,
+            height,
+            stride: width * 4,
+            pixel_format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Straight,
+            timestamp: Instant::now(),
+            content_scale: 1.0,
+            dirty_rects: None,
+            color_range: None,
+        }
+    }
+
+    /// Borrow the pixels if they're already in CPU memory (no copy, no readback)
+    pub fn cpu_pixels(&self) -> Option<&[u8]> {
+        match &self.data {
+            FrameData::Cpu(data) => Some(data),
+            FrameData::Gpu(_) => None,
+        }
+    }
+
+    /// The pixels in CPU memory as stored (`pixel_format`, rows `stride` apart); borrowed for
+    /// CPU frames, read back for GPU frames
+    pub fn pixels(&self) -> Option<Cow<'_, [u8]>> {
+        match &self.data {
+            FrameData::Cpu(data) => Some(Cow::Borrowed(data)),
+            FrameData::Gpu(source) => source.read_pixels().map(Cow::Owned),
+        }
+    }
+
+    /// Pixels as tightly packed straight-alpha RGBA (width*height*4 bytes), whatever the
+    /// stride, format, alpha mode and location; None if a GPU frame couldn't be read back
+    pub fn into_rgba(self) -> Option<Vec<u8>> {
+        let packed = self.stride == self.width * 4;
+        let mut rgba = match self.data {
+            // Sole owner of a packed buffer: reuse it rather than copy
+            FrameData::Cpu(data) if packed => Arc::unwrap_or_clone(data).into_vec(),
+            FrameData::Cpu(data) => strip_row_padding(&data, self.width, self.height, self.stride),
+            FrameData::Gpu(source) => {
+                let data = source.read_pixels()?;
+                if packed {
+                    data
+                } else {
+                    strip_row_padding(&data, self.width, self.height, self.stride)
+                }
+            }
+        };
+        if self.pixel_format == PixelFormat::Bgra8 {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        // Translucent pixels would otherwise come out darker each time they're blended
+        if self.alpha_mode == AlphaMode::Premultiplied {
+            unpremultiply_alpha(&mut rgba);
+        }
+        Some(rgba)
+    }
+
+    /// Called when this frame replaces `older` before anyone took it: the changes in the
+    /// skipped frame must still be reported
+    pub fn merge_dirty_rects(&mut self, older: &Frame) {
+        match (&mut self.dirty_rects, &older.dirty_rects) {
+            (Some(rects), Some(older_rects)) => rects.extend_from_slice(older_rects),
+            _ => self.dirty_rects = None,
+        }
+    }
+}
+
+/// Resolution frames are captured at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureScale {
+    /// Backing pixels (full Retina detail)
+    #[default]
+    Native,
+    /// Points (a quarter of the pixels on a 2x display, for lower bandwidth)
+    Points,
+}
+
+/// A capturable source (display or individual window)
+/// In config files: `{ display = 1 }`, `{ window = 42 }`, `{ region = { display = 1, x = 0, ... } }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    /// A whole display, by platform display ID
+    Display(u32),
+    /// A single window, by platform window ID
+    Window(u32),
+    /// A rectangle of a display, in points relative to the display's top-left corner
+    Region {
+        display: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    /// Every display, composited into one frame
+    AllDisplays(DisplayArrangement),
+}
+
+/// How several displays are arranged in one output frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayArrangement {
+    /// Left to right, in desktop order
+    SideBySide,
+    /// Top to bottom, in desktop order
+    Stacked,
+    /// Rows of equally sized cells (overview of every display)
+    Grid,
+}
+
+/// What a platform's capture implementation can do, so callers can adapt instead of
+/// assuming macOS feature parity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureCapabilities {
+    /// Individual windows can be captured (`CaptureSource::Window`)
+    pub window_capture: bool,
+    /// Rectangles of a display can be captured (`CaptureSource::Region`)
+    pub region_capture: bool,
+    /// The cursor can be left out of captured frames
+    pub cursor_toggle: bool,
+    /// System audio can be captured alongside frames
+    pub audio: bool,
+    /// Frames can be captured in a high dynamic range format
+    pub hdr: bool,
+    /// Highest frame rate the capture delivers (None = unknown or capture unavailable)
+    pub max_fps: Option<u32>,
+}
+
+impl CaptureCapabilities {
+    /// Whether `source` can be captured at all
+    pub fn supports(&self, source: &CaptureSource) -> bool {
+        match source {
+            CaptureSource::Display(_) | CaptureSource::AllDisplays(_) => self.max_fps.is_some(),
+            CaptureSource::Window(_) => self.window_capture,
+            CaptureSource::Region { .. } => self.region_capture,
+        }
+    }
+}
+
+/// Description of a capturable source, used by the source picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub source: CaptureSource,
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A connected display, for `cloakshare list-displays` and scripts targeting displays by ID
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DisplayInfo {
+    /// Platform display ID (as used by `CaptureSource::Display` and `--display`)
+    pub id: u32,
+    pub name: String,
+    /// Width in points
     pub width: u32,
+    /// Height in points
     pub height: u32,
+    /// Backing pixels per point (2.0 on Retina displays)
+    pub scale_factor: f64,
+    /// The display with the menu bar
+    pub main: bool,
+}
+
+/// Position and size of a window in global desktop coordinates (points)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WindowFrame {
+    /// Left edge; negative on displays left of the primary one
+    pub x: i32,
+    /// Top edge; negative on displays above the primary one
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A window on the desktop, for window capture, exclusion lists and the source picker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WindowInfo {
+    /// Platform window ID (as used by `CaptureSource::Window`)
+    pub id: u32,
+    pub title: String,
+    /// Name of the application owning the window
+    pub app_name: String,
+    /// Bundle identifier (macOS) or executable name of the owning application
+    pub app_id: String,
+    pub frame: WindowFrame,
+    /// Stacking layer (0 = normal app windows; higher = menus, overlays, the menu bar)
+    pub layer: u32,
+    /// Whether the window is currently visible (not minimized, hidden or on another Space)
+    pub on_screen: bool,
+}
+
+/// Small RGBA preview image of a capture source
+#[derive(Debug, Clone)]
+pub struct SourceThumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Notifications sent from the capture thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// A new frame was stored in the frame buffer
+    FrameReady,
+    /// The capture stream stopped on its own (permission revoked, source closed, ...)
+    Stopped(String),
 }
 
+/// Called from the capture thread for every CaptureEvent
+pub type CaptureEventCallback = Arc<dyn Fn(CaptureEvent) + Send + Sync>;
+
 /// Platform-specific screen capture capabilities
 pub trait ScreenCapture {
+    /// What this implementation supports (sources, cursor, audio, HDR, frame rate)
+    fn capabilities(&self) -> CaptureCapabilities;
+
     /// Get the primary display resolution
     fn get_display_resolution(&self) -> Result<DisplayResolution, String>;
 
-    /// Start capturing the primary display at its native resolution
+    /// Start capturing the primary display at the resolution chosen by `set_capture_scale`
+    /// If a stream is already running it keeps delivering frames until the new one has started,
+    /// and keeps running if the new one fails
     fn start_capture(
         &mut self,
         exclude_window: Option<&winit::window::Window>,
     ) -> Result<(), String>;
 
-    /// Get the latest captured frame as RGBA data (width*height*4 bytes)
-    fn get_latest_frame(&self) -> Option<Vec<u8>>;
+    /// Get the latest captured frame
+    fn get_latest_frame(&self) -> Option<Frame>;
 
     /// Stop screen capture
     fn stop_capture(&mut self);
 
-    /// Get the shared frame buffer for thread-safe access
-    fn get_frame_buffer(&self) -> Arc<Mutex<Option<Vec<u8>>>>;
+    /// Channel the capture thread delivers frames through (shared, so clones see the same queue)
+    fn frame_channel(&self) -> FrameChannel;
+
+    /// Register a callback fired on the capture thread for new frames and stream errors
+    /// Takes effect on the next `start_capture`
+    fn set_event_callback(&mut self, callback: CaptureEventCallback);
+
+    /// Platform identifier of the display being captured, if capture has started
+    fn captured_display_id(&self) -> Option<u32> {
+        None
+    }
+
+    /// Platform identifiers of every display in the capture: the captured display, or each
+    /// display of an `AllDisplays` grid (empty for a window or before capture has started)
+    fn captured_display_ids(&self) -> Vec<u32> {
+        self.captured_display_id().into_iter().collect()
+    }
+
+    /// For multi-display sources: the display shown at output pixel (x, y)
+    fn display_at(&self, _x: u32, _y: u32) -> Option<u32> {
+        None
+    }
+
+    /// List every window the platform reports, visible or not
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String>;
+
+    /// List connected displays
+    /// Backends that can't read scale factors report the displays from `list_sources` at 1.0
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        let displays = self
+            .list_sources()?
+            .into_iter()
+            .filter_map(|info| match info.source {
+                CaptureSource::Display(id) => Some((id, info)),
+                _ => None,
+            })
+            .enumerate()
+            .map(|(index, (id, info))| DisplayInfo {
+                id,
+                name: info.title,
+                width: info.width,
+                height: info.height,
+                scale_factor: 1.0,
+                main: index == 0,
+            })
+            .collect();
+        Ok(displays)
+    }
+
+    /// List displays and windows that can be captured
+    fn list_sources(&self) -> Result<Vec<SourceInfo>, String>;
+
+    /// Select the source used by the next `start_capture` (defaults to the primary display)
+    /// Calling `start_capture` while capturing switches to the new source without a gap
+    fn set_source(&mut self, source: CaptureSource);
+
+    /// Capture at backing pixels or points; takes effect on the next `start_capture`
+    fn set_capture_scale(&mut self, scale: CaptureScale);
+
+    /// Deliver up to `fps` frames per second (above 60 for high refresh displays); takes effect
+    /// on the next `start_capture`. Backends with a fixed rate ignore it
+    fn set_frame_rate(&mut self, _fps: u32) {}
+
+    /// Capture a single preview image of a source, scaled to fit within max_width x max_height
+    fn capture_thumbnail(
+        &self,
+        source: CaptureSource,
+        max_width: u32,
+        max_height: u32,
+    ) -> Result<SourceThumbnail, String>;
 }
 
 /// Factory for creating platform-specific screen capture implementations
@@ -36,10 +431,83 @@ pub trait ScreenCaptureFactory {
     fn create() -> Self::Capture;
 }
 
+/// A frame as a capture backend hands it over, before conversion to RGBA
+#[derive(Clone, Copy)]
+pub enum RawCaptureBuffer<'a> {
+    /// ScreenCaptureKit sample buffer holding a BGRA CVPixelBuffer
+    #[cfg(target_os = "macos")]
+    SampleBuffer(&'a screencapturekit::output::CMSampleBuffer),
+    /// DXGI desktop duplication surface mapped for reading (B8G8R8A8, rows `pitch` bytes apart)
+    DxgiSurface {
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        pitch: u32,
+    },
+    /// Mapped data of a PipeWire SPA buffer
+    PipeWire {
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: PixelFormat,
+    },
+    /// BGRA pixels already in CPU memory
+    CpuBgra {
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+    },
+}
+
+impl RawCaptureBuffer<'_> {
+    /// Convert a variant whose pixels are mapped in CPU memory to tightly packed RGBA
+    /// None for platform handles (left to the platform's converter) or if `data` is too short
+    pub fn mapped_to_rgba(&self) -> Option<Vec<u8>> {
+        let (data, width, height, stride, format) = match *self {
+            #[cfg(target_os = "macos")]
+            RawCaptureBuffer::SampleBuffer(_) => return None,
+            RawCaptureBuffer::DxgiSurface {
+                data,
+                width,
+                height,
+                pitch,
+            } => (data, width, height, pitch, PixelFormat::Bgra8),
+            RawCaptureBuffer::PipeWire {
+                data,
+                width,
+                height,
+                stride,
+                format,
+            } => (data, width, height, stride, format),
+            RawCaptureBuffer::CpuBgra {
+                data,
+                width,
+                height,
+                stride,
+            } => (data, width, height, stride, PixelFormat::Bgra8),
+        };
+
+        let row_bytes = width as usize * 4;
+        let needed = (stride as usize * height.saturating_sub(1) as usize) + row_bytes;
+        if height == 0 || (stride as usize) < row_bytes || data.len() < needed {
+            return None;
+        }
+        let mut rgba = strip_row_padding(data, width, height, stride);
+        if format == PixelFormat::Bgra8 {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Some(rgba)
+    }
+}
+
 /// Platform-specific pixel format conversion
 pub trait PixelConverter: Send + Sync {
-    /// Convert platform-specific buffer to RGBA format
-    fn convert_to_rgba(&self, buffer: &dyn std::any::Any) -> Option<Vec<u8>>;
+    /// Convert a backend's buffer to tightly packed RGBA; None if this platform can't read it
+    fn convert_to_rgba(&self, buffer: RawCaptureBuffer<'_>) -> Option<Vec<u8>>;
 }
 
 /// Supported platforms
@@ -1,22 +1,31 @@
+use crate::cross_platform_capture::{CaptureBackend, CaptureBackendRegistry};
+use crate::frame_channel::FrameChannel;
 use crate::platform::traits::{
-    DisplayResolution, PixelConverter, ScreenCapture, ScreenCaptureFactory,
+    CaptureCapabilities, CaptureEventCallback, CaptureScale, CaptureSource, DisplayResolution,
+    Frame, PixelConverter, RawCaptureBuffer, ScreenCapture, ScreenCaptureFactory, SourceInfo,
+    SourceThumbnail, WindowInfo,
 };
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 /// Windows implementation (placeholder - not implemented)
 pub struct WindowsScreenCapture {
-    latest_frame: Arc<Mutex<Option<Vec<u8>>>>,
+    frames: FrameChannel,
 }
 
 impl WindowsScreenCapture {
     pub fn new() -> Self {
         Self {
-            latest_frame: Arc::new(Mutex::new(None)),
+            frames: FrameChannel::default(),
         }
     }
 }
 
 impl ScreenCapture for WindowsScreenCapture {
+    fn capabilities(&self) -> CaptureCapabilities {
+        // Nothing can be captured until Windows capture is implemented
+        CaptureCapabilities::default()
+    }
+
     fn get_display_resolution(&self) -> Result<DisplayResolution, String> {
         Err("Windows display resolution detection not implemented yet".to_string())
     }
@@ -28,7 +37,7 @@ impl ScreenCapture for WindowsScreenCapture {
         Err("Windows screen capture not implemented yet".to_string())
     }
 
-    fn get_latest_frame(&self) -> Option<Vec<u8>> {
+    fn get_latest_frame(&self) -> Option<Frame> {
         None
     }
 
@@ -36,8 +45,37 @@ impl ScreenCapture for WindowsScreenCapture {
         // No-op
     }
 
-    fn get_frame_buffer(&self) -> Arc<Mutex<Option<Vec<u8>>>> {
-        self.latest_frame.clone()
+    fn frame_channel(&self) -> FrameChannel {
+        self.frames.clone()
+    }
+
+    fn set_event_callback(&mut self, _callback: CaptureEventCallback) {
+        // No-op
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        Err("Windows window enumeration not implemented yet".to_string())
+    }
+
+    fn list_sources(&self) -> Result<Vec<SourceInfo>, String> {
+        Err("Windows source enumeration not implemented yet".to_string())
+    }
+
+    fn set_source(&mut self, _source: CaptureSource) {
+        // No-op
+    }
+
+    fn set_capture_scale(&mut self, _scale: CaptureScale) {
+        // No-op
+    }
+
+    fn capture_thumbnail(
+        &self,
+        _source: CaptureSource,
+        _max_width: u32,
+        _max_height: u32,
+    ) -> Result<SourceThumbnail, String> {
+        Err("Windows thumbnails not implemented yet".to_string())
     }
 }
 
@@ -52,12 +90,27 @@ impl ScreenCaptureFactory for WindowsScreenCaptureFactory {
     }
 }
 
-/// Windows pixel converter (placeholder)
+/// Register the Windows placeholder; it never reports itself available, so nothing selects it
+pub fn register_capture_backends(registry: &mut CaptureBackendRegistry) {
+    registry.register(CaptureBackend {
+        name: "placeholder".to_string(),
+        priority: 0,
+        is_available: Arc::new(|| false),
+        create: Arc::new(|| {
+            Ok((
+                Box::new(WindowsScreenCaptureFactory::create()) as Box<dyn ScreenCapture>,
+                Box::new(WindowsPixelConverter) as Box<dyn PixelConverter>,
+            ))
+        }),
+    });
+}
+
+/// Windows pixel converter for mapped DXGI and CPU buffers
 pub struct WindowsPixelConverter;
 
 impl PixelConverter for WindowsPixelConverter {
-    fn convert_to_rgba(&self, _buffer: &dyn std::any::Any) -> Option<Vec<u8>> {
-        unimplemented!("Windows pixel conversion not implemented yet")
+    fn convert_to_rgba(&self, buffer: RawCaptureBuffer<'_>) -> Option<Vec<u8>> {
+        buffer.mapped_to_rgba()
     }
 }
 
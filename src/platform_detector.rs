@@ -1,5 +1,16 @@
+use crate::l10n::tr;
 use crate::platform::Platform;
 
+/// Whether the app may capture the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionStatus {
+    Granted,
+    /// The user was asked and refused (or revoked access later)
+    Denied,
+    /// The user hasn't been asked yet; capturing or requesting access will prompt
+    NotDetermined,
+}
+
 /// Utility for detecting platform capabilities and providing user-friendly messages
 pub struct PlatformDetector;
 
@@ -20,21 +31,107 @@ impl PlatformDetector {
                 }
             }
 
-            Platform::Windows => Err(format!(
-                "Windows support is not implemented yet.\n\
-                    To add Windows support:\n\
-                    1. Implement Windows-specific screen capture using DXGI or GDI+\n\
-                    2. Add pixel format conversion for Windows capture formats\n\
-                    3. Update platform::windows module with real implementation"
-            )),
-
-            Platform::Linux => Err(format!(
-                "Linux support is not implemented yet.\n\
-                    To add Linux support:\n\
-                    1. Implement X11/Wayland screen capture using xrandr or wlroots\n\
-                    2. Add pixel format conversion for Linux capture formats\n\
-                    3. Update platform::linux module with real implementation"
-            )),
+            Platform::Windows => Err(tr("unsupported-windows")),
+
+            Platform::Linux => Err(tr("unsupported-linux")),
+        }
+    }
+
+    /// Human-readable OS name and version, e.g. "macOS Version 14.5 (Build 23F79)"
+    pub fn os_version() -> String {
+        #[cfg(target_os = "macos")]
+        {
+            use objc2::rc::Retained;
+            use objc2::runtime::AnyObject;
+            use objc2::{class, msg_send, msg_send_id};
+            use std::ffi::{CStr, c_char};
+
+            let version = unsafe {
+                let info: Retained<AnyObject> = msg_send_id![class!(NSProcessInfo), processInfo];
+                let version: Retained<AnyObject> =
+                    msg_send_id![&*info, operatingSystemVersionString];
+                let utf8: *const c_char = msg_send![&*version, UTF8String];
+                (!utf8.is_null()).then(|| CStr::from_ptr(utf8).to_string_lossy().into_owned())
+            };
+            format!("macOS {}", version.unwrap_or_default())
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            // PRETTY_NAME="Ubuntu 24.04 LTS"
+            std::fs::read_to_string("/etc/os-release")
+                .ok()
+                .and_then(|release| {
+                    release.lines().find_map(|line| {
+                        let name = line.strip_prefix("PRETTY_NAME=")?;
+                        Some(name.trim_matches('"').to_string())
+                    })
+                })
+                .unwrap_or_else(|| "Linux".to_string())
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        {
+            std::env::consts::OS.to_string()
+        }
+    }
+
+    /// Screen Recording permission of this process, without prompting
+    /// Platforms without a permission model report Granted
+    pub fn screen_recording_permission() -> PermissionStatus {
+        #[cfg(target_os = "macos")]
+        {
+            if crate::platform::macos_permission::screen_capture_access_granted() {
+                PermissionStatus::Granted
+            } else if permission_requested_marker().is_some_and(|marker| marker.exists()) {
+                // macOS only says "not granted"; remember asking to tell denial from never asked
+                PermissionStatus::Denied
+            } else {
+                PermissionStatus::NotDetermined
+            }
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            PermissionStatus::Granted
+        }
+    }
+
+    /// Ask for Screen Recording permission (shows the system prompt the first time)
+    /// On macOS a new grant only applies after the app restarts
+    pub fn request_screen_recording_permission() -> PermissionStatus {
+        #[cfg(target_os = "macos")]
+        {
+            let granted = crate::platform::macos_permission::request_screen_capture_access();
+            if let Some(marker) = permission_requested_marker() {
+                if let Some(dir) = marker.parent() {
+                    let _ = std::fs::create_dir_all(dir);
+                }
+                if let Err(e) = std::fs::write(&marker, b"") {
+                    eprintln!(
+                        "Failed to record permission request in {}: {}",
+                        marker.display(),
+                        e
+                    );
+                }
+            }
+            if granted {
+                PermissionStatus::Granted
+            } else {
+                PermissionStatus::Denied
+            }
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            PermissionStatus::Granted
         }
     }
 }
+
+/// File next to the config that records that Screen Recording access was requested
+#[cfg(target_os = "macos")]
+fn permission_requested_marker() -> Option<std::path::PathBuf> {
+    let config = crate::config::Config::path()?;
+    Some(config.parent()?.join("screen-recording-requested"))
+}
@@ -0,0 +1,503 @@
+use crate::cross_platform_capture::{CaptureBackend, CaptureBackendRegistry};
+use crate::frame_bus::ProcessedFrame;
+use crate::frame_channel::FrameChannel;
+use crate::locked_memory::FrameBuffer;
+use crate::platform::{
+    AlphaMode, CaptureCapabilities, CaptureEvent, CaptureEventCallback, CaptureScale,
+    CaptureSource, DisplayResolution, Frame, FrameData, PixelConverter, PixelFormat,
+    RawCaptureBuffer, ScreenCapture, SourceInfo, SourceThumbnail, WindowInfo,
+};
+use crate::sinks::FrameSink;
+use libloading::Library;
+use serde::Deserialize;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Version of the structs below; plugins built for another version are refused
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Symbol every plugin exports: `extern "C" fn cloakshare_plugin() -> *const PluginDeclaration`
+pub const PLUGIN_ENTRY_SYMBOL: &[u8] = b"cloakshare_plugin\0";
+
+/// `PluginFrame::format` value for RGBA pixels
+pub const PLUGIN_FORMAT_RGBA: u32 = 0;
+/// `PluginFrame::format` value for BGRA pixels
+pub const PLUGIN_FORMAT_BGRA: u32 = 1;
+
+/// A frame crossing the plugin boundary; the pixels are only valid during the call
+#[repr(C)]
+pub struct PluginFrame {
+    pub data: *const u8,
+    pub len: usize,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes from the start of one row to the next (at least width*4)
+    pub stride: u32,
+    /// PLUGIN_FORMAT_RGBA or PLUGIN_FORMAT_BGRA
+    pub format: u32,
+    /// Sequence number for sink frames; ignored for captured frames
+    pub sequence: u64,
+}
+
+/// Called by a capture plugin, from any thread, for every new frame
+pub type PluginFrameCallback =
+    unsafe extern "C" fn(context: *mut c_void, frame: *const PluginFrame);
+
+/// Function table of a sink plugin
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SinkPluginV1 {
+    /// Create a sink from the configured settings string; null on failure
+    pub create: unsafe extern "C" fn(settings: *const c_char) -> *mut c_void,
+    /// Handle one redacted frame; non-zero detaches the sink
+    pub consume: unsafe extern "C" fn(sink: *mut c_void, frame: *const PluginFrame) -> i32,
+    /// Optional: the content went static or started changing again
+    pub set_content_static: Option<unsafe extern "C" fn(sink: *mut c_void, is_static: bool)>,
+    /// Flush and free the sink
+    pub destroy: unsafe extern "C" fn(sink: *mut c_void),
+}
+
+/// Function table of a capture plugin
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CapturePluginV1 {
+    /// Backends are tried highest priority first (the built-in macOS backend is 100)
+    pub priority: i32,
+    /// Highest frame rate the plugin delivers (0 = unknown)
+    pub max_fps: u32,
+    /// Whether the plugin can capture on this machine
+    pub is_available: unsafe extern "C" fn() -> bool,
+    /// Create a capture from the configured settings string; null on failure
+    pub create: unsafe extern "C" fn(settings: *const c_char) -> *mut c_void,
+    /// Write the size of captured frames; false if unknown
+    pub frame_size:
+        unsafe extern "C" fn(capture: *mut c_void, width: *mut u32, height: *mut u32) -> bool,
+    /// Start delivering frames to `on_frame(context, frame)`; non-zero on failure
+    pub start: unsafe extern "C" fn(
+        capture: *mut c_void,
+        on_frame: PluginFrameCallback,
+        context: *mut c_void,
+    ) -> i32,
+    /// Stop delivering frames; `on_frame` must not be called once this returns
+    pub stop: unsafe extern "C" fn(capture: *mut c_void),
+    /// Free the capture (always stopped first)
+    pub destroy: unsafe extern "C" fn(capture: *mut c_void),
+}
+
+/// What a plugin provides, returned by its `cloakshare_plugin` entry point
+/// Plain C function tables, so plugins can be written in any language; sinks only ever see
+/// redacted frames, and captured frames go through the redaction stage like any other capture
+#[repr(C)]
+pub struct PluginDeclaration {
+    /// Must equal PLUGIN_ABI_VERSION
+    pub abi_version: u32,
+    /// NUL-terminated plugin name, used in logs and as the capture backend name
+    pub name: *const c_char,
+    /// Null if the plugin has no sink
+    pub sink: *const SinkPluginV1,
+    /// Null if the plugin has no capture backend
+    pub capture: *const CapturePluginV1,
+}
+
+/// A plugin to load, as listed under `[[plugins]]` in the config file
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginConfig {
+    /// Shared library to load (.dylib, .so or .dll)
+    pub path: PathBuf,
+    /// Passed verbatim to the plugin's `create` functions
+    #[serde(default)]
+    pub settings: String,
+    /// Feed the plugin's sink this many frames a second, repeating the last frame when the
+    /// source is slower (virtual cameras need a constant rate)
+    #[serde(default)]
+    pub frame_rate: Option<u32>,
+}
+
+/// A loaded plugin; the library stays loaded while any sink or capture created from it exists
+pub struct Plugin {
+    name: String,
+    settings: CString,
+    frame_rate: Option<u32>,
+    sink: Option<SinkPluginV1>,
+    capture: Option<CapturePluginV1>,
+    library: Arc<Library>,
+}
+
+impl Plugin {
+    /// Load a plugin library and check that it was built for this ABI version
+    pub fn load(config: &PluginConfig) -> Result<Self, String> {
+        let path = config.path.display();
+        let settings = CString::new(config.settings.as_str())
+            .map_err(|_| format!("Settings for plugin {} contain a NUL byte", path))?;
+
+        // SAFETY: loading runs the library's initializers; plugins are trusted like the binary
+        let library = unsafe { Library::new(&config.path) }
+            .map_err(|e| format!("Failed to load plugin {}: {}", path, e))?;
+        let declaration = unsafe {
+            let entry = library
+                .get::<unsafe extern "C" fn() -> *const PluginDeclaration>(PLUGIN_ENTRY_SYMBOL)
+                .map_err(|e| format!("{} is not a CloakShare plugin: {}", path, e))?;
+            entry()
+        };
+        // SAFETY: the declaration is static data inside the library, which outlives this borrow
+        let declaration = unsafe { declaration.as_ref() }
+            .ok_or_else(|| format!("Plugin {} returned no declaration", path))?;
+        if declaration.abi_version != PLUGIN_ABI_VERSION {
+            return Err(format!(
+                "Plugin {} uses ABI version {} (expected {})",
+                path, declaration.abi_version, PLUGIN_ABI_VERSION
+            ));
+        }
+
+        let name = if declaration.name.is_null() {
+            config.path.file_stem().map_or_else(
+                || path.to_string(),
+                |stem| stem.to_string_lossy().into_owned(),
+            )
+        } else {
+            unsafe { CStr::from_ptr(declaration.name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        // Copy the function tables so nothing points into the declaration afterwards
+        let sink = unsafe { declaration.sink.as_ref() }.copied();
+        let capture = unsafe { declaration.capture.as_ref() }.copied();
+        if sink.is_none() && capture.is_none() {
+            return Err(format!(
+                "Plugin {} provides neither a sink nor a capture",
+                name
+            ));
+        }
+
+        Ok(Self {
+            name,
+            settings,
+            frame_rate: config.frame_rate,
+            sink,
+            capture,
+            library: Arc::new(library),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Create an output fed with redacted frames (None if the plugin has no sink)
+    pub fn create_sink(&self) -> Option<Result<PluginSink, String>> {
+        let vtable = self.sink?;
+        let handle = unsafe { (vtable.create)(self.settings.as_ptr()) };
+        if handle.is_null() {
+            return Some(Err(format!(
+                "Plugin {} failed to create its sink",
+                self.name
+            )));
+        }
+        Some(Ok(PluginSink {
+            name: self.name.clone(),
+            handle,
+            vtable,
+            frame_rate: self.frame_rate,
+            _library: self.library.clone(),
+        }))
+    }
+
+    /// Add the plugin's capture backend (if any) to `registry` under the plugin's name
+    pub fn register_capture_backend(self: &Arc<Self>, registry: &mut CaptureBackendRegistry) {
+        let Some(vtable) = self.capture else {
+            return;
+        };
+        let plugin = self.clone();
+        registry.register(CaptureBackend {
+            name: self.name.clone(),
+            priority: vtable.priority,
+            is_available: Arc::new(move || unsafe { (vtable.is_available)() }),
+            create: Arc::new(move || {
+                Ok((
+                    Box::new(PluginCapture::new(&plugin, vtable)?) as Box<dyn ScreenCapture>,
+                    Box::new(PluginPixelConverter) as Box<dyn PixelConverter>,
+                ))
+            }),
+        });
+    }
+}
+
+/// Load every configured plugin, skipping (and reporting) ones that fail
+pub fn load_plugins(configs: &[PluginConfig]) -> Vec<Arc<Plugin>> {
+    configs
+        .iter()
+        .filter_map(|config| match Plugin::load(config) {
+            Ok(plugin) => {
+                println!(
+                    "Loaded plugin {} from {}",
+                    plugin.name,
+                    config.path.display()
+                );
+                Some(Arc::new(plugin))
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// FrameSink forwarding redacted frames to a plugin
+pub struct PluginSink {
+    name: String,
+    handle: *mut c_void,
+    vtable: SinkPluginV1,
+    frame_rate: Option<u32>,
+    _library: Arc<Library>,
+}
+
+// SAFETY: the handle is only used from the sink thread that owns the PluginSink
+unsafe impl Send for PluginSink {}
+
+impl FrameSink for PluginSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// A plugin can do anything with the frames, so assume the worst
+    fn writes_to_disk(&self) -> bool {
+        true
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.frame_rate
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        let data = frame.data();
+        let plugin_frame = PluginFrame {
+            data: data.as_ptr(),
+            len: data.len(),
+            width: frame.width(),
+            height: frame.height(),
+            stride: frame.width() * 4,
+            format: PLUGIN_FORMAT_RGBA,
+            sequence: frame.sequence(),
+        };
+        match unsafe { (self.vtable.consume)(self.handle, &plugin_frame) } {
+            0 => Ok(()),
+            code => Err(format!(
+                "Plugin {} rejected a frame (error {})",
+                self.name, code
+            )),
+        }
+    }
+
+    fn set_content_static(&mut self, is_static: bool) {
+        if let Some(set_content_static) = self.vtable.set_content_static {
+            unsafe { set_content_static(self.handle, is_static) };
+        }
+    }
+}
+
+impl Drop for PluginSink {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.destroy)(self.handle) };
+    }
+}
+
+/// State handed to a capture plugin as the callback context
+struct CallbackContext {
+    frames: FrameChannel,
+    event_callback: Option<CaptureEventCallback>,
+}
+
+/// ScreenCapture backed by a capture plugin
+pub struct PluginCapture {
+    name: String,
+    handle: *mut c_void,
+    vtable: CapturePluginV1,
+    frames: FrameChannel,
+    event_callback: Option<CaptureEventCallback>,
+    /// Context of the running capture (boxed so its address stays fixed)
+    running: Option<Box<CallbackContext>>,
+    _library: Arc<Library>,
+}
+
+// SAFETY: plugins must accept calls from any thread; calls are serialized through &mut self
+unsafe impl Send for PluginCapture {}
+unsafe impl Sync for PluginCapture {}
+
+impl PluginCapture {
+    fn new(plugin: &Plugin, vtable: CapturePluginV1) -> Result<Self, String> {
+        let handle = unsafe { (vtable.create)(plugin.settings.as_ptr()) };
+        if handle.is_null() {
+            return Err(format!(
+                "Plugin {} failed to create its capture",
+                plugin.name
+            ));
+        }
+        Ok(Self {
+            name: plugin.name.clone(),
+            handle,
+            vtable,
+            frames: FrameChannel::default(),
+            event_callback: None,
+            running: None,
+            _library: plugin.library.clone(),
+        })
+    }
+
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        let (mut width, mut height) = (0, 0);
+        unsafe { (self.vtable.frame_size)(self.handle, &mut width, &mut height) }
+            .then_some((width, height))
+    }
+}
+
+/// Copy a plugin frame into the frame channel and report it
+unsafe extern "C" fn on_plugin_frame(context: *mut c_void, frame: *const PluginFrame) {
+    // SAFETY: context is the CallbackContext passed to `start`, alive until `stop` returns
+    let (Some(context), Some(frame)) = (
+        unsafe { (context as *const CallbackContext).as_ref() },
+        unsafe { frame.as_ref() },
+    ) else {
+        return;
+    };
+    let row_bytes = frame.width as usize * 4;
+    let needed = (frame.stride as usize * frame.height.saturating_sub(1) as usize) + row_bytes;
+    if frame.data.is_null() || frame.width == 0 || frame.height == 0 || frame.len < needed {
+        return;
+    }
+    let pixel_format = match frame.format {
+        PLUGIN_FORMAT_RGBA => PixelFormat::Rgba8,
+        PLUGIN_FORMAT_BGRA => PixelFormat::Bgra8,
+        _ => return,
+    };
+    let data = unsafe { std::slice::from_raw_parts(frame.data, needed) }.to_vec();
+
+    let captured = Frame {
+        data: FrameData::Cpu(Arc::new(FrameBuffer::new(data))),
+        width: frame.width,
+        height: frame.height,
+        stride: frame.stride.max(frame.width * 4),
+        pixel_format,
+        alpha_mode: AlphaMode::Straight,
+        timestamp: Instant::now(),
+        content_scale: 1.0,
+        dirty_rects: None,
+        color_range: None,
+    };
+    context.frames.send(captured);
+    if let Some(callback) = &context.event_callback {
+        callback(CaptureEvent::FrameReady);
+    }
+}
+
+impl ScreenCapture for PluginCapture {
+    fn capabilities(&self) -> CaptureCapabilities {
+        // Plugins capture one fixed source; report a frame rate so display capture counts
+        CaptureCapabilities {
+            max_fps: Some(self.vtable.max_fps.max(1)),
+            ..CaptureCapabilities::default()
+        }
+    }
+
+    fn get_display_resolution(&self) -> Result<DisplayResolution, String> {
+        let (width, height) = self
+            .frame_size()
+            .ok_or_else(|| format!("Plugin {} doesn't know its frame size", self.name))?;
+        Ok(DisplayResolution {
+            width,
+            height,
+            scale_factor: 1.0,
+        })
+    }
+
+    fn start_capture(
+        &mut self,
+        _exclude_window: Option<&winit::window::Window>,
+    ) -> Result<(), String> {
+        self.stop_capture();
+        let context = Box::new(CallbackContext {
+            frames: self.frames.clone(),
+            event_callback: self.event_callback.clone(),
+        });
+        let context_ptr = &*context as *const CallbackContext as *mut c_void;
+        match unsafe { (self.vtable.start)(self.handle, on_plugin_frame, context_ptr) } {
+            0 => {
+                self.running = Some(context);
+                Ok(())
+            }
+            code => Err(format!(
+                "Plugin {} failed to start capture (error {})",
+                self.name, code
+            )),
+        }
+    }
+
+    fn get_latest_frame(&self) -> Option<Frame> {
+        self.frames.peek_latest()
+    }
+
+    fn stop_capture(&mut self) {
+        if self.running.is_some() {
+            unsafe { (self.vtable.stop)(self.handle) };
+            // The plugin no longer calls back, so the context can go
+            self.running = None;
+        }
+    }
+
+    fn frame_channel(&self) -> FrameChannel {
+        self.frames.clone()
+    }
+
+    fn set_event_callback(&mut self, callback: CaptureEventCallback) {
+        self.event_callback = Some(callback);
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        Err(format!("Capture plugin {} can't list windows", self.name))
+    }
+
+    fn list_sources(&self) -> Result<Vec<SourceInfo>, String> {
+        let (width, height) = self.frame_size().unwrap_or_default();
+        Ok(vec![SourceInfo {
+            source: CaptureSource::Display(0),
+            title: self.name.clone(),
+            width,
+            height,
+        }])
+    }
+
+    fn set_source(&mut self, _source: CaptureSource) {
+        // Plugins capture whatever their settings select
+    }
+
+    fn set_capture_scale(&mut self, _scale: CaptureScale) {
+        // Plugins capture whatever their settings select
+    }
+
+    fn capture_thumbnail(
+        &self,
+        _source: CaptureSource,
+        _max_width: u32,
+        _max_height: u32,
+    ) -> Result<SourceThumbnail, String> {
+        Err(format!("Capture plugin {} has no thumbnails", self.name))
+    }
+}
+
+impl Drop for PluginCapture {
+    fn drop(&mut self) {
+        self.stop_capture();
+        unsafe { (self.vtable.destroy)(self.handle) };
+    }
+}
+
+/// Plugin frames arrive in CPU memory, so mapping them is all conversion takes
+pub struct PluginPixelConverter;
+
+impl PixelConverter for PluginPixelConverter {
+    fn convert_to_rgba(&self, buffer: RawCaptureBuffer<'_>) -> Option<Vec<u8>> {
+        buffer.mapped_to_rgba()
+    }
+}
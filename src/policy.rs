@@ -0,0 +1,186 @@
+use crate::cross_platform_capture::CrossPlatformScreenCapture;
+use crate::platform::{CaptureSource, WindowInfo};
+use crate::redaction::RedactionRect;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Organization-provided rules that the user's config can't weaken
+/// Loaded from `policy.json`; see `Policy::path` for where it's looked up
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Regions masked at all times, on top of the config's and the active profile's rules
+    pub required_redactions: Vec<RedactionRect>,
+
+    /// Space IDs that are always private
+    pub required_private_spaces: Vec<u64>,
+
+    /// Applications (name or bundle ID, case-insensitive) whose windows may not be captured
+    pub forbidden_apps: Vec<String>,
+
+    /// Sources that may not be captured; a forbidden display also rules out its regions
+    /// and the all-displays overview
+    pub forbidden_sources: Vec<CaptureSource>,
+
+    /// Config settings forced to these values (e.g. `{"mask_fill": "black"}`)
+    /// User values for these keys are ignored, and profiles may not override them
+    pub locked_settings: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Policy {
+    /// Location of the policy: a system-wide path only administrators can write
+    /// Debug builds also honour `$CLOAK_SHARE_POLICY`, for testing policies
+    pub fn path() -> PathBuf {
+        if cfg!(debug_assertions)
+            && let Ok(path) = std::env::var("CLOAK_SHARE_POLICY")
+        {
+            return PathBuf::from(path);
+        }
+        if cfg!(target_os = "macos") {
+            PathBuf::from("/Library/Application Support/CloakShare/policy.json")
+        } else if cfg!(target_os = "windows") {
+            let program_data =
+                std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+            PathBuf::from(program_data)
+                .join("CloakShare")
+                .join("policy.json")
+        } else {
+            PathBuf::from("/etc/cloakshare/policy.json")
+        }
+    }
+
+    /// Load the policy; no file means no policy, but an unreadable or invalid one is an error
+    /// (silently running without it would drop the organization's rules)
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let policy: Policy = serde_json::from_str(&contents)
+                    .map_err(|e| format!("Invalid policy file {}: {}", path.display(), e))?;
+                println!("Enforcing policy from {}", path.display());
+                Ok(policy)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!(
+                "Failed to read policy file {}: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    /// Whether the policy sets anything at all
+    pub fn is_empty(&self) -> bool {
+        self.required_redactions.is_empty()
+            && self.required_private_spaces.is_empty()
+            && self.forbidden_apps.is_empty()
+            && self.forbidden_sources.is_empty()
+            && self.locked_settings.is_empty()
+    }
+
+    /// Check `source` against the forbidden sources and apps
+    /// `windows` is the current window list, needed to find the app owning a window source
+    pub fn check_source(
+        &self,
+        source: &CaptureSource,
+        windows: Option<&[WindowInfo]>,
+    ) -> Result<(), String> {
+        let display_forbidden = |display: u32| {
+            self.forbidden_sources
+                .contains(&CaptureSource::Display(display))
+        };
+        let forbidden = match *source {
+            _ if self.forbidden_sources.contains(source) => true,
+            CaptureSource::Region { display, .. } => display_forbidden(display),
+            CaptureSource::AllDisplays(_) => self
+                .forbidden_sources
+                .iter()
+                .any(|source| matches!(source, CaptureSource::Display(_))),
+            CaptureSource::Window(id) if !self.forbidden_apps.is_empty() => {
+                // Without the window list the owner is unknown; refuse rather than risk it
+                let Some(window) = windows.and_then(|windows| windows.iter().find(|w| w.id == id))
+                else {
+                    return Err(format!(
+                        "Policy restricts window capture and window {} can't be identified",
+                        id
+                    ));
+                };
+                self.forbidden_apps.iter().any(|app| {
+                    app.eq_ignore_ascii_case(&window.app_name)
+                        || app.eq_ignore_ascii_case(&window.app_id)
+                })
+            }
+            _ => false,
+        };
+        if forbidden {
+            return Err(format!("{:?} may not be captured (policy)", source));
+        }
+        Ok(())
+    }
+
+    /// Check the display `screen_capture` falls back to when no source was set (the first one
+    /// it lists), which is captured without ever being chosen
+    pub fn check_default_display(
+        &self,
+        screen_capture: &CrossPlatformScreenCapture,
+    ) -> Result<(), String> {
+        if screen_capture.source().is_some() || self.forbidden_sources.is_empty() {
+            return Ok(());
+        }
+        let display = screen_capture
+            .list_displays()?
+            .first()
+            .map(|info| info.id)
+            .ok_or("No displays found")?;
+        self.check_source(&CaptureSource::Display(display), None)
+    }
+
+    /// Overlay the locked settings on a parsed user config table, reporting overridden values
+    pub(crate) fn lock_settings(&self, table: &mut toml::Table) -> Result<(), String> {
+        for (key, value) in &self.locked_settings {
+            let value = toml::Value::try_from(value)
+                .map_err(|e| format!("Policy value for {} is invalid: {}", key, e))?;
+            if table.get(key).is_some_and(|user| *user != value) {
+                eprintln!(
+                    "Config setting {} is locked by policy; ignoring your value",
+                    key
+                );
+            }
+            table.insert(key.clone(), value);
+        }
+        Ok(())
+    }
+
+    /// Refuse configs whose profiles would switch off what the policy requires
+    pub(crate) fn check_profiles(&self, table: &toml::Table) -> Result<(), String> {
+        let Some(profiles) = table.get("profiles").and_then(toml::Value::as_table) else {
+            return Ok(());
+        };
+        for (name, profile) in profiles {
+            let Some(profile) = profile.as_table() else {
+                continue;
+            };
+            if let Some(key) = profile
+                .keys()
+                .find(|key| self.locked_settings.contains_key(*key))
+            {
+                return Err(format!(
+                    "Profile {} overrides {}, which is locked by policy",
+                    name, key
+                ));
+            }
+            if let Some(source) = profile.get("source") {
+                let source: CaptureSource = source
+                    .clone()
+                    .try_into()
+                    .map_err(|e| format!("Profile {} has an invalid source: {}", name, e))?;
+                // Window owners can't be checked before capture starts; selection checks them
+                if !matches!(source, CaptureSource::Window(_)) {
+                    self.check_source(&source, None)
+                        .map_err(|e| format!("Profile {}: {}", name, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
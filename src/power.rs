@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+/// Most frames per second captured while on battery (`low_power_on_battery`)
+pub const BATTERY_FRAME_RATE: u32 = 30;
+
+/// How often the power source is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tracks whether the machine runs on its battery, so the mirror can trade smoothness and
+/// effects for battery life while unplugged
+pub struct PowerWatcher {
+    on_battery: bool,
+    last_check: Instant,
+}
+
+impl PowerWatcher {
+    pub fn new() -> Self {
+        Self {
+            on_battery: on_battery(),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn on_battery(&self) -> bool {
+        self.on_battery
+    }
+
+    /// Check the power source (at most every few seconds); returns the new state when the
+    /// machine was plugged in or unplugged since the last change
+    pub fn poll(&mut self) -> Option<bool> {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+        let on_battery = on_battery();
+        if on_battery == self.on_battery {
+            return None;
+        }
+        self.on_battery = on_battery;
+        Some(on_battery)
+    }
+}
+
+impl Default for PowerWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the machine runs on its battery right now
+#[cfg(target_os = "macos")]
+pub fn on_battery() -> bool {
+    crate::platform::macos_power::on_battery().unwrap_or(false)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn on_battery() -> bool {
+    // Windows (GetSystemPowerStatus) and Linux (/sys/class/power_supply) aren't wired up yet
+    false
+}
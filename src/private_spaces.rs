@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Tracks the Spaces shown on the captured displays and whether the user marked any private
+pub struct PrivateSpaceWatcher {
+    /// `private_spaces` from the config (and policy), which marking never removes
+    configured: HashSet<u64>,
+    /// Spaces marked private from the mirror (Cmd+Shift+B), kept across launches in
+    /// `private_spaces.json`
+    marked: HashSet<u64>,
+    /// Space on each captured display, in capture order
+    current_spaces: Vec<u64>,
+}
+
+impl PrivateSpaceWatcher {
+    /// Watch for the configured Spaces and the ones marked in earlier sessions
+    pub fn new(private_spaces: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            configured: private_spaces.into_iter().collect(),
+            marked: load_marked(),
+            current_spaces: Vec::new(),
+        }
+    }
+
+    /// Check the current Space on every captured display (the active display when the capture
+    /// isn't tied to one, e.g. a window); returns true if any of them is private
+    /// Cheap enough to call every frame, which keeps the window for leaking a frame minimal
+    pub fn poll(&mut self, display_ids: &[u32]) -> bool {
+        let spaces: Vec<u64> = if display_ids.is_empty() {
+            current_space_id(None).into_iter().collect()
+        } else {
+            display_ids
+                .iter()
+                .filter_map(|&id| current_space_id(Some(id)))
+                .collect()
+        };
+        if spaces != self.current_spaces {
+            for id in spaces.iter().filter(|id| !self.current_spaces.contains(id)) {
+                // Logged so users can find the ID to put in `private_spaces`
+                println!("Captured display switched to Space {}", id);
+            }
+            self.current_spaces = spaces;
+        }
+
+        self.current_spaces
+            .iter()
+            .any(|id| self.configured.contains(id) || self.marked.contains(id))
+    }
+
+    /// Mark the Space currently on screen (the first captured display's) as private, or
+    /// unmark it if it was marked before, and save the marks
+    /// Returns the Space and whether it's now marked; Spaces from the config stay private
+    pub fn toggle_current_private(&mut self) -> Result<(u64, bool), String> {
+        let id = self
+            .current_space()
+            .ok_or("No Space is known for the captured display")?;
+        let marked = self.marked.insert(id);
+        if !marked {
+            self.marked.remove(&id);
+        }
+        save_marked(&self.marked)?;
+        Ok((id, marked))
+    }
+
+    pub fn current_space(&self) -> Option<u64> {
+        self.current_spaces.first().copied()
+    }
+}
+
+/// `private_spaces.json` next to the config file
+fn marked_path() -> Option<PathBuf> {
+    Some(
+        crate::config::Config::path()?
+            .parent()?
+            .join("private_spaces.json"),
+    )
+}
+
+/// Spaces marked in earlier sessions; none if the file is missing or unreadable
+fn load_marked() -> HashSet<u64> {
+    let Some(contents) = marked_path().and_then(|path| std::fs::read(path).ok()) else {
+        return HashSet::new();
+    };
+    match serde_json::from_slice(&contents) {
+        Ok(marked) => marked,
+        Err(e) => {
+            eprintln!("Ignoring saved private Spaces: {}", e);
+            HashSet::new()
+        }
+    }
+}
+
+fn save_marked(marked: &HashSet<u64>) -> Result<(), String> {
+    let path = marked_path().ok_or("No config directory for private Spaces")?;
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let mut ids: Vec<u64> = marked.iter().copied().collect();
+    ids.sort_unstable();
+    let contents = serde_json::to_vec_pretty(&ids)
+        .map_err(|e| format!("Failed to encode private Spaces: {}", e))?;
+    std::fs::write(&path, contents)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(target_os = "macos")]
+fn current_space_id(display_id: Option<u32>) -> Option<u64> {
+    crate::platform::macos_spaces::current_space_id(display_id)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn current_space_id(_display_id: Option<u32>) -> Option<u64> {
+    // Spaces are a macOS concept; other platforms never blank for this reason
+    None
+}
@@ -0,0 +1,449 @@
+use crate::stream_auth;
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+/// ALPN protocol of the CloakShare frame transport
+pub const ALPN: &[u8] = b"cloakshare-frames/1";
+
+/// Server name the sender connects with (receivers use a self-signed certificate)
+pub const SERVER_NAME: &str = "cloakshare";
+
+/// Sent by the receiver on the control stream when it lost a frame and needs a keyframe
+pub const KEYFRAME_REQUEST: &str = "keyframe";
+
+const WIRE_VERSION: u8 = 1;
+
+/// version, flags, frame id, index, data shards, shard size, frame length, pts
+const HEADER_LEN: usize = 1 + 1 + 4 + 2 + 2 + 2 + 4 + 8;
+
+const FLAG_KEYFRAME: u8 = 1;
+const FLAG_PARITY: u8 = 2;
+
+/// Data shards covered by one XOR parity shard: 25% overhead, recovers one lost shard per group
+pub const FEC_GROUP: usize = 4;
+
+/// Incomplete frames kept waiting for late shards; anything older is given up on
+const MAX_PENDING_FRAMES: u32 = 8;
+
+/// Label for the TLS exporter the stream key proofs are bound to
+const EXPORTER_LABEL: &[u8] = b"EXPORTER-cloakshare-stream-key";
+
+/// Largest line accepted on the control stream, in either direction (leaves room for a remote
+/// text event at its longest)
+const MAX_LINE: usize = 2048;
+
+/// An encoded frame as carried over the transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireFrame {
+    pub id: u32,
+    pub pts_ms: u64,
+    pub keyframe: bool,
+    /// Annex B access unit
+    pub data: Vec<u8>,
+}
+
+/// Split a frame into datagrams of at most `max_datagram` bytes: the data shards, then one
+/// parity shard per `FEC_GROUP` data shards
+pub fn shard_frame(frame: &WireFrame, max_datagram: usize) -> Vec<Vec<u8>> {
+    let shard_size = max_datagram
+        .saturating_sub(HEADER_LEN)
+        .clamp(1, u16::MAX as usize);
+    let shards: Vec<&[u8]> = if frame.data.is_empty() {
+        vec![&[]]
+    } else {
+        frame.data.chunks(shard_size).collect()
+    };
+    let data_shards = shards.len() as u16;
+    let flags = if frame.keyframe { FLAG_KEYFRAME } else { 0 };
+    let header = |flags: u8, index: usize| {
+        let mut datagram = Vec::with_capacity(HEADER_LEN + shard_size);
+        datagram.push(WIRE_VERSION);
+        datagram.push(flags);
+        datagram.extend_from_slice(&frame.id.to_be_bytes());
+        datagram.extend_from_slice(&(index as u16).to_be_bytes());
+        datagram.extend_from_slice(&data_shards.to_be_bytes());
+        datagram.extend_from_slice(&(shard_size as u16).to_be_bytes());
+        datagram.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+        datagram.extend_from_slice(&frame.pts_ms.to_be_bytes());
+        datagram
+    };
+
+    let mut datagrams: Vec<Vec<u8>> = shards
+        .iter()
+        .enumerate()
+        .map(|(index, shard)| {
+            let mut datagram = header(flags, index);
+            datagram.extend_from_slice(shard);
+            datagram
+        })
+        .collect();
+    for (group, members) in shards.chunks(FEC_GROUP).enumerate() {
+        let mut parity = vec![0u8; shard_size];
+        for shard in members {
+            xor_into(&mut parity, shard);
+        }
+        let mut datagram = header(flags | FLAG_PARITY, group);
+        datagram.extend_from_slice(&parity);
+        datagrams.push(datagram);
+    }
+    datagrams
+}
+
+fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (target, source) in target.iter_mut().zip(source) {
+        *target ^= source;
+    }
+}
+
+/// A frame still missing shards
+struct PartialFrame {
+    pts_ms: u64,
+    keyframe: bool,
+    length: usize,
+    shard_size: usize,
+    data: Vec<Option<Vec<u8>>>,
+    parity: Vec<Option<Vec<u8>>>,
+}
+
+impl PartialFrame {
+    /// Fill single gaps from parity; the frame's bytes once every data shard is there
+    fn complete(&mut self) -> Option<Vec<u8>> {
+        for (group, parity) in self.parity.iter().enumerate() {
+            let Some(parity) = parity else {
+                continue;
+            };
+            let start = group * FEC_GROUP;
+            let end = (start + FEC_GROUP).min(self.data.len());
+            let missing: Vec<usize> = (start..end).filter(|&i| self.data[i].is_none()).collect();
+            if let [index] = missing[..] {
+                let mut recovered = parity.clone();
+                for shard in self.data[start..end].iter().flatten() {
+                    xor_into(&mut recovered, shard);
+                }
+                recovered.truncate(self.shard_length(index));
+                self.data[index] = Some(recovered);
+            }
+        }
+        if self.data.iter().any(Option::is_none) {
+            return None;
+        }
+        Some(self.data.iter().flatten().flatten().copied().collect())
+    }
+
+    /// Every shard is full-size except the last
+    fn shard_length(&self, index: usize) -> usize {
+        self.length
+            .saturating_sub(index * self.shard_size)
+            .min(self.shard_size)
+    }
+}
+
+/// Reassembles frames from datagrams on the receiver
+/// Frames are delivered in order as soon as they're complete; after a loss, delta frames are
+/// held back until the next keyframe so the picture freezes instead of smearing
+pub struct FrameAssembler {
+    pending: BTreeMap<u32, PartialFrame>,
+    last_delivered: Option<u32>,
+    waiting_for_keyframe: bool,
+}
+
+impl Default for FrameAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameAssembler {
+    pub fn new() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            last_delivered: None,
+            // The decoder can't start on a delta frame
+            waiting_for_keyframe: true,
+        }
+    }
+
+    /// Whether frames were lost and the sender should be asked for a keyframe
+    pub fn needs_keyframe(&self) -> bool {
+        self.waiting_for_keyframe
+    }
+
+    /// Add a datagram; returns the frame it completed, if any
+    pub fn push(&mut self, datagram: &[u8]) -> Option<WireFrame> {
+        if datagram.len() < HEADER_LEN || datagram[0] != WIRE_VERSION {
+            return None;
+        }
+        let flags = datagram[1];
+        let id = u32::from_be_bytes(datagram[2..6].try_into().ok()?);
+        let index = u16::from_be_bytes(datagram[6..8].try_into().ok()?) as usize;
+        let data_shards = u16::from_be_bytes(datagram[8..10].try_into().ok()?) as usize;
+        let shard_size = u16::from_be_bytes(datagram[10..12].try_into().ok()?) as usize;
+        let length = u32::from_be_bytes(datagram[12..16].try_into().ok()?) as usize;
+        let pts_ms = u64::from_be_bytes(datagram[16..24].try_into().ok()?);
+        let payload = &datagram[HEADER_LEN..];
+        if data_shards == 0 || shard_size == 0 || length > data_shards * shard_size {
+            return None;
+        }
+        if self.last_delivered.is_some_and(|last| id <= last) {
+            return None;
+        }
+
+        let frame = self.pending.entry(id).or_insert_with(|| PartialFrame {
+            pts_ms,
+            keyframe: flags & FLAG_KEYFRAME != 0,
+            length,
+            shard_size,
+            data: vec![None; data_shards],
+            parity: vec![None; data_shards.div_ceil(FEC_GROUP)],
+        });
+        if frame.data.len() != data_shards || frame.shard_size != shard_size {
+            return None;
+        }
+        if flags & FLAG_PARITY != 0 {
+            if payload.len() == shard_size
+                && let Some(slot) = frame.parity.get_mut(index)
+            {
+                *slot = Some(payload.to_vec());
+            }
+        } else if index < data_shards && payload.len() == frame.shard_length(index) {
+            frame.data[index] = Some(payload.to_vec());
+        }
+
+        let newest = *self.pending.keys().next_back()?;
+        let data = self.pending.get_mut(&id)?.complete();
+        let Some(data) = data else {
+            // Give up on frames that fell too far behind the newest one
+            let expired: Vec<u32> = self
+                .pending
+                .keys()
+                .copied()
+                .take_while(|&pending| pending < newest.saturating_sub(MAX_PENDING_FRAMES))
+                .collect();
+            for pending in expired {
+                self.pending.remove(&pending);
+                self.waiting_for_keyframe = true;
+            }
+            return None;
+        };
+
+        // Anything older than this frame that hasn't completed is lost
+        let frame = self.pending.remove(&id)?;
+        let skipped_pending = self.pending.keys().any(|&pending| pending < id);
+        self.pending.retain(|&pending, _| pending > id);
+        let skipped_unseen = self
+            .last_delivered
+            .is_some_and(|last| last.checked_add(1) != Some(id));
+        self.last_delivered = Some(id);
+        if skipped_pending || skipped_unseen {
+            self.waiting_for_keyframe = true;
+        }
+        if self.waiting_for_keyframe && !frame.keyframe {
+            return None;
+        }
+        self.waiting_for_keyframe = false;
+        Some(WireFrame {
+            id,
+            pts_ms: frame.pts_ms,
+            keyframe: frame.keyframe,
+            data,
+        })
+    }
+}
+
+/// Transport settings shared by both ends: large datagram buffers for keyframe bursts and
+/// keep-alives so an idle (static) stream isn't timed out
+fn transport_config() -> quinn::TransportConfig {
+    let mut transport = quinn::TransportConfig::default();
+    transport
+        .datagram_receive_buffer_size(Some(16 << 20))
+        .datagram_send_buffer_size(8 << 20)
+        .keep_alive_interval(Some(Duration::from_secs(2)));
+    transport
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Receiver side: a fresh self-signed certificate per run
+/// Senders authenticate it with the stream key, not the certificate
+pub fn server_config() -> Result<quinn::ServerConfig, String> {
+    let certified = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])
+        .map_err(|e| format!("Failed to create QUIC certificate: {}", e))?;
+    let certificate = CertificateDer::from(certified.cert);
+    let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+    let mut crypto = rustls::ServerConfig::builder_with_provider(crypto_provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| format!("Failed to configure QUIC TLS: {}", e))?
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate], key.into())
+        .map_err(|e| format!("Failed to configure QUIC TLS: {}", e))?;
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    let crypto = QuicServerConfig::try_from(crypto)
+        .map_err(|e| format!("Failed to configure QUIC TLS: {}", e))?;
+    let mut config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    config.transport_config(Arc::new(transport_config()));
+    Ok(config)
+}
+
+/// Sender side: any certificate is accepted at the TLS layer; with a stream key, the
+/// handshake then proves both ends share it over this exact TLS session
+pub fn client_config() -> Result<quinn::ClientConfig, String> {
+    let provider = crypto_provider();
+    let mut crypto = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(|e| format!("Failed to configure QUIC TLS: {}", e))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(KeyAuthenticated(provider)))
+        .with_no_client_auth();
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    let crypto = QuicClientConfig::try_from(crypto)
+        .map_err(|e| format!("Failed to configure QUIC TLS: {}", e))?;
+    let mut config = quinn::ClientConfig::new(Arc::new(crypto));
+    config.transport_config(Arc::new(transport_config()));
+    Ok(config)
+}
+
+/// Checks the certificate's signatures but not who issued it (see `client_config`)
+#[derive(Debug)]
+struct KeyAuthenticated(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for KeyAuthenticated {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Hex TLS exporter value both ends of `connection` agree on (and a relay couldn't)
+fn session_binding(connection: &quinn::Connection) -> Result<String, String> {
+    let mut output = [0u8; 32];
+    connection
+        .export_keying_material(&mut output, EXPORTER_LABEL, b"")
+        .map_err(|_| "Failed to derive QUIC session binding".to_string())?;
+    Ok(output.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Read one control stream line, without its line ending
+/// A line longer than `MAX_LINE` is an error, so a peer can't make the reader buffer without
+/// limit
+pub async fn read_line<R: tokio::io::AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+) -> Result<String, String> {
+    let mut line = String::new();
+    let read = (&mut *reader)
+        .take(MAX_LINE as u64)
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("Control stream failed: {}", e))?;
+    if read == 0 {
+        return Err("Control stream closed".to_string());
+    }
+    if read == MAX_LINE && !line.ends_with('\n') {
+        return Err(format!("Control line longer than {} bytes", MAX_LINE));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// Sender side of the handshake: open the control stream and prove the stream key
+/// Returns the stream the receiver sends keyframe requests on
+pub async fn client_handshake(
+    connection: &quinn::Connection,
+    stream_key: Option<&str>,
+) -> Result<BufReader<quinn::RecvStream>, String> {
+    let binding = session_binding(connection)?;
+    let (mut send, recv) = connection
+        .open_bi()
+        .await
+        .map_err(|e| format!("Failed to open control stream: {}", e))?;
+    let proof = stream_key
+        .map(|key| stream_auth::offer_tag(key, &binding))
+        .transpose()?
+        .unwrap_or_default();
+    send.write_all(format!("{}\n", proof).as_bytes())
+        .await
+        .map_err(|e| format!("Control stream failed: {}", e))?;
+
+    let mut recv = BufReader::new(recv);
+    let answer = read_line(&mut recv).await?;
+    if let Some(key) = stream_key
+        && !stream_auth::matches(
+            &stream_auth::answer_tag(key, &binding, "quic"),
+            Some(answer.as_str()),
+        )
+    {
+        return Err("Receiver didn't prove it knows the stream key".to_string());
+    }
+    Ok(recv)
+}
+
+/// Receiver side of the handshake: check the sender's proof and answer with our own
+/// Returns the stream keyframe requests are written to
+pub async fn server_handshake(
+    connection: &quinn::Connection,
+    stream_key: Option<&str>,
+) -> Result<quinn::SendStream, String> {
+    let binding = session_binding(connection)?;
+    let (mut send, recv) = connection
+        .accept_bi()
+        .await
+        .map_err(|e| format!("Sender didn't open a control stream: {}", e))?;
+    let proof = read_line(&mut BufReader::new(recv)).await?;
+    let answer = match stream_key {
+        Some(key) => {
+            // The binding is unique to this connection, so the nonce can't have been used
+            stream_auth::verify_offer(key, &binding, Some(&proof))?;
+            stream_auth::answer_tag(key, &binding, "quic")
+        }
+        None => String::new(),
+    };
+    send.write_all(format!("{}\n", answer).as_bytes())
+        .await
+        .map_err(|e| format!("Control stream failed: {}", e))?;
+    Ok(send)
+}
@@ -0,0 +1,880 @@
+use crate::cli::ReceiveOptions;
+use crate::config::Config;
+use crate::encoding::h264::H264Decoder;
+use crate::encoding::mp4::has_idr;
+use crate::frame_bus::{FrameSubscriber, ProcessedFrame, ProcessedFrameBus};
+use crate::gpu_renderer::{GpuRenderer, GpuSettings};
+#[cfg(feature = "quic")]
+use crate::remote_input::{PIXELS_PER_LINE, PointerButton, RemoteInputEvent, SpecialKey};
+use crate::stream_auth;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, TcpListener, TcpStream, UdpSocket};
+use std::sync::Arc;
+#[cfg(feature = "quic")]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use webrtc::api::APIBuilder;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MIME_TYPE_H264, MediaEngine};
+use webrtc::interceptor::registry::Registry;
+use webrtc::media::io::sample_builder::SampleBuilder;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::rtp::codecs::h264::H264Packet;
+use webrtc::rtp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_codec::RTPCodecType;
+use webrtc::util::marshal::Unmarshal;
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::WindowEvent;
+#[cfg(feature = "quic")]
+use winit::event::{KeyEvent, MouseButton, MouseScrollDelta};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+#[cfg(feature = "quic")]
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+use winit::window::{Window, WindowId};
+
+/// Path senders POST their offer to (advertised over mDNS as the `path` TXT record)
+pub const WHIP_PATH: &str = "/whip";
+
+/// WHIP resource of the current session, deleted by the sender to hang up
+const SESSION_PATH: &str = "/whip/session";
+
+/// Packets the depacketizer waits for a missing one before giving up on that frame
+const MAX_LATE_PACKETS: u16 = 256;
+
+/// RTP clock rate of H.264 video
+const VIDEO_CLOCK_RATE: u32 = 90_000;
+
+/// Largest request (headers or SDP body) the WHIP endpoint accepts
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Access units queued for the decoder; when it falls further behind, units are dropped
+const DECODE_QUEUE_LENGTH: usize = 4;
+
+/// Minimum time between keyframe requests to a QUIC sender (a bit over a LAN round trip plus
+/// encoding a keyframe), so one loss burst doesn't trigger a keyframe per lost frame
+#[cfg(feature = "quic")]
+const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Wakes the receiver window when a decoded frame is on the bus
+#[derive(Debug, Clone, Copy)]
+enum ReceiverEvent {
+    FrameReady,
+}
+
+/// Show incoming streams in a window until it is closed
+/// Senders must present `token` as a bearer token, and prove they know the config's
+/// `stream_key`, when those are set
+pub fn run(options: ReceiveOptions, token: Option<String>, config: &Config) -> Result<(), String> {
+    if options.remote_input && !cfg!(feature = "quic") {
+        return Err("--remote-input needs the quic cargo feature".to_string());
+    }
+    match &config.stream_key {
+        None if config.require_encrypted_streams => {
+            return Err("require_encrypted_streams needs a stream_key to receive".to_string());
+        }
+        None => eprintln!(
+            "No stream_key: only accepting senders on this machine; set a stream_key to pair \
+             senders on the network"
+        ),
+        Some(_) if options.rtp_port.is_some() && config.require_encrypted_streams => {
+            return Err(
+                "Plain RTP isn't encrypted; --rtp-port can't be used with require_encrypted_streams"
+                    .to_string(),
+            );
+        }
+        Some(_) => {}
+    }
+
+    // Without a stream key nothing tells a paired sender from any other, so stay off the network
+    let host = if config.stream_key.is_some() {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    };
+
+    let event_loop = EventLoop::<ReceiverEvent>::with_user_event()
+        .build()
+        .map_err(|e| format!("Failed to create event loop: {}", e))?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+
+    let bus = ProcessedFrameBus::new();
+    let proxy = event_loop.create_proxy();
+    let units = spawn_decoder(bus.clone(), move || {
+        let _ = proxy.send_event(ReceiverEvent::FrameReady);
+    })?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start WebRTC runtime: {}", e))?;
+    let listener = TcpListener::bind((host, options.port))
+        .map_err(|e| format!("Failed to listen on port {}: {}", options.port, e))?;
+    let mut server = WhipServer {
+        runtime,
+        session: None,
+        units: units.clone(),
+        token,
+        stream_key: config.stream_key.clone(),
+        answer: None,
+        nonces: stream_auth::NonceGuard::default(),
+    };
+    std::thread::Builder::new()
+        .name("whip-server".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                if let Err(e) = server.handle(stream) {
+                    eprintln!("WHIP request failed: {}", e);
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to spawn WHIP server thread: {}", e))?;
+    println!(
+        "Receiving WHIP streams on port {} at {}",
+        options.port, WHIP_PATH
+    );
+
+    // QUIC senders use the same port number over UDP
+    #[cfg(feature = "quic")]
+    let (_quic_runtime, quic_peer) =
+        serve_quic(host, options.port, config.stream_key.clone(), units.clone())?;
+
+    if let Some(rtp_port) = options.rtp_port {
+        let socket = UdpSocket::bind((host, rtp_port))
+            .map_err(|e| format!("Failed to listen for RTP on port {}: {}", rtp_port, e))?;
+        std::thread::Builder::new()
+            .name("rtp-receiver".to_string())
+            .spawn(move || receive_rtp(socket, units))
+            .map_err(|e| format!("Failed to spawn RTP receiver thread: {}", e))?;
+        println!("Receiving RTP/H.264 on UDP port {}", rtp_port);
+    }
+
+    // Let senders on the local network find this receiver with --send-to / Cmd+Shift+R
+    let _advertisement = if cfg!(feature = "discovery") && config.stream_key.is_some() {
+        crate::discovery::ReceiverAdvertisement::start(&options.name, options.port, WHIP_PATH)
+            .map_err(|e| eprintln!("{}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    let mut app = ReceiverApp {
+        title: options.name,
+        frames: bus.subscribe(),
+        gpu_settings: config.gpu_settings(),
+        window: None,
+        renderer: None,
+        #[cfg(feature = "quic")]
+        input: options.remote_input.then(|| InputForwarder {
+            peer: quic_peer,
+            modifiers: ModifiersState::default(),
+        }),
+    };
+    event_loop
+        .run_app(&mut app)
+        .map_err(|e| format!("Receiver event loop failed: {}", e))
+}
+
+/// Hands access units to the decoder thread without ever blocking the network side
+#[derive(Clone)]
+struct DecodeQueue {
+    sender: mpsc::SyncSender<Vec<u8>>,
+    /// Set when a unit was dropped on a full queue; the frames after it would decode smeared,
+    /// so nothing but an IDR access unit is queued until this is cleared
+    waiting_for_idr: Arc<AtomicBool>,
+}
+
+impl DecodeQueue {
+    /// Queue a unit, or drop it if the decoder is behind or still needs an IDR
+    /// Returns false once the decoder thread is gone
+    fn send(&self, unit: Vec<u8>) -> bool {
+        if self.waiting_for_idr.load(Ordering::Relaxed) && !has_idr(&unit) {
+            return true;
+        }
+        match self.sender.try_send(unit) {
+            Ok(()) => {
+                self.waiting_for_idr.store(false, Ordering::Relaxed);
+                true
+            }
+            Err(mpsc::TrySendError::Full(_)) => {
+                if !self.waiting_for_idr.swap(true, Ordering::Relaxed) {
+                    eprintln!("Decoder is falling behind; dropping frames until the next keyframe");
+                }
+                true
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// Whether units are being dropped until the next IDR
+    #[cfg(feature = "quic")]
+    fn needs_keyframe(&self) -> bool {
+        self.waiting_for_idr.load(Ordering::Relaxed)
+    }
+}
+
+/// Decode access units on their own thread so a slow decode never stalls the network side
+fn spawn_decoder(
+    bus: ProcessedFrameBus,
+    on_frame: impl Fn() + Send + 'static,
+) -> Result<DecodeQueue, String> {
+    let mut decoder = H264Decoder::new()?;
+    let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(DECODE_QUEUE_LENGTH);
+    std::thread::Builder::new()
+        .name("receiver-decoder".to_string())
+        .spawn(move || {
+            while let Ok(access_unit) = receiver.recv() {
+                match decoder.decode(&access_unit) {
+                    Ok(Some((rgba, width, height))) => {
+                        bus.publish(ProcessedFrame::new(rgba, width, height));
+                        on_frame();
+                    }
+                    Ok(None) => {}
+                    // A damaged access unit only costs that frame; the next keyframe recovers
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to spawn decoder thread: {}", e))?;
+    Ok(DecodeQueue {
+        sender,
+        waiting_for_idr: Arc::default(),
+    })
+}
+
+/// Reassembles RTP packets into Annex B access units
+struct Depacketizer {
+    builder: SampleBuilder<H264Packet>,
+}
+
+impl Depacketizer {
+    fn new() -> Self {
+        Self {
+            builder: SampleBuilder::new(MAX_LATE_PACKETS, H264Packet::default(), VIDEO_CLOCK_RATE),
+        }
+    }
+
+    /// Add a packet and return the access units it completed
+    fn push(&mut self, packet: Packet) -> Vec<Vec<u8>> {
+        self.builder.push(packet);
+        std::iter::from_fn(|| self.builder.pop())
+            .map(|sample| sample.data.to_vec())
+            .collect()
+    }
+}
+
+/// Plain RTP from senders that don't speak WHIP (`ffmpeg -f rtp rtp://host:port`)
+fn receive_rtp(socket: UdpSocket, units: DecodeQueue) {
+    let mut depacketizer = Depacketizer::new();
+    let mut buffer = vec![0u8; 65536];
+    while let Ok(length) = socket.recv(&mut buffer) {
+        let Ok(packet) = Packet::unmarshal(&mut &buffer[..length]) else {
+            continue;
+        };
+        for unit in depacketizer.push(packet) {
+            if !units.send(unit) {
+                return;
+            }
+        }
+    }
+}
+
+/// The connected QUIC sender and the lines queued for its control stream
+#[cfg(feature = "quic")]
+struct QuicPeer {
+    connection: quinn::Connection,
+    control: tokio::sync::mpsc::UnboundedSender<String>,
+}
+
+#[cfg(feature = "quic")]
+type CurrentPeer = Arc<Mutex<Option<QuicPeer>>>;
+
+/// Accept QUIC senders; the returned runtime runs the listener until it is dropped
+#[cfg(feature = "quic")]
+fn serve_quic(
+    host: IpAddr,
+    port: u16,
+    stream_key: Option<String>,
+    units: DecodeQueue,
+) -> Result<(Runtime, CurrentPeer), String> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start QUIC runtime: {}", e))?;
+    let config = crate::quic::server_config()?;
+    let endpoint = runtime
+        .block_on(async { quinn::Endpoint::server(config, std::net::SocketAddr::new(host, port)) })
+        .map_err(|e| format!("Failed to listen for QUIC on port {}: {}", port, e))?;
+
+    // One sender at a time, like WHIP: a newly connected sender replaces the current one
+    let current = CurrentPeer::default();
+    let peer = current.clone();
+    runtime.spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            let stream_key = stream_key.clone();
+            let units = units.clone();
+            let current = current.clone();
+            tokio::spawn(async move {
+                if let Err(e) = receive_quic(incoming, stream_key, units, current).await {
+                    eprintln!("QUIC sender failed: {}", e);
+                }
+            });
+        }
+    });
+    println!("Receiving QUIC streams on UDP port {}", port);
+    Ok((runtime, peer))
+}
+
+/// Reassemble one QUIC sender's frames until it disconnects
+#[cfg(feature = "quic")]
+async fn receive_quic(
+    incoming: quinn::Incoming,
+    stream_key: Option<String>,
+    units: DecodeQueue,
+    current: CurrentPeer,
+) -> Result<(), String> {
+    let connection = incoming
+        .await
+        .map_err(|e| format!("QUIC handshake failed: {}", e))?;
+    let mut control_stream =
+        crate::quic::server_handshake(&connection, stream_key.as_deref()).await?;
+
+    // Keyframe requests and remote input share the control stream; one task writes both
+    let (control, mut lines) = tokio::sync::mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(line) = lines.recv().await {
+            if control_stream.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+    let peer = QuicPeer {
+        connection: connection.clone(),
+        control: control.clone(),
+    };
+    if let Ok(mut current) = current.lock()
+        && let Some(previous) = current.replace(peer)
+    {
+        previous
+            .connection
+            .close(0u32.into(), b"replaced by another sender");
+    }
+    println!(
+        "Sender connected over QUIC from {}",
+        connection.remote_address()
+    );
+
+    let mut assembler = crate::quic::FrameAssembler::new();
+    let mut last_request: Option<std::time::Instant> = None;
+    loop {
+        let datagram = match connection.read_datagram().await {
+            Ok(datagram) => datagram,
+            Err(quinn::ConnectionError::ApplicationClosed(_)) => {
+                println!("Sender hung up");
+                return Ok(());
+            }
+            // Replaced by a newer sender
+            Err(quinn::ConnectionError::LocallyClosed) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        };
+        if let Some(frame) = assembler.push(&datagram)
+            && !units.send(frame.data)
+        {
+            return Ok(());
+        }
+        // Until a keyframe arrives the picture stays frozen; ask again if the keyframe was lost
+        // too, or was dropped because the decoder fell behind
+        if (assembler.needs_keyframe() || units.needs_keyframe())
+            && last_request.is_none_or(|last| last.elapsed() >= KEYFRAME_REQUEST_INTERVAL)
+        {
+            last_request = Some(std::time::Instant::now());
+            control
+                .send(format!("{}\n", crate::quic::KEYFRAME_REQUEST))
+                .map_err(|_| "Control stream closed".to_string())?;
+        }
+    }
+}
+
+/// A parsed HTTP/1.1 request
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    /// Stream key proof (`stream_auth::AUTH_HEADER`)
+    proof: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Minimal WHIP endpoint: one session at a time, a new offer replaces the current sender
+struct WhipServer {
+    runtime: Runtime,
+    session: Option<Arc<RTCPeerConnection>>,
+    units: DecodeQueue,
+    token: Option<String>,
+    stream_key: Option<String>,
+    /// Answer SDP of the current session; the sender's hangup proof covers it
+    answer: Option<String>,
+    /// Offer proofs already accepted, so a captured offer can't start a session again
+    nonces: stream_auth::NonceGuard,
+}
+
+impl WhipServer {
+    fn handle(&mut self, mut stream: TcpStream) -> Result<(), String> {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .map_err(|e| e.to_string())?;
+        let request = read_request(&stream)?;
+
+        // Browser-based senders need CORS; the preflight carries no token
+        if request.method == "OPTIONS" {
+            return write_response(&mut stream, "204 No Content", &[], "");
+        }
+        if let Some(token) = &self.token
+            && request.authorization.as_deref() != Some(&format!("Bearer {}", token))
+        {
+            return write_response(&mut stream, "401 Unauthorized", &[], "");
+        }
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", WHIP_PATH) => {
+                let offer = String::from_utf8(request.body)
+                    .map_err(|_| "Offer is not valid UTF-8".to_string())?;
+                if let Some(key) = &self.stream_key {
+                    let verified = stream_auth::verify_offer(key, &offer, request.proof.as_deref())
+                        .and_then(|nonce| {
+                            if self.nonces.first_use(nonce) {
+                                Ok(())
+                            } else {
+                                Err("Offer proof was already used (replayed)".to_string())
+                            }
+                        });
+                    if let Err(e) = verified {
+                        write_response(&mut stream, "401 Unauthorized", &[], "")?;
+                        return Err(e);
+                    }
+                }
+                match self.answer(offer.clone()) {
+                    Ok(answer) => {
+                        let proof = self
+                            .stream_key
+                            .as_ref()
+                            .map(|key| stream_auth::answer_tag(key, &offer, &answer));
+                        let mut headers = vec![
+                            ("Content-Type", "application/sdp"),
+                            ("Location", SESSION_PATH),
+                        ];
+                        if let Some(proof) = &proof {
+                            headers.push((stream_auth::AUTH_HEADER, proof.as_str()));
+                        }
+                        write_response(&mut stream, "201 Created", &headers, &answer)
+                    }
+                    Err(e) => {
+                        write_response(&mut stream, "400 Bad Request", &[], &e)?;
+                        Err(e)
+                    }
+                }
+            }
+            ("DELETE", SESSION_PATH) => {
+                let Some(answer) = &self.answer else {
+                    return write_response(&mut stream, "404 Not Found", &[], "");
+                };
+                if let Some(key) = &self.stream_key
+                    && !stream_auth::matches(
+                        &stream_auth::hangup_tag(key, answer),
+                        request.proof.as_deref(),
+                    )
+                {
+                    return write_response(&mut stream, "401 Unauthorized", &[], "");
+                }
+                self.close();
+                println!("Sender hung up");
+                write_response(&mut stream, "200 OK", &[], "")
+            }
+            _ => write_response(&mut stream, "404 Not Found", &[], ""),
+        }
+    }
+
+    /// Answer a sender's offer with a receive-only H.264 transceiver
+    fn answer(&mut self, offer: String) -> Result<String, String> {
+        self.close();
+        let units = self.units.clone();
+        let (peer_connection, answer) = self
+            .runtime
+            .block_on(async move {
+                let mut media_engine = MediaEngine::default();
+                media_engine.register_default_codecs()?;
+                let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+                let api = APIBuilder::new()
+                    .with_media_engine(media_engine)
+                    .with_interceptor_registry(registry)
+                    .build();
+                let peer_connection =
+                    Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
+                peer_connection
+                    .add_transceiver_from_kind(RTPCodecType::Video, None)
+                    .await?;
+
+                peer_connection.on_track(Box::new(move |track, _, _| {
+                    let units = units.clone();
+                    Box::pin(async move {
+                        let codec = track.codec().capability.mime_type;
+                        if !codec.eq_ignore_ascii_case(MIME_TYPE_H264) {
+                            eprintln!("Ignoring {} track; only H.264 can be shown", codec);
+                            return;
+                        }
+                        // Read on a task of its own so the handler returns right away
+                        tokio::spawn(async move {
+                            let mut depacketizer = Depacketizer::new();
+                            while let Ok((packet, _)) = track.read_rtp().await {
+                                for unit in depacketizer.push(packet) {
+                                    if !units.send(unit) {
+                                        return;
+                                    }
+                                }
+                            }
+                        });
+                    })
+                }));
+
+                peer_connection
+                    .set_remote_description(RTCSessionDescription::offer(offer)?)
+                    .await?;
+                // No trickle: answer once every candidate is in the SDP
+                let answer = peer_connection.create_answer(None).await?;
+                let mut gathered = peer_connection.gathering_complete_promise().await;
+                peer_connection.set_local_description(answer).await?;
+                let _ = gathered.recv().await;
+                let answer = peer_connection
+                    .local_description()
+                    .await
+                    .map(|description| description.sdp)
+                    .unwrap_or_default();
+                Ok::<_, webrtc::Error>((peer_connection, answer))
+            })
+            .map_err(|e| format!("Failed to answer WHIP offer: {}", e))?;
+        self.session = Some(peer_connection);
+        self.answer = Some(answer.clone());
+        println!("Sender connected");
+        Ok(answer)
+    }
+
+    fn close(&mut self) {
+        self.answer = None;
+        if let Some(peer_connection) = self.session.take() {
+            let _ = self.runtime.block_on(peer_connection.close());
+        }
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or("Empty request")?.to_string();
+    let path = parts.next().ok_or("Request has no path")?;
+    // The query string doesn't select anything here
+    let path = path.split('?').next().unwrap_or(path).to_string();
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut proof = None;
+    let mut header_bytes = line.len();
+    loop {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        header_bytes += read;
+        if header_bytes > MAX_REQUEST_BYTES {
+            return Err("Request headers are too large".to_string());
+        }
+        let header = line.trim_end();
+        if read == 0 || header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("Content-Length") {
+            content_length = value
+                .parse()
+                .map_err(|_| format!("Invalid Content-Length: {}", value))?;
+        } else if name.eq_ignore_ascii_case("Authorization") {
+            authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case(stream_auth::AUTH_HEADER) {
+            proof = Some(value.to_string());
+        }
+    }
+    if content_length > MAX_REQUEST_BYTES {
+        return Err(format!(
+            "Request body is too large ({} bytes)",
+            content_length
+        ));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("Failed to read request body: {}", e))?;
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        proof,
+        body,
+    })
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Result<(), String> {
+    let mut response = format!(
+        "HTTP/1.1 {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, DELETE, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type, X-CloakShare-Auth\r\n\
+         Access-Control-Expose-Headers: Location, X-CloakShare-Auth\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|e| format!("Failed to send response: {}", e))
+}
+
+/// The receiver window: shows the latest decoded frame
+struct ReceiverApp {
+    title: String,
+    frames: FrameSubscriber,
+    gpu_settings: GpuSettings,
+    window: Option<Arc<Window>>,
+    /// Created for the first frame and recreated when the sender's resolution changes
+    renderer: Option<GpuRenderer>,
+    /// Sends input over the window to the QUIC sender (`--remote-input`)
+    #[cfg(feature = "quic")]
+    input: Option<InputForwarder>,
+}
+
+#[cfg(feature = "quic")]
+struct InputForwarder {
+    peer: CurrentPeer,
+    modifiers: ModifiersState,
+}
+
+#[cfg(feature = "quic")]
+impl InputForwarder {
+    /// Forward `event` if it's input and a sender is connected
+    /// The picture is stretched over the whole window, so positions are fractions of its size
+    fn forward(&mut self, window: &Window, event: &WindowEvent) {
+        let input = match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                return;
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let size = window.inner_size();
+                RemoteInputEvent::Move {
+                    x: (position.x / size.width.max(1) as f64) as f32,
+                    y: (position.y / size.height.max(1) as f64) as f32,
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => RemoteInputEvent::Button {
+                button: match button {
+                    MouseButton::Left => PointerButton::Left,
+                    MouseButton::Right => PointerButton::Right,
+                    MouseButton::Middle => PointerButton::Middle,
+                    _ => return,
+                },
+                pressed: state.is_pressed(),
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        let points = position.to_logical::<f32>(window.scale_factor());
+                        (points.x / PIXELS_PER_LINE, points.y / PIXELS_PER_LINE)
+                    }
+                };
+                RemoteInputEvent::Scroll { x, y }
+            }
+            WindowEvent::KeyboardInput {
+                event,
+                is_synthetic: false,
+                ..
+            } => match key_input(event, self.modifiers) {
+                Some(input) => input,
+                None => return,
+            },
+            _ => return,
+        };
+        if let Ok(peer) = self.peer.lock()
+            && let Some(peer) = peer.as_ref()
+        {
+            let _ = peer.control.send(input.to_line());
+        }
+    }
+}
+
+/// Special keys as key presses, anything else as typed text
+/// Shortcuts would arrive as plain letters on the sender, so only typing is forwarded
+#[cfg(feature = "quic")]
+fn key_input(event: &KeyEvent, modifiers: ModifiersState) -> Option<RemoteInputEvent> {
+    let pressed = event.state.is_pressed();
+    let special = match &event.logical_key {
+        Key::Named(NamedKey::Enter) => Some(SpecialKey::Enter),
+        Key::Named(NamedKey::Tab) => Some(SpecialKey::Tab),
+        Key::Named(NamedKey::Backspace) => Some(SpecialKey::Backspace),
+        Key::Named(NamedKey::Delete) => Some(SpecialKey::Delete),
+        Key::Named(NamedKey::Escape) => Some(SpecialKey::Escape),
+        Key::Named(NamedKey::ArrowLeft) => Some(SpecialKey::Left),
+        Key::Named(NamedKey::ArrowRight) => Some(SpecialKey::Right),
+        Key::Named(NamedKey::ArrowUp) => Some(SpecialKey::Up),
+        Key::Named(NamedKey::ArrowDown) => Some(SpecialKey::Down),
+        Key::Named(NamedKey::Home) => Some(SpecialKey::Home),
+        Key::Named(NamedKey::End) => Some(SpecialKey::End),
+        Key::Named(NamedKey::PageUp) => Some(SpecialKey::PageUp),
+        Key::Named(NamedKey::PageDown) => Some(SpecialKey::PageDown),
+        _ => None,
+    };
+    if let Some(key) = special {
+        return Some(RemoteInputEvent::Key { key, pressed });
+    }
+    if !pressed || modifiers.control_key() || modifiers.super_key() {
+        return None;
+    }
+    let text = event.text.as_ref()?.to_string();
+    (!text.is_empty() && !text.chars().any(char::is_control))
+        .then_some(RemoteInputEvent::Text { text })
+}
+
+impl ReceiverApp {
+    fn render(&mut self, event_loop: &ActiveEventLoop) {
+        let (Some(window), Some(frame)) = (&self.window, self.frames.latest()) else {
+            return;
+        };
+
+        let size_changed = self.renderer.as_ref().is_none_or(|renderer| {
+            renderer.capture_width != frame.width() || renderer.capture_height != frame.height()
+        });
+        if size_changed {
+            // Release the old surface before configuring a new one on the same window
+            self.renderer = None;
+            match pollster::block_on(GpuRenderer::new(
+                window.clone(),
+                frame.width(),
+                frame.height(),
+                &self.gpu_settings,
+            )) {
+                Ok(renderer) => self.renderer = Some(renderer),
+                Err(e) => {
+                    eprintln!("Failed to start the GPU renderer: {}", e);
+                    event_loop.exit();
+                    return;
+                }
+            }
+            window.set_title(&format!(
+                "{} - {}x{}",
+                self.title,
+                frame.width(),
+                frame.height()
+            ));
+        }
+
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        renderer.update_texture(frame.data());
+        match renderer.render() {
+            Ok(()) => {}
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                renderer.resize(renderer.size());
+                window.request_redraw();
+            }
+            Err(wgpu::SurfaceError::Timeout) => {}
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                if renderer.reduce_memory() {
+                    window.request_redraw();
+                } else {
+                    eprintln!("GPU out of memory");
+                    event_loop.exit();
+                }
+            }
+            Err(e) => eprintln!("Render error: {e:?}"),
+        }
+    }
+}
+
+impl ApplicationHandler<ReceiverEvent> for ReceiverApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title(format!("{} - Waiting for a sender", self.title))
+            .with_inner_size(LogicalSize::new(1280.0, 720.0));
+        match event_loop.create_window(attributes) {
+            Ok(window) => self.window = Some(Arc::new(window)),
+            Err(e) => {
+                eprintln!("Failed to create receiver window: {}", e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: ReceiverEvent) {
+        match event {
+            ReceiverEvent::FrameReady => {
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        #[cfg(feature = "quic")]
+        if let (Some(input), Some(window)) = (&mut self.input, &self.window) {
+            input.forward(window, &event);
+        }
+
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.resize(size);
+                }
+            }
+            WindowEvent::RedrawRequested => self.render(event_loop),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use crate::encoding::VideoCodec;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often the bytes written since the last segment are hashed and the manifest re-signed
+/// A recording cut short (crash, power loss) stays verifiable up to the last segment
+pub const SEGMENT_INTERVAL: Duration = Duration::from_secs(10);
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Prefixed to the signed bytes so a manifest signature can't be passed off as anything else
+const SIGNATURE_CONTEXT: &[u8] = b"cloakshare-recording-manifest-v1\n";
+
+/// A hashed byte range of the recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub offset: u64,
+    pub length: u64,
+    /// Hex SHA-256 of the range
+    pub sha256: String,
+    /// Recording time at the end of the segment
+    pub end_ms: u64,
+}
+
+/// What the signature covers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// File name of the recording (the manifest sits next to it)
+    pub file: String,
+    pub codec: String,
+    /// Milliseconds since the Unix epoch when recording started
+    pub started_at_ms: u64,
+    /// False while recording, or if recording never finished
+    pub complete: bool,
+    /// Contiguous ranges from the start of the file
+    pub segments: Vec<Segment>,
+}
+
+/// The manifest file: the manifest, the signer's public key and the signature, all hex
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedManifest {
+    pub manifest: Manifest,
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Manifest path for a recording: `<recording>.manifest.json`
+pub fn manifest_path(recording: &Path) -> PathBuf {
+    let mut path = recording.as_os_str().to_owned();
+    path.push(".manifest.json");
+    PathBuf::from(path)
+}
+
+/// The ed25519 key recordings are signed with
+pub struct ManifestSigner {
+    key: SigningKey,
+}
+
+impl ManifestSigner {
+    /// Load the hex-encoded key at `path`, or create one there (readable only by the user)
+    pub fn load_or_create(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let bytes: [u8; 32] = from_hex(contents.trim())
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| format!("{} is not an ed25519 signing key", path.display()))?;
+                Ok(Self {
+                    key: SigningKey::from_bytes(&bytes),
+                })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut bytes = [0u8; 32];
+                getrandom::getrandom(&mut bytes)
+                    .map_err(|e| format!("Failed to generate a signing key: {}", e))?;
+                if let Some(dir) = path.parent() {
+                    let _ = std::fs::create_dir_all(dir);
+                }
+                let mut options = std::fs::OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                options
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{}", to_hex(&bytes)))
+                    .map_err(|e| format!("Failed to save signing key {}: {}", path.display(), e))?;
+                let signer = Self {
+                    key: SigningKey::from_bytes(&bytes),
+                };
+                println!(
+                    "Created recording signing key {} (public key {})",
+                    path.display(),
+                    signer.public_key()
+                );
+                Ok(signer)
+            }
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Hex public key recipients check manifests against
+    pub fn public_key(&self) -> String {
+        to_hex(&self.key.verifying_key().to_bytes())
+    }
+
+    fn sign(&self, manifest: Manifest) -> Result<SignedManifest, String> {
+        let signature = self.key.sign(&signed_bytes(&manifest)?);
+        Ok(SignedManifest {
+            manifest,
+            public_key: self.public_key(),
+            signature: to_hex(&signature.to_bytes()),
+        })
+    }
+}
+
+/// Hashes a recording as it's written and keeps a signed manifest next to it
+pub struct ManifestRecorder {
+    recording: PathBuf,
+    signer: ManifestSigner,
+    manifest: Manifest,
+    /// Bytes covered by segments so far
+    hashed: u64,
+    last_segment: Instant,
+}
+
+impl ManifestRecorder {
+    pub fn new(recording: PathBuf, codec: VideoCodec, signer: ManifestSigner) -> Self {
+        let file = recording
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            recording,
+            signer,
+            manifest: Manifest {
+                version: MANIFEST_VERSION,
+                file,
+                codec: format!("{:?}", codec),
+                started_at_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_millis() as u64)
+                    .unwrap_or_default(),
+                complete: false,
+                segments: Vec::new(),
+            },
+            hashed: 0,
+            last_segment: Instant::now(),
+        }
+    }
+
+    /// Whether a segment should be closed now
+    pub fn is_due(&self) -> bool {
+        self.last_segment.elapsed() >= SEGMENT_INTERVAL
+    }
+
+    /// Hash everything written since the last segment and re-sign the manifest
+    /// The recording must be flushed first
+    pub fn add_segment(&mut self, end_ms: u64) -> Result<(), String> {
+        if self.hash_tail(end_ms)? {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Cover the rest of the finished file and mark the manifest complete
+    pub fn finish(&mut self, end_ms: u64) -> Result<PathBuf, String> {
+        // Containers patch their header when finishing (e.g. the IVF frame count)
+        if let Some(first) = self.manifest.segments.first_mut() {
+            first.sha256 = hash_range(&self.recording, first.offset, first.length)?;
+        }
+        self.hash_tail(end_ms)?;
+        self.manifest.complete = true;
+        self.write()?;
+        Ok(manifest_path(&self.recording))
+    }
+
+    /// Add a segment for the bytes not hashed yet; false if there were none
+    fn hash_tail(&mut self, end_ms: u64) -> Result<bool, String> {
+        self.last_segment = Instant::now();
+        let length = file_length(&self.recording)?.saturating_sub(self.hashed);
+        if length == 0 {
+            return Ok(false);
+        }
+        let sha256 = hash_range(&self.recording, self.hashed, length)?;
+        self.manifest.segments.push(Segment {
+            offset: self.hashed,
+            length,
+            sha256,
+            end_ms,
+        });
+        self.hashed += length;
+        Ok(true)
+    }
+
+    /// Sign and replace the manifest file atomically
+    fn write(&self) -> Result<(), String> {
+        let signed = self.signer.sign(self.manifest.clone())?;
+        let contents = serde_json::to_vec_pretty(&signed)
+            .map_err(|e| format!("Failed to encode manifest: {}", e))?;
+        let path = manifest_path(&self.recording);
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, contents)
+            .and_then(|_| std::fs::rename(&temporary, &path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Result of a successful verification
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// Hex public key the manifest was signed with; compare it with the sender's
+    pub public_key: String,
+    pub segments: usize,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    /// False if recording was cut short; the verified part is still authentic
+    pub complete: bool,
+}
+
+/// Check a recording against its manifest: signature, every segment's hash, and that the
+/// segments cover the whole file (nothing cut out, nothing appended to a complete recording)
+pub fn verify(recording: &Path) -> Result<Verification, String> {
+    let path = manifest_path(recording);
+    let contents = std::fs::read(&path)
+        .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+    let signed: SignedManifest = serde_json::from_slice(&contents)
+        .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+
+    let public_key: [u8; 32] = from_hex(&signed.public_key)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Manifest has an invalid public key")?;
+    let public_key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| format!("Manifest has an invalid public key: {}", e))?;
+    let signature: [u8; 64] = from_hex(&signed.signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Manifest has an invalid signature")?;
+    public_key
+        .verify(
+            &signed_bytes(&signed.manifest)?,
+            &Signature::from_bytes(&signature),
+        )
+        .map_err(|_| "Manifest signature doesn't match; the manifest was edited".to_string())?;
+
+    let manifest = &signed.manifest;
+    let mut offset = 0;
+    for (index, segment) in manifest.segments.iter().enumerate() {
+        if segment.offset != offset {
+            return Err(format!("Segment {} doesn't follow the previous one", index));
+        }
+        let actual = hash_range(recording, segment.offset, segment.length)?;
+        if actual != segment.sha256 {
+            return Err(format!(
+                "Segment {} (bytes {}-{}) was modified",
+                index,
+                segment.offset,
+                segment.offset + segment.length
+            ));
+        }
+        offset += segment.length;
+    }
+    let length = file_length(recording)?;
+    if length < offset {
+        return Err("Recording was truncated".to_string());
+    }
+    if manifest.complete && length != offset {
+        return Err(format!(
+            "Recording has {} bytes not covered by the manifest",
+            length - offset
+        ));
+    }
+
+    Ok(Verification {
+        public_key: signed.public_key.clone(),
+        segments: manifest.segments.len(),
+        bytes: offset,
+        duration_ms: manifest
+            .segments
+            .last()
+            .map(|segment| segment.end_ms)
+            .unwrap_or_default(),
+        complete: manifest.complete,
+    })
+}
+
+fn signed_bytes(manifest: &Manifest) -> Result<Vec<u8>, String> {
+    let json =
+        serde_json::to_vec(manifest).map_err(|e| format!("Failed to encode manifest: {}", e))?;
+    Ok([SIGNATURE_CONTEXT, &json].concat())
+}
+
+fn file_length(path: &Path) -> Result<u64, String> {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn hash_range(path: &Path, offset: u64, length: u64) -> Result<String, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let copied = std::io::copy(&mut file.take(length), &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if copied != length {
+        return Err(format!("{} is shorter than its manifest", path.display()));
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,404 @@
+use crate::blanking::BlankingController;
+use crate::compliance::{ComplianceEvent, ComplianceLog};
+use crate::cross_platform_capture::CrossPlatformScreenCapture;
+use crate::frame_analysis::{BlackFrameDetector, ContentChangeTracker, frame_hash};
+use crate::frame_bus::{ProcessedFrame, ProcessedFrameBus};
+use serde::{Deserialize, Serialize};
+
+/// How masked regions and blanked frames are filled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskFill {
+    /// Opaque black
+    #[default]
+    Black,
+    /// Pure green with zero alpha: alpha-aware consumers (raw pipe, socket) get true
+    /// transparency, everything else (window, encoders, y4m) shows a keyable green
+    ChromaKey,
+}
+
+impl MaskFill {
+    /// RGBA value painted into masked pixels
+    pub fn rgba(self) -> [u8; 4] {
+        match self {
+            MaskFill::Black => [0, 0, 0, 255],
+            MaskFill::ChromaKey => [0, 255, 0, 0],
+        }
+    }
+}
+
+/// A rectangular region of the captured frame (in capture pixels) that is always masked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RedactionRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Applies solid-box redaction to RGBA frames
+#[derive(Debug, Default, Clone)]
+pub struct Redactor {
+    rules: Vec<RedactionRect>,
+    fill: MaskFill,
+}
+
+impl Redactor {
+    pub fn new(rules: Vec<RedactionRect>) -> Self {
+        Self {
+            rules,
+            fill: MaskFill::default(),
+        }
+    }
+
+    /// Change how masked regions are painted
+    pub fn set_fill(&mut self, fill: MaskFill) {
+        self.fill = fill;
+    }
+
+    pub fn fill(&self) -> MaskFill {
+        self.fill
+    }
+
+    /// Replace the active redaction rules
+    pub fn set_rules(&mut self, rules: Vec<RedactionRect>) {
+        self.rules = rules;
+    }
+
+    /// Get the active redaction rules
+    pub fn rules(&self) -> &[RedactionRect] {
+        &self.rules
+    }
+
+    /// The rules clipped to a `width`x`height` frame, without those that hide nothing
+    pub fn masked_regions(&self, width: u32, height: u32) -> Vec<RedactionRect> {
+        self.rules
+            .iter()
+            .map(|rect| clip(rect, width, height))
+            .filter(|rect| rect.width > 0 && rect.height > 0)
+            .collect()
+    }
+
+    /// Paint every rule as a solid box (see `MaskFill`) into a tightly packed RGBA frame
+    pub fn apply(&self, rgba: &mut [u8], width: u32, height: u32) {
+        let fill = self.fill.rgba();
+        for rect in &self.rules {
+            // Clip the rule to the frame so stale rules never index out of bounds
+            let rect = clip(rect, width, height);
+            let (x0, y0) = (rect.x as usize, rect.y as usize);
+            let x1 = (rect.x + rect.width) as usize;
+            let y1 = (rect.y + rect.height) as usize;
+
+            for y in y0..y1 {
+                let row_start = (y * width as usize + x0) * 4;
+                let row_end = (y * width as usize + x1) * 4;
+                for pixel in rgba[row_start..row_end].chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&fill);
+                }
+            }
+        }
+    }
+}
+
+/// The part of `rect` inside a `width`x`height` frame (empty when it lies outside)
+fn clip(rect: &RedactionRect, width: u32, height: u32) -> RedactionRect {
+    let x = rect.x.min(width);
+    let y = rect.y.min(height);
+    RedactionRect {
+        x,
+        y,
+        width: rect.x.saturating_add(rect.width).min(width) - x,
+        height: rect.y.saturating_add(rect.height).min(height) - y,
+    }
+}
+
+/// RedactionStage: the only component allowed to read raw captured frames
+/// Pulls frames from the capture backend, masks them, and publishes the result
+/// to the ProcessedFrameBus that every output consumes from
+pub struct RedactionStage {
+    /// Raw frame source (never handed out to consumers)
+    screen_capture: CrossPlatformScreenCapture,
+    redactor: Redactor,
+    blanking: BlankingController,
+    bus: ProcessedFrameBus,
+    width: u32,
+    height: u32,
+    /// Whether the frame currently on the bus is a blank frame
+    blank_published: bool,
+    /// Last non-blank frame, restored when blanking ends on a static screen
+    last_visible: Option<ProcessedFrame>,
+    /// Rules generation `last_visible` was masked under
+    last_visible_rules_generation: u64,
+    /// Watches raw frames for a capture that only delivers black
+    black_frames: BlackFrameDetector,
+    /// Hashes published frames so outputs can tell when the screen goes static
+    content_changes: ContentChangeTracker,
+    /// Bumped whenever the rules may have changed, so identical pixels under new rules still count as new
+    rules_generation: u64,
+    /// Keep the last frame on the bus and leave new raw frames untouched
+    paused: bool,
+    /// Rules generation of the last published frame; damage is only valid under the same rules
+    published_rules_generation: u64,
+    /// What was shown and hidden this session, for compliance export
+    compliance: ComplianceLog,
+}
+
+impl RedactionStage {
+    pub fn new(screen_capture: CrossPlatformScreenCapture, width: u32, height: u32) -> Self {
+        Self {
+            screen_capture,
+            redactor: Redactor::default(),
+            blanking: BlankingController::new(),
+            bus: ProcessedFrameBus::new(),
+            width,
+            height,
+            blank_published: false,
+            last_visible: None,
+            last_visible_rules_generation: 0,
+            black_frames: BlackFrameDetector::new(),
+            content_changes: ContentChangeTracker::new(),
+            rules_generation: 0,
+            paused: false,
+            published_rules_generation: 0,
+            compliance: ComplianceLog::new(),
+        }
+    }
+
+    /// Take the newest raw frame (if any), redact it, and publish it to the bus
+    /// Returns true when a new processed frame was published
+    pub fn process(&mut self) -> bool {
+        if self.blanking.is_blanked() {
+            // Raw frames are consumed and discarded so nothing captured while blanked survives
+            self.screen_capture.set_frame_held(false);
+            drop(self.screen_capture.take_latest_frame());
+            if !self.blank_published {
+                let reasons = self
+                    .blanking
+                    .reasons()
+                    .map(|reason| format!("{:?}", reason))
+                    .collect();
+                self.compliance.record(ComplianceEvent::Blanked { reasons });
+                let blank = self
+                    .redactor
+                    .fill()
+                    .rgba()
+                    .repeat((self.width * self.height) as usize);
+                self.publish(
+                    ProcessedFrame::new(blank, self.width, self.height)
+                        .with_redactions(Vec::new(), true),
+                );
+                self.blank_published = true;
+                return true;
+            }
+            return false;
+        }
+
+        // A `BlockCapture` output hasn't taken the last frame: raw frames stay queued, so the
+        // capture thread waits for it rather than this one
+        let held = self.bus.holds_capture();
+        self.screen_capture.set_frame_held(held);
+        if held {
+            return false;
+        }
+
+        // While paused the newest raw frame stays in the capture buffer, so resuming
+        // shows the current screen even if nothing changes afterwards
+        let raw = if self.paused {
+            None
+        } else {
+            self.screen_capture.take_latest_frame()
+        };
+
+        let Some(raw) = raw else {
+            // Screen may be static after unblanking - restore what was visible before
+            if self.blank_published {
+                self.blank_published = false;
+                self.compliance.record(ComplianceEvent::Unblanked);
+                // Rules changed while blanked are painted over it first
+                if let Some(frame) = self.remasked_last_visible() {
+                    // The bus showed a blank frame, so everything changed
+                    let captured_at = frame.captured_at();
+                    self.publish(frame.with_capture_info(captured_at, None));
+                    return true;
+                }
+            }
+            // Rules changed on a static (or paused) screen: the frame on the bus was masked
+            // under the old ones
+            return self.reapply_rules();
+        };
+
+        if raw.width != self.width || raw.height != self.height {
+            // Drop frames we can't safely mask rather than letting them through unredacted
+            eprintln!(
+                "Dropping frame with unexpected size: {}x{}, expected {}x{}",
+                raw.width, raw.height, self.width, self.height
+            );
+            return false;
+        }
+        let captured_at = raw.timestamp;
+        let dirty_rects = raw.dirty_rects.clone();
+        let Some(mut raw) = raw.into_rgba() else {
+            eprintln!("Dropping frame whose pixels couldn't be read back from the GPU");
+            return false;
+        };
+        if raw.len() != (self.width * self.height * 4) as usize {
+            eprintln!("Dropping truncated frame: {} bytes", raw.len());
+            return false;
+        }
+
+        // Inspect the raw frame before redaction adds black boxes of its own
+        if let Some(black) = self.black_frames.observe(&raw) {
+            println!(
+                "Capture source {}",
+                if black {
+                    "is delivering black frames"
+                } else {
+                    "has content again"
+                }
+            );
+        }
+
+        self.redactor.apply(&mut raw, self.width, self.height);
+        self.compliance.record_redaction(
+            self.rules_generation,
+            self.redactor.rules(),
+            self.width,
+            self.height,
+        );
+        // After blanking, a restore or a rule change the previous frame on the bus differs
+        // outside the captured damage too
+        let dirty_rects = dirty_rects.filter(|_| {
+            !self.blank_published && self.published_rules_generation == self.rules_generation
+        });
+        let masked_regions = self.redactor.masked_regions(self.width, self.height);
+        let frame = ProcessedFrame::new(raw, self.width, self.height)
+            .with_capture_info(captured_at, dirty_rects)
+            .with_redactions(masked_regions, false);
+        self.last_visible = Some(frame.clone());
+        self.last_visible_rules_generation = self.rules_generation;
+        self.publish(frame);
+        if self.blank_published {
+            self.blank_published = false;
+            self.compliance.record(ComplianceEvent::Unblanked);
+        }
+        true
+    }
+
+    /// Republish the current frame masked under the current rules if it was published under
+    /// older ones (a profile switch or `redactor_mut` on a static screen); true if it was
+    /// Masks only add, so painting the new ones over the old frame hides nothing less
+    pub fn reapply_rules(&mut self) -> bool {
+        if self.blanking.is_blanked()
+            || self.blank_published
+            || self.published_rules_generation == self.rules_generation
+        {
+            return false;
+        }
+        match self.remasked_last_visible() {
+            Some(frame) => {
+                self.publish(frame);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `last_visible` with the current rules painted over it, if they changed since it was
+    /// masked
+    fn remasked_last_visible(&mut self) -> Option<ProcessedFrame> {
+        let frame = self.last_visible.as_ref()?;
+        if self.last_visible_rules_generation == self.rules_generation {
+            return Some(frame.clone());
+        }
+        let captured_at = frame.captured_at();
+        let mut data = frame.data().to_vec();
+        self.redactor.apply(&mut data, self.width, self.height);
+        self.compliance.record_redaction(
+            self.rules_generation,
+            self.redactor.rules(),
+            self.width,
+            self.height,
+        );
+        let masked_regions = self.redactor.masked_regions(self.width, self.height);
+        let frame = ProcessedFrame::new(data, self.width, self.height)
+            .with_capture_info(captured_at, None)
+            .with_redactions(masked_regions, false);
+        self.last_visible = Some(frame.clone());
+        self.last_visible_rules_generation = self.rules_generation;
+        Some(frame)
+    }
+
+    /// Publish a frame, hashed for static-content detection
+    fn publish(&mut self, frame: ProcessedFrame) {
+        self.published_rules_generation = self.rules_generation;
+        let hash = frame_hash(frame.data(), frame.width())
+            ^ self.rules_generation.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let frame = frame.with_content_hash(hash);
+        let changed = self.content_changes.observe(hash);
+
+        if changed {
+            self.bus.mark_content_changed();
+        }
+        self.bus.publish(frame);
+    }
+
+    /// Full-resolution bus that all outputs (recorder, streamer, ...) subscribe to
+    pub fn bus(&self) -> &ProcessedFrameBus {
+        &self.bus
+    }
+
+    /// Native size of the frames published on the full-resolution bus
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Whether the capture currently delivers only black frames
+    pub fn source_is_black(&self) -> bool {
+        self.black_frames.is_black()
+    }
+
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// Change the rules or fill; the current frame is masked again by `reapply_rules`, at the
+    /// latest on the next `process` without a new raw frame
+    pub fn redactor_mut(&mut self) -> &mut Redactor {
+        self.rules_generation += 1;
+        &mut self.redactor
+    }
+
+    /// Freeze the output on the last published frame (blanking still applies)
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn compliance(&self) -> &ComplianceLog {
+        &self.compliance
+    }
+
+    pub fn compliance_mut(&mut self) -> &mut ComplianceLog {
+        &mut self.compliance
+    }
+
+    pub fn blanking(&self) -> &BlankingController {
+        &self.blanking
+    }
+
+    pub fn blanking_mut(&mut self) -> &mut BlankingController {
+        &mut self.blanking
+    }
+
+    /// Access the capture backend for control operations (start/stop, resolution)
+    pub fn screen_capture(&self) -> &CrossPlatformScreenCapture {
+        &self.screen_capture
+    }
+
+    pub fn screen_capture_mut(&mut self) -> &mut CrossPlatformScreenCapture {
+        &mut self.screen_capture
+    }
+}
@@ -0,0 +1,298 @@
+use crate::gpu_renderer::{GpuRenderer, GpuSettings};
+use crate::platform::{CaptureSource, SourceThumbnail};
+use std::sync::Arc;
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window, WindowId},
+};
+
+/// Largest canvas edge; outputs of bigger displays are scaled down to this
+pub const MAX_CANVAS_SIZE: u32 = 2048;
+
+/// Selections smaller than this (in display points) are treated as stray clicks
+const MIN_REGION_SIZE: u32 = 16;
+
+/// How much of the screenshot's brightness shows outside the selection
+const DIM: u16 = 110; // of 255
+const BORDER_WIDTH: u32 = 2;
+
+/// RegionSelection: a dimmed, redacted image of one display with a rectangle dragged over it
+/// Handles drag state, composing the overlay into an RGBA canvas and mapping the rectangle
+/// back to display points
+pub struct RegionSelection {
+    display: u32,
+    /// Display size in points (the unit of `CaptureSource::Region`)
+    display_width: u32,
+    display_height: u32,
+    background: SourceThumbnail,
+    /// Color of the selection's outline (the theme's `text`)
+    border: [u8; 4],
+    /// Drag start and current corner in canvas pixels (None before the first press)
+    drag: Option<((f64, f64), (f64, f64))>,
+}
+
+impl RegionSelection {
+    /// `background` is the redacted output of `display` (points size `display_width` x
+    /// `display_height`) or a blank canvas, at any resolution with the same aspect ratio
+    pub fn new(
+        display: u32,
+        display_width: u32,
+        display_height: u32,
+        background: SourceThumbnail,
+        border: [u8; 4],
+    ) -> Self {
+        Self {
+            display,
+            display_width,
+            display_height,
+            background,
+            border,
+            drag: None,
+        }
+    }
+
+    pub fn display(&self) -> u32 {
+        self.display
+    }
+
+    /// Size of the composed canvas in pixels
+    pub fn canvas_size(&self) -> (u32, u32) {
+        (self.background.width, self.background.height)
+    }
+
+    pub fn start_drag(&mut self, x: f64, y: f64) {
+        self.drag = Some(((x, y), (x, y)));
+    }
+
+    /// Move the dragged corner; returns true if a drag is in progress (canvas needs composing)
+    pub fn update_drag(&mut self, x: f64, y: f64) -> bool {
+        match &mut self.drag {
+            Some((_, corner)) => {
+                *corner = (x, y);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The selection in canvas pixels: (x, y, width, height)
+    fn canvas_rect(&self) -> Option<(u32, u32, u32, u32)> {
+        let ((x0, y0), (x1, y1)) = self.drag?;
+        let (width, height) = self.canvas_size();
+        let clamp_x = |x: f64| x.clamp(0.0, width as f64) as u32;
+        let clamp_y = |y: f64| y.clamp(0.0, height as f64) as u32;
+        let (left, right) = (clamp_x(x0.min(x1)), clamp_x(x0.max(x1)));
+        let (top, bottom) = (clamp_y(y0.min(y1)), clamp_y(y0.max(y1)));
+        Some((left, top, right - left, bottom - top))
+    }
+
+    /// The selection as a capture source, or None if nothing (or only a click) was selected
+    pub fn region(&self) -> Option<CaptureSource> {
+        let (x, y, width, height) = self.canvas_rect()?;
+        let (canvas_width, canvas_height) = self.canvas_size();
+        let to_points_x = |value: u32| value * self.display_width / canvas_width.max(1);
+        let to_points_y = |value: u32| value * self.display_height / canvas_height.max(1);
+        let region = CaptureSource::Region {
+            display: self.display,
+            x: to_points_x(x),
+            y: to_points_y(y),
+            width: to_points_x(width),
+            height: to_points_y(height),
+        };
+        match region {
+            CaptureSource::Region { width, height, .. }
+                if width >= MIN_REGION_SIZE && height >= MIN_REGION_SIZE =>
+            {
+                Some(region)
+            }
+            _ => None,
+        }
+    }
+
+    /// Render the background, dimmed outside the selection, with a border around it
+    pub fn compose(&self) -> Vec<u8> {
+        let (width, _) = self.canvas_size();
+        let mut canvas = self.background.rgba.clone();
+        let selection = self.canvas_rect();
+        let inside = |x: u32, y: u32| {
+            selection
+                .is_some_and(|(sx, sy, sw, sh)| x >= sx && x < sx + sw && y >= sy && y < sy + sh)
+        };
+
+        for (index, pixel) in canvas.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (index as u32 % width, index as u32 / width);
+            if !inside(x, y) {
+                for channel in &mut pixel[..3] {
+                    *channel = (*channel as u16 * DIM / 255) as u8;
+                }
+            }
+        }
+
+        if let Some((x, y, selection_width, selection_height)) = selection {
+            let mut fill = |x0: u32, y0: u32, w: u32, h: u32| {
+                for row in y0..(y0 + h).min(self.background.height) {
+                    for column in x0..(x0 + w).min(width) {
+                        let start = ((row * width + column) * 4) as usize;
+                        canvas[start..start + 4].copy_from_slice(&self.border);
+                    }
+                }
+            };
+            let (x0, y0) = (
+                x.saturating_sub(BORDER_WIDTH),
+                y.saturating_sub(BORDER_WIDTH),
+            );
+            let outer_width = selection_width + BORDER_WIDTH * 2;
+            let outer_height = selection_height + BORDER_WIDTH * 2;
+            fill(x0, y0, outer_width, BORDER_WIDTH);
+            fill(x0, y + selection_height, outer_width, BORDER_WIDTH);
+            fill(x0, y0, BORDER_WIDTH, outer_height);
+            fill(x + selection_width, y0, BORDER_WIDTH, outer_height);
+        }
+
+        canvas
+    }
+}
+
+/// What the region selection window wants the app to do after an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionAction {
+    None,
+    Select(CaptureSource),
+    Close,
+}
+
+/// RegionSelectWindow: borderless fullscreen overlay on the display being selected from
+/// A separate window and never part of the mirror; the output stays blanked while it is open
+/// (`SafeMirror::set_region_selection_open`) since the capture may not exclude it
+pub struct RegionSelectWindow {
+    window: Arc<Window>,
+    renderer: GpuRenderer,
+    selection: RegionSelection,
+    needs_compose: bool,
+    /// Last cursor position in canvas pixels, where the next drag starts
+    cursor: (f64, f64),
+}
+
+impl RegionSelectWindow {
+    /// Cover the selection's display with the overlay
+    pub fn open(event_loop: &ActiveEventLoop, selection: RegionSelection) -> Result<Self, String> {
+        let (width, height) = selection.canvas_size();
+        let monitor = monitor_for_display(event_loop, selection.display());
+
+        // Title contains "CloakShare" so the capture filter excludes this window too
+        let window = Arc::new(
+            event_loop
+                .create_window(
+                    Window::default_attributes()
+                        .with_title("CloakShare - Select Region")
+                        .with_decorations(false)
+                        .with_fullscreen(Some(Fullscreen::Borderless(monitor))),
+                )
+                .map_err(|e| format!("Failed to create region selection window: {}", e))?,
+        );
+        window.set_cursor(winit::window::CursorIcon::Crosshair);
+
+        let renderer = pollster::block_on(GpuRenderer::new(
+            window.clone(),
+            width,
+            height,
+            &GpuSettings::default(),
+        ))
+        .map_err(|e| format!("Failed to start region selection renderer: {}", e))?;
+
+        Ok(Self {
+            window,
+            renderer,
+            selection,
+            needs_compose: true,
+            cursor: (0.0, 0.0),
+        })
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// Handle an event for the overlay: drag to select, release to capture, Escape to cancel
+    pub fn handle_event(&mut self, event: &WindowEvent) -> RegionAction {
+        match event {
+            WindowEvent::CloseRequested => return RegionAction::Close,
+
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state == ElementState::Pressed
+                    && event.logical_key == Key::Named(NamedKey::Escape) =>
+            {
+                return RegionAction::Close;
+            }
+
+            WindowEvent::Resized(size) => self.renderer.resize(*size),
+
+            WindowEvent::CursorMoved { position, .. } => {
+                let (x, y) = self.to_canvas(position.x, position.y);
+                if self.selection.update_drag(x, y) {
+                    self.needs_compose = true;
+                    self.window.request_redraw();
+                }
+                self.cursor = (x, y);
+            }
+
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match *state {
+                ElementState::Pressed => {
+                    let (x, y) = self.cursor;
+                    self.selection.start_drag(x, y);
+                    self.needs_compose = true;
+                    self.window.request_redraw();
+                }
+                ElementState::Released => {
+                    if let Some(region) = self.selection.region() {
+                        return RegionAction::Select(region);
+                    }
+                }
+            },
+
+            WindowEvent::RedrawRequested => {
+                if self.needs_compose {
+                    self.renderer.update_texture(&self.selection.compose());
+                    self.needs_compose = false;
+                }
+                if let Err(e) = self.renderer.render() {
+                    eprintln!("Region selection render error: {e:?}");
+                }
+            }
+
+            _ => {}
+        }
+        RegionAction::None
+    }
+
+    /// Map window pixel coordinates to canvas coordinates (the canvas is stretched to the window)
+    fn to_canvas(&self, x: f64, y: f64) -> (f64, f64) {
+        let (canvas_width, canvas_height) = self.selection.canvas_size();
+        let size = self.renderer.size();
+        (
+            x * canvas_width as f64 / size.width.max(1) as f64,
+            y * canvas_height as f64 / size.height.max(1) as f64,
+        )
+    }
+}
+
+/// The winit monitor showing `display` (None lets the overlay open on the current monitor)
+#[cfg(target_os = "macos")]
+fn monitor_for_display(event_loop: &ActiveEventLoop, display: u32) -> Option<MonitorHandle> {
+    use winit::platform::macos::MonitorHandleExtMacOS;
+    event_loop
+        .available_monitors()
+        .find(|monitor| monitor.native_id() == display)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn monitor_for_display(_event_loop: &ActiveEventLoop, _display: u32) -> Option<MonitorHandle> {
+    None
+}
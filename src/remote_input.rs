@@ -0,0 +1,263 @@
+use crate::l10n::{tr, tr_args};
+use crate::platform::WindowFrame;
+#[cfg(target_os = "macos")]
+use crate::platform::macos_input::{Injector, input_injection_permitted};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, mpsc};
+
+/// Control stream lines carrying a `RemoteInputEvent` start with this, followed by JSON
+pub const INPUT_PREFIX: &str = "input ";
+
+/// Longest text a single event may type, so a receiver can't flood the sender's keyboard
+const MAX_TEXT_CHARS: usize = 256;
+
+/// Scroll deltas are sent in lines; pixel deltas (trackpads) are converted at this rate
+pub const PIXELS_PER_LINE: f32 = 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Keys that don't type text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpecialKey {
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Escape,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+}
+
+/// Mouse or keyboard input a receiver sends back to the sender
+/// Positions are fractions of the shared picture (0.0-1.0), so they don't depend on either
+/// side's resolution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteInputEvent {
+    Move {
+        x: f32,
+        y: f32,
+    },
+    Button {
+        button: PointerButton,
+        pressed: bool,
+    },
+    /// Lines to scroll; positive is up and left, like macOS scroll wheels
+    Scroll {
+        x: f32,
+        y: f32,
+    },
+    Key {
+        key: SpecialKey,
+        pressed: bool,
+    },
+    /// Typed characters, injected as text so the two keyboard layouts don't have to match
+    Text {
+        text: String,
+    },
+}
+
+impl RemoteInputEvent {
+    /// Control stream line for this event, including the newline
+    pub fn to_line(&self) -> String {
+        // Serializing plain enums and strings can't fail
+        format!(
+            "{}{}\n",
+            INPUT_PREFIX,
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+
+    /// Parse a control stream line; None for other messages and malformed events
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let event: Self = serde_json::from_str(line.trim_end().strip_prefix(INPUT_PREFIX)?).ok()?;
+        match &event {
+            Self::Move { x, y } if !x.is_finite() || !y.is_finite() => None,
+            Self::Scroll { x, y } if !x.is_finite() || !y.is_finite() => None,
+            Self::Text { text }
+                if text.chars().count() > MAX_TEXT_CHARS || text.chars().any(char::is_control) =>
+            {
+                None
+            }
+            _ => Some(event),
+        }
+    }
+}
+
+/// A connection from a receiver that proved it knows the stream key
+/// Consent belongs to the session, so a reconnect, or anyone else at the same address, is
+/// asked again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSession {
+    /// Unique for each connection while CloakShare runs
+    pub id: u64,
+    /// Receiver address, shown in the consent prompt
+    pub receiver: String,
+}
+
+impl RemoteSession {
+    /// A new session for a connection to `receiver` that passed the stream key handshake
+    pub fn authenticated(receiver: &str) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            receiver: receiver.to_string(),
+        }
+    }
+}
+
+/// Where outputs deliver remote input, from any thread
+#[derive(Clone)]
+pub struct RemoteInputSender {
+    events: mpsc::Sender<(RemoteSession, RemoteInputEvent)>,
+    /// Wakes the mirror, which may be idle while the screen is static
+    wake: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl RemoteInputSender {
+    /// Deliver an event from `session`; false once the mirror is gone
+    pub fn send(&self, session: &RemoteSession, event: RemoteInputEvent) -> bool {
+        let sent = self.events.send((session.clone(), event)).is_ok();
+        if sent {
+            (self.wake)();
+        }
+        sent
+    }
+}
+
+/// Input from receivers, waiting for the user's consent and then injected on the sender
+/// Each session is asked once; nothing it sends before the answer is replayed afterwards
+pub struct RemoteInput {
+    sender: RemoteInputSender,
+    events: mpsc::Receiver<(RemoteSession, RemoteInputEvent)>,
+    /// Each session's answer by ID, None while the prompt is still open
+    consent: HashMap<u64, Option<bool>>,
+    injector: Injector,
+}
+
+impl RemoteInput {
+    /// `wake` is called whenever an event arrives, so it gets processed promptly
+    pub fn new(wake: impl Fn() + Send + Sync + 'static) -> Self {
+        let (events_tx, events) = mpsc::channel();
+        Self {
+            sender: RemoteInputSender {
+                events: events_tx,
+                wake: Arc::new(wake),
+            },
+            events,
+            consent: HashMap::new(),
+            injector: Injector::default(),
+        }
+    }
+
+    pub fn sender(&self) -> RemoteInputSender {
+        self.sender.clone()
+    }
+
+    /// Inject pending events from allowed receivers into `area` (the desktop rectangle the
+    /// shared picture shows, resolved only when needed); returns sessions to ask for consent
+    pub fn process(&mut self, area: impl FnOnce() -> Option<WindowFrame>) -> Vec<RemoteSession> {
+        let mut ask = Vec::new();
+        let mut allowed = Vec::new();
+        while let Ok((session, event)) = self.events.try_recv() {
+            match self.consent.get(&session.id) {
+                Some(Some(true)) => allowed.push(event),
+                Some(_) => {}
+                None => {
+                    self.consent.insert(session.id, None);
+                    ask.push(session);
+                }
+            }
+        }
+        if allowed.is_empty() {
+            return ask;
+        }
+
+        let Some(area) = area() else {
+            // Several displays in one picture: there's no single place a click could go
+            return ask;
+        };
+        for event in &allowed {
+            if let Err(e) = self.injector.inject(event, &area) {
+                eprintln!("Failed to inject remote input: {}", e);
+                break;
+            }
+        }
+        ask
+    }
+
+    /// Record the answer to the consent prompt for `session`
+    pub fn set_allowed(&mut self, session: &RemoteSession, allowed: bool) {
+        self.consent.insert(session.id, Some(allowed));
+        if allowed {
+            println!("Remote input from {} allowed", session.receiver);
+            if !input_injection_permitted() {
+                eprintln!("{}", tr("permission-accessibility"));
+            }
+        } else {
+            println!("Remote input from {} denied", session.receiver);
+        }
+    }
+}
+
+/// Ask the user whether `receiver` may control the mouse and keyboard
+/// "Deny" is the default button, so pressing Enter by reflex doesn't hand over control
+pub fn ask_consent(receiver: &str) -> bool {
+    let message = tr_args("remote-input-message", &[("receiver", receiver)]);
+    show_dialog(&message, &[&tr("button-deny"), &tr("button-allow")]) == Some(1)
+}
+
+#[cfg(target_os = "macos")]
+fn show_dialog(message: &str, buttons: &[&str]) -> Option<usize> {
+    crate::platform::macos_alert::show_alert(&tr("remote-input-title"), message, buttons)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_dialog(message: &str, _buttons: &[&str]) -> Option<usize> {
+    // No native dialog on this platform yet, and no consent means no control
+    eprintln!("{}: {} (denied)", tr("remote-input-title"), message);
+    None
+}
+
+/// Desktop rectangle of `display` (the main display for None), in global points
+#[cfg(target_os = "macos")]
+pub fn display_area(display: Option<u32>) -> Option<WindowFrame> {
+    crate::platform::macos_input::display_bounds(display)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn display_area(_display: Option<u32>) -> Option<WindowFrame> {
+    None
+}
+
+#[cfg(not(target_os = "macos"))]
+fn input_injection_permitted() -> bool {
+    false
+}
+
+/// Input injection not implemented on this platform yet
+#[cfg(not(target_os = "macos"))]
+#[derive(Default)]
+struct Injector {}
+
+#[cfg(not(target_os = "macos"))]
+impl Injector {
+    fn inject(&mut self, _event: &RemoteInputEvent, _area: &WindowFrame) -> Result<(), String> {
+        Err("Remote input isn't supported on this platform yet".to_string())
+    }
+}
@@ -1,65 +1,1889 @@
-use crate::{cross_platform_capture::CrossPlatformScreenCapture, gpu_renderer::GpuRenderer};
+#[cfg(any(feature = "whip", feature = "quic"))]
+use crate::encoding::{EncoderSettings, create_encoder};
+use crate::{
+    blanking::BlankReason,
+    compliance::ComplianceEvent,
+    config::{Config, Profile},
+    crash_report,
+    cross_platform_capture::CrossPlatformScreenCapture,
+    discovery::DiscoveredReceiver,
+    effects::{Effect, load_effects},
+    encoding::VideoCodec,
+    follow_cursor::FollowCursor,
+    frame_analysis::{ChangeDetector, ContentChangeTracker, frame_hash},
+    frame_bus::{FramePolicy, FrameSubscriber, ProcessedFrame, ProcessedFrameBus},
+    frame_channel::FrameChannelStats,
+    frame_pacing::{DEFAULT_FRAME_RATE, capture_frame_rate, window_refresh_rate},
+    gpu_memory::{GpuMemoryUsage, MEGABYTE},
+    gpu_renderer::{GpuRenderer, GpuSettings, RendererError},
+    idle::IdleWatcher,
+    l10n::{self, tr},
+    locked_memory::{self, FrameMemoryStatus},
+    mirror_error::{ErrorCard, MirrorError},
+    platform::{
+        CaptureCapabilities, CaptureEvent, CaptureScale, CaptureSource, DisplayArrangement,
+        SourceInfo, SourceThumbnail, WindowInfo,
+    },
+    platform_detector::{PermissionStatus, PlatformDetector},
+    policy::Policy,
+    power::{BATTERY_FRAME_RATE, PowerWatcher},
+    private_spaces::PrivateSpaceWatcher,
+    redaction::MaskFill,
+    redaction::RedactionRect,
+    redaction::{RedactionStage, Redactor},
+    region_select::{MAX_CANVAS_SIZE, RegionSelection},
+    scaling::downscale_rgba,
+    scene::{LayerSource, Scene, Transform},
+    screenshot::{ScreenshotOptions, default_screenshot_path, save_screenshot},
+    sinks::{
+        FrameSink, SinkId, SinkManager,
+        recording::{RecordingControl, RecordingSink},
+        socket::SocketSink,
+    },
+    source_picker::{PickerEntry, SourcePicker, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH},
+    stats_hud::StatsHud,
+    theme::Theme,
+    webcam::Webcam,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use winit::window::Window;
 
+/// Minimum time between two significant-change events, so scrolling doesn't flood observers
+const CHANGE_EVENT_COOLDOWN: Duration = Duration::from_secs(1);
+
+/// How often the pipeline state kept for crash reports is refreshed
+const CRASH_CONTEXT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// State changes surfaced to the app (UI state, notifications, ...)
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirrorEvent {
+    /// The capture only delivers black frames (permissions, DRM content, sleeping display)
+    SourceBlack,
+    /// The capture delivers real content again
+    SourceRestored,
+    /// A large part of the screen changed at once (e.g. a demo or slide deck started)
+    SignificantChange {
+        /// Fraction of the screen that changed (0.0-1.0)
+        changed_fraction: f32,
+    },
+    /// `lock_frame_memory` is on but frame buffers couldn't be locked (see `locked_memory::status`)
+    FrameMemoryUnlocked,
+    /// The capture switched to another display, window or region
+    SourceChanged {
+        /// Name the platform shows for the source ("Display 2", "Safari - Docs", ...)
+        name: Option<String>,
+    },
+    /// The output was paused or resumed
+    PausedChanged { paused: bool },
+    /// The output was blanked or shown again (see `BlankReason`)
+    BlankingChanged { blanked: bool },
+    /// Low-power mode started (unplugged) or ended (plugged in)
+    LowPowerChanged { low_power: bool },
+    /// The capture stream stopped on its own; the output keeps its last frame
+    CaptureStopped,
+    /// Something keeps the mirror from showing the screen; its card stays in the window until
+    /// `retry` gets past it
+    Error(MirrorError),
+    /// A receiver wants to control the mouse and keyboard; answer with `set_remote_input_allowed`
+    #[cfg(feature = "quic")]
+    RemoteInputRequested {
+        session: crate::remote_input::RemoteSession,
+    },
+    /// The release feed has a version newer than this build (`check_for_updates`)
+    #[cfg(feature = "update-check")]
+    UpdateAvailable(crate::update_check::Release),
+}
+
 /// SafeMirror: The core structure that handles GPU rendering and screen capture
 /// Coordinates between screen capture and GPU rendering components
 pub struct SafeMirror {
     /// GPU renderer handles all wgpu operations
     gpu_renderer: GpuRenderer,
 
-    /// Cross-platform screen capture manager
-    screen_capture: CrossPlatformScreenCapture,
+    /// Redaction stage - owns the screen capture and is the only reader of raw frames
+    redaction_stage: RedactionStage,
+
+    /// Renderer's subscription to the processed (redacted) frame bus
+    render_subscriber: FrameSubscriber,
+
+    /// Additional outputs (recorder, virtual camera, network stream, ...) running alongside the mirror
+    sinks: SinkManager,
+
+    /// Frames exactly as shown in the mirror window, for outputs that must match it
+    composite_bus: ProcessedFrameBus,
+
+    /// Sinks fed from `composite_bus` (the mirror reads back its output only while any run)
+    composite_sinks: HashSet<SinkId>,
+
+    /// Hashes composited frames so composite sinks also learn when the output goes static
+    composite_changes: ContentChangeTracker,
+
+    /// Blanks the output while the captured display shows a private Space
+    private_spaces: PrivateSpaceWatcher,
+
+    /// Notices nobody using the machine (`idle_pause_minutes`); None when that's off
+    idle: Option<IdleWatcher>,
+    /// Recordings paused for being idle, resumed at the next input; None while not idle
+    idle_paused: Option<Vec<SinkId>>,
+    /// The idle pause stopped a running capture, so the next input restarts it
+    idle_stopped_capture: bool,
+
+    /// Switches capture to the display under the cursor when enabled
+    follow_cursor: FollowCursor,
+
+    /// Mirror window (excluded from capture when restarting the stream)
+    window: Arc<Window>,
+
+    /// Last black-source state reported through `events`
+    source_black: bool,
+    /// Name of the captured source, for the window title
+    source_name: Option<String>,
+    /// The capture stream stopped on its own and no source has been selected since
+    capture_stopped: bool,
+    /// What keeps the mirror from showing the screen, with the card drawn for it (None if the
+    /// text couldn't be rendered)
+    error: Option<(MirrorError, Option<ErrorCard>)>,
+
+    /// Events raised since the app last drained them
+    events: Vec<MirrorEvent>,
+
+    /// Encoding used by `save_screenshot`
+    screenshot_options: ScreenshotOptions,
+
+    /// Watches processed frames for significant screen changes
+    change_subscriber: FrameSubscriber,
+    change_detector: ChangeDetector,
+    last_change_event: Option<Instant>,
+
+    /// Content hash of the frame currently in the GPU texture
+    uploaded_hash: Option<u64>,
+
+    /// Force the next update_and_render to draw (first frame, resize, lost surface)
+    needs_render: bool,
+
+    /// Whether frames are captured at backing pixels or points
+    capture_scale: CaptureScale,
+
+    /// Follow displays faster than 60 Hz (`high_refresh`), the rate that asks for, and the
+    /// rate captured at now (lower when the scene caps its capture layers' fps)
+    high_refresh: bool,
+    display_frame_rate: u32,
+    frame_rate: u32,
+    /// Rate set in the config, kept regardless of the display
+    fixed_frame_rate: Option<u32>,
+
+    /// Adapter choice, reused when the renderer is rebuilt after a device loss
+    gpu_settings: GpuSettings,
+
+    /// Watches for running on battery (`low_power_on_battery`); None when that's off
+    power: Option<PowerWatcher>,
+    /// Lower capture rate, the integrated GPU and no sharpening, effects or float pipeline
+    low_power: bool,
+
+    /// Presets from the config, by name
+    profiles: BTreeMap<String, Profile>,
+    active_profile: Option<String>,
+    /// Outputs started by the active profile (detached when switching)
+    profile_sinks: Vec<SinkId>,
+    /// Mask fill for profiles that don't set their own
+    default_mask_fill: MaskFill,
+    /// Rules masked under every profile (config `redactions` plus policy-required ones)
+    base_redactions: Vec<RedactionRect>,
+    /// Organization policy; decides which sources may be selected
+    policy: Policy,
+    /// Whether `start_recording` may write files (`allow_recording`)
+    recording_allowed: bool,
+    /// Receiver currently streamed to with `next_receiver`, and its sink
+    receiver_stream: Option<(String, SinkId)>,
+    /// Whether recordings skip, queue or hold up frames the encoder can't keep up with
+    recording_frame_policy: FramePolicy,
+    /// Pause and resume handles of the running recordings
+    recordings: HashMap<SinkId, RecordingControl>,
+
+    /// Key recording manifests are signed with; None unless `sign_recordings` is on
+    recording_signing_key: Option<PathBuf>,
+    /// Recordings get a redaction timeline sidecar (`recording_redaction_log`)
+    recording_redaction_log: bool,
+    /// Whether `MirrorEvent::FrameMemoryUnlocked` was already sent
+    frame_memory_unlocked: bool,
+    /// No-disk mode: screenshots are refused and the sink manager refuses disk-writing sinks
+    no_disk: bool,
+    /// Key proving WHIP and QUIC streams go to a CloakShare receiver that shares it
+    #[cfg(any(feature = "whip", feature = "quic"))]
+    stream_key: Option<String>,
+    /// `require_encrypted_streams`: WHIP needs https:// or a stream key, QUIC a stream key
+    #[cfg(any(feature = "whip", feature = "quic"))]
+    require_encrypted_streams: bool,
+    /// Input from QUIC receivers waiting for consent or injection; None unless
+    /// `allow_remote_input` is on
+    #[cfg(feature = "quic")]
+    remote_input: Option<crate::remote_input::RemoteInput>,
+    /// WHIP streams adapt to network conditions (`adaptive_bitrate`)
+    #[cfg(feature = "whip")]
+    adaptive_bitrate: bool,
+    /// Looks for newer releases; None with `check_for_updates` off
+    #[cfg(feature = "update-check")]
+    updates: Option<crate::update_check::UpdateChecker>,
+
+    /// Layers composited into the output
+    scene: Scene,
+
+    /// Whole-number capture scaling (see GpuRenderer::set_integer_scaling)
+    integer_scaling: bool,
+    /// Aspect-preserving fit with black bars (see GpuRenderer::set_letterbox)
+    letterbox: bool,
+
+    /// Capture sharpening on/off and its strength when on
+    sharpen: bool,
+    sharpen_strength: f32,
+    /// `low_latency`: sharpening stays off
+    low_latency: bool,
+
+    /// Debug HUD with every output's stats (Cmd+Shift+I); None while hidden
+    stats_hud: Option<StatsHud>,
+    /// Colors, font and corners of the HUD, error card, picker and region selection
+    theme: Theme,
+
+    /// Post-processing effects, kept to recompile them when the renderer is recreated
+    effects: Vec<Effect>,
+
+    /// Camera for webcam layers; only running while a visible layer shows it
+    webcam: Option<Webcam>,
+    webcam_device: Option<String>,
+
+    /// Source selected through `select_source` (None = primary display)
+    current_source: Option<CaptureSource>,
+    /// Source to return to when the overview grid is closed without picking a display
+    /// (Some while the overview is shown)
+    source_before_overview: Option<CaptureSource>,
+
+    /// When the crash report's pipeline section was last refreshed
+    crash_context_at: Option<Instant>,
 }
 
 impl SafeMirror {
     /// Creates a new SafeMirror instance with full GPU setup
-    /// This initializes the entire rendering pipeline from scratch
-    pub async fn new(window: Arc<Window>, mut screen_capture: CrossPlatformScreenCapture) -> Self {
+    /// This initializes the entire rendering pipeline from scratch; capture only starts once
+    /// the renderer is up
+    pub async fn new(
+        window: Arc<Window>,
+        mut screen_capture: CrossPlatformScreenCapture,
+        config: &Config,
+    ) -> Result<Self, RendererError> {
+        crash_report::set_config(config);
+        l10n::init(config);
+        // A config that didn't come from `Config::load` still gets what low_latency implies
+        let mut config = config.clone();
+        if config.low_latency {
+            config.prefer_latency();
+        }
+        let config = &config;
+
         // Get the actual display resolution from the provided screen capture
         let resolution = screen_capture.get_display_resolution().unwrap_or_else(|e| {
             eprintln!("Failed to get display resolution: {}, using fallback", e);
             crate::platform::DisplayResolution {
                 width: 1920,
                 height: 1080,
+                scale_factor: 1.0,
             }
         });
 
+        // Native backing pixels by default; points trade Retina detail for bandwidth
+        let (capture_width, capture_height) = resolution.capture_size(config.capture_scale);
         println!(
-            "Display resolution: {}x{}",
-            resolution.width, resolution.height
+            "Display resolution: {}x{} points, scale factor {}, capturing {}x{} ({:?})",
+            resolution.width,
+            resolution.height,
+            resolution.scale_factor,
+            capture_width,
+            capture_height,
+            config.capture_scale
         );
 
-        let gpu_renderer =
-            GpuRenderer::new(window.clone(), resolution.width, resolution.height).await;
+        screen_capture.set_capture_scale(config.capture_scale);
+        screen_capture.set_frame_policy(config.capture_frame_policy);
+
+        // Match a ProMotion display's rate, and present so high rate frames don't queue up
+        let display_frame_rate = config.frame_rate.map(|fps| fps.max(1)).unwrap_or_else(|| {
+            capture_frame_rate(config.high_refresh, window_refresh_rate(&window))
+        });
+        let scene = config.scene();
+        // On battery, trade frame rate, the discrete GPU and expensive filters for battery life
+        let power = config.low_power_on_battery.then(PowerWatcher::new);
+        let low_power = power.as_ref().is_some_and(PowerWatcher::on_battery);
+        if low_power {
+            println!("On battery: low-power mode");
+        }
+        let frame_rate = Self::capped_frame_rate(display_frame_rate, low_power);
+        screen_capture.set_frame_rate(frame_rate);
+        if frame_rate != DEFAULT_FRAME_RATE {
+            println!("Capturing at {} fps", frame_rate);
+        }
+
+        let mut redaction_stage =
+            RedactionStage::new(screen_capture, capture_width, capture_height);
+        redaction_stage.redactor_mut().set_fill(config.mask_fill);
+        redaction_stage
+            .redactor_mut()
+            .set_rules(config.redactions.clone());
+
+        // The on-screen preview may run at reduced resolution (downscaled by the renderer);
+        // sinks still get native frames
+        if config.preview_scale > 1 {
+            println!(
+                "Preview resolution: {}x{}",
+                (capture_width / config.preview_scale).max(1),
+                (capture_height / config.preview_scale).max(1)
+            );
+        }
 
-        if let Err(e) = screen_capture.start_capture(Some(&window)) {
+        let gpu_settings = GpuSettings {
+            mailbox: display_frame_rate > DEFAULT_FRAME_RATE,
+            ..config.gpu_settings()
+        };
+        let mut gpu_renderer = GpuRenderer::new(
+            window.clone(),
+            capture_width,
+            capture_height,
+            &Self::power_gpu_settings(&gpu_settings, low_power),
+        )
+        .await?;
+        gpu_renderer.set_scene(&scene);
+        gpu_renderer.set_integer_scaling(config.integer_scaling);
+        gpu_renderer.set_letterbox(config.letterbox);
+        if config.sharpen && !low_power {
+            gpu_renderer.set_sharpen(config.sharpen_strength);
+        }
+        // Effects cost a pass each; low-latency mode skips them
+        let effects = if config.low_latency {
+            Vec::new()
+        } else {
+            load_effects(&config.effects_dir_path())
+        };
+        // Kept while in low-power mode, to come back when plugged in
+        if !low_power {
+            gpu_renderer.set_effects(&effects);
+        }
+
+        let capture_error = config
+            .policy
+            .check_default_display(redaction_stage.screen_capture())
+            .and_then(|()| {
+                redaction_stage
+                    .screen_capture_mut()
+                    .start_capture(Some(&window))
+            })
+            .err();
+        if let Some(e) = &capture_error {
             eprintln!("Failed to start screen capture: {}", e);
         }
 
-        Self {
+        let render_subscriber = redaction_stage.bus().subscribe();
+        let change_subscriber = redaction_stage.bus().subscribe();
+        let mut sinks = SinkManager::new(redaction_stage.bus().clone());
+        sinks.set_static_after(Duration::from_secs(config.static_after_secs));
+        sinks.set_no_disk(config.no_disk);
+        if config.no_disk {
+            println!("No-disk mode: outputs that write frames to files are disabled");
+        }
+
+        // Remote input wakes the mirror, which may be idle while the screen is static
+        #[cfg(feature = "quic")]
+        let remote_input = config.allow_remote_input.then(|| {
+            let window = window.clone();
+            crate::remote_input::RemoteInput::new(move || window.request_redraw())
+        });
+
+        // A static desktop delivers no frames, so the watcher wakes the mirror itself
+        let idle = config.idle_pause_after().map(|after| {
+            let window = window.clone();
+            IdleWatcher::new(after, move || window.request_redraw())
+        });
+
+        // A found release only needs a redraw to reach the title
+        #[cfg(feature = "update-check")]
+        let updates = config
+            .check_for_updates
+            .then(|| {
+                let window = window.clone();
+                crate::update_check::UpdateChecker::start(
+                    config.update_feed_url.clone(),
+                    move || window.request_redraw(),
+                )
+            })
+            .and_then(|started| {
+                started
+                    .inspect_err(|e| eprintln!("Not checking for updates: {}", e))
+                    .ok()
+            });
+
+        let mut mirror = Self {
             gpu_renderer,
-            screen_capture,
+            redaction_stage,
+            render_subscriber,
+            sinks,
+            composite_bus: ProcessedFrameBus::new(),
+            composite_sinks: HashSet::new(),
+            composite_changes: ContentChangeTracker::new(),
+            private_spaces: PrivateSpaceWatcher::new(config.private_spaces.iter().copied()),
+            idle,
+            idle_paused: None,
+            idle_stopped_capture: false,
+            follow_cursor: FollowCursor::new(
+                config.follow_cursor,
+                Duration::from_millis(config.follow_cursor_debounce_ms),
+            ),
+            window,
+            source_black: false,
+            source_name: None,
+            capture_stopped: false,
+            error: None,
+            events: Vec::new(),
+            screenshot_options: config.screenshot_options(),
+            change_subscriber,
+            change_detector: ChangeDetector::new(config.change_threshold),
+            last_change_event: None,
+            uploaded_hash: None,
+            needs_render: true,
+            capture_scale: config.capture_scale,
+            high_refresh: config.high_refresh,
+            display_frame_rate,
+            frame_rate,
+            fixed_frame_rate: config.frame_rate,
+            gpu_settings,
+            power,
+            low_power,
+            profiles: config.profiles.clone(),
+            active_profile: None,
+            profile_sinks: Vec::new(),
+            default_mask_fill: config.mask_fill,
+            base_redactions: config.redactions.clone(),
+            policy: config.policy.clone(),
+            recording_allowed: config.allow_recording,
+            receiver_stream: None,
+            recording_frame_policy: config.recording_frame_policy,
+            recordings: HashMap::new(),
+            recording_signing_key: config
+                .sign_recordings
+                .then(|| config.recording_signing_key_path()),
+            recording_redaction_log: config.recording_redaction_log,
+            frame_memory_unlocked: false,
+            no_disk: config.no_disk,
+            #[cfg(any(feature = "whip", feature = "quic"))]
+            stream_key: config.stream_key.clone(),
+            #[cfg(any(feature = "whip", feature = "quic"))]
+            require_encrypted_streams: config.require_encrypted_streams,
+            #[cfg(feature = "quic")]
+            remote_input,
+            #[cfg(feature = "whip")]
+            adaptive_bitrate: config.adaptive_bitrate,
+            #[cfg(feature = "update-check")]
+            updates,
+            scene,
+            integer_scaling: config.integer_scaling,
+            letterbox: config.letterbox,
+            sharpen: config.sharpen,
+            sharpen_strength: config.sharpen_strength,
+            low_latency: config.low_latency,
+            stats_hud: None,
+            theme: config.theme.clone(),
+            effects,
+            webcam: None,
+            webcam_device: config.webcam_device.clone(),
+            current_source: None,
+            source_before_overview: None,
+            crash_context_at: None,
+        };
+        if let Some(display) = mirror
+            .redaction_stage
+            .screen_capture()
+            .captured_display_id()
+        {
+            mirror.record_capture_target(CaptureSource::Display(display));
+        }
+        if let Some(e) = capture_error {
+            mirror.set_error(MirrorError::CaptureFailed(e));
         }
+        mirror.sync_webcam();
+        Ok(mirror)
     }
 
     /// Handles window resizing by updating GPU surface configuration
     /// When user drags window corner, we need to tell GPU about new dimensions
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.gpu_renderer.resize(new_size);
+        // The resized surface must be drawn even if the content is unchanged
+        self.needs_render = true;
     }
 
     /// Updates the screen capture texture with new image data and renders
     pub fn update_and_render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // Get latest frame or use test pattern
-        let texture_data = self
-            .screen_capture
-            .get_latest_frame()
-            .unwrap_or_else(|| self.gpu_renderer.create_test_pattern());
+        // Follow the cursor to another display if that mode is on
+        let display_id = self.redaction_stage.screen_capture().captured_display_id();
+        if let Some(target) = self.follow_cursor.poll(display_id)
+            && let Err(e) = self.select_source(CaptureSource::Display(target))
+        {
+            eprintln!("Failed to follow cursor to display {}: {}", target, e);
+        }
+
+        #[cfg(feature = "quic")]
+        self.process_remote_input();
+
+        // Pause on an unattended desktop before any more of its frames are processed
+        let idle = self.idle.as_ref().is_some_and(IdleWatcher::is_idle);
+        if idle != self.idle_paused.is_some() {
+            self.set_idle(idle);
+        }
+
+        // Check the Space right before processing so a private Space is blanked
+        // before any of its frames can be published
+        let display_ids = self.redaction_stage.screen_capture().captured_display_ids();
+        let private = self.private_spaces.poll(&display_ids);
+        if self.set_blanking(BlankReason::PrivateSpace, private) {
+            println!(
+                "Output {} (private Space)",
+                if private { "blanked" } else { "restored" }
+            );
+        }
+
+        // Run the redaction stage so any new raw frame is masked and published
+        self.redaction_stage.process();
+
+        // Report a black capture instead of silently mirroring blackness
+        let source_black = self.redaction_stage.source_is_black();
+        if source_black != self.source_black {
+            self.source_black = source_black;
+            self.events.push(if source_black {
+                MirrorEvent::SourceBlack
+            } else {
+                MirrorEvent::SourceRestored
+            });
+        }
+
+        // Locking fails on the first frame that exceeds the limit; tell the user once
+        if !self.frame_memory_unlocked
+            && matches!(locked_memory::status(), FrameMemoryStatus::Unlocked(_))
+        {
+            self.frame_memory_unlocked = true;
+            self.events.push(MirrorEvent::FrameMemoryUnlocked);
+        }
+
+        // Compare each new processed frame with the previous one for large changes
+        if let Some(frame) = self.change_subscriber.next_frame()
+            && let Some(changed_fraction) = self.change_detector.observe(frame.data())
+            && self
+                .last_change_event
+                .is_none_or(|last| last.elapsed() >= CHANGE_EVENT_COOLDOWN)
+        {
+            self.last_change_event = Some(Instant::now());
+            self.events
+                .push(MirrorEvent::SignificantChange { changed_fraction });
+        }
+
+        // Follow the power source: plugged in or unplugged since the last check
+        if let Some(on_battery) = self.power.as_mut().and_then(PowerWatcher::poll) {
+            self.set_low_power(on_battery);
+        }
+
+        // Announce a newer release once; the title keeps mentioning it
+        #[cfg(feature = "update-check")]
+        if let Some(release) = self
+            .updates
+            .as_mut()
+            .and_then(crate::update_check::UpdateChecker::poll)
+        {
+            self.events.push(MirrorEvent::UpdateAvailable(release));
+        }
+
+        // Rebuild the GPU state if the device went away since the last frame
+        if self.gpu_renderer.is_device_lost() {
+            self.recover_gpu();
+        }
+
+        // A new camera frame changes the output even when the capture is static
+        if let Some(frame) = self.webcam.as_ref().and_then(Webcam::take_frame) {
+            self.gpu_renderer
+                .update_webcam(&frame.rgba, frame.width, frame.height);
+            self.needs_render = true;
+        }
+
+        // Keep the crash report's view of the pipeline current without formatting it every frame
+        if self
+            .crash_context_at
+            .is_none_or(|at| at.elapsed() >= CRASH_CONTEXT_INTERVAL)
+        {
+            self.crash_context_at = Some(Instant::now());
+            crash_report::set_context("pipeline", self.describe_pipeline());
+        }
+
+        // The HUD's counters change even while the content doesn't
+        let gpu_memory = self.gpu_renderer.memory_usage();
+        if let Some(hud) = &mut self.stats_hud
+            && let Some(rendered) =
+                hud.update(&self.sinks, &gpu_memory, self.window.scale_factor() as f32)
+        {
+            match rendered {
+                Ok((rgba, width, height)) => {
+                    self.gpu_renderer.set_hud(Some((&rgba, width, height)));
+                    self.needs_render = true;
+                }
+                Err(e) => eprintln!("Failed to draw the stats HUD: {}", e),
+            }
+        }
+
+        // Composited frames read back on earlier frames, once the GPU has finished them
+        while let Some(readback) = self.gpu_renderer.take_output_readback() {
+            match readback {
+                Ok((rgba, width, height)) => {
+                    let frame = ProcessedFrame::new(rgba, width, height);
+                    let hash = frame_hash(frame.data(), frame.width());
+                    if self.composite_changes.observe(hash) {
+                        self.composite_bus.mark_content_changed();
+                    }
+                    self.composite_bus.publish(frame.with_content_hash(hash));
+                }
+                Err(e) => eprintln!("Failed to export composited frame: {}", e),
+            }
+        }
+
+        // The renderer only ever sees frames from the processed bus
+        let frame = self.render_subscriber.latest();
+
+        // Identical content needs neither a multi-megabyte upload nor a redraw
+        let content_hash = frame.as_ref().map(|frame| frame.content_hash());
+        if !self.needs_render && content_hash.is_some() && content_hash == self.uploaded_hash {
+            return Ok(());
+        }
+
+        match &frame {
+            Some(frame) => self.gpu_renderer.update_texture(frame.data()),
+            None => {
+                let pattern = self.gpu_renderer.create_test_pattern();
+                self.gpu_renderer.update_texture(&pattern);
+            }
+        }
+        self.uploaded_hash = content_hash;
+
+        if let Err(e) = self.gpu_renderer.render() {
+            // A lost device surfaces as an arbitrary surface error; rebuild and draw again
+            if self.gpu_renderer.is_device_lost() {
+                self.recover_gpu();
+                self.window.request_redraw();
+                return Ok(());
+            }
+            return Err(e);
+        }
+        self.needs_render = false;
+
+        // Reading the output back is expensive, so only do it while someone consumes it; the
+        // frame is collected on a later call instead of waiting for the GPU here
+        if self
+            .composite_sinks
+            .iter()
+            .any(|id| self.sinks.is_running(*id))
+        {
+            self.gpu_renderer.start_output_readback();
+        }
+
+        Ok(())
+    }
+
+    /// Recreate the device, pipeline and textures after a device loss (driver reset, eGPU unplug)
+    /// Capture, redaction and sinks keep running; only the GPU side is rebuilt
+    fn recover_gpu(&mut self) {
+        println!("Recreating GPU renderer after device loss");
+        self.rebuild_renderer();
+    }
+
+    /// Create a new renderer from the current settings and restore its scene, filters and card
+    fn rebuild_renderer(&mut self) {
+        let renderer = pollster::block_on(GpuRenderer::new(
+            self.window.clone(),
+            self.gpu_renderer.capture_width,
+            self.gpu_renderer.capture_height,
+            &Self::power_gpu_settings(&self.gpu_settings, self.low_power),
+        ));
+        // Keep the lost renderer if no GPU is available yet; the next frame tries again
+        self.gpu_renderer = match renderer {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                eprintln!("Failed to recreate GPU renderer: {}", e);
+                self.set_error(MirrorError::Gpu(e.to_string()));
+                return;
+            }
+        };
+        self.gpu_renderer.set_scene(&self.scene);
+        if !self.low_power {
+            self.gpu_renderer.set_effects(&self.effects);
+        }
+        self.gpu_renderer.set_integer_scaling(self.integer_scaling);
+        self.gpu_renderer.set_letterbox(self.letterbox);
+        if self.sharpen && !self.low_power {
+            self.gpu_renderer.set_sharpen(self.sharpen_strength);
+        }
+        if let Some(hud) = &mut self.stats_hud {
+            hud.invalidate();
+        }
+        match &self.error {
+            Some((MirrorError::Gpu(_), _)) => self.clear_error(),
+            Some((_, Some(card))) => {
+                self.gpu_renderer
+                    .set_overlay(Some((&card.rgba, card.width, card.height)));
+            }
+            _ => {}
+        }
+        // The new texture is empty until the current frame is uploaded again
+        self.uploaded_hash = None;
+        self.needs_render = true;
+    }
+
+    /// GPU memory the renderer holds, by purpose
+    pub fn gpu_memory(&self) -> GpuMemoryUsage {
+        self.gpu_renderer.memory_usage()
+    }
+
+    /// The GPU ran out of memory: give back effects and scene layer resolution (see
+    /// `GpuRenderer::reduce_memory`) and draw again; false if nothing was left to give back
+    pub fn reduce_gpu_memory(&mut self) -> bool {
+        let reduced = self.gpu_renderer.reduce_memory();
+        self.needs_render = true;
+        reduced
+    }
+
+    /// Render the current output offscreen and read it back
+    /// The result is exactly what the mirror window shows (redacted, overlaid, letterboxed)
+    pub fn export_composited_frame(&self) -> Result<ProcessedFrame, String> {
+        let size = self.gpu_renderer.size();
+        let texture = self.gpu_renderer.render_to_texture(size.width, size.height);
+        let rgba = pollster::block_on(self.gpu_renderer.read_texture_rgba(&texture))?;
+        Ok(ProcessedFrame::new(rgba, texture.width(), texture.height()))
+    }
+
+    /// Save a screenshot of exactly what the mirror shows; returns the file path
+    pub fn save_screenshot(&self, path: Option<PathBuf>) -> Result<PathBuf, String> {
+        if self.no_disk {
+            return Err("Screenshots are disabled in no-disk mode".to_string());
+        }
+        let frame = self.export_composited_frame()?;
+        let path = path.unwrap_or_else(|| default_screenshot_path(self.screenshot_options.format));
+        save_screenshot(&frame, &path, &self.screenshot_options)?;
+        Ok(path)
+    }
+
+    /// Bus carrying composited frames; only fed while a composite sink is attached
+    pub fn composite_bus(&self) -> &ProcessedFrameBus {
+        &self.composite_bus
+    }
+
+    /// Take all events raised since the last call
+    pub fn drain_events(&mut self) -> Vec<MirrorEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Whether frames are captured at backing pixels (native) or points
+    pub fn capture_scale(&self) -> CaptureScale {
+        self.capture_scale
+    }
+
+    /// Frames per second the capture is asked for (above 60 on high refresh displays)
+    pub fn frame_rate(&self) -> u32 {
+        self.frame_rate
+    }
+
+    /// The window moved to a display refreshing at `refresh_hz`: follow its rate, dropping
+    /// back to 60 on ordinary displays. Restarts the capture only when the rate changes
+    pub fn set_display_refresh(&mut self, refresh_hz: Option<u32>) {
+        if self.fixed_frame_rate.is_some() {
+            return;
+        }
+        self.display_frame_rate = capture_frame_rate(self.high_refresh, refresh_hz);
+        self.update_capture_rate();
+    }
+
+    /// Rate to capture at: the display's, capped by `BATTERY_FRAME_RATE` in low-power mode
+    /// Capture layers with an `fps` cap are held by the renderer instead, since the capture
+    /// also feeds the outputs that don't composite the scene
+    fn capped_frame_rate(display_frame_rate: u32, low_power: bool) -> u32 {
+        if low_power {
+            display_frame_rate.min(BATTERY_FRAME_RATE)
+        } else {
+            display_frame_rate
+        }
+    }
+
+    /// `settings` for low-power mode: the integrated GPU first and no float pipeline
+    fn power_gpu_settings(settings: &GpuSettings, low_power: bool) -> GpuSettings {
+        GpuSettings {
+            low_power,
+            float_pipeline: settings.float_pipeline && !low_power,
+            ..settings.clone()
+        }
+    }
+
+    /// Enter low-power mode on battery or leave it when plugged in: the capture rate is
+    /// capped and the renderer rebuilt on the integrated GPU without sharpening, effects or
+    /// the float pipeline
+    fn set_low_power(&mut self, low_power: bool) {
+        if low_power == self.low_power {
+            return;
+        }
+        self.low_power = low_power;
+        println!(
+            "{}",
+            if low_power {
+                "On battery: low-power mode"
+            } else {
+                "Plugged in: low-power mode off"
+            }
+        );
+        self.update_capture_rate();
+        self.rebuild_renderer();
+        self.events.push(MirrorEvent::LowPowerChanged { low_power });
+    }
+
+    /// Whether low-power mode is on (running on battery with `low_power_on_battery`)
+    pub fn is_low_power(&self) -> bool {
+        self.low_power
+    }
+
+    /// Restart the capture at the display's rate (capped in low-power mode), if that differs
+    /// from the rate captured at now
+    fn update_capture_rate(&mut self) {
+        let frame_rate = Self::capped_frame_rate(self.display_frame_rate, self.low_power);
+        if frame_rate == self.frame_rate {
+            return;
+        }
+        self.redaction_stage
+            .screen_capture_mut()
+            .set_frame_rate(frame_rate);
+        // An idle pause stopped the capture; it restarts at this rate with the next input
+        if self.idle_paused.is_some() {
+            self.frame_rate = frame_rate;
+            return;
+        }
+        match self.start_capture() {
+            Ok(()) => {
+                println!("Capturing at {} fps", frame_rate);
+                self.frame_rate = frame_rate;
+            }
+            Err(e) => {
+                eprintln!("Failed to change the capture rate: {}", e);
+                // The running stream keeps its rate; ask for that again next time
+                self.redaction_stage
+                    .screen_capture_mut()
+                    .set_frame_rate(self.frame_rate);
+            }
+        }
+    }
+
+    /// (Re)start the capture with its current settings; a capture without a chosen source is
+    /// checked against the policy first, as nothing else has checked its default display
+    fn start_capture(&mut self) -> Result<(), String> {
+        self.policy
+            .check_default_display(self.redaction_stage.screen_capture())?;
+        self.redaction_stage
+            .screen_capture_mut()
+            .start_capture(Some(&self.window))
+    }
+
+    /// Pause capture and outputs while nobody is at the machine: the output is blanked, the
+    /// capture stopped and running recordings paused so the file skips the idle stretch.
+    /// All of it is undone at the next input
+    fn set_idle(&mut self, idle: bool) {
+        if idle {
+            let paused = self
+                .recordings
+                .iter()
+                .filter(|(id, control)| self.sinks.is_running(**id) && !control.is_paused())
+                .map(|(id, control)| {
+                    control.set_paused(true);
+                    *id
+                })
+                .collect();
+            self.idle_paused = Some(paused);
+            // A capture that already stopped or failed stays that way until its own retry
+            self.idle_stopped_capture = !self.capture_stopped
+                && !matches!(
+                    self.error(),
+                    Some(MirrorError::CaptureFailed(_) | MirrorError::CaptureStopped(_))
+                );
+            if self.idle_stopped_capture {
+                self.redaction_stage.screen_capture_mut().stop_capture();
+            }
+            println!("No input for a while: capture and outputs paused until the next input");
+        } else {
+            for id in self.idle_paused.take().into_iter().flatten() {
+                if let Some(control) = self.recordings.get(&id) {
+                    control.set_paused(false);
+                }
+            }
+            if std::mem::take(&mut self.idle_stopped_capture) {
+                match self.start_capture() {
+                    Ok(()) => println!("Input again: capture and outputs resumed"),
+                    Err(e) => self.set_error(MirrorError::CaptureFailed(e)),
+                }
+            } else {
+                println!("Input again: outputs resumed");
+            }
+        }
+        self.set_blanking(BlankReason::Idle, idle);
+    }
+
+    /// The capture layer reported that its stream stopped (`CaptureEvent::Stopped`)
+    pub fn capture_stopped(&mut self, error: &str) {
+        if !self.capture_stopped {
+            self.capture_stopped = true;
+            self.events.push(MirrorEvent::CaptureStopped);
+        }
+        self.set_error(MirrorError::CaptureStopped(error.to_string()));
+    }
+
+    /// Show `error` as a card over the output and raise `MirrorEvent::Error` (once per error)
+    pub fn set_error(&mut self, error: MirrorError) {
+        if self
+            .error
+            .as_ref()
+            .is_some_and(|(current, _)| *current == error)
+        {
+            return;
+        }
+        let card = match ErrorCard::new(&error, self.window.scale_factor() as f32, &self.theme) {
+            Ok(card) => Some(card),
+            Err(e) => {
+                eprintln!("Failed to draw the error card: {}", e);
+                None
+            }
+        };
+        self.gpu_renderer.set_overlay(
+            card.as_ref()
+                .map(|card| (card.rgba.as_slice(), card.width, card.height)),
+        );
+        self.events.push(MirrorEvent::Error(error.clone()));
+        self.error = Some((error, card));
+        self.needs_render = true;
+    }
+
+    /// Remove the error card
+    pub fn clear_error(&mut self) {
+        if self.error.take().is_some() {
+            self.gpu_renderer.set_overlay(None);
+            self.needs_render = true;
+        }
+    }
+
+    /// What currently keeps the mirror from showing the screen
+    pub fn error(&self) -> Option<&MirrorError> {
+        self.error.as_ref().map(|(error, _)| error)
+    }
+
+    /// Try to get past the current error: ask for the permission again, restart the capture
+    /// or recreate the renderer. The card is replaced if the retry fails too
+    pub fn retry(&mut self) {
+        match self.error() {
+            None => return,
+            Some(MirrorError::PermissionDenied)
+                if PlatformDetector::screen_recording_permission() != PermissionStatus::Granted =>
+            {
+                PlatformDetector::request_screen_recording_permission();
+                return;
+            }
+            Some(MirrorError::Gpu(_)) => {
+                self.recover_gpu();
+                return;
+            }
+            Some(_) => {}
+        }
+        match self.start_capture() {
+            Ok(()) => {
+                println!("Screen capture restarted");
+                self.capture_stopped = false;
+                self.clear_error();
+            }
+            Err(e) => {
+                eprintln!("Failed to restart screen capture: {}", e);
+                self.set_error(MirrorError::CaptureFailed(e));
+            }
+        }
+    }
+
+    /// Whether a click at `position` (window pixels) lands on the error card's Retry button
+    pub fn hits_retry_button(&self, position: winit::dpi::PhysicalPosition<f64>) -> bool {
+        let (Some((_, Some(card))), Some((x, y, width, _))) =
+            (&self.error, self.gpu_renderer.overlay_rect())
+        else {
+            return false;
+        };
+        // The card may be drawn smaller than its pixels in small windows
+        let scale = card.width as f64 / width.max(1.0) as f64;
+        card.hits_retry(
+            (position.x - x as f64) * scale,
+            (position.y - y as f64) * scale,
+        )
+    }
+
+    /// Window title naming the captured source and whatever keeps the output from being
+    /// live, e.g. "CloakShare - Display 2 (paused)"
+    /// Always starts with "CloakShare", which keeps the mirror window out of the capture
+    pub fn window_title(&self) -> String {
+        let mut states = Vec::new();
+        if self.capture_stopped {
+            states.push(tr("title-stopped"));
+        }
+        if self.is_paused() {
+            states.push(tr("title-paused"));
+        }
+        if self.idle_paused.is_some() {
+            states.push(tr("title-idle"));
+        }
+        if self.low_power {
+            states.push(tr("title-low-power"));
+        }
+        if self.redaction_stage.blanking().is_blanked() {
+            states.push(tr("title-blanked"));
+        }
+        if self.source_black {
+            states.push(tr("title-no-picture"));
+        }
+        if self.frame_memory_unlocked {
+            states.push(tr("title-memory-unlocked"));
+        }
+        #[cfg(feature = "update-check")]
+        if let Some(release) = self
+            .updates
+            .as_ref()
+            .and_then(crate::update_check::UpdateChecker::available)
+        {
+            states.push(l10n::tr_args(
+                "title-update-available",
+                &[("version", &release.version)],
+            ));
+        }
+        let source = self
+            .source_name
+            .clone()
+            .unwrap_or_else(|| tr("title-default-source"));
+        if states.is_empty() {
+            format!("CloakShare - {}", source)
+        } else {
+            format!("CloakShare - {} ({})", source, states.join(", "))
+        }
+    }
+
+    /// Pipeline state for crash reports: source and its states, capture, scene, GPU memory and
+    /// outputs (never pixel data)
+    fn describe_pipeline(&self) -> String {
+        let (width, height) = self.redaction_stage.output_size();
+        let mut lines = vec![
+            self.window_title(),
+            format!(
+                "Capture: {}x{} at {} fps ({:?})",
+                width, height, self.frame_rate, self.capture_scale
+            ),
+            format!("Scene layers: {}", self.scene.layers().len()),
+            format!(
+                "GPU memory: {} MB",
+                self.gpu_renderer.memory_usage().total().div_ceil(MEGABYTE)
+            ),
+        ];
+        for (id, name) in self.sinks.sinks() {
+            lines.push(match self.sinks.stats(id) {
+                Some(stats) => format!(
+                    "Output {}: {} frames, {} skipped, queue {}",
+                    name, stats.frames, stats.skipped, stats.queue_depth
+                ),
+                None => format!("Output {}", name),
+            });
+        }
+        lines.join("\n")
+    }
 
-        // Update GPU texture and render
-        self.gpu_renderer.update_texture(&texture_data);
-        self.gpu_renderer.render()
+    /// Whether the capture currently delivers only black frames
+    pub fn source_is_black(&self) -> bool {
+        self.source_black
+    }
+
+    /// Processed frame bus for attaching additional outputs (recorder, streamer, ...)
+    pub fn frame_bus(&self) -> &ProcessedFrameBus {
+        self.redaction_stage.bus()
+    }
+
+    /// What the platform's capture supports, for adapting menus and hotkeys
+    pub fn capture_capabilities(&self) -> CaptureCapabilities {
+        self.redaction_stage.screen_capture().capabilities()
+    }
+
+    /// List displays and windows that can be captured on this platform (and the policy allows)
+    pub fn list_sources(&self) -> Result<Vec<SourceInfo>, String> {
+        let capabilities = self.capture_capabilities();
+        let windows = self.policy_windows();
+        Ok(self
+            .redaction_stage
+            .screen_capture()
+            .list_sources()?
+            .into_iter()
+            .filter(|info| capabilities.supports(&info.source))
+            .filter(|info| {
+                self.policy
+                    .check_source(&info.source, windows.as_deref())
+                    .is_ok()
+            })
+            .collect())
+    }
+
+    /// Current windows, when the policy needs them to tell which app owns a window source
+    fn policy_windows(&self) -> Option<Vec<WindowInfo>> {
+        if self.policy.forbidden_apps.is_empty() {
+            return None;
+        }
+        self.redaction_stage.screen_capture().list_windows().ok()
+    }
+
+    /// Build a source picker with thumbnails of every capturable source
+    pub fn source_picker(&self) -> Result<SourcePicker, String> {
+        let screen_capture = self.redaction_stage.screen_capture();
+        let entries = self
+            .list_sources()?
+            .into_iter()
+            .map(|info| {
+                // A missing thumbnail shouldn't stop the source from being selectable
+                let thumbnail = screen_capture
+                    .capture_thumbnail(info.source, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+                    .inspect_err(|e| eprintln!("No thumbnail for {}: {}", info.title, e))
+                    .ok();
+                PickerEntry { info, thumbnail }
+            })
+            .collect();
+        Ok(SourcePicker::new(entries, self.theme.clone()))
+    }
+
+    /// Move the capture to the display after the current one in the platform's display order,
+    /// wrapping around; returns the new display's name, or None with fewer than two
+    /// displays the policy allows
+    pub fn next_display(&mut self) -> Result<Option<String>, String> {
+        // Displays the policy forbids are skipped rather than stopping the cycle at them
+        let displays: Vec<SourceInfo> = self
+            .list_sources()?
+            .into_iter()
+            .filter(|info| matches!(info.source, CaptureSource::Display(_)))
+            .filter(|info| self.policy.check_source(&info.source, None).is_ok())
+            .collect();
+        if displays.len() < 2 {
+            return Ok(None);
+        }
+        let current = match self.current_source {
+            Some(CaptureSource::Display(display)) => Some(display),
+            _ => self.redaction_stage.screen_capture().captured_display_id(),
+        };
+        // Without a captured display (a window, the grid) cycling starts at the first one
+        let next = current
+            .and_then(|display| {
+                displays
+                    .iter()
+                    .position(|info| info.source == CaptureSource::Display(display))
+            })
+            .map_or(0, |index| (index + 1) % displays.len());
+        let info = &displays[next];
+        self.select_source(info.source)?;
+        Ok(Some(info.title.clone()))
+    }
+
+    /// Start selecting a region of the captured display (the primary one when the capture
+    /// isn't showing a single display), over its redacted output
+    pub fn region_selection(&self) -> Result<RegionSelection, String> {
+        if !self.capture_capabilities().region_capture {
+            return Err("Region capture isn't supported on this platform".to_string());
+        }
+        let displays: Vec<SourceInfo> = self
+            .list_sources()?
+            .into_iter()
+            .filter(|info| matches!(info.source, CaptureSource::Display(_)))
+            .collect();
+        let captured = match self.current_source {
+            Some(CaptureSource::Display(display)) | Some(CaptureSource::Region { display, .. }) => {
+                Some(display)
+            }
+            _ => self.redaction_stage.screen_capture().captured_display_id(),
+        };
+        let info = displays
+            .iter()
+            .find(|info| Some(info.source) == captured.map(CaptureSource::Display))
+            .or(displays.first())
+            .ok_or("No display to select a region from")?;
+        let CaptureSource::Display(display) = info.source else {
+            return Err("No display to select a region from".to_string());
+        };
+
+        // Only the output of a capture of the whole display shows all of it redacted; for any
+        // other source the selection is made over a blank canvas instead of raw pixels
+        let whole_display = matches!(self.current_source, None | Some(CaptureSource::Display(_)))
+            && self.redaction_stage.screen_capture().captured_display_id() == Some(display);
+        let background = match self.redaction_stage.bus().latest() {
+            Some(frame) if whole_display => {
+                let factor = frame.width().max(frame.height()).div_ceil(MAX_CANVAS_SIZE);
+                let (rgba, width, height) =
+                    downscale_rgba(frame.data(), frame.width(), frame.height(), factor);
+                SourceThumbnail {
+                    width,
+                    height,
+                    rgba,
+                }
+            }
+            _ => {
+                let factor = info.width.max(info.height).div_ceil(MAX_CANVAS_SIZE).max(1);
+                let (width, height) = (info.width / factor, info.height / factor);
+                SourceThumbnail {
+                    width,
+                    height,
+                    rgba: self.theme.background.repeat((width * height) as usize),
+                }
+            }
+        };
+        Ok(RegionSelection::new(
+            display,
+            info.width,
+            info.height,
+            background,
+            self.theme.text,
+        ))
+    }
+
+    /// Switch the capture to a different display, window or region while running
+    /// The new stream is started before the old one stops, so the output never drops out;
+    /// if the switch fails the previous source keeps being captured
+    pub fn select_source(&mut self, source: CaptureSource) -> Result<(), String> {
+        if !self.capture_capabilities().supports(&source) {
+            return Err(format!(
+                "{:?} can't be captured on {:?}",
+                source,
+                self.redaction_stage.screen_capture().platform()
+            ));
+        }
+        self.policy
+            .check_source(&source, self.policy_windows().as_deref())?;
+        let screen_capture = self.redaction_stage.screen_capture_mut();
+        screen_capture.set_source(source);
+        screen_capture.start_capture(Some(&self.window))?;
+        self.current_source = Some(source);
+        self.record_capture_target(source);
+        // Any explicit switch ends the overview
+        self.source_before_overview = None;
+        println!("Switched capture source to {:?}", source);
+        Ok(())
+    }
+
+    /// Log a new capture target with whatever name the platform knows for it
+    fn record_capture_target(&mut self, source: CaptureSource) {
+        let screen_capture = self.redaction_stage.screen_capture();
+        let title = match source {
+            CaptureSource::Window(id) => screen_capture.list_windows().ok().and_then(|windows| {
+                windows
+                    .into_iter()
+                    .find(|window| window.id == id)
+                    .map(|window| format!("{} - {}", window.app_name, window.title))
+            }),
+            _ => screen_capture.list_sources().ok().and_then(|sources| {
+                sources
+                    .into_iter()
+                    .find(|info| info.source == source)
+                    .map(|info| info.title)
+            }),
+        };
+        self.source_name = title.clone();
+        self.capture_stopped = false;
+        // A running capture ends capture errors; permission and GPU errors have their own fixes
+        if matches!(
+            self.error(),
+            Some(MirrorError::CaptureFailed(_) | MirrorError::CaptureStopped(_))
+        ) {
+            self.clear_error();
+        }
+        self.events.push(MirrorEvent::SourceChanged {
+            name: title.clone(),
+        });
+        self.redaction_stage
+            .compliance_mut()
+            .record(ComplianceEvent::CaptureTarget {
+                source: format!("{:?}", source),
+                title,
+            });
+    }
+
+    /// Write the session's capture targets, blanking and redaction hits to `path`
+    /// (JSON, or CSV for `.csv` files)
+    pub fn export_compliance_log(&self, path: &Path) -> Result<(), String> {
+        self.redaction_stage.compliance().export(path)
+    }
+
+    /// Raw frames the capture delivered, and how many were dropped because rendering fell behind
+    pub fn capture_stats(&self) -> FrameChannelStats {
+        self.redaction_stage.screen_capture().frame_stats()
+    }
+
+    /// Whether the live grid of all displays is shown
+    pub fn is_overview(&self) -> bool {
+        self.source_before_overview.is_some()
+    }
+
+    /// Show every display live in a grid, or return to the previous source; returns the new state
+    pub fn toggle_overview(&mut self) -> Result<bool, String> {
+        if let Some(previous) = self.source_before_overview {
+            self.select_source(previous)?;
+            return Ok(false);
+        }
+
+        let previous = self.current_source.or_else(|| {
+            self.redaction_stage
+                .screen_capture()
+                .captured_display_id()
+                .map(CaptureSource::Display)
+        });
+        self.select_source(CaptureSource::AllDisplays(DisplayArrangement::Grid))?;
+        self.source_before_overview = previous;
+        Ok(self.is_overview())
+    }
+
+    /// In the overview, promote the display under a window position (physical pixels) to the
+    /// full mirror; returns the selected display
+    pub fn select_overview_display(
+        &mut self,
+        position: winit::dpi::PhysicalPosition<f64>,
+    ) -> Result<Option<u32>, String> {
+        if !self.is_overview() {
+            return Ok(None);
+        }
+
+        // Map through the rectangle the capture is drawn in, which letterboxing and integer
+        // scaling shrink; clicks on the bars select nothing
+        let (left, top, width, height) = self.gpu_renderer.capture_rect();
+        let x = (position.x - left as f64) / width.max(1) as f64;
+        let y = (position.y - top as f64) / height.max(1) as f64;
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return Ok(None);
+        }
+        let (output_width, output_height) = self.redaction_stage.output_size();
+        let x = x * output_width as f64;
+        let y = y * output_height as f64;
+
+        let Some(display) = self
+            .redaction_stage
+            .screen_capture()
+            .display_at(x as u32, y as u32)
+        else {
+            return Ok(None);
+        };
+        self.select_source(CaptureSource::Display(display))?;
+        Ok(Some(display))
+    }
+
+    /// Turn follow-cursor display switching on or off; returns the new state
+    pub fn toggle_follow_cursor(&mut self) -> bool {
+        let enabled = !self.follow_cursor.is_enabled();
+        self.follow_cursor.set_enabled(enabled);
+        println!(
+            "Follow cursor {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+        enabled
+    }
+
+    /// Turn capture sharpening on or off; returns the new state
+    pub fn toggle_sharpen(&mut self) -> bool {
+        if self.low_latency {
+            println!("Sharpening stays off in low-latency mode");
+            return false;
+        }
+        self.sharpen = !self.sharpen;
+        self.gpu_renderer.set_sharpen(if self.sharpen {
+            self.sharpen_strength
+        } else {
+            0.0
+        });
+        self.needs_render = true;
+        println!(
+            "Sharpening {}",
+            if self.sharpen { "enabled" } else { "disabled" }
+        );
+        self.sharpen
+    }
+
+    /// Show or hide the stats HUD in the mirror window; returns whether it is shown
+    pub fn toggle_stats_hud(&mut self) -> bool {
+        self.stats_hud = match self.stats_hud {
+            Some(_) => {
+                self.gpu_renderer.set_hud(None);
+                None
+            }
+            None => Some(StatsHud::new(self.theme.clone())),
+        };
+        self.needs_render = true;
+        self.stats_hud.is_some()
+    }
+
+    /// Freeze the output on the last frame; the capture stream keeps running
+    /// Unlike blanking, which hides the output, pausing keeps showing the frozen frame
+    pub fn pause(&mut self) {
+        if !self.redaction_stage.is_paused() {
+            self.redaction_stage.set_paused(true);
+            println!("Output paused");
+            self.events
+                .push(MirrorEvent::PausedChanged { paused: true });
+        }
+    }
+
+    /// Continue with live frames after `pause`
+    pub fn resume(&mut self) {
+        if self.redaction_stage.is_paused() {
+            self.redaction_stage.set_paused(false);
+            println!("Output resumed");
+            self.events
+                .push(MirrorEvent::PausedChanged { paused: false });
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.redaction_stage.is_paused()
+    }
+
+    /// Pause or resume the output; returns true if now paused
+    pub fn toggle_pause(&mut self) -> bool {
+        if self.is_paused() {
+            self.resume();
+        } else {
+            self.pause();
+        }
+        self.is_paused()
+    }
+
+    /// Blank the output while the source picker is open; call before its window shows, so no
+    /// frame with the window in it is published
+    pub fn set_source_picker_open(&mut self, open: bool) {
+        self.set_blanking(BlankReason::SourcePicker, open);
+    }
+
+    /// Blank the output while the region selection overlay is open, like the source picker
+    pub fn set_region_selection_open(&mut self, open: bool) {
+        self.set_blanking(BlankReason::RegionSelection, open);
+    }
+
+    /// Activate or clear a blanking reason and report a change of the blanked state; returns
+    /// true if it changed
+    fn set_blanking(&mut self, reason: BlankReason, active: bool) -> bool {
+        let changed = self.redaction_stage.blanking_mut().set(reason, active);
+        if changed {
+            self.events.push(MirrorEvent::BlankingChanged {
+                blanked: self.redaction_stage.blanking().is_blanked(),
+            });
+        }
+        changed
+    }
+
+    /// Mark the Space currently shown on the captured display as private (saved for later
+    /// launches), or unmark it; the output is blanked from the next frame
+    pub fn toggle_current_space_private(&mut self) -> Result<(u64, bool), String> {
+        self.private_spaces.toggle_current_private()
+    }
+
+    /// Layers currently composited into the output
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Replace the whole scene (an empty scene shows just the capture)
+    pub fn set_scene(&mut self, scene: Scene) {
+        self.scene = scene;
+        self.apply_scene();
+    }
+
+    /// Show or hide a layer by name; returns false if there is no such layer
+    pub fn set_layer_visible(&mut self, name: &str, visible: bool) -> bool {
+        let Some(layer) = self.scene.layer_mut(name) else {
+            return false;
+        };
+        layer.visible = visible;
+        self.apply_scene();
+        true
+    }
+
+    /// Show or hide the nth image layer (0-based); returns its name and new visibility
+    pub fn toggle_image_layer(&mut self, index: usize) -> Option<(String, bool)> {
+        let layer = self.scene.image_layer_mut(index)?;
+        layer.visible = !layer.visible;
+        let toggled = (layer.name.clone(), layer.visible);
+        self.apply_scene();
+        Some(toggled)
+    }
+
+    /// Change what a text layer says (e.g. a lower-third); returns false if there is no such
+    /// text layer
+    pub fn set_layer_text(&mut self, name: &str, text: &str) -> bool {
+        if !self.scene.set_text(name, text) {
+            return false;
+        }
+        self.apply_scene();
+        true
+    }
+
+    /// Move/resize a layer by name; returns false if there is no such layer
+    pub fn set_layer_transform(&mut self, name: &str, transform: Transform) -> bool {
+        let Some(layer) = self.scene.layer_mut(name) else {
+            return false;
+        };
+        layer.transform = transform;
+        self.apply_scene();
+        true
+    }
+
+    /// Cap how often a live layer (capture, webcam) updates, e.g. a 5 fps thumbnail; None = as
+    /// fast as its source delivers. Returns false if there is no such layer
+    pub fn set_layer_fps(&mut self, name: &str, fps: Option<u32>) -> bool {
+        let Some(layer) = self.scene.layer_mut(name) else {
+            return false;
+        };
+        layer.fps = fps;
+        self.apply_scene();
+        true
+    }
+
+    /// Push scene changes to the renderer and start/stop the camera to match
+    fn apply_scene(&mut self) {
+        self.gpu_renderer.set_scene(&self.scene);
+        self.sync_webcam();
+        self.needs_render = true;
+    }
+
+    /// Run the camera exactly while a visible layer shows it (the camera light is a privacy signal),
+    /// restarting it when the layers' fps cap changes
+    fn sync_webcam(&mut self) {
+        let wanted = self.scene.has_visible_webcam();
+        let frame_rate = self.scene.source_fps(&LayerSource::Webcam);
+        if wanted
+            && self
+                .webcam
+                .as_ref()
+                .is_some_and(|webcam| webcam.frame_rate() != frame_rate)
+        {
+            self.webcam = None;
+        }
+        if wanted && self.webcam.is_none() {
+            match Webcam::start(self.webcam_device.as_deref(), frame_rate) {
+                Ok(webcam) => self.webcam = Some(webcam),
+                Err(e) => eprintln!("Failed to start webcam: {}", e),
+            }
+        } else if !wanted && self.webcam.take().is_some() {
+            println!("Stopped webcam");
+        }
+    }
+
+    /// Names of the configured profiles, in sorted order
+    pub fn profile_names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+
+    /// Switch to a named profile: its source, redaction rules and outputs replace the current ones
+    /// Nothing changes if the profile's source can't be captured
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+
+        if let Some(source) = profile.source {
+            self.select_source(source)?;
+        }
+
+        for id in std::mem::take(&mut self.profile_sinks) {
+            self.detach_sink(id);
+        }
+
+        let redactor = self.redaction_stage.redactor_mut();
+        // Config-wide (and policy-required) rules stay on whatever the profile says
+        redactor.set_rules([self.base_redactions.clone(), profile.redactions].concat());
+        redactor.set_fill(profile.mask_fill.unwrap_or(self.default_mask_fill));
+        // A static screen sends no new frame to mask under the profile's rules
+        self.redaction_stage.reapply_rules();
+
+        // A failing output shouldn't keep the rest of the profile from applying
+        if let Some(path) = profile.socket {
+            match self.attach_sink(Box::new(SocketSink::new(path))) {
+                Ok(id) => self.profile_sinks.push(id),
+                Err(e) => eprintln!("Profile {}: failed to start frame socket: {}", name, e),
+            }
+        }
+        if let Some(path) = profile.recording {
+            let codec = profile.recording_codec.unwrap_or(VideoCodec::H264);
+            match self.start_recording(path, codec) {
+                Ok(id) => self.profile_sinks.push(id),
+                Err(e) => eprintln!("Profile {}: failed to start recording: {}", name, e),
+            }
+        }
+
+        self.active_profile = Some(name.to_string());
+        println!("Switched to profile {}", name);
+        Ok(())
+    }
+
+    /// Switch to the profile after the active one (wrapping around); returns its name
+    pub fn next_profile(&mut self) -> Result<Option<String>, String> {
+        let names: Vec<String> = self.profiles.keys().cloned().collect();
+        if names.is_empty() {
+            return Ok(None);
+        }
+        let next = match &self.active_profile {
+            Some(active) => names
+                .iter()
+                .position(|name| name == active)
+                .map_or(0, |index| (index + 1) % names.len()),
+            None => 0,
+        };
+        self.apply_profile(&names[next])?;
+        Ok(Some(names[next].clone()))
+    }
+
+    /// Attach an output that receives every processed frame on its own thread
+    pub fn attach_sink(&mut self, sink: Box<dyn FrameSink>) -> Result<SinkId, String> {
+        self.sinks.attach(sink)
+    }
+
+    /// Attach an output that receives the composited mirror output instead of the processed frame
+    /// Use this for screenshots, recordings and virtual cameras that must match the mirror window
+    pub fn attach_composite_sink(&mut self, sink: Box<dyn FrameSink>) -> Result<SinkId, String> {
+        let id = self.sinks.attach_to(&self.composite_bus, sink)?;
+        self.composite_sinks.insert(id);
+        Ok(id)
+    }
+
+    /// Start recording processed frames at native resolution (IVF for AV1/VP9; H.264 as MP4
+    /// for .mp4/.mov paths, otherwise Annex B)
+    pub fn start_recording(
+        &mut self,
+        path: impl Into<PathBuf>,
+        codec: VideoCodec,
+    ) -> Result<SinkId, String> {
+        if !self.recording_allowed {
+            return Err("Recording is disabled by your organization".to_string());
+        }
+        let (width, height) = self.redaction_stage.output_size();
+        let sink = RecordingSink::create(
+            path.into(),
+            codec,
+            width,
+            height,
+            self.recording_signing_key.as_deref(),
+        )?
+        .with_frame_policy(self.recording_frame_policy)
+        .with_redaction_log(self.recording_redaction_log);
+        let control = sink.control();
+        let id = self.attach_sink(Box::new(sink))?;
+        self.recordings.insert(id, control);
+        Ok(id)
+    }
+
+    /// Pause or resume a recording started with `start_recording`; the paused part is left
+    /// out of the file rather than recorded as a frozen frame
+    pub fn set_recording_paused(&mut self, id: SinkId, paused: bool) -> Result<(), String> {
+        let control = self
+            .recordings
+            .get(&id)
+            .ok_or_else(|| format!("No recording with ID {}", id.as_u64()))?;
+        control.set_paused(paused);
+        Ok(())
+    }
+
+    /// Mark a chapter in every running recording (see `RecordingControl::add_chapter`);
+    /// returns how many recordings got it
+    pub fn add_chapter(&mut self, title: Option<String>) -> usize {
+        let running: Vec<&RecordingControl> = self
+            .recordings
+            .iter()
+            .filter(|(id, _)| self.sinks.is_running(**id))
+            .map(|(_, control)| control)
+            .collect();
+        for control in &running {
+            control.add_chapter(title.clone());
+        }
+        running.len()
+    }
+
+    /// Stream processed frames as H.264 to a WHIP ingest URL, with an optional bearer token
+    #[cfg(feature = "whip")]
+    pub fn start_whip(&mut self, url: String, token: Option<String>) -> Result<SinkId, String> {
+        crate::stream_auth::check_endpoint(
+            &url,
+            self.require_encrypted_streams,
+            self.stream_key.as_deref(),
+        )?;
+        let (width, height) = self.redaction_stage.output_size();
+        let settings = EncoderSettings::new(width, height);
+        let encoder = create_encoder(VideoCodec::H264, &settings)?;
+        let mut sink =
+            crate::sinks::whip::WhipSink::new(url, token, self.stream_key.clone(), encoder)
+                .with_frame_rate(settings.fps);
+        if self.adaptive_bitrate {
+            sink = sink.with_adaptive_bitrate(settings);
+        }
+        self.attach_sink(Box::new(sink))
+    }
+
+    #[cfg(not(feature = "whip"))]
+    pub fn start_whip(&mut self, _url: String, _token: Option<String>) -> Result<SinkId, String> {
+        Err("WHIP output isn't in this build (enable the whip cargo feature)".to_string())
+    }
+
+    /// Stream processed frames as H.264 to a CloakShare receiver over QUIC (`host:port`)
+    #[cfg(feature = "quic")]
+    pub fn start_quic(&mut self, address: String) -> Result<SinkId, String> {
+        // QUIC is always encrypted, but only the stream key proves who is on the other end
+        if self.require_encrypted_streams && self.stream_key.is_none() {
+            return Err(format!(
+                "Not streaming to {}: require_encrypted_streams needs a stream_key for QUIC",
+                address
+            ));
+        }
+        let (width, height) = self.redaction_stage.output_size();
+        let settings = EncoderSettings::new(width, height);
+        let encoder = create_encoder(VideoCodec::H264, &settings)?;
+        // Without a key anyone who can reach the sender could ask for control
+        if self.remote_input.is_some() && self.stream_key.is_none() {
+            eprintln!(
+                "Remote input needs a stream_key; ignoring it from {}",
+                address
+            );
+        }
+        let mut sink = crate::sinks::quic::QuicSink::new(address, self.stream_key.clone(), encoder)
+            .with_frame_rate(settings.fps);
+        if let Some(remote_input) = &self.remote_input
+            && self.stream_key.is_some()
+        {
+            sink = sink.with_remote_input(remote_input.sender());
+        }
+        self.attach_sink(Box::new(sink))
+    }
+
+    /// Answer a `MirrorEvent::RemoteInputRequested`
+    #[cfg(feature = "quic")]
+    pub fn set_remote_input_allowed(
+        &mut self,
+        session: &crate::remote_input::RemoteSession,
+        allowed: bool,
+    ) {
+        if let Some(remote_input) = &mut self.remote_input {
+            remote_input.set_allowed(session, allowed);
+        }
+    }
+
+    /// Inject allowed remote input and ask about receivers that just started sending it
+    #[cfg(feature = "quic")]
+    fn process_remote_input(&mut self) {
+        let Some(remote_input) = &mut self.remote_input else {
+            return;
+        };
+        let source = self.current_source;
+        let screen_capture = self.redaction_stage.screen_capture();
+        let requests = remote_input.process(|| match source {
+            None => crate::remote_input::display_area(None),
+            Some(CaptureSource::Display(display)) => {
+                crate::remote_input::display_area(Some(display))
+            }
+            Some(CaptureSource::Region {
+                display,
+                x,
+                y,
+                width,
+                height,
+            }) => crate::remote_input::display_area(Some(display)).map(|area| {
+                crate::platform::WindowFrame {
+                    x: area.x + x as i32,
+                    y: area.y + y as i32,
+                    width,
+                    height,
+                }
+            }),
+            Some(CaptureSource::Window(id)) => screen_capture
+                .list_windows()
+                .ok()?
+                .into_iter()
+                .find(|window| window.id == id)
+                .map(|window| window.frame),
+            Some(CaptureSource::AllDisplays(_)) => None,
+        });
+        self.events.extend(
+            requests
+                .into_iter()
+                .map(|session| MirrorEvent::RemoteInputRequested { session }),
+        );
+    }
+
+    #[cfg(not(feature = "quic"))]
+    pub fn start_quic(&mut self, _address: String) -> Result<SinkId, String> {
+        Err("QUIC output isn't in this build (enable the quic cargo feature)".to_string())
+    }
+
+    /// Stream to the receiver after the current one, or stop after the last; returns the
+    /// receiver now streamed to
+    pub fn next_receiver(
+        &mut self,
+        receivers: &[DiscoveredReceiver],
+    ) -> Result<Option<String>, String> {
+        let next = match self.receiver_stream.take() {
+            Some((name, id)) => {
+                self.detach_sink(id);
+                receivers
+                    .iter()
+                    .position(|receiver| receiver.name == name)
+                    .map_or(0, |index| index + 1)
+            }
+            None => 0,
+        };
+        let Some(receiver) = receivers.get(next) else {
+            return Ok(None);
+        };
+        let id = self.send_to(receiver)?;
+        self.receiver_stream = Some((receiver.name.clone(), id));
+        Ok(Some(receiver.name.clone()))
+    }
+
+    /// Stream to a receiver found on the network, which needs a stream key; QUIC when both
+    /// ends have it, WHIP otherwise
+    pub fn send_to(&mut self, receiver: &DiscoveredReceiver) -> Result<SinkId, String> {
+        // Without whip or quic in the build there's no stream to refuse
+        #[cfg(any(feature = "whip", feature = "quic"))]
+        receiver.check_paired(self.stream_key.as_deref())?;
+        match receiver.quic_address().filter(|_| cfg!(feature = "quic")) {
+            Some(address) => self.start_quic(address),
+            None => {
+                let url = receiver
+                    .whip_url()
+                    .ok_or_else(|| format!("Receiver {} has no address", receiver.name))?;
+                self.start_whip(url, None)
+            }
+        }
+    }
+
+    /// Stop and remove an attached output
+    pub fn detach_sink(&mut self, id: SinkId) -> bool {
+        self.composite_sinks.remove(&id);
+        self.recordings.remove(&id);
+        self.sinks.detach(id)
+    }
+
+    /// Manager for all attached outputs
+    pub fn sinks(&self) -> &SinkManager {
+        &self.sinks
+    }
+
+    /// Redaction rules applied before any frame reaches an output; a static screen's frame
+    /// is masked again under changed rules on the next redraw
+    pub fn redactor_mut(&mut self) -> &mut Redactor {
+        self.redaction_stage.redactor_mut()
     }
 
     /// Get current window size for resize operations
@@ -67,3 +1891,98 @@ impl SafeMirror {
         self.gpu_renderer.size()
     }
 }
+
+/// Configures a SafeMirror in code instead of through config.toml, for library use:
+/// `SafeMirror::builder().display(1).fps(30).redaction(rules).letterbox(true).build(window)`
+/// Anything not set keeps its `Config` default
+#[derive(Default)]
+pub struct SafeMirrorBuilder {
+    config: Config,
+    display: Option<u32>,
+    screen_capture: Option<CrossPlatformScreenCapture>,
+}
+
+impl SafeMirror {
+    pub fn builder() -> SafeMirrorBuilder {
+        SafeMirrorBuilder::default()
+    }
+}
+
+impl SafeMirrorBuilder {
+    /// Start from a loaded config (e.g. `Config::load()`) instead of the defaults
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Capture this display instead of the primary one
+    pub fn display(mut self, display: u32) -> Self {
+        self.display = Some(display);
+        self
+    }
+
+    /// Capture at most `fps` frames per second instead of following the display's refresh
+    pub fn fps(mut self, fps: u32) -> Self {
+        self.config.frame_rate = Some(fps);
+        self
+    }
+
+    /// Regions (in capture pixels) masked at all times
+    pub fn redaction(mut self, rules: Vec<RedactionRect>) -> Self {
+        self.config.redactions = rules;
+        self
+    }
+
+    /// Fit the capture inside the window at its own aspect ratio instead of stretching it
+    pub fn letterbox(mut self, enabled: bool) -> Self {
+        self.config.letterbox = enabled;
+        self
+    }
+
+    /// Capture through this backend instead of the config's `capture_backend`; its event
+    /// callback is left as is, so the caller decides when the window redraws
+    pub fn screen_capture(mut self, screen_capture: CrossPlatformScreenCapture) -> Self {
+        self.screen_capture = Some(screen_capture);
+        self
+    }
+
+    /// Start the renderer and capture for `window`
+    /// Without `screen_capture`, new frames request a redraw of `window`
+    pub async fn build(self, window: Arc<Window>) -> Result<SafeMirror, String> {
+        let mut screen_capture = match self.screen_capture {
+            Some(screen_capture) => screen_capture,
+            None => {
+                let mut screen_capture = CrossPlatformScreenCapture::with_backend(
+                    self.config.capture_backend.as_deref(),
+                )?;
+                let redraw_window = window.clone();
+                screen_capture.set_event_callback(Arc::new(move |event| {
+                    if matches!(event, CaptureEvent::FrameReady) {
+                        redraw_window.request_redraw();
+                    }
+                }));
+                screen_capture
+            }
+        };
+
+        // Checked here because the capture starts inside SafeMirror::new
+        let source = self.display.map(CaptureSource::Display);
+        if let Some(source) = source {
+            if !screen_capture.capabilities().supports(&source) {
+                return Err(format!(
+                    "{:?} can't be captured on {:?}",
+                    source,
+                    screen_capture.platform()
+                ));
+            }
+            self.config.policy.check_source(&source, None)?;
+            screen_capture.set_source(source);
+        }
+
+        let mut mirror = SafeMirror::new(window, screen_capture, &self.config)
+            .await
+            .map_err(|e| e.to_string())?;
+        mirror.current_source = source;
+        Ok(mirror)
+    }
+}
@@ -0,0 +1,91 @@
+/// Downscale a tightly packed RGBA frame by an integer factor using a box filter
+/// Returns the scaled pixels and their dimensions; factor 1 returns a copy
+pub fn downscale_rgba(src: &[u8], width: u32, height: u32, factor: u32) -> (Vec<u8>, u32, u32) {
+    let factor = factor.max(1);
+    if factor == 1 {
+        return (src.to_vec(), width, height);
+    }
+
+    let out_width = (width / factor).max(1);
+    let out_height = (height / factor).max(1);
+    let samples = factor * factor;
+    let mut dst = vec![0u8; (out_width * out_height * 4) as usize];
+
+    for oy in 0..out_height {
+        for ox in 0..out_width {
+            // Average the factor x factor block of source pixels
+            let mut sum = [0u32; 4];
+            for dy in 0..factor {
+                let sy = (oy * factor + dy).min(height - 1);
+                let row = (sy * width) as usize;
+                for dx in 0..factor {
+                    let sx = (ox * factor + dx).min(width - 1);
+                    let si = (row + sx as usize) * 4;
+                    for c in 0..4 {
+                        sum[c] += src[si + c] as u32;
+                    }
+                }
+            }
+
+            let di = ((oy * out_width + ox) * 4) as usize;
+            for c in 0..4 {
+                dst[di + c] = (sum[c] / samples) as u8;
+            }
+        }
+    }
+
+    (dst, out_width, out_height)
+}
+
+/// Repack RGBA rows that are `bytes_per_row` apart (row padding, GPU copy alignment) into a
+/// tightly packed width*height*4 image; missing trailing rows are left out
+pub fn strip_row_padding(src: &[u8], width: u32, height: u32, bytes_per_row: u32) -> Vec<u8> {
+    let row_bytes = width as usize * 4;
+    let bytes_per_row = (bytes_per_row as usize).max(row_bytes);
+    let mut dst = Vec::with_capacity(row_bytes * height as usize);
+    for row in src.chunks(bytes_per_row).take(height as usize) {
+        if row.len() < row_bytes {
+            break;
+        }
+        dst.extend_from_slice(&row[..row_bytes]);
+    }
+    dst
+}
+
+/// Divide the color of premultiplied RGBA (or BGRA) pixels by their alpha, giving straight
+/// alpha; opaque pixels are left as they are and fully transparent ones stay black
+pub fn unpremultiply_alpha(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
+/// Copy a tightly packed RGBA image into a larger RGBA frame at (x, y), clipped to the frame
+pub fn blit_rgba(
+    dst: &mut [u8],
+    dst_width: u32,
+    dst_height: u32,
+    src: &[u8],
+    src_width: u32,
+    x: u32,
+    y: u32,
+) {
+    let copy_width = src_width.min(dst_width.saturating_sub(x)) as usize;
+    if copy_width == 0 {
+        return;
+    }
+    for (row, src_row) in src.chunks_exact(src_width as usize * 4).enumerate() {
+        let dy = y as usize + row;
+        if dy >= dst_height as usize {
+            break;
+        }
+        let start = (dy * dst_width as usize + x as usize) * 4;
+        dst[start..start + copy_width * 4].copy_from_slice(&src_row[..copy_width * 4]);
+    }
+}
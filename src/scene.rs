@@ -0,0 +1,231 @@
+use crate::text::TextStyle;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// What a layer shows
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerSource {
+    /// The cloaked capture (already redacted - raw frames never reach the scene)
+    #[default]
+    Capture,
+    /// A static image file (PNG or WebP), e.g. a logo, title card or "be right back" slide
+    Image(PathBuf),
+    /// A solid RGBA color
+    Color([u8; 4]),
+    /// The camera from the `webcam_device` config option, e.g. as a corner facecam
+    Webcam,
+    /// Styled text such as a lower-third or title; the layer height sets its size and the
+    /// width follows the text
+    Text(TextStyle),
+}
+
+/// Placement of a layer in the output, as fractions of the output size (top-left origin)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+}
+
+impl Default for Transform {
+    /// Fills the whole output
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// One source in the scene
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Layer {
+    pub name: String,
+    pub source: LayerSource,
+    #[serde(flatten)]
+    pub transform: Transform,
+    pub visible: bool,
+    /// Frames per second this layer updates at from a live source (capture, webcam), e.g. 5
+    /// for a thumbnail; None = as fast as the source delivers. A capture layer holds a copy
+    /// between updates, leaving the capture itself at full rate. Ignored for static sources
+    pub fps: Option<u32>,
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            source: LayerSource::Capture,
+            transform: Transform::default(),
+            visible: true,
+            fps: None,
+        }
+    }
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>, source: LayerSource) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            ..Self::default()
+        }
+    }
+}
+
+/// Ordered layers composited into the output, bottom first
+/// An empty scene shows just the capture, filling the output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scene {
+    layers: Vec<Layer>,
+}
+
+impl Scene {
+    pub fn new(layers: Vec<Layer>) -> Self {
+        Self { layers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Layers in draw order (bottom first)
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Add a layer on top of the others
+    pub fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    /// Remove a layer by name; returns it if it existed
+    pub fn remove(&mut self, name: &str) -> Option<Layer> {
+        let index = self.layers.iter().position(|layer| layer.name == name)?;
+        Some(self.layers.remove(index))
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// Whether any visible layer shows the webcam (the camera only runs while one does)
+    pub fn has_visible_webcam(&self) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| layer.visible && layer.source == LayerSource::Webcam)
+    }
+
+    /// Rate a live source needs to run at: the highest `fps` among the visible layers showing
+    /// it, or None if any of them is uncapped (or none shows it)
+    pub fn source_fps(&self, source: &LayerSource) -> Option<u32> {
+        let mut showing = self
+            .layers
+            .iter()
+            .filter(|layer| layer.visible && &layer.source == source)
+            .peekable();
+        showing.peek()?;
+        showing
+            .map(|layer| layer.fps.map(|fps| fps.max(1)))
+            .try_fold(0, |highest, fps| fps.map(|fps| highest.max(fps)))
+    }
+
+    /// Replace the text of a text layer by name; returns false if there is no such text layer
+    pub fn set_text(&mut self, name: &str, text: impl Into<String>) -> bool {
+        match self.layer_mut(name) {
+            Some(Layer {
+                source: LayerSource::Text(style),
+                ..
+            }) => {
+                style.text = text.into();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The nth image layer (0-based, bottom first); image layers are what the show/hide
+    /// hotkeys toggle
+    pub fn image_layer_mut(&mut self, index: usize) -> Option<&mut Layer> {
+        self.layers
+            .iter_mut()
+            .filter(|layer| matches!(layer.source, LayerSource::Image(_)))
+            .nth(index)
+    }
+}
+
+/// Decode a PNG or WebP file (by extension) into tightly packed RGBA bytes;
+/// returns (rgba, width, height)
+pub fn load_image(path: &Path) -> Result<(Vec<u8>, u32, u32), String> {
+    let is_webp = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("webp"));
+    if is_webp {
+        load_webp(path)
+    } else {
+        load_png(path)
+    }
+}
+
+fn load_webp(path: &Path) -> Result<(Vec<u8>, u32, u32), String> {
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let image = webp::Decoder::new(&data).decode().ok_or_else(|| {
+        format!(
+            "Failed to decode {} (animated WebP is not supported)",
+            path.display()
+        )
+    })?;
+
+    let rgba = if image.is_alpha() {
+        image.to_vec()
+    } else {
+        image
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect()
+    };
+    Ok((rgba, image.width(), image.height()))
+}
+
+fn load_png(path: &Path) -> Result<(Vec<u8>, u32, u32), String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut decoder = png::Decoder::new(file);
+    // Palette and low bit depths expand to 8 bits per channel
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+    let pixels = &buffer[..info.buffer_size()];
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(format!("Unexpanded palette image: {}", path.display()));
+        }
+    };
+    Ok((rgba, info.width, info.height))
+}
@@ -96,7 +96,7 @@ impl SCStreamOutputTrait for ScreenCaptureOutputHandler {
         output_type: SCStreamOutputType,
     ) {
         if matches!(output_type, SCStreamOutputType::Screen) {
-            if let Some(rgba_data) = convert_sample_buffer_to_rgba(&sample_buffer) {
+            if let Some((rgba_data, _, _)) = convert_sample_buffer_to_rgba(&sample_buffer) {
                 if let Ok(mut latest) = self.frame_data.lock() {
                     *latest = Some(rgba_data);
                 }
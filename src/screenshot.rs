@@ -0,0 +1,94 @@
+use crate::frame_bus::ProcessedFrame;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Image format for screenshots
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenshotFormat {
+    Png,
+    /// WebP, much smaller than PNG for large (5K) screens
+    Webp,
+}
+
+impl ScreenshotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Webp => "webp",
+        }
+    }
+}
+
+/// How screenshots are encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenshotOptions {
+    pub format: ScreenshotFormat,
+    /// WebP only: lossless encoding (ignores `quality`)
+    pub lossless: bool,
+    /// WebP lossy quality, 0-100
+    pub quality: f32,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        Self {
+            format: ScreenshotFormat::Png,
+            lossless: false,
+            quality: 90.0,
+        }
+    }
+}
+
+/// Encode a processed frame as an image file's bytes
+pub fn encode_screenshot(
+    frame: &ProcessedFrame,
+    options: &ScreenshotOptions,
+) -> Result<Vec<u8>, String> {
+    match options.format {
+        ScreenshotFormat::Png => encode_png(frame),
+        ScreenshotFormat::Webp => {
+            let encoder = webp::Encoder::from_rgba(frame.data(), frame.width(), frame.height());
+            let encoded = if options.lossless {
+                encoder.encode_lossless()
+            } else {
+                encoder.encode(options.quality.clamp(0.0, 100.0))
+            };
+            Ok(encoded.to_vec())
+        }
+    }
+}
+
+fn encode_png(frame: &ProcessedFrame) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, frame.width(), frame.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(frame.data()))
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(bytes)
+}
+
+/// Encode and write a screenshot to disk
+pub fn save_screenshot(
+    frame: &ProcessedFrame,
+    path: &Path,
+    options: &ScreenshotOptions,
+) -> Result<(), String> {
+    let bytes = encode_screenshot(frame, options)?;
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Default screenshot location: `~/Desktop/CloakShare <unix time>.<ext>`
+pub fn default_screenshot_path(format: ScreenshotFormat) -> PathBuf {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let directory = std::env::var("HOME")
+        .map(|home| PathBuf::from(home).join("Desktop"))
+        .unwrap_or_else(|_| PathBuf::from("."));
+    directory.join(format!("CloakShare {}.{}", timestamp, format.extension()))
+}
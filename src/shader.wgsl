@@ -101,6 +101,19 @@ var t_screen: texture_2d<f32>;
 @group(0) @binding(1)
 var s_screen: sampler;
 
+/// Capture sharpening - x: strength (0 = off, up to 1); yzw unused padding
+/// @group(0) @binding(2) is written by GpuRenderer::set_sharpen
+struct Sharpen {
+    params: vec4<f32>,
+}
+
+@group(0) @binding(2)
+var<uniform> sharpening: Sharpen;
+
+/// Set when the window surface is composited as premultiplied alpha: everything up to here
+/// is straight alpha, so the color has to be multiplied by alpha on the way out
+override premultiply_output: bool = false;
+
 // =============================================================================
 // FRAGMENT SHADER: Determines the color of each pixel
 // =============================================================================
@@ -120,7 +133,9 @@ var s_screen: sampler;
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
     // Sample color from the screen capture texture at the interpolated coordinates
     // This displays our uploaded texture data (currently test pattern, later real screen capture)
-    return textureSample(t_screen, s_screen, input.tex_coords);
+    // sharpen() (sharpen.wgsl) returns the plain sample when sharpening is off
+    let color = sharpen(t_screen, s_screen, input.tex_coords, sharpening.params.x);
+    return select(color, vec4<f32>(color.rgb * color.a, color.a), premultiply_output);
 }
 
 // =============================================================================
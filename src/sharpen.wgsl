@@ -0,0 +1,27 @@
+// Contrast-adaptive sharpening of the capture, shared by shader.wgsl and layer.wgsl
+//
+// Recovers text edges softened by downscaling (preview scale, capture scale, GPU texture
+// limits). Each pixel is pushed away from its four neighbours, less where the neighbourhood
+// already has high contrast, so strong edges don't ring and flat areas don't gain noise.
+// Only the capture is sharpened: overlays (images, text, webcam) are drawn as they are.
+
+fn sharpen(t: texture_2d<f32>, s: sampler, uv: vec2<f32>, strength: f32) -> vec4<f32> {
+    // Neighbours one capture pixel away (sampled even when off: sampling must stay uniform)
+    let texel = 1.0 / vec2<f32>(textureDimensions(t));
+    let center = textureSample(t, s, uv);
+    let up = textureSample(t, s, uv - vec2<f32>(0.0, texel.y)).rgb;
+    let down = textureSample(t, s, uv + vec2<f32>(0.0, texel.y)).rgb;
+    let left = textureSample(t, s, uv - vec2<f32>(texel.x, 0.0)).rgb;
+    let right = textureSample(t, s, uv + vec2<f32>(texel.x, 0.0)).rgb;
+
+    let lo = min(center.rgb, min(min(up, down), min(left, right)));
+    let hi = max(center.rgb, max(max(up, down), max(left, right)));
+    // 1 in low-contrast areas, falling towards 0 as the neighbourhood spans the full range
+    let headroom = min(lo, 1.0 - hi) / max(hi, vec3<f32>(0.0001));
+    let amount = sqrt(clamp(headroom, vec3<f32>(0.0), vec3<f32>(1.0)));
+    // Negative neighbour weight: -1/8 (subtle) to -1/5 (strongest)
+    let weight = -amount / mix(8.0, 5.0, clamp(strength, 0.0, 1.0));
+    let sharpened = (center.rgb + (up + down + left + right) * weight) / (1.0 + 4.0 * weight);
+    let result = vec4<f32>(clamp(sharpened, vec3<f32>(0.0), vec3<f32>(1.0)), center.a);
+    return select(center, result, strength > 0.0);
+}
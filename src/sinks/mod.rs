@@ -0,0 +1,374 @@
+pub mod pipe;
+#[cfg(feature = "quic")]
+pub mod quic;
+pub mod recording;
+pub mod socket;
+#[cfg(feature = "whip")]
+pub mod whip;
+
+use crate::encoding::EncodedPacket;
+use crate::frame_bus::{FramePolicy, FrameSubscriber, ProcessedFrame, ProcessedFrameBus};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long a sink thread waits for a frame before re-checking its stop flag
+const FRAME_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Default time without content changes before sinks are told the output is static
+pub const DEFAULT_STATIC_AFTER: Duration = Duration::from_secs(5);
+
+/// Window the encoded bitrate is averaged over
+const BITRATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// What a sink that encodes is producing, for diagnosing choppy remote viewing
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EncoderStats {
+    /// Encoded bitrate over the last second
+    pub bitrate_kbps: u32,
+    /// Time between the last two keyframes (None before the second one)
+    pub keyframe_interval: Option<Duration>,
+    /// Packets lost on the way to the receiver (network sinks only)
+    pub dropped_packets: u64,
+}
+
+/// Counters for one attached sink, updated by its thread
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SinkStats {
+    /// Frames handed to the sink, including repeats for paced sinks
+    pub frames: u64,
+    /// Frames the sink fell too far behind on and never got
+    pub skipped: u64,
+    /// Frames published that the sink hasn't taken yet
+    pub queue_depth: usize,
+    /// Encoder output, for sinks that encode
+    pub encoder: Option<EncoderStats>,
+}
+
+/// Tracks bitrate and keyframe spacing from the packets an encoder sink sends or writes
+#[derive(Debug)]
+pub struct EncoderStatsRecorder {
+    stats: EncoderStats,
+    window_start: Instant,
+    window_bytes: u64,
+    last_keyframe_pts_ms: Option<u64>,
+}
+
+impl Default for EncoderStatsRecorder {
+    fn default() -> Self {
+        Self {
+            stats: EncoderStats::default(),
+            window_start: Instant::now(),
+            window_bytes: 0,
+            last_keyframe_pts_ms: None,
+        }
+    }
+}
+
+impl EncoderStatsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one packet that left the encoder
+    pub fn record(&mut self, packet: &EncodedPacket) {
+        self.window_bytes += packet.data.len() as u64;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= BITRATE_WINDOW {
+            self.stats.bitrate_kbps =
+                (self.window_bytes * 8 / elapsed.as_millis().max(1) as u64) as u32;
+            self.window_start = Instant::now();
+            self.window_bytes = 0;
+        }
+        if packet.keyframe {
+            if let Some(last) = self.last_keyframe_pts_ms {
+                self.stats.keyframe_interval =
+                    Some(Duration::from_millis(packet.pts_ms.saturating_sub(last)));
+            }
+            self.last_keyframe_pts_ms = Some(packet.pts_ms);
+        }
+    }
+
+    /// Total packets lost in transit, as reported by the transport
+    pub fn set_dropped_packets(&mut self, dropped_packets: u64) {
+        self.stats.dropped_packets = dropped_packets;
+    }
+
+    pub fn stats(&self) -> EncoderStats {
+        self.stats
+    }
+}
+
+/// An output that consumes processed (redacted) frames
+/// Each attached sink runs on its own thread with its own subscription to the frame bus,
+/// so a slow recorder can't stall the on-screen mirror or a network stream
+pub trait FrameSink: Send {
+    /// Human-readable name for logs
+    fn name(&self) -> &str;
+
+    /// Whether frames (or anything derived from them) can end up in a file
+    /// Such sinks are refused in no-disk mode (see `SinkManager::set_no_disk`)
+    fn writes_to_disk(&self) -> bool {
+        false
+    }
+
+    /// What happens to frames this sink hasn't consumed yet when newer ones arrive
+    /// Live outputs want the newest frame; recordings may rather queue or hold up capture
+    fn frame_policy(&self) -> FramePolicy {
+        FramePolicy::DropOldest
+    }
+
+    /// Constant rate the sink must be fed at (virtual cameras, encoders), or None for new
+    /// frames only; when the source updates less often, the last frame is re-sent each tick
+    fn frame_rate(&self) -> Option<u32> {
+        None
+    }
+
+    /// Encoder output so far, for sinks that encode (reported in `SinkStats`)
+    fn encoder_stats(&self) -> Option<EncoderStats> {
+        None
+    }
+
+    /// Called on the sink thread before the first frame
+    fn start(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Handle one processed frame; returning an error detaches the sink
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String>;
+
+    /// Called on the sink thread when the content goes static or starts changing again
+    /// Encoders and streamers can drop to keyframe-only, ultra-low bitrate while static
+    fn set_content_static(&mut self, _is_static: bool) {}
+
+    /// Called on the sink thread after the last frame (flush files, close sockets, ...)
+    fn stop(&mut self) {}
+}
+
+/// Identifier for an attached sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SinkId(u64);
+
+impl SinkId {
+    /// Number identifying the sink in logs and the daemon's control protocol
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+struct SinkHandle {
+    name: String,
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<SinkStats>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// SinkManager: attaches and detaches frame sinks with independent lifecycles
+pub struct SinkManager {
+    bus: ProcessedFrameBus,
+    sinks: HashMap<SinkId, SinkHandle>,
+    next_id: u64,
+    /// Unchanged time after which sinks are told the content is static
+    static_after: Duration,
+    /// Refuse sinks that write to disk
+    no_disk: bool,
+}
+
+impl SinkManager {
+    pub fn new(bus: ProcessedFrameBus) -> Self {
+        Self {
+            bus,
+            sinks: HashMap::new(),
+            next_id: 0,
+            static_after: DEFAULT_STATIC_AFTER,
+            no_disk: false,
+        }
+    }
+
+    /// Change how long content must stay unchanged before it counts as static
+    /// Applies to sinks attached afterwards
+    pub fn set_static_after(&mut self, static_after: Duration) {
+        self.static_after = static_after;
+    }
+
+    /// "Frames never touch disk": refuse to attach sinks whose `writes_to_disk` is true
+    pub fn set_no_disk(&mut self, no_disk: bool) {
+        self.no_disk = no_disk;
+    }
+
+    /// Start a sink on its own thread, fed from the processed frame bus
+    pub fn attach(&mut self, sink: Box<dyn FrameSink>) -> Result<SinkId, String> {
+        let bus = self.bus.clone();
+        self.attach_to(&bus, sink)
+    }
+
+    /// Start a sink fed from a different bus (e.g. the composited mirror output)
+    pub fn attach_to(
+        &mut self,
+        bus: &ProcessedFrameBus,
+        sink: Box<dyn FrameSink>,
+    ) -> Result<SinkId, String> {
+        if self.no_disk && sink.writes_to_disk() {
+            return Err(format!(
+                "{} writes to disk, which no-disk mode doesn't allow",
+                sink.name()
+            ));
+        }
+        self.next_id += 1;
+        let id = SinkId(self.next_id);
+        let name = sink.name().to_string();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let subscriber = bus.subscribe_with_policy(sink.frame_policy());
+        let thread_stop = stop.clone();
+        let stats = Arc::new(Mutex::new(SinkStats::default()));
+        let thread_stats = stats.clone();
+        let static_after = self.static_after;
+        let thread = std::thread::Builder::new()
+            .name(format!("sink-{}", name))
+            .spawn(move || run_sink(sink, subscriber, thread_stop, thread_stats, static_after))
+            .map_err(|e| format!("Failed to spawn thread for sink {}: {}", name, e))?;
+
+        println!("Attached sink: {}", name);
+        self.sinks.insert(
+            id,
+            SinkHandle {
+                name,
+                stop,
+                stats,
+                thread: Some(thread),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stop a sink and wait for its thread to finish
+    pub fn detach(&mut self, id: SinkId) -> bool {
+        let Some(mut handle) = self.sinks.remove(&id) else {
+            return false;
+        };
+        handle.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = handle.thread.take() {
+            let _ = thread.join();
+        }
+        println!("Detached sink: {}", handle.name);
+        true
+    }
+
+    /// Whether a sink is still running (sinks stop themselves on error)
+    pub fn is_running(&self, id: SinkId) -> bool {
+        self.sinks
+            .get(&id)
+            .and_then(|handle| handle.thread.as_ref())
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Frame, queue and encoder counters of a sink (None if it isn't attached)
+    pub fn stats(&self, id: SinkId) -> Option<SinkStats> {
+        let handle = self.sinks.get(&id)?;
+        let stats = handle.stats.lock().unwrap_or_else(|e| e.into_inner());
+        Some(*stats)
+    }
+
+    /// Names of all attached sinks
+    pub fn sinks(&self) -> impl Iterator<Item = (SinkId, &str)> {
+        self.sinks
+            .iter()
+            .map(|(id, handle)| (*id, handle.name.as_str()))
+    }
+
+    /// Stop every attached sink
+    pub fn detach_all(&mut self) {
+        let ids: Vec<SinkId> = self.sinks.keys().copied().collect();
+        for id in ids {
+            self.detach(id);
+        }
+    }
+}
+
+impl Drop for SinkManager {
+    fn drop(&mut self) {
+        self.detach_all();
+    }
+}
+
+/// Sink thread body: wait for frames and hand them to the sink until stopped or failed
+fn run_sink(
+    mut sink: Box<dyn FrameSink>,
+    mut subscriber: FrameSubscriber,
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<SinkStats>>,
+    static_after: Duration,
+) {
+    if let Err(e) = sink.start() {
+        eprintln!("Sink {} failed to start: {}", sink.name(), e);
+        return;
+    }
+
+    // Paced sinks get the last frame again whenever a tick passes without a new one
+    let interval = sink
+        .frame_rate()
+        .filter(|&fps| fps > 0)
+        .map(|fps| Duration::from_secs(1) / fps);
+    let mut last_frame: Option<ProcessedFrame> = None;
+    let mut next_tick = Instant::now();
+
+    let mut is_static = false;
+    while !stop.load(Ordering::Relaxed) {
+        // Checked on every wakeup, including timeouts, since a static screen delivers no frames
+        let now_static = subscriber
+            .unchanged_for()
+            .is_some_and(|unchanged| unchanged >= static_after);
+        if now_static != is_static {
+            is_static = now_static;
+            sink.set_content_static(is_static);
+        }
+
+        let timeout = match (interval, &last_frame) {
+            (Some(_), Some(_)) => next_tick
+                .saturating_duration_since(Instant::now())
+                .min(FRAME_WAIT_TIMEOUT),
+            _ => FRAME_WAIT_TIMEOUT,
+        };
+        let frame = match subscriber.wait_next_frame(timeout) {
+            Some(frame) => frame,
+            None => match (interval, &last_frame) {
+                (Some(_), Some(last)) if Instant::now() >= next_tick => last.repeated(),
+                _ => continue,
+            },
+        };
+        if let Err(e) = sink.consume(&frame) {
+            eprintln!("Sink {} failed, detaching: {}", sink.name(), e);
+            break;
+        }
+        if let Ok(mut stats) = stats.lock() {
+            stats.frames += 1;
+            stats.skipped = subscriber.skipped();
+            stats.queue_depth = subscriber.pending();
+            stats.encoder = sink.encoder_stats();
+        }
+
+        if let Some(interval) = interval {
+            // Scheduled on absolute ticks so the cadence doesn't drift; after a stall
+            // (or a new frame) the schedule restarts from now instead of bursting to catch up
+            let now = Instant::now();
+            next_tick = if frame.is_repeat() && next_tick + interval > now {
+                next_tick + interval
+            } else {
+                now + interval
+            };
+            last_frame = Some(frame);
+        }
+    }
+
+    if subscriber.skipped() > 0 {
+        println!(
+            "Sink {} skipped {} frames it fell behind on",
+            sink.name(),
+            subscriber.skipped()
+        );
+    }
+    sink.stop();
+}
@@ -0,0 +1,232 @@
+use crate::encoding::yuv::rgba_to_i420;
+use crate::frame_bus::ProcessedFrame;
+use crate::sinks::FrameSink;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Frame format written to the pipe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeFormat {
+    /// YUV4MPEG2 (I420), self-describing: `cloakshare --pipe | ffmpeg -i - ...`
+    Y4m,
+    /// Raw RGBA frames; the reader must be told size and rate
+    Raw,
+}
+
+impl PipeFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "y4m" => Some(PipeFormat::Y4m),
+            "raw" | "rawvideo" => Some(PipeFormat::Raw),
+            _ => None,
+        }
+    }
+}
+
+/// Writes processed frames to a pipe (normally stdout) at a fixed rate
+/// The latest frame is repeated when the screen is static, so readers see a constant frame rate
+pub struct PipeSink {
+    format: PipeFormat,
+    fps: u32,
+    output: Option<File>,
+    /// stdout was redirected to a regular file rather than a pipe or terminal
+    to_file: bool,
+    latest: Arc<Mutex<Option<ProcessedFrame>>>,
+    stop: Arc<AtomicBool>,
+    /// Set by the writer thread when the reader went away or the stream can't continue
+    failed: Arc<AtomicBool>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl PipeSink {
+    pub fn new(output: File, format: PipeFormat, fps: u32) -> Self {
+        let to_file = output
+            .metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_file());
+        Self {
+            format,
+            fps: fps.max(1),
+            output: Some(output),
+            to_file,
+            latest: Arc::new(Mutex::new(None)),
+            stop: Arc::new(AtomicBool::new(false)),
+            failed: Arc::new(AtomicBool::new(false)),
+            writer: None,
+        }
+    }
+}
+
+impl FrameSink for PipeSink {
+    fn name(&self) -> &str {
+        "pipe"
+    }
+
+    fn writes_to_disk(&self) -> bool {
+        self.to_file
+    }
+
+    fn start(&mut self) -> Result<(), String> {
+        let output = self.output.take().ok_or("Pipe output already in use")?;
+        let writer = PipeWriter {
+            out: BufWriter::new(output),
+            format: self.format,
+            fps: self.fps,
+            size: None,
+        };
+        let latest = self.latest.clone();
+        let stop = self.stop.clone();
+        let failed = self.failed.clone();
+        let interval = Duration::from_secs(1) / self.fps;
+
+        let thread = std::thread::Builder::new()
+            .name("pipe-writer".to_string())
+            .spawn(move || {
+                if let Err(e) = writer.run(latest, stop, interval) {
+                    eprintln!("Pipe output stopped: {}", e);
+                    failed.store(true, Ordering::Relaxed);
+                }
+            })
+            .map_err(|e| format!("Failed to spawn pipe writer: {}", e))?;
+        self.writer = Some(thread);
+        Ok(())
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        if self.failed.load(Ordering::Relaxed) {
+            return Err("pipe reader went away".to_string());
+        }
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = Some(frame.clone());
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.writer.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Writer thread state
+struct PipeWriter {
+    out: BufWriter<File>,
+    format: PipeFormat,
+    fps: u32,
+    /// Stream size, fixed by the first frame
+    size: Option<(u32, u32)>,
+}
+
+impl PipeWriter {
+    /// Write the latest frame once per interval until stopped
+    fn run(
+        mut self,
+        latest: Arc<Mutex<Option<ProcessedFrame>>>,
+        stop: Arc<AtomicBool>,
+        interval: Duration,
+    ) -> Result<(), String> {
+        let mut next_tick = Instant::now();
+        while !stop.load(Ordering::Relaxed) {
+            let frame = latest.lock().ok().and_then(|latest| latest.clone());
+            if let Some(frame) = frame {
+                self.write_frame(&frame)?;
+            }
+
+            // Schedule against absolute ticks so the rate doesn't drift
+            next_tick += interval;
+            let now = Instant::now();
+            if next_tick > now {
+                std::thread::sleep(next_tick - now);
+            } else {
+                next_tick = now;
+            }
+        }
+        self.out.flush().map_err(|e| e.to_string())
+    }
+
+    fn write_frame(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        let size = (frame.width(), frame.height());
+        match self.size {
+            None => {
+                self.size = Some(size);
+                self.write_header(size)?;
+            }
+            Some(stream_size) if stream_size != size => {
+                return Err(format!(
+                    "frame size changed from {}x{} to {}x{}",
+                    stream_size.0, stream_size.1, size.0, size.1
+                ));
+            }
+            Some(_) => {}
+        }
+
+        let result = match self.format {
+            PipeFormat::Y4m => {
+                let yuv = rgba_to_i420(frame.data(), frame.width(), frame.height());
+                self.out
+                    .write_all(b"FRAME\n")
+                    .and_then(|_| self.out.write_all(&yuv.y))
+                    .and_then(|_| self.out.write_all(&yuv.u))
+                    .and_then(|_| self.out.write_all(&yuv.v))
+            }
+            PipeFormat::Raw => self.out.write_all(frame.data()),
+        };
+        result
+            .and_then(|_| self.out.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    fn write_header(&mut self, (width, height): (u32, u32)) -> Result<(), String> {
+        match self.format {
+            PipeFormat::Y4m => {
+                let header = format!(
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED\n",
+                    width, height, self.fps
+                );
+                self.out
+                    .write_all(header.as_bytes())
+                    .map_err(|e| e.to_string())
+            }
+            PipeFormat::Raw => {
+                // Raw video carries no header; tell the user how to read it
+                eprintln!(
+                    "Raw pipe: -f rawvideo -pix_fmt rgba -s {}x{} -r {}",
+                    width, height, self.fps
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Take over stdout for frame data and send everything else printed to stdout to stderr
+/// Logs use println!, so without this they would corrupt the frame stream
+#[cfg(unix)]
+pub fn take_stdout() -> Result<File, String> {
+    use std::os::fd::FromRawFd;
+
+    std::io::stdout()
+        .flush()
+        .map_err(|e| format!("Failed to flush stdout: {}", e))?;
+    unsafe {
+        let frames_fd = libc::dup(libc::STDOUT_FILENO);
+        if frames_fd < 0 {
+            return Err("Failed to duplicate stdout".to_string());
+        }
+        if libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            libc::close(frames_fd);
+            return Err("Failed to redirect stdout to stderr".to_string());
+        }
+        Ok(File::from_raw_fd(frames_fd))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn take_stdout() -> Result<File, String> {
+    Err("Pipe output is only supported on macOS and Linux".to_string())
+}
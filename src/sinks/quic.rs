@@ -0,0 +1,246 @@
+use crate::encoding::{Encoder, VideoCodec};
+use crate::frame_bus::ProcessedFrame;
+use crate::quic::{self, WireFrame};
+use crate::remote_input::{RemoteInputEvent, RemoteInputSender, RemoteSession};
+use crate::sinks::{EncoderStats, EncoderStatsRecorder, FrameSink};
+use bytes::Bytes;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+/// While the content is static, send only one keyframe per interval
+const STATIC_KEYFRAME_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to wait for the receiver to answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An established connection to a receiver
+struct QuicSession {
+    runtime: Runtime,
+    endpoint: quinn::Endpoint,
+    connection: quinn::Connection,
+}
+
+/// Streams processed frames as H.264 to a CloakShare receiver over QUIC
+/// Each frame goes out as unreliable datagrams with XOR parity, so a lost packet costs at most
+/// one frame instead of stalling the stream behind a retransmission; the receiver asks for a
+/// keyframe when it couldn't recover one
+pub struct QuicSink {
+    name: String,
+    address: String,
+    stream_key: Option<String>,
+    encoder: Box<dyn Encoder>,
+    session: Option<QuicSession>,
+    started: Option<Instant>,
+    next_frame_id: u32,
+    /// Set by the control stream task when the receiver lost a frame
+    keyframe_requested: Arc<AtomicBool>,
+    /// Where mouse and keyboard input from the receiver goes (`allow_remote_input`)
+    remote_input: Option<RemoteInputSender>,
+    is_static: bool,
+    last_static_keyframe: Option<Instant>,
+    /// Rate frames are fed at even when the source updates less often (the encoder's fps)
+    frame_rate: Option<u32>,
+    stats: EncoderStatsRecorder,
+}
+
+impl QuicSink {
+    /// `address` is the receiver's host:port; `encoder` must produce H.264
+    pub fn new(address: String, stream_key: Option<String>, encoder: Box<dyn Encoder>) -> Self {
+        Self {
+            name: format!("QUIC ({}, {})", encoder.name(), address),
+            address,
+            stream_key,
+            encoder,
+            session: None,
+            started: None,
+            next_frame_id: 0,
+            keyframe_requested: Arc::new(AtomicBool::new(false)),
+            remote_input: None,
+            is_static: false,
+            last_static_keyframe: None,
+            frame_rate: None,
+            stats: EncoderStatsRecorder::new(),
+        }
+    }
+
+    /// Keep feeding the encoder `fps` frames a second, repeating the last frame when the
+    /// source is slower, so rate control and players see a constant cadence
+    pub fn with_frame_rate(mut self, fps: u32) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    /// Accept the receiver's mouse and keyboard input (the user is asked before it's injected)
+    pub fn with_remote_input(mut self, remote_input: RemoteInputSender) -> Self {
+        self.remote_input = Some(remote_input);
+        self
+    }
+
+    fn connect(&self) -> Result<QuicSession, String> {
+        let address: SocketAddr = self
+            .address
+            .to_socket_addrs()
+            .map_err(|e| format!("Invalid receiver address {}: {}", self.address, e))?
+            .next()
+            .ok_or_else(|| format!("Receiver address {} didn't resolve", self.address))?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start QUIC runtime: {}", e))?;
+
+        let keyframe_requested = self.keyframe_requested.clone();
+        // Input is only taken from a receiver that proved it knows the stream key
+        let remote_input = self
+            .remote_input
+            .clone()
+            .filter(|_| self.stream_key.is_some());
+        let (endpoint, connection) = runtime.block_on(async {
+            let bind: SocketAddr = if address.is_ipv6() {
+                "[::]:0".parse()
+            } else {
+                "0.0.0.0:0".parse()
+            }
+            .map_err(|e| format!("Invalid bind address: {}", e))?;
+            let mut endpoint = quinn::Endpoint::client(bind)
+                .map_err(|e| format!("Failed to open QUIC socket: {}", e))?;
+            endpoint.set_default_client_config(quic::client_config()?);
+
+            let connecting = endpoint
+                .connect(address, quic::SERVER_NAME)
+                .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+            let connection = tokio::time::timeout(CONNECT_TIMEOUT, connecting)
+                .await
+                .map_err(|_| format!("Receiver {} didn't answer", address))?
+                .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+            let mut control =
+                quic::client_handshake(&connection, self.stream_key.as_deref()).await?;
+            let session = RemoteSession::authenticated(&self.address);
+
+            // The receiver writes one line per keyframe request or input event for as long as
+            // it's connected; one that won't end (or a broken stream) drops the connection
+            let reader_connection = connection.clone();
+            tokio::spawn(async move {
+                loop {
+                    let line = match quic::read_line(&mut control).await {
+                        Ok(line) => line,
+                        Err(e) => {
+                            eprintln!("{}; disconnecting from the receiver", e);
+                            reader_connection.close(0u32.into(), b"control stream failed");
+                            break;
+                        }
+                    };
+                    if line == quic::KEYFRAME_REQUEST {
+                        keyframe_requested.store(true, Ordering::Relaxed);
+                    } else if let Some(remote_input) = &remote_input
+                        && let Some(event) = RemoteInputEvent::parse_line(&line)
+                        && !remote_input.send(&session, event)
+                    {
+                        break;
+                    }
+                }
+            });
+            Ok::<_, String>((endpoint, connection))
+        })?;
+
+        Ok(QuicSession {
+            runtime,
+            endpoint,
+            connection,
+        })
+    }
+}
+
+impl FrameSink for QuicSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&mut self) -> Result<(), String> {
+        if self.encoder.codec() != VideoCodec::H264 {
+            return Err(format!(
+                "QUIC streaming needs an H.264 encoder, not {:?}",
+                self.encoder.codec()
+            ));
+        }
+        self.session = Some(self.connect()?);
+        println!("Streaming to {} over QUIC", self.address);
+        Ok(())
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.frame_rate
+    }
+
+    fn encoder_stats(&self) -> Option<EncoderStats> {
+        Some(self.stats.stats())
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        let mut force_keyframe = self.keyframe_requested.swap(false, Ordering::Relaxed);
+        // Static content: drop to keyframe-only at a very low rate
+        if self.is_static && !force_keyframe {
+            if self
+                .last_static_keyframe
+                .is_some_and(|last| last.elapsed() < STATIC_KEYFRAME_INTERVAL)
+            {
+                return Ok(());
+            }
+            self.last_static_keyframe = Some(Instant::now());
+            force_keyframe = true;
+        }
+
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let pts_ms = started.elapsed().as_millis() as u64;
+        let packets = self.encoder.encode(frame, pts_ms, force_keyframe)?;
+
+        let session = self
+            .session
+            .as_ref()
+            .ok_or("QUIC session was not started")?;
+        let max_datagram = session
+            .connection
+            .max_datagram_size()
+            .ok_or("Receiver doesn't accept QUIC datagrams")?;
+        for packet in packets {
+            self.stats.record(&packet);
+            let wire_frame = WireFrame {
+                id: self.next_frame_id,
+                pts_ms: packet.pts_ms,
+                keyframe: packet.keyframe,
+                data: packet.data,
+            };
+            self.next_frame_id = self.next_frame_id.wrapping_add(1);
+            for datagram in quic::shard_frame(&wire_frame, max_datagram) {
+                session
+                    .connection
+                    .send_datagram(Bytes::from(datagram))
+                    .map_err(|e| format!("Failed to send to {}: {}", self.address, e))?;
+            }
+        }
+        self.stats
+            .set_dropped_packets(session.connection.stats().path.lost_packets);
+        Ok(())
+    }
+
+    fn set_content_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+        self.last_static_keyframe = None;
+    }
+
+    fn stop(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        session.connection.close(0u32.into(), b"stopped");
+        // Let the close reach the receiver so it doesn't wait for a timeout
+        session.runtime.block_on(async {
+            let _ =
+                tokio::time::timeout(Duration::from_secs(1), session.endpoint.wait_idle()).await;
+        });
+        println!("Stopped streaming to {}", self.address);
+    }
+}
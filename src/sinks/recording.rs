@@ -0,0 +1,508 @@
+use crate::encoding::ivf::IvfWriter;
+use crate::encoding::mp4::{Mp4Writer, is_mp4_path};
+use crate::encoding::{
+    EncodedPacket, Encoder, EncoderSettings, VideoCodec, create_encoder, even_size,
+};
+use crate::frame_bus::{FramePolicy, ProcessedFrame};
+#[cfg(feature = "signed-recordings")]
+use crate::recording_manifest::ManifestRecorder;
+use crate::sinks::{EncoderStats, EncoderStatsRecorder, FrameSink};
+use crate::webvtt::{self, Cue};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// While the content is static, encode only one keyframe per interval
+const STATIC_KEYFRAME_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Output container, chosen from the codec (and the file extension for H.264)
+enum RecordingWriter {
+    /// AV1/VP9 in IVF
+    Ivf(IvfWriter<BufWriter<File>>),
+    /// H.264 Annex B elementary stream (packets already carry start codes)
+    AnnexB(BufWriter<File>),
+    /// H.264 in MP4 (.mp4, .m4v, .mov)
+    Mp4(Mp4Writer<BufWriter<File>>),
+}
+
+impl RecordingWriter {
+    fn create(path: &Path, codec: VideoCodec, width: u32, height: u32) -> Result<Self, String> {
+        if is_mp4_path(path) && codec != VideoCodec::H264 {
+            return Err(format!(
+                "{:?} can't be recorded to MP4 yet; use an .ivf file",
+                codec
+            ));
+        }
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let out = BufWriter::new(file);
+        match codec {
+            // OpenH264 drops an odd last column or row
+            VideoCodec::H264 if is_mp4_path(path) => Ok(RecordingWriter::Mp4(Mp4Writer::new(
+                out,
+                even_size(width),
+                even_size(height),
+            )?)),
+            VideoCodec::H264 => Ok(RecordingWriter::AnnexB(out)),
+            VideoCodec::Av1 | VideoCodec::Vp9 => Ok(RecordingWriter::Ivf(IvfWriter::new(
+                out, codec, width, height,
+            )?)),
+        }
+    }
+
+    fn write_packet(&mut self, packet: &EncodedPacket) -> Result<(), String> {
+        match self {
+            RecordingWriter::Ivf(writer) => writer.write_packet(packet),
+            RecordingWriter::Mp4(writer) => writer.write_packet(packet),
+            RecordingWriter::AnnexB(out) => out
+                .write_all(&packet.data)
+                .map_err(|e| format!("Failed to write H.264 stream: {}", e)),
+        }
+    }
+
+    /// Get everything written so far onto disk (before hashing it for the manifest)
+    #[cfg(feature = "signed-recordings")]
+    fn flush(&mut self) -> Result<(), String> {
+        match self {
+            RecordingWriter::Ivf(writer) => writer.flush(),
+            RecordingWriter::Mp4(writer) => writer.flush(),
+            RecordingWriter::AnnexB(out) => out
+                .flush()
+                .map_err(|e| format!("Failed to flush H.264 stream: {}", e)),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            RecordingWriter::Ivf(writer) => writer.finish().map(|_| ()),
+            RecordingWriter::Mp4(writer) => writer.finish().map(|_| ()),
+            RecordingWriter::AnnexB(mut out) => out
+                .flush()
+                .map_err(|e| format!("Failed to flush H.264 stream: {}", e)),
+        }
+    }
+}
+
+/// Timeline of what was masked, written as `<recording>.redactions.vtt`
+/// Cues give positions only, so reviewers can audit the masking without seeing the content
+#[derive(Debug, Default)]
+struct RedactionTimeline {
+    /// Description and start (ms) of the masking in effect since the last change
+    current: Option<(String, u64)>,
+    /// The sidecar was started; later cues are appended to it
+    started: bool,
+}
+
+impl RedactionTimeline {
+    /// Note what `frame`, shown from `pts_ms`, hides; returns the cue that ended, if any
+    fn observe(&mut self, frame: &ProcessedFrame, pts_ms: u64) -> Option<Cue> {
+        let text = describe_redactions(frame);
+        if self.current.as_ref().map(|(current, _)| current) == text.as_ref() {
+            return None;
+        }
+        let ended = self.close(pts_ms);
+        self.current = text.map(|text| (text, pts_ms));
+        ended
+    }
+
+    /// End the current cue at `end_ms`; None if nothing was masked
+    fn close(&mut self, end_ms: u64) -> Option<Cue> {
+        let (text, start_ms) = self.current.take()?;
+        Some(Cue {
+            start_ms,
+            end_ms,
+            text,
+        })
+    }
+}
+
+/// Cue text for what the redaction stage hid in `frame` (None = nothing)
+fn describe_redactions(frame: &ProcessedFrame) -> Option<String> {
+    if frame.is_blanked() {
+        return Some("Output blanked".to_string());
+    }
+    let regions = frame.masked_regions();
+    if regions.is_empty() {
+        return None;
+    }
+    let regions: Vec<String> = regions
+        .iter()
+        .map(|rect| format!("{}x{} at ({}, {})", rect.width, rect.height, rect.x, rect.y))
+        .collect();
+    Some(format!("Masked {}", regions.join(", ")))
+}
+
+/// State shared between a recording's sink thread and its `RecordingControl`s
+#[derive(Debug, Default)]
+struct RecordingShared {
+    paused: AtomicBool,
+    /// Chapters added since the sink last looked (None = untitled)
+    chapters: Mutex<Vec<Option<String>>>,
+}
+
+/// Controls a running recording from outside its sink thread (get one with
+/// `RecordingSink::control` before attaching the sink)
+#[derive(Debug, Clone, Default)]
+pub struct RecordingControl {
+    shared: Arc<RecordingShared>,
+}
+
+impl RecordingControl {
+    /// Stop or resume writing frames; the file stays one continuous recording without the gap
+    pub fn set_paused(&self, paused: bool) {
+        self.shared.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::Relaxed)
+    }
+
+    /// Start a chapter at the current point of the recording (untitled chapters are numbered),
+    /// listed in `<recording>.chapters.vtt` for navigating long recordings later
+    pub fn add_chapter(&self, title: Option<String>) {
+        if let Ok(mut chapters) = self.shared.chapters.lock() {
+            chapters.push(title);
+        }
+    }
+}
+
+/// Records processed frames to a file (IVF, MP4 or raw H.264) using any Encoder backend
+pub struct RecordingSink {
+    name: String,
+    path: PathBuf,
+    encoder: Box<dyn Encoder>,
+    writer: Option<RecordingWriter>,
+    started: Option<Instant>,
+    is_static: bool,
+    last_static_keyframe: Option<Instant>,
+    /// Whether frames the encoder can't keep up with are skipped, queued or hold up capture
+    frame_policy: FramePolicy,
+    /// Rate frames are fed at even when the source updates less often (the encoder's fps)
+    frame_rate: Option<u32>,
+    stats: EncoderStatsRecorder,
+    control: RecordingControl,
+    /// When the recording was seen paused, until it resumes
+    paused_since: Option<Instant>,
+    /// Time spent paused, left out of the timestamps
+    paused_total: Duration,
+    /// Chapter start times (ms) and titles, in order
+    chapters: Vec<(u64, String)>,
+    /// When and where content was masked (`recording_redaction_log`)
+    redaction_log: Option<RedactionTimeline>,
+    /// Signed manifest kept next to the file
+    #[cfg(feature = "signed-recordings")]
+    manifest: Option<ManifestRecorder>,
+}
+
+impl RecordingSink {
+    pub fn new(path: impl Into<PathBuf>, encoder: Box<dyn Encoder>) -> Self {
+        let path = path.into();
+        Self {
+            name: format!("recording ({}, {})", encoder.name(), path.display()),
+            path,
+            encoder,
+            writer: None,
+            started: None,
+            is_static: false,
+            last_static_keyframe: None,
+            frame_policy: FramePolicy::DropOldest,
+            frame_rate: None,
+            stats: EncoderStatsRecorder::new(),
+            control: RecordingControl::default(),
+            paused_since: None,
+            paused_total: Duration::ZERO,
+            chapters: Vec::new(),
+            redaction_log: None,
+            #[cfg(feature = "signed-recordings")]
+            manifest: None,
+        }
+    }
+
+    /// Record `width`x`height` frames with the best available encoder for `codec`, signing the
+    /// file when `signing_key` is given (created on first use)
+    pub fn create(
+        path: PathBuf,
+        codec: VideoCodec,
+        width: u32,
+        height: u32,
+        signing_key: Option<&Path>,
+    ) -> Result<Self, String> {
+        #[cfg(not(feature = "signed-recordings"))]
+        if signing_key.is_some() {
+            // Recording unsigned when signatures are expected would defeat the point
+            return Err(
+                "Signed recordings aren't in this build (enable the signed-recordings cargo feature)"
+                    .to_string(),
+            );
+        }
+
+        let settings = EncoderSettings::new(width, height);
+        let encoder = create_encoder(codec, &settings)?;
+        let sink = Self::new(path.clone(), encoder).with_frame_rate(settings.fps);
+
+        #[cfg(feature = "signed-recordings")]
+        let sink = if let Some(key) = signing_key {
+            let signer = crate::recording_manifest::ManifestSigner::load_or_create(key)?;
+            sink.with_manifest(ManifestRecorder::new(path, codec, signer))
+        } else {
+            sink
+        };
+        Ok(sink)
+    }
+
+    pub fn with_frame_policy(mut self, frame_policy: FramePolicy) -> Self {
+        self.frame_policy = frame_policy;
+        self
+    }
+
+    /// Keep feeding the encoder `fps` frames a second, repeating the last frame when the
+    /// source is slower, so rate control and players see a constant cadence
+    pub fn with_frame_rate(mut self, fps: u32) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    /// Hash the file as it's written and keep a signed manifest next to it
+    #[cfg(feature = "signed-recordings")]
+    pub fn with_manifest(mut self, manifest: ManifestRecorder) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Also write `<recording>.redactions.vtt`, a WebVTT timeline of when and where the
+    /// output was masked or blanked
+    pub fn with_redaction_log(mut self, enabled: bool) -> Self {
+        self.redaction_log = enabled.then(RedactionTimeline::default);
+        self
+    }
+
+    /// Pause, resume (and so on) the recording while it runs on the sink thread
+    pub fn control(&self) -> RecordingControl {
+        self.control.clone()
+    }
+
+    /// Milliseconds recorded since the first frame, not counting time spent paused
+    fn elapsed_ms(&self) -> u64 {
+        self.started
+            .map(|started| {
+                started
+                    .elapsed()
+                    .saturating_sub(self.paused_total)
+                    .as_millis() as u64
+            })
+            .unwrap_or_default()
+    }
+
+    /// Place chapters added since the last frame at the current timestamp and rewrite the
+    /// sidecar, so the markers survive a recording that never finishes
+    fn take_chapters(&mut self) {
+        let pending = match self.control.shared.chapters.lock() {
+            Ok(mut chapters) => std::mem::take(&mut *chapters),
+            Err(_) => return,
+        };
+        if pending.is_empty() {
+            return;
+        }
+        let start_ms = self.elapsed_ms();
+        for title in pending {
+            let title = title.unwrap_or_else(|| format!("Chapter {}", self.chapters.len() + 1));
+            println!(
+                "Chapter \"{}\" at {} in {}",
+                title,
+                webvtt::timestamp(start_ms),
+                self.path.display()
+            );
+            self.chapters.push((start_ms, title));
+        }
+        self.write_chapters(start_ms);
+    }
+
+    /// Write the chapters as WebVTT cues, each running until the next one starts and the last
+    /// until `end_ms`
+    fn write_chapters(&self, end_ms: u64) {
+        let cues: Vec<Cue> = self
+            .chapters
+            .iter()
+            .enumerate()
+            .map(|(index, (start_ms, title))| Cue {
+                start_ms: *start_ms,
+                end_ms: self
+                    .chapters
+                    .get(index + 1)
+                    .map_or(end_ms, |(next_ms, _)| *next_ms),
+                text: title.clone(),
+            })
+            .collect();
+        if let Err(e) = webvtt::write(&webvtt::sidecar_path(&self.path, "chapters"), &cues) {
+            eprintln!("Failed to save chapters: {}", e);
+        }
+    }
+
+    /// Add a finished cue to the redaction log, so writing it stays cheap however long the
+    /// recording runs
+    fn write_redaction_cue(&mut self, cue: Cue) {
+        let Some(log) = &mut self.redaction_log else {
+            return;
+        };
+        let path = webvtt::sidecar_path(&self.path, "redactions");
+        // The first cue replaces any log left at this path by an earlier recording
+        let result = if log.started {
+            webvtt::append(&path, &cue)
+        } else {
+            webvtt::write(&path, &[cue])
+        };
+        match result {
+            Ok(()) => log.started = true,
+            Err(e) => eprintln!("Failed to save the redaction log: {}", e),
+        }
+    }
+
+    fn write_packets(&mut self, packets: Vec<EncodedPacket>) -> Result<(), String> {
+        let writer = self.writer.as_mut().ok_or("Recording was not started")?;
+        for packet in &packets {
+            self.stats.record(packet);
+            writer.write_packet(packet)?;
+        }
+        Ok(())
+    }
+}
+
+impl FrameSink for RecordingSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn writes_to_disk(&self) -> bool {
+        true
+    }
+
+    fn frame_policy(&self) -> FramePolicy {
+        self.frame_policy
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.frame_rate
+    }
+
+    fn encoder_stats(&self) -> Option<EncoderStats> {
+        Some(self.stats.stats())
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        if self.writer.is_none() {
+            // The file is opened on the first frame, once the output size is known
+            self.writer = Some(RecordingWriter::create(
+                &self.path,
+                self.encoder.codec(),
+                frame.width(),
+                frame.height(),
+            )?);
+            self.started = Some(Instant::now());
+        }
+
+        // Paused: drop frames, then close the gap in the timestamps so playback runs straight
+        // from the last frame before the pause to the first one after it
+        if self.control.is_paused() {
+            if self.paused_since.is_none() {
+                self.paused_since = Some(Instant::now());
+                println!("Recording {} paused", self.path.display());
+            }
+            return Ok(());
+        }
+        let mut force_keyframe = false;
+        if let Some(since) = self.paused_since.take() {
+            self.paused_total += since.elapsed();
+            // Start the resumed part on a keyframe, like a new segment
+            force_keyframe = true;
+            println!("Recording {} resumed", self.path.display());
+        }
+        // Chapters added while paused start where the recording resumed
+        self.take_chapters();
+
+        // Static content: drop to keyframe-only at a very low rate
+        if self.is_static && !force_keyframe {
+            if self
+                .last_static_keyframe
+                .is_some_and(|last| last.elapsed() < STATIC_KEYFRAME_INTERVAL)
+            {
+                return Ok(());
+            }
+            self.last_static_keyframe = Some(Instant::now());
+            force_keyframe = true;
+        }
+
+        let pts_ms = self.elapsed_ms();
+        let packets = self.encoder.encode(frame, pts_ms, force_keyframe)?;
+        self.write_packets(packets)?;
+        if let Some(cue) = self
+            .redaction_log
+            .as_mut()
+            .and_then(|log| log.observe(frame, pts_ms))
+        {
+            self.write_redaction_cue(cue);
+        }
+
+        #[cfg(feature = "signed-recordings")]
+        if let Some(manifest) = &mut self.manifest
+            && manifest.is_due()
+        {
+            if let Some(writer) = &mut self.writer {
+                writer.flush()?;
+            }
+            manifest.add_segment(pts_ms)?;
+        }
+        Ok(())
+    }
+
+    fn set_content_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+        self.last_static_keyframe = None;
+    }
+
+    fn stop(&mut self) {
+        if self.writer.is_none() {
+            return;
+        }
+        let result = self
+            .encoder
+            .finish()
+            .and_then(|packets| self.write_packets(packets))
+            .and_then(|_| {
+                self.writer
+                    .take()
+                    .ok_or("Recording was not started".to_string())
+            })
+            .and_then(|writer| writer.finish());
+        match result {
+            Ok(_) => println!("Recording saved to {}", self.path.display()),
+            Err(e) => eprintln!("Failed to finish recording {}: {}", self.path.display(), e),
+        }
+
+        // A recording stopped while paused ends at its last frame
+        if let Some(since) = self.paused_since.take() {
+            self.paused_total += since.elapsed();
+        }
+        self.take_chapters();
+        let end_ms = self.elapsed_ms();
+        if !self.chapters.is_empty() {
+            self.write_chapters(end_ms);
+        }
+        if let Some(cue) = self
+            .redaction_log
+            .as_mut()
+            .and_then(|log| log.close(end_ms))
+        {
+            self.write_redaction_cue(cue);
+        }
+        #[cfg(feature = "signed-recordings")]
+        if let Some(manifest) = &mut self.manifest {
+            match manifest.finish(end_ms) {
+                Ok(path) => println!("Signed manifest saved to {}", path.display()),
+                Err(e) => eprintln!("Failed to sign recording {}: {}", self.path.display(), e),
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::frame_bus::ProcessedFrame;
+use crate::sinks::FrameSink;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Give up on a client that can't take a frame within this time
+#[cfg(unix)]
+const CLIENT_WRITE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// JSON header sent before every frame's pixels
+#[derive(Debug, Serialize)]
+struct FrameMetadata {
+    sequence: u64,
+    width: u32,
+    height: u32,
+    /// Pixel layout of the payload
+    format: &'static str,
+    content_hash: u64,
+    /// Milliseconds since the Unix epoch when the frame was sent
+    timestamp_ms: u64,
+}
+
+/// Streams processed frames to local companion processes over a Unix socket
+///
+/// Each message is: u32 LE metadata length, metadata JSON, u32 LE payload length, RGBA payload.
+/// The socket is created with owner-only permissions so other users can't read the feed.
+pub struct SocketSink {
+    path: PathBuf,
+    #[cfg(unix)]
+    listener: Option<std::os::unix::net::UnixListener>,
+    #[cfg(unix)]
+    clients: Vec<std::os::unix::net::UnixStream>,
+}
+
+impl SocketSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            #[cfg(unix)]
+            listener: None,
+            #[cfg(unix)]
+            clients: Vec::new(),
+        }
+    }
+
+    /// Serialize one frame into a wire message
+    fn encode_message(frame: &ProcessedFrame) -> Result<Vec<u8>, String> {
+        let metadata = FrameMetadata {
+            sequence: frame.sequence(),
+            width: frame.width(),
+            height: frame.height(),
+            format: "rgba",
+            content_hash: frame.content_hash(),
+            timestamp_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+        };
+        let json = serde_json::to_vec(&metadata)
+            .map_err(|e| format!("Failed to encode frame metadata: {}", e))?;
+
+        let mut message = Vec::with_capacity(8 + json.len() + frame.data().len());
+        message.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        message.extend_from_slice(&json);
+        message.extend_from_slice(&(frame.data().len() as u32).to_le_bytes());
+        message.extend_from_slice(frame.data());
+        Ok(message)
+    }
+}
+
+#[cfg(unix)]
+impl FrameSink for SocketSink {
+    fn name(&self) -> &str {
+        "socket"
+    }
+
+    fn start(&mut self) -> Result<(), String> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+        use std::os::unix::net::UnixListener;
+
+        // A socket file left over from a previous run would make bind fail; anything else at
+        // the path isn't ours to delete
+        if let Ok(metadata) = std::fs::symlink_metadata(&self.path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!(
+                    "{} already exists and isn't a socket",
+                    self.path.display()
+                ));
+            }
+            std::fs::remove_file(&self.path)
+                .map_err(|e| format!("Failed to remove stale {}: {}", self.path.display(), e))?;
+        }
+
+        let listener = UnixListener::bind(&self.path)
+            .map_err(|e| format!("Failed to bind {}: {}", self.path.display(), e))?;
+        std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict {}: {}", self.path.display(), e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to configure {}: {}", self.path.display(), e))?;
+
+        println!("Frame socket listening on {}", self.path.display());
+        self.listener = Some(listener);
+        Ok(())
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        use std::io::Write;
+
+        // Pick up clients that connected since the last frame
+        if let Some(listener) = &self.listener {
+            while let Ok((client, _)) = listener.accept() {
+                if client.set_nonblocking(false).is_ok()
+                    && client.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)).is_ok()
+                {
+                    println!("Frame socket client connected");
+                    self.clients.push(client);
+                }
+            }
+        }
+
+        if self.clients.is_empty() {
+            return Ok(());
+        }
+
+        let message = Self::encode_message(frame)?;
+        self.clients
+            .retain_mut(|client| match client.write_all(&message) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("Frame socket client disconnected: {}", e);
+                    false
+                }
+            });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.clients.clear();
+        // Only a socket this sink bound is removed
+        if self.listener.take().is_some() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl FrameSink for SocketSink {
+    fn name(&self) -> &str {
+        "socket"
+    }
+
+    fn start(&mut self) -> Result<(), String> {
+        Err(format!(
+            "Frame socket {} not supported: named pipe transport not implemented yet",
+            self.path.display()
+        ))
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        Self::encode_message(frame).map(|_| ())
+    }
+}
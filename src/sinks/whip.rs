@@ -0,0 +1,432 @@
+use crate::encoding::adaptive::{AdaptiveBitrate, NetworkFeedback};
+use crate::encoding::{Encoder, EncoderSettings, VideoCodec, create_encoder};
+use crate::frame_bus::ProcessedFrame;
+use crate::scaling::{downscale_rgba, strip_row_padding};
+use crate::sinks::{EncoderStats, EncoderStatsRecorder, FrameSink};
+use crate::stream_auth;
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use webrtc::api::APIBuilder;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MIME_TYPE_H264, MediaEngine};
+use webrtc::interceptor::registry::Registry;
+use webrtc::media::Sample;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::stats::StatsReportType;
+use webrtc::track::track_local::TrackLocal;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+/// While the content is static, send only one keyframe per interval
+const STATIC_KEYFRAME_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Frame duration reported for the first sample, before there's a previous timestamp
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(33);
+
+/// How often RTCP statistics are read for packet loss and adaptive bitrate
+const FEEDBACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An established WHIP session
+struct WhipSession {
+    runtime: Runtime,
+    peer_connection: Arc<RTCPeerConnection>,
+    track: Arc<TrackLocalStaticSample>,
+    /// WHIP resource URL from the server's Location header, deleted to end the session
+    resource: Option<String>,
+    /// Stream key proof for deleting the resource
+    hangup_tag: Option<String>,
+}
+
+/// Adaptive bitrate state for a WHIP stream
+struct AdaptiveStream {
+    controller: AdaptiveBitrate,
+    /// Full-quality settings; targets scale the bitrate and size down from these
+    settings: EncoderSettings,
+    /// Sequence of the last consumed frame, to notice frames skipped while sending was slow
+    last_sequence: Option<u64>,
+    /// Whether sending the previous frame took longer than a frame interval
+    send_was_slow: bool,
+    skipped_frames: u64,
+}
+
+/// Streams processed frames as H.264 over WebRTC to a WHIP ingest endpoint
+/// (Cloudflare Stream, LiveKit, MediaMTX, ...)
+pub struct WhipSink {
+    name: String,
+    url: String,
+    token: Option<String>,
+    /// Key shared with a CloakShare receiver; both ends prove they know it
+    stream_key: Option<String>,
+    encoder: Box<dyn Encoder>,
+    session: Option<WhipSession>,
+    started: Option<Instant>,
+    last_pts_ms: Option<u64>,
+    is_static: bool,
+    last_static_keyframe: Option<Instant>,
+    adaptive: Option<AdaptiveStream>,
+    /// When RTCP statistics were last read
+    last_report: Instant,
+    /// Rate frames are fed at even when the source updates less often (the encoder's fps)
+    frame_rate: Option<u32>,
+    stats: EncoderStatsRecorder,
+}
+
+impl WhipSink {
+    /// `encoder` must produce H.264, the codec every WHIP server accepts
+    pub fn new(
+        url: String,
+        token: Option<String>,
+        stream_key: Option<String>,
+        encoder: Box<dyn Encoder>,
+    ) -> Self {
+        Self {
+            name: format!("WHIP ({}, {})", encoder.name(), url),
+            url,
+            token,
+            stream_key,
+            encoder,
+            session: None,
+            started: None,
+            last_pts_ms: None,
+            is_static: false,
+            last_static_keyframe: None,
+            adaptive: None,
+            last_report: Instant::now(),
+            frame_rate: None,
+            stats: EncoderStatsRecorder::new(),
+        }
+    }
+
+    /// Keep feeding the encoder `fps` frames a second, repeating the last frame when the
+    /// source is slower, so rate control and players see a constant cadence
+    pub fn with_frame_rate(mut self, fps: u32) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    /// Adapt bitrate and resolution to RTCP loss/RTT and send backpressure, starting from
+    /// (and never exceeding) `settings`, which must match the encoder
+    pub fn with_adaptive_bitrate(mut self, settings: EncoderSettings) -> Self {
+        self.adaptive = Some(AdaptiveStream {
+            controller: AdaptiveBitrate::new(settings.bitrate_kbps),
+            settings,
+            last_sequence: None,
+            send_was_slow: false,
+            skipped_frames: 0,
+        });
+        self
+    }
+
+    /// Read loss and RTT from the receiver's RTCP reports into the stats and, with adaptive
+    /// bitrate, switch encoders when the controller picks a new target (the new encoder
+    /// starts with a keyframe)
+    fn adapt(&mut self) -> Result<(), String> {
+        let Some(session) = &self.session else {
+            return Ok(());
+        };
+        if self.last_report.elapsed() < FEEDBACK_INTERVAL {
+            return Ok(());
+        }
+        self.last_report = Instant::now();
+
+        let mut feedback = NetworkFeedback::default();
+        let stats = session
+            .runtime
+            .block_on(session.peer_connection.get_stats());
+        for report in stats.reports.values() {
+            if let StatsReportType::RemoteInboundRTP(remote) = report {
+                feedback.rtt = remote.round_trip_time.map(Duration::from_secs_f64);
+                feedback.loss = remote.fraction_lost as f32;
+                self.stats
+                    .set_dropped_packets(remote.packets_lost.max(0) as u64);
+            }
+        }
+
+        let Some(adaptive) = &mut self.adaptive else {
+            return Ok(());
+        };
+        feedback.skipped_frames = std::mem::take(&mut adaptive.skipped_frames);
+
+        let Some(target) = adaptive.controller.update(feedback) else {
+            return Ok(());
+        };
+        let mut settings = adaptive.settings.clone();
+        settings.bitrate_kbps = target.bitrate_kbps;
+        settings.width = scaled_even(settings.width, target.downscale);
+        settings.height = scaled_even(settings.height, target.downscale);
+        self.encoder = create_encoder(VideoCodec::H264, &settings)?;
+        println!(
+            "WHIP stream to {} now {} kbps at {}x{}",
+            self.url, settings.bitrate_kbps, settings.width, settings.height
+        );
+        Ok(())
+    }
+
+    /// Offer a send-only H.264 track, POST it to the endpoint and apply the answer
+    fn connect(&self) -> Result<WhipSession, String> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start WebRTC runtime: {}", e))?;
+
+        let (peer_connection, track, offer) = runtime
+            .block_on(async {
+                let mut media_engine = MediaEngine::default();
+                media_engine.register_default_codecs()?;
+                let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+                let api = APIBuilder::new()
+                    .with_media_engine(media_engine)
+                    .with_interceptor_registry(registry)
+                    .build();
+                let peer_connection =
+                    Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
+
+                let track = Arc::new(TrackLocalStaticSample::new(
+                    RTCRtpCodecCapability {
+                        mime_type: MIME_TYPE_H264.to_owned(),
+                        ..Default::default()
+                    },
+                    "video".to_owned(),
+                    "cloakshare".to_owned(),
+                ));
+                peer_connection
+                    .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+                    .await?;
+
+                // WHIP has no trickle by default: send the offer once every candidate is in it
+                let offer = peer_connection.create_offer(None).await?;
+                let mut gathered = peer_connection.gathering_complete_promise().await;
+                peer_connection.set_local_description(offer).await?;
+                let _ = gathered.recv().await;
+                let offer = peer_connection
+                    .local_description()
+                    .await
+                    .map(|description| description.sdp)
+                    .unwrap_or_default();
+                Ok::<_, webrtc::Error>((peer_connection, track, offer))
+            })
+            .map_err(|e| format!("Failed to create WebRTC offer: {}", e))?;
+
+        let mut request = ureq::post(&self.url).set("Content-Type", "application/sdp");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        if let Some(key) = &self.stream_key {
+            request = request.set(
+                stream_auth::AUTH_HEADER,
+                &stream_auth::offer_tag(key, &offer)?,
+            );
+        }
+        let response = request
+            .send_string(&offer)
+            .map_err(|e| format!("WHIP endpoint {} refused the offer: {}", self.url, e))?;
+        let resource = response
+            .header("Location")
+            .map(|location| resolve_location(&self.url, location));
+        let answer_proof = response
+            .header(stream_auth::AUTH_HEADER)
+            .map(str::to_string);
+        let answer = response
+            .into_string()
+            .map_err(|e| format!("Failed to read WHIP answer: {}", e))?;
+
+        // Without the proof, whoever answered could be relaying the stream to themselves
+        if let Some(key) = &self.stream_key
+            && !stream_auth::matches(
+                &stream_auth::answer_tag(key, &offer, &answer),
+                answer_proof.as_deref(),
+            )
+        {
+            return Err(format!(
+                "{} didn't prove it knows the stream key; not streaming to it",
+                self.url
+            ));
+        }
+        let hangup_tag = self
+            .stream_key
+            .as_ref()
+            .map(|key| stream_auth::hangup_tag(key, &answer));
+
+        runtime
+            .block_on(async {
+                let answer = RTCSessionDescription::answer(answer)?;
+                peer_connection.set_remote_description(answer).await
+            })
+            .map_err(|e| format!("Invalid WHIP answer: {}", e))?;
+
+        Ok(WhipSession {
+            runtime,
+            peer_connection,
+            track,
+            resource,
+            hangup_tag,
+        })
+    }
+}
+
+/// `size / downscale`, rounded down to the even sizes H.264's 4:2:0 chroma needs
+fn scaled_even(size: u32, downscale: u32) -> u32 {
+    ((size / downscale.max(1)).max(2)) & !1
+}
+
+/// Downscale a frame for a reduced-resolution stream
+fn downscale_frame(frame: &ProcessedFrame, downscale: u32) -> ProcessedFrame {
+    let (data, width, height) =
+        downscale_rgba(frame.data(), frame.width(), frame.height(), downscale);
+    let (even_width, even_height) = (width & !1, height & !1);
+    let data = if (even_width, even_height) == (width, height) {
+        data
+    } else {
+        strip_row_padding(&data, even_width, even_height, width * 4)
+    };
+    ProcessedFrame::new(data, even_width, even_height).with_capture_info(frame.captured_at(), None)
+}
+
+/// The Location header may be relative to the endpoint URL
+fn resolve_location(endpoint: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    // scheme://host is everything before the first '/' after "://"
+    let host_end = endpoint
+        .find("://")
+        .and_then(|scheme_end| {
+            endpoint[scheme_end + 3..]
+                .find('/')
+                .map(|path| scheme_end + 3 + path)
+        })
+        .unwrap_or(endpoint.len());
+    if location.starts_with('/') {
+        format!("{}{}", &endpoint[..host_end], location)
+    } else {
+        let base = endpoint.rfind('/').filter(|&slash| slash >= host_end);
+        format!("{}/{}", &endpoint[..base.unwrap_or(host_end)], location)
+    }
+}
+
+impl FrameSink for WhipSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&mut self) -> Result<(), String> {
+        if self.encoder.codec() != VideoCodec::H264 {
+            return Err(format!(
+                "WHIP needs an H.264 encoder, not {:?}",
+                self.encoder.codec()
+            ));
+        }
+        self.session = Some(self.connect()?);
+        println!("Streaming to WHIP endpoint {}", self.url);
+        Ok(())
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.frame_rate
+    }
+
+    fn encoder_stats(&self) -> Option<EncoderStats> {
+        Some(self.stats.stats())
+    }
+
+    fn consume(&mut self, frame: &ProcessedFrame) -> Result<(), String> {
+        // Static content: drop to keyframe-only at a very low rate
+        let mut force_keyframe = false;
+        if self.is_static {
+            if self
+                .last_static_keyframe
+                .is_some_and(|last| last.elapsed() < STATIC_KEYFRAME_INTERVAL)
+            {
+                return Ok(());
+            }
+            self.last_static_keyframe = Some(Instant::now());
+            force_keyframe = true;
+        }
+
+        // Frames the bus replaced while the previous one was still being sent count as
+        // backpressure; skips caused by a slow encoder don't, a lower bitrate wouldn't help
+        if let Some(adaptive) = &mut self.adaptive {
+            if adaptive.send_was_slow
+                && let Some(last) = adaptive.last_sequence
+            {
+                adaptive.skipped_frames += frame.sequence().saturating_sub(last + 1);
+            }
+            adaptive.last_sequence = Some(frame.sequence());
+        }
+        self.adapt()?;
+
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let pts_ms = started.elapsed().as_millis() as u64;
+        let downscale = self
+            .adaptive
+            .as_ref()
+            .map_or(1, |adaptive| adaptive.controller.target().downscale);
+        let packets = if downscale > 1 {
+            let frame = downscale_frame(frame, downscale);
+            self.encoder.encode(&frame, pts_ms, force_keyframe)?
+        } else {
+            self.encoder.encode(frame, pts_ms, force_keyframe)?
+        };
+
+        let session = self
+            .session
+            .as_ref()
+            .ok_or("WHIP session was not started")?;
+        let sending = Instant::now();
+        for packet in packets {
+            self.stats.record(&packet);
+            // The track paces RTP timestamps by each sample's duration
+            let duration = self
+                .last_pts_ms
+                .map(|last| Duration::from_millis(packet.pts_ms.saturating_sub(last)))
+                .unwrap_or(DEFAULT_FRAME_DURATION);
+            self.last_pts_ms = Some(packet.pts_ms);
+            let sample = Sample {
+                data: Bytes::from(packet.data),
+                duration,
+                ..Default::default()
+            };
+            session
+                .runtime
+                .block_on(session.track.write_sample(&sample))
+                .map_err(|e| format!("Failed to send to WHIP endpoint: {}", e))?;
+        }
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.send_was_slow = sending.elapsed() > DEFAULT_FRAME_DURATION;
+        }
+        Ok(())
+    }
+
+    fn set_content_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+        self.last_static_keyframe = None;
+    }
+
+    fn stop(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        if let Err(e) = session.runtime.block_on(session.peer_connection.close()) {
+            eprintln!("Failed to close WebRTC connection: {}", e);
+        }
+        // Tell the server the stream ended instead of letting it time out
+        if let Some(resource) = session.resource {
+            let mut request = ureq::delete(&resource);
+            if let Some(token) = &self.token {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+            if let Some(tag) = &session.hangup_tag {
+                request = request.set(stream_auth::AUTH_HEADER, tag);
+            }
+            if let Err(e) = request.call() {
+                eprintln!("Failed to end WHIP session {}: {}", resource, e);
+            }
+        }
+        println!("Stopped streaming to {}", self.url);
+    }
+}
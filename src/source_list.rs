@@ -0,0 +1,96 @@
+use crate::cross_platform_capture::CrossPlatformScreenCapture;
+use serde::Serialize;
+
+/// Which sources `cloakshare list-displays` / `list-windows` prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceListing {
+    Displays,
+    Windows,
+}
+
+/// The listing as an aligned table, or as a JSON array for scripts
+pub fn report(listing: SourceListing, json: bool) -> Result<String, String> {
+    let capture = CrossPlatformScreenCapture::new()?;
+    match listing {
+        SourceListing::Displays => {
+            let displays = capture.list_displays()?;
+            if json {
+                return to_json(&displays);
+            }
+            let rows = displays
+                .iter()
+                .map(|display| {
+                    let pixels = |points: u32| (points as f64 * display.scale_factor).round();
+                    vec![
+                        display.id.to_string(),
+                        format!("{}x{}", display.width, display.height),
+                        format!("{}x{}", pixels(display.width), pixels(display.height)),
+                        format!("{:.1}", display.scale_factor),
+                        if display.main {
+                            format!("{} (main)", display.name)
+                        } else {
+                            display.name.clone()
+                        },
+                    ]
+                })
+                .collect();
+            Ok(table(&["ID", "SIZE", "PIXELS", "SCALE", "NAME"], rows))
+        }
+        SourceListing::Windows => {
+            let windows = capture.list_windows()?;
+            if json {
+                return to_json(&windows);
+            }
+            let rows = windows
+                .iter()
+                .map(|window| {
+                    let frame = window.frame;
+                    vec![
+                        window.id.to_string(),
+                        format!("{}x{}", frame.width, frame.height),
+                        format!("{},{}", frame.x, frame.y),
+                        if window.on_screen { "yes" } else { "no" }.to_string(),
+                        format!("{} - {}", window.app_name, window.title),
+                    ]
+                })
+                .collect();
+            Ok(table(&["ID", "SIZE", "POSITION", "VISIBLE", "NAME"], rows))
+        }
+    }
+}
+
+fn to_json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string_pretty(value)
+        .map(|json| json + "\n")
+        .map_err(|e| format!("Failed to encode the list: {}", e))
+}
+
+/// Left-aligned columns separated by two spaces; the last column isn't padded
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = header.iter().map(|title| title.to_string()).collect();
+    let mut output = String::new();
+    for row in std::iter::once(header).chain(rows) {
+        let last = row.len().saturating_sub(1);
+        let cells: Vec<String> = row
+            .into_iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                if index == last {
+                    cell
+                } else {
+                    format!("{:<width$}", cell, width = widths[index])
+                }
+            })
+            .collect();
+        output.push_str(&cells.join("  "));
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,273 @@
+use crate::gpu_renderer::{GpuRenderer, GpuSettings};
+use crate::l10n::tr;
+use crate::platform::{CaptureSource, SourceInfo, SourceThumbnail};
+use crate::theme::Theme;
+use std::sync::Arc;
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    event_loop::ActiveEventLoop,
+    window::{Window, WindowId},
+};
+
+/// Thumbnail cell size in picker canvas pixels
+pub const THUMBNAIL_WIDTH: u32 = 320;
+pub const THUMBNAIL_HEIGHT: u32 = 200;
+const PADDING: u32 = 16;
+const COLUMNS: u32 = 4;
+
+const HIGHLIGHT_WIDTH: u32 = 4;
+
+/// One selectable source with its preview
+pub struct PickerEntry {
+    pub info: SourceInfo,
+    pub thumbnail: Option<SourceThumbnail>,
+}
+
+/// SourcePicker: grid of capture sources with thumbnails
+/// Handles layout, hit testing, and composing the grid into an RGBA canvas
+pub struct SourcePicker {
+    entries: Vec<PickerEntry>,
+    /// Background, cell and highlight colors
+    theme: Theme,
+    hovered: Option<usize>,
+    canvas_width: u32,
+    canvas_height: u32,
+}
+
+impl SourcePicker {
+    pub fn new(entries: Vec<PickerEntry>, theme: Theme) -> Self {
+        let count = entries.len().max(1) as u32;
+        let columns = count.min(COLUMNS);
+        let rows = count.div_ceil(COLUMNS);
+
+        Self {
+            entries,
+            theme,
+            hovered: None,
+            canvas_width: columns * (THUMBNAIL_WIDTH + PADDING) + PADDING,
+            canvas_height: rows * (THUMBNAIL_HEIGHT + PADDING) + PADDING,
+        }
+    }
+
+    /// Size of the composed canvas in pixels
+    pub fn canvas_size(&self) -> (u32, u32) {
+        (self.canvas_width, self.canvas_height)
+    }
+
+    pub fn entries(&self) -> &[PickerEntry] {
+        &self.entries
+    }
+
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    /// Update the hovered entry; returns true if it changed (canvas needs recomposing)
+    pub fn set_hovered(&mut self, hovered: Option<usize>) -> bool {
+        let changed = self.hovered != hovered;
+        self.hovered = hovered;
+        changed
+    }
+
+    /// Top-left corner of an entry's cell in canvas pixels
+    fn cell_origin(&self, index: usize) -> (u32, u32) {
+        let column = index as u32 % COLUMNS;
+        let row = index as u32 / COLUMNS;
+        (
+            PADDING + column * (THUMBNAIL_WIDTH + PADDING),
+            PADDING + row * (THUMBNAIL_HEIGHT + PADDING),
+        )
+    }
+
+    /// Find the entry under a point in canvas coordinates
+    pub fn hit_test(&self, x: f64, y: f64) -> Option<usize> {
+        (0..self.entries.len()).find(|&index| {
+            let (cx, cy) = self.cell_origin(index);
+            x >= cx as f64
+                && x < (cx + THUMBNAIL_WIDTH) as f64
+                && y >= cy as f64
+                && y < (cy + THUMBNAIL_HEIGHT) as f64
+        })
+    }
+
+    /// Render the grid (cells, centered thumbnails, hover highlight) into an RGBA canvas
+    pub fn compose(&self) -> Vec<u8> {
+        let mut canvas = self
+            .theme
+            .background
+            .repeat((self.canvas_width * self.canvas_height) as usize);
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (cx, cy) = self.cell_origin(index);
+
+            if self.hovered == Some(index) {
+                self.fill_rect(
+                    &mut canvas,
+                    cx.saturating_sub(HIGHLIGHT_WIDTH),
+                    cy.saturating_sub(HIGHLIGHT_WIDTH),
+                    THUMBNAIL_WIDTH + HIGHLIGHT_WIDTH * 2,
+                    THUMBNAIL_HEIGHT + HIGHLIGHT_WIDTH * 2,
+                    self.theme.accent,
+                );
+            }
+            self.fill_rect(
+                &mut canvas,
+                cx,
+                cy,
+                THUMBNAIL_WIDTH,
+                THUMBNAIL_HEIGHT,
+                self.theme.surface,
+            );
+
+            if let Some(thumbnail) = &entry.thumbnail {
+                // Center the thumbnail in its cell, cropping anything larger than the cell
+                let width = thumbnail.width.min(THUMBNAIL_WIDTH);
+                let height = thumbnail.height.min(THUMBNAIL_HEIGHT);
+                let ox = cx + (THUMBNAIL_WIDTH - width) / 2;
+                let oy = cy + (THUMBNAIL_HEIGHT - height) / 2;
+
+                for row in 0..height {
+                    let src_start = (row * thumbnail.width * 4) as usize;
+                    let dst_start = (((oy + row) * self.canvas_width + ox) * 4) as usize;
+                    let len = (width * 4) as usize;
+                    canvas[dst_start..dst_start + len]
+                        .copy_from_slice(&thumbnail.rgba[src_start..src_start + len]);
+                }
+            }
+        }
+
+        canvas
+    }
+
+    fn fill_rect(
+        &self,
+        canvas: &mut [u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        color: [u8; 4],
+    ) {
+        let x1 = (x + width).min(self.canvas_width);
+        let y1 = (y + height).min(self.canvas_height);
+        for row in y..y1 {
+            let start = ((row * self.canvas_width + x) * 4) as usize;
+            let end = ((row * self.canvas_width + x1) * 4) as usize;
+            for pixel in canvas[start..end].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+/// What the picker window wants the app to do after an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerAction {
+    None,
+    Select(CaptureSource),
+    Close,
+}
+
+/// PickerWindow: separate window showing the SourcePicker
+/// Kept out of the mirror window on purpose - thumbnails are unredacted and must never be shared,
+/// so the output stays blanked while it is open (`SafeMirror::set_source_picker_open`)
+pub struct PickerWindow {
+    window: Arc<Window>,
+    renderer: GpuRenderer,
+    picker: SourcePicker,
+    needs_compose: bool,
+}
+
+impl PickerWindow {
+    /// Open the picker window for the given entries
+    pub fn open(event_loop: &ActiveEventLoop, picker: SourcePicker) -> Result<Self, String> {
+        let (width, height) = picker.canvas_size();
+
+        // Title contains "CloakShare" so the capture filter excludes this window too
+        let window = Arc::new(
+            event_loop
+                .create_window(
+                    Window::default_attributes()
+                        .with_title(format!("CloakShare - {}", tr("title-choose-source")))
+                        .with_inner_size(winit::dpi::PhysicalSize::new(width, height)),
+                )
+                .map_err(|e| format!("Failed to create picker window: {}", e))?,
+        );
+
+        let renderer = pollster::block_on(GpuRenderer::new(
+            window.clone(),
+            width,
+            height,
+            &GpuSettings::default(),
+        ))
+        .map_err(|e| format!("Failed to start picker renderer: {}", e))?;
+
+        Ok(Self {
+            window,
+            renderer,
+            picker,
+            needs_compose: true,
+        })
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// Handle an event for the picker window
+    pub fn handle_event(&mut self, event: &WindowEvent) -> PickerAction {
+        match event {
+            WindowEvent::CloseRequested => return PickerAction::Close,
+
+            WindowEvent::Resized(size) => self.renderer.resize(*size),
+
+            WindowEvent::CursorMoved { position, .. } => {
+                let (x, y) = self.to_canvas(position.x, position.y);
+                if self.picker.set_hovered(self.picker.hit_test(x, y)) {
+                    self.needs_compose = true;
+                    // Titles can't be drawn into the canvas yet, so show the hovered one here
+                    let title = self
+                        .picker
+                        .hovered()
+                        .map(|i| self.picker.entries()[i].info.title.clone())
+                        .unwrap_or_else(|| tr("title-choose-source"));
+                    self.window.set_title(&format!("CloakShare - {}", title));
+                    self.window.request_redraw();
+                }
+            }
+
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if let Some(index) = self.picker.hovered() {
+                    return PickerAction::Select(self.picker.entries()[index].info.source);
+                }
+            }
+
+            WindowEvent::RedrawRequested => {
+                if self.needs_compose {
+                    self.renderer.update_texture(&self.picker.compose());
+                    self.needs_compose = false;
+                }
+                if let Err(e) = self.renderer.render() {
+                    eprintln!("Picker render error: {e:?}");
+                }
+            }
+
+            _ => {}
+        }
+        PickerAction::None
+    }
+
+    /// Map window pixel coordinates to canvas coordinates (the canvas is stretched to the window)
+    fn to_canvas(&self, x: f64, y: f64) -> (f64, f64) {
+        let (canvas_width, canvas_height) = self.picker.canvas_size();
+        let size = self.renderer.size();
+        (
+            x * canvas_width as f64 / size.width.max(1) as f64,
+            y * canvas_height as f64 / size.height.max(1) as f64,
+        )
+    }
+}
@@ -0,0 +1,112 @@
+use crate::gpu_memory::{GpuMemoryUsage, MEGABYTE};
+use crate::sinks::{SinkManager, SinkStats};
+use crate::text::{TextRenderer, TextStyle};
+use crate::theme::Theme;
+use std::time::{Duration, Instant};
+
+/// HUD layout in points; multiplied by the window's scale factor
+const HUD_TEXT_SIZE: f32 = 13.0;
+const HUD_PADDING: f32 = 8.0;
+
+/// How often the HUD text is refreshed
+const HUD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tightly packed RGBA pixels, width and height
+type HudImage = (Vec<u8>, u32, u32);
+
+/// Debug HUD in the corner of the mirror window listing every output's frame, queue and
+/// encoder counters and the renderer's GPU memory (Cmd+Shift+I), for diagnosing choppy
+/// remote viewing
+/// Drawn in the window only, like the error card, so it never reaches an output
+#[derive(Default)]
+pub struct StatsHud {
+    theme: Theme,
+    text: TextRenderer,
+    last_update: Option<Instant>,
+    last_text: String,
+}
+
+impl StatsHud {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            theme,
+            ..Self::default()
+        }
+    }
+
+    /// Draw the HUD again on the next `update`, e.g. after the renderer was recreated
+    pub fn invalidate(&mut self) {
+        self.last_update = None;
+        self.last_text.clear();
+    }
+
+    /// The HUD as RGBA (pixels, width, height) at `scale` pixels per point when its text
+    /// changed since the last call; at most once per second
+    pub fn update(
+        &mut self,
+        sinks: &SinkManager,
+        gpu_memory: &GpuMemoryUsage,
+        scale: f32,
+    ) -> Option<Result<HudImage, String>> {
+        if self
+            .last_update
+            .is_some_and(|last| last.elapsed() < HUD_INTERVAL)
+        {
+            return None;
+        }
+        self.last_update = Some(Instant::now());
+
+        let text = format!(
+            "{}\nGPU memory: {} MB",
+            stats_text(sinks),
+            gpu_memory.total().div_ceil(MEGABYTE)
+        );
+        if text == self.last_text {
+            return None;
+        }
+        let rendered = self.text.render(&TextStyle {
+            text: text.clone(),
+            font: self.theme.font.clone(),
+            size: HUD_TEXT_SIZE * scale,
+            color: self.theme.hud_text,
+            background: self.theme.background,
+            padding: HUD_PADDING * scale,
+            corner_radius: Some(self.theme.corner_radius as f32 * scale),
+        });
+        self.last_text = text;
+        Some(rendered)
+    }
+}
+
+/// One line per attached output, oldest first
+fn stats_text(sinks: &SinkManager) -> String {
+    let mut outputs: Vec<_> = sinks.sinks().collect();
+    if outputs.is_empty() {
+        return "No outputs".to_string();
+    }
+    outputs.sort_by_key(|(id, _)| id.as_u64());
+    outputs
+        .into_iter()
+        .map(|(id, name)| match sinks.stats(id) {
+            Some(stats) => format!("{}: {}", name, describe(&stats)),
+            None => name.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn describe(stats: &SinkStats) -> String {
+    let mut parts = vec![
+        format!("{} frames", stats.frames),
+        format!("{} skipped", stats.skipped),
+        format!("queue {}", stats.queue_depth),
+    ];
+    if let Some(encoder) = &stats.encoder {
+        parts.push(format!("{} kbps", encoder.bitrate_kbps));
+        if let Some(interval) = encoder.keyframe_interval {
+            parts.push(format!("keyframe every {:.1} s", interval.as_secs_f32()));
+        }
+        parts.push(format!("{} packets lost", encoder.dropped_packets));
+    }
+    parts.join(", ")
+}
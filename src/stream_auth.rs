@@ -0,0 +1,135 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// HTTP header carrying the stream key proofs on WHIP requests and responses
+pub const AUTH_HEADER: &str = "X-CloakShare-Auth";
+
+/// How far an offer's timestamp may be from the receiver's clock before it's refused
+/// Bounds how long a captured offer could be replayed, and how long its nonce is remembered
+const MAX_OFFER_AGE_SECS: u64 = 30;
+
+/// Proof that whoever sent `offer` knows the stream key: `<unix time>.<nonce>.<tag>`
+/// The time and a random nonce make each proof usable once, within `MAX_OFFER_AGE_SECS`
+pub fn offer_tag(key: &str, offer: &str) -> Result<String, String> {
+    let mut nonce = [0u8; 16];
+    getrandom::getrandom(&mut nonce).map_err(|e| format!("No randomness for a nonce: {}", e))?;
+    let issued = unix_time().to_string();
+    let nonce = hex(&nonce);
+    let tag = tag(key, "offer", &[&issued, &nonce, offer]);
+    Ok(format!("{}.{}.{}", issued, nonce, tag))
+}
+
+/// Check an offer proof made by `offer_tag`; returns its nonce, which the caller must only
+/// accept once (see `NonceGuard`)
+pub fn verify_offer(key: &str, offer: &str, proof: Option<&str>) -> Result<String, String> {
+    let invalid = || "Sender doesn't know the stream key".to_string();
+    let mut parts = proof.ok_or_else(invalid)?.splitn(3, '.');
+    let (Some(issued), Some(nonce), Some(received)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    if !matches(&tag(key, "offer", &[issued, nonce, offer]), Some(received)) {
+        return Err(invalid());
+    }
+    let issued: u64 = issued.parse().map_err(|_| invalid())?;
+    if unix_time().abs_diff(issued) > MAX_OFFER_AGE_SECS {
+        return Err("Offer proof expired (or the two clocks disagree)".to_string());
+    }
+    Ok(nonce.to_string())
+}
+
+/// Nonces of offers accepted within the last `MAX_OFFER_AGE_SECS`, so a replayed offer is
+/// refused even before its proof expires
+#[derive(Debug, Default)]
+pub struct NonceGuard {
+    seen: HashMap<String, u64>,
+}
+
+impl NonceGuard {
+    /// Remember `nonce`; false if it was already used
+    pub fn first_use(&mut self, nonce: String) -> bool {
+        let now = unix_time();
+        self.seen
+            .retain(|_, seen| now.saturating_sub(*seen) <= 2 * MAX_OFFER_AGE_SECS);
+        self.seen.insert(nonce, now).is_none()
+    }
+}
+
+/// Proof that whoever answered `offer` with `answer` knows the stream key
+/// Binding both SDPs authenticates the DTLS fingerprints in them, so the media can't be
+/// intercepted even when the signaling is plain HTTP
+pub fn answer_tag(key: &str, offer: &str, answer: &str) -> String {
+    tag(key, "answer", &[offer, answer])
+}
+
+/// Proof for ending the session that was answered with `answer`
+pub fn hangup_tag(key: &str, answer: &str) -> String {
+    tag(key, "hangup", &[answer])
+}
+
+/// Compare a received tag with the expected one in constant time
+pub fn matches(expected: &str, received: Option<&str>) -> bool {
+    let Some(received) = received else {
+        return false;
+    };
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Check that streaming to `url` is allowed under `require_encrypted_streams`
+/// Plain http:// signaling is trusted when it never leaves the machine, or when a stream key
+/// authenticates the answer (`answer_tag`), which is how receivers found on the network are
+/// reached; otherwise the endpoint needs https://
+pub fn check_endpoint(url: &str, required: bool, stream_key: Option<&str>) -> Result<(), String> {
+    if !required || stream_key.is_some() || url.starts_with("https://") || is_loopback(url) {
+        return Ok(());
+    }
+    Err(format!(
+        "Not streaming to {}: require_encrypted_streams needs an https:// endpoint or a \
+         stream_key shared with the receiver",
+        url
+    ))
+}
+
+fn is_loopback(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or(bracketed),
+        None => authority.split(':').next().unwrap_or(authority),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+/// Hex HMAC-SHA256 over the label and length-prefixed parts
+fn tag(key: &str, label: &str, parts: &[&str]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("cloakshare-whip-{}\n", label).as_bytes());
+    for part in parts {
+        mac.update(&(part.len() as u64).to_be_bytes());
+        mac.update(part.as_bytes());
+    }
+    hex(&mac.finalize().into_bytes())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
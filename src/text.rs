@@ -0,0 +1,150 @@
+use crate::theme::round_corners;
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Fonts tried in order when a text layer doesn't name one
+const DEFAULT_FONTS: &[&str] = &[
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/System/Library/Fonts/SFNS.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+];
+
+/// Content and styling of a text layer (lower-thirds, titles)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TextStyle {
+    /// Text to show; `\n` starts a new line
+    pub text: String,
+    /// TrueType/OpenType font file (first face of a collection); None = system default
+    pub font: Option<PathBuf>,
+    /// Glyph size in pixels
+    pub size: f32,
+    /// Text color (RGBA)
+    pub color: [u8; 4],
+    /// Box behind the text (RGBA); fully transparent by default
+    pub background: [u8; 4],
+    /// Space between the text and the edge of the box, in pixels
+    pub padding: f32,
+    /// Radius of the box's rounded corners in pixels; None = the theme's `corner_radius`
+    pub corner_radius: Option<f32>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            font: None,
+            size: 48.0,
+            color: [255, 255, 255, 255],
+            background: [0, 0, 0, 0],
+            padding: 12.0,
+            corner_radius: None,
+        }
+    }
+}
+
+/// Rasterizes text layers on the CPU, keeping parsed fonts around between renders
+#[derive(Default)]
+pub struct TextRenderer {
+    fonts: HashMap<PathBuf, FontVec>,
+}
+
+impl TextRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render text into tightly packed RGBA sized to fit it; returns (rgba, width, height)
+    pub fn render(&mut self, style: &TextStyle) -> Result<(Vec<u8>, u32, u32), String> {
+        let font = self.font(style.font.as_deref())?;
+        let scale = PxScale::from(style.size.max(1.0));
+        let scaled = font.as_scaled(scale);
+        let padding = style.padding.max(0.0);
+        let line_height = scaled.height() + scaled.line_gap();
+
+        // Lay out every line first so the texture can be sized to the text
+        let mut glyphs = Vec::new();
+        let mut text_width = 0.0f32;
+        let mut line_count = 0;
+        for (index, line) in style.text.lines().enumerate() {
+            let baseline = padding + scaled.ascent() + index as f32 * line_height;
+            let mut x = padding;
+            let mut previous = None;
+            for c in line.chars() {
+                let id = scaled.glyph_id(c);
+                if let Some(previous) = previous {
+                    x += scaled.kern(previous, id);
+                }
+                glyphs.push(id.with_scale_and_position(scale, point(x, baseline)));
+                x += scaled.h_advance(id);
+                previous = Some(id);
+            }
+            text_width = text_width.max(x - padding);
+            line_count = index + 1;
+        }
+
+        let text_height = line_count.max(1) as f32 * line_height - scaled.line_gap();
+        let width = (text_width + 2.0 * padding).ceil().max(1.0) as u32;
+        let height = (text_height + 2.0 * padding).ceil().max(1.0) as u32;
+
+        let mut rgba = style.background.repeat((width * height) as usize);
+        for glyph in glyphs {
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue; // Whitespace has no outline
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let x = bounds.min.x as i64 + gx as i64;
+                let y = bounds.min.y as i64 + gy as i64;
+                if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    return;
+                }
+                let offset = (y as usize * width as usize + x as usize) * 4;
+                blend_over(&mut rgba[offset..offset + 4], style.color, coverage);
+            });
+        }
+        if let Some(radius) = style.corner_radius {
+            round_corners(&mut rgba, width, height, radius);
+        }
+        Ok((rgba, width, height))
+    }
+
+    fn font(&mut self, path: Option<&Path>) -> Result<&FontVec, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => DEFAULT_FONTS
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+                .ok_or("No default font found; set `font` on the text layer")?,
+        };
+
+        if !self.fonts.contains_key(&path) {
+            let data = std::fs::read(&path)
+                .map_err(|e| format!("Failed to open font {}: {}", path.display(), e))?;
+            let font = FontVec::try_from_vec(data)
+                .map_err(|e| format!("Failed to parse font {}: {}", path.display(), e))?;
+            self.fonts.insert(path.clone(), font);
+        }
+        Ok(&self.fonts[&path])
+    }
+}
+
+/// Blend `color` (with extra glyph coverage 0-1) over a non-premultiplied RGBA pixel
+fn blend_over(pixel: &mut [u8], color: [u8; 4], coverage: f32) {
+    let src_alpha = color[3] as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+    let dst_alpha = pixel[3] as f32 / 255.0;
+    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    if out_alpha <= 0.0 {
+        return;
+    }
+    for channel in 0..3 {
+        let src = color[channel] as f32 * src_alpha;
+        let dst = pixel[channel] as f32 * dst_alpha * (1.0 - src_alpha);
+        pixel[channel] = ((src + dst) / out_alpha).round() as u8;
+    }
+    pixel[3] = (out_alpha * 255.0).round() as u8;
+}
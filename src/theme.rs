@@ -0,0 +1,99 @@
+use crate::text::TextStyle;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Look of everything CloakShare draws itself: the stats HUD, error card, source picker and
+/// region selection in the window, and text layers that don't style themselves, which reach
+/// recordings and streams (`[theme]` in the config)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// TrueType/OpenType font for overlay text and text layers without a `font`; None = system
+    /// default
+    pub font: Option<PathBuf>,
+    /// Panels: HUD, error card, picker background (RGBA)
+    pub background: [u8; 4],
+    /// Elements on a panel, e.g. the picker's cells
+    pub surface: [u8; 4],
+    /// Titles, button labels and the region selection outline
+    pub text: [u8; 4],
+    /// Explanations under a title, e.g. the error card's message
+    pub secondary_text: [u8; 4],
+    /// HUD counters
+    pub hud_text: [u8; 4],
+    /// Buttons and the picker's hover highlight
+    pub accent: [u8; 4],
+    /// Corner radius of panels, buttons and text layer boxes: points in the window, pixels in
+    /// text layers; 0 = square corners
+    pub corner_radius: u32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            font: None,
+            background: [38, 40, 46, 255],
+            surface: [45, 45, 52, 255],
+            text: [255, 255, 255, 255],
+            secondary_text: [200, 202, 208, 255],
+            hud_text: [160, 230, 160, 255],
+            accent: [58, 110, 215, 255],
+            corner_radius: 0,
+        }
+    }
+}
+
+impl Theme {
+    /// `style` with the theme's font and corner radius where the text layer leaves them unset
+    pub fn text_layer(&self, style: &TextStyle) -> TextStyle {
+        TextStyle {
+            font: style.font.clone().or_else(|| self.font.clone()),
+            corner_radius: Some(style.corner_radius.unwrap_or(self.corner_radius as f32)),
+            ..style.clone()
+        }
+    }
+}
+
+/// Make the corners of tightly packed RGBA transparent outside a `radius` pixel quarter
+/// circle, with an antialiased edge
+pub fn round_corners(rgba: &mut [u8], width: u32, height: u32, radius: f32) {
+    let radius = radius.min(width as f32 / 2.0).min(height as f32 / 2.0);
+    if radius < 1.0 {
+        return;
+    }
+    let size = radius.ceil() as u32;
+    for y in 0..size {
+        for x in 0..size {
+            // Distance from the pixel center to the circle's center, past the radius
+            let dx = radius - (x as f32 + 0.5);
+            let dy = radius - (y as f32 + 0.5);
+            let coverage = (radius + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+            if coverage >= 1.0 {
+                continue;
+            }
+            for (cx, cy) in [
+                (x, y),
+                (width - 1 - x, y),
+                (x, height - 1 - y),
+                (width - 1 - x, height - 1 - y),
+            ] {
+                let alpha = &mut rgba[((cy * width + cx) * 4 + 3) as usize];
+                *alpha = (*alpha as f32 * coverage).round() as u8;
+            }
+        }
+    }
+}
+
+/// Composite tightly packed RGBA over an opaque `background`, e.g. a rounded button before
+/// it's copied onto its panel
+pub fn flatten(rgba: &mut [u8], background: [u8; 4]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = pixel[3] as f32 / 255.0;
+        for channel in 0..3 {
+            pixel[channel] = (pixel[channel] as f32 * alpha
+                + background[channel] as f32 * (1.0 - alpha))
+                .round() as u8;
+        }
+        pixel[3] = background[3];
+    }
+}
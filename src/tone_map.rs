@@ -0,0 +1,236 @@
+use std::sync::{Mutex, OnceLock};
+
+/// Format of the capture texture and scene target in the float pipeline: linear half floats,
+/// so blending, filtering and effects don't band and values above 1.0 (EDR) survive
+pub const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Bytes per pixel of `SCENE_FORMAT`
+pub const SCENE_TEXEL_SIZE: u32 = 8;
+
+/// The float scene target for one output size
+struct Target {
+    width: u32,
+    height: u32,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Final pass of the float pipeline: reads the scene drawn into a `SCENE_FORMAT` target and
+/// writes it to the real target, rolling off values above 1.0 instead of clipping them
+pub struct ToneMapPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Recreated when the output size changes (window resize, offscreen export)
+    target: Mutex<Option<Target>>,
+}
+
+impl ToneMapPass {
+    /// Build the pass for targets of `format`; `premultiply` multiplies color by alpha on the
+    /// way out, for premultiplied window surfaces
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, premultiply: bool) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("tone_map_bind_group_layout"),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tone Map Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tone Map Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("tone_map.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tone Map Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: Some("vs_tone_map"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: Some("fs_tone_map"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &[("premultiply_output", if premultiply { 1.0 } else { 0.0 })],
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        // The target is the output's size, so every pixel is read exactly
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Tone Map Sampler"),
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            target: Mutex::new(None),
+        }
+    }
+
+    /// `SCENE_FORMAT` view the scene (and effects) must be drawn into before `encode` for a
+    /// `width`x`height` output
+    pub fn input_view(&self, device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+        let mut target = self.target.lock().unwrap_or_else(|e| e.into_inner());
+        let target = match target.take() {
+            Some(existing) if existing.width == width && existing.height == height => {
+                target.insert(existing)
+            }
+            _ => target.insert(self.create_target(device, width, height)),
+        };
+        target.view.clone()
+    }
+
+    /// Tone-map the scene drawn into `input_view` to `output`
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let target = self.target.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(target) = target.as_ref() else {
+            return;
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tone Map Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &target.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn create_target(&self, device: &wgpu::Device, width: u32, height: u32) -> Target {
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Float Scene Target"),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: SCENE_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tone_map_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        Target {
+            width,
+            height,
+            view,
+            bind_group,
+        }
+    }
+}
+
+/// Write a row of 8-bit sRGB RGBA pixels as linear `SCENE_FORMAT` texels
+/// (`texels` holds `SCENE_TEXEL_SIZE` bytes per pixel)
+pub fn write_scene_texels(rgba: &[u8], texels: &mut [u8]) {
+    static SRGB_TO_LINEAR: OnceLock<[[u8; 2]; 256]> = OnceLock::new();
+    static UNORM: OnceLock<[[u8; 2]; 256]> = OnceLock::new();
+    let srgb_to_linear = SRGB_TO_LINEAR.get_or_init(|| {
+        std::array::from_fn(|value| {
+            let value = value as f32 / 255.0;
+            let linear = if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            };
+            f16_bits(linear).to_le_bytes()
+        })
+    });
+    // Alpha isn't gamma encoded
+    let unorm = UNORM
+        .get_or_init(|| std::array::from_fn(|value| f16_bits(value as f32 / 255.0).to_le_bytes()));
+
+    for (pixel, texel) in rgba
+        .chunks_exact(4)
+        .zip(texels.chunks_exact_mut(SCENE_TEXEL_SIZE as usize))
+    {
+        for channel in 0..3 {
+            texel[channel * 2..channel * 2 + 2]
+                .copy_from_slice(&srgb_to_linear[pixel[channel] as usize]);
+        }
+        texel[6..8].copy_from_slice(&unorm[pixel[3] as usize]);
+    }
+}
+
+/// IEEE half-precision bits of `value`, rounded to nearest (overflow becomes infinity)
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= 31 {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal (or too small to represent at all)
+        if exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        return sign | ((mantissa + (1 << (shift - 1))) >> shift) as u16;
+    }
+    // A mantissa that rounds up carries into the exponent, which is still the right value
+    sign | (((exponent as u32) << 10) + ((mantissa + 0x1000) >> 13)) as u16
+}
@@ -0,0 +1,43 @@
+// Tone map pass: the last step of the float pipeline (GpuSettings::float_pipeline)
+//
+// The scene is drawn into a linear RGBA16F target so blending, filtering and effects keep
+// their precision; this pass writes it to the window surface (or offscreen texture).
+// Values up to 1.0 pass through unchanged, so SDR content (white included) looks the same as
+// without the float pipeline; brighter (EDR) colors are scaled back to 1.0 as a whole instead
+// of clipping each channel, so highlights keep their hue.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_scene: sampler;
+
+// Set when the window surface is composited as premultiplied alpha (see shader.wgsl)
+override premultiply_output: bool = false;
+
+// Fullscreen triangle, as in shader.wgsl
+@vertex
+fn vs_tone_map(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = select(-1.0, 3.0, vertex_index == 1u);
+    let y = select(-1.0, 3.0, vertex_index == 2u);
+    return VertexOutput(
+        vec4<f32>(x, y, 0.0, 1.0),
+        vec2<f32>((x + 1.0) / 2.0, 1.0 - (y + 1.0) / 2.0),
+    );
+}
+
+@fragment
+fn fs_tone_map(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_scene, s_scene, input.tex_coords);
+    // Bring the brightest channel down to 1.0 and scale the others with it, so hues don't shift
+    let peak = max(color.r, max(color.g, color.b));
+    let scale = select(1.0, 1.0 / peak, peak > 1.0);
+    let rgb = max(color.rgb * scale, vec3<f32>(0.0));
+    let alpha = clamp(color.a, 0.0, 1.0);
+    return vec4<f32>(select(rgb, rgb * alpha, premultiply_output), alpha);
+}
@@ -0,0 +1,120 @@
+use serde::Deserialize;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How often the feed is checked again while the app runs
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a check may take before it's given up until the next one
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A release newer than this build
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    /// Version as published, e.g. "0.2.0" or "v0.2.0"
+    #[serde(alias = "tag_name")]
+    pub version: String,
+    /// Release notes / download page
+    #[serde(alias = "html_url")]
+    pub url: String,
+}
+
+/// Checks a release feed in the background, at startup and then once a day
+/// The feed is JSON with `version` and `url`; a GitHub "latest release" API response also works
+pub struct UpdateChecker {
+    found: mpsc::Receiver<Release>,
+    latest: Option<Release>,
+    /// Dropped with the checker, which ends the thread
+    _stop: mpsc::Sender<()>,
+}
+
+impl UpdateChecker {
+    /// Start checking `feed_url`; `wake` is called when a newer release shows up
+    pub fn start(feed_url: String, wake: impl Fn() + Send + 'static) -> Result<Self, String> {
+        let (found_tx, found) = mpsc::channel();
+        let (stop, stop_rx) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("update-check".to_string())
+            .spawn(move || {
+                loop {
+                    match fetch_latest(&feed_url) {
+                        Ok(release) if is_newer(&release.version, env!("CARGO_PKG_VERSION")) => {
+                            if found_tx.send(release).is_err() {
+                                break;
+                            }
+                            wake();
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("Update check failed: {}", e),
+                    }
+                    if stop_rx.recv_timeout(CHECK_INTERVAL) != Err(mpsc::RecvTimeoutError::Timeout)
+                    {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to spawn update check thread: {}", e))?;
+        Ok(Self {
+            found,
+            latest: None,
+            _stop: stop,
+        })
+    }
+
+    /// The newest release found so far, if it's newer than this build
+    pub fn available(&self) -> Option<&Release> {
+        self.latest.as_ref()
+    }
+
+    /// A release found since the last call that hadn't been reported yet
+    pub fn poll(&mut self) -> Option<Release> {
+        let mut new = None;
+        while let Ok(release) = self.found.try_recv() {
+            if self.latest.as_ref() != Some(&release) {
+                self.latest = Some(release.clone());
+                new = Some(release);
+            }
+        }
+        new
+    }
+}
+
+fn fetch_latest(feed_url: &str) -> Result<Release, String> {
+    let body = ureq::AgentBuilder::new()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .get(feed_url)
+        .set(
+            "User-Agent",
+            concat!("CloakShare/", env!("CARGO_PKG_VERSION")),
+        )
+        .set("Accept", "application/json")
+        .call()
+        .map_err(|e| format!("{}: {}", feed_url, e))?
+        .into_string()
+        .map_err(|e| format!("Failed to read {}: {}", feed_url, e))?;
+    serde_json::from_str(&body).map_err(|e| format!("Invalid release feed {}: {}", feed_url, e))
+}
+
+/// Whether `latest` is a newer release than `current`; pre-releases ("1.0.0-beta") and
+/// versions that don't parse never count
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// "v1.2.0" -> [1, 2] (trailing zeros dropped so "1.2" and "1.2.0" compare equal)
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let mut parts = version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    while parts.last() == Some(&0) {
+        parts.pop();
+    }
+    Some(parts)
+}
@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+
+/// One camera frame (tightly packed RGBA)
+#[derive(Debug, Clone)]
+pub struct WebcamFrame {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A running camera feeding the scene's webcam layers
+/// Camera frames are composited after redaction: they never contain screen content
+pub struct Webcam {
+    /// Newest frame not yet picked up by the renderer (older ones are simply replaced)
+    latest: Arc<Mutex<Option<WebcamFrame>>>,
+    /// Cap the camera was started with (None = every frame it delivers)
+    frame_rate: Option<u32>,
+    #[cfg(target_os = "macos")]
+    _session: crate::platform::macos_camera::CameraSession,
+}
+
+impl Webcam {
+    /// Open a camera by name (see `--list-cameras`), or the system default, converting at
+    /// most `frame_rate` frames per second when set
+    #[cfg(target_os = "macos")]
+    pub fn start(device: Option<&str>, frame_rate: Option<u32>) -> Result<Self, String> {
+        let latest = Arc::new(Mutex::new(None));
+        let sender = latest.clone();
+        let session = crate::platform::macos_camera::CameraSession::start(
+            device,
+            frame_rate,
+            Box::new(move |rgba, width, height| {
+                if let Ok(mut latest) = sender.lock() {
+                    *latest = Some(WebcamFrame {
+                        rgba,
+                        width,
+                        height,
+                    });
+                }
+            }),
+        )?;
+        Ok(Self {
+            latest,
+            frame_rate,
+            _session: session,
+        })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn start(_device: Option<&str>, _frame_rate: Option<u32>) -> Result<Self, String> {
+        // Media Foundation (Windows) and V4L2 (Linux) cameras aren't implemented yet
+        Err("Webcam capture is not supported on this platform yet".to_string())
+    }
+
+    pub fn frame_rate(&self) -> Option<u32> {
+        self.frame_rate
+    }
+
+    /// Take the newest frame if one arrived since the last call
+    pub fn take_frame(&self) -> Option<WebcamFrame> {
+        self.latest.lock().ok()?.take()
+    }
+}
+
+/// Names of the available cameras
+#[cfg(target_os = "macos")]
+pub fn list_cameras() -> Vec<String> {
+    crate::platform::macos_camera::list_cameras()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn list_cameras() -> Vec<String> {
+    Vec::new()
+}
@@ -0,0 +1,63 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One WebVTT cue: `text` from `start_ms` to `end_ms` (milliseconds into the recording)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// Sidecar path for a recording: `<recording>.<kind>.vtt`
+pub fn sidecar_path(recording: &Path, kind: &str) -> PathBuf {
+    let mut path = recording.as_os_str().to_owned();
+    path.push(format!(".{}.vtt", kind));
+    PathBuf::from(path)
+}
+
+/// `HH:MM:SS.mmm`, the WebVTT timestamp format
+pub fn timestamp(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Write `cues` as a WebVTT file, replacing any previous one
+pub fn write(path: &Path, cues: &[Cue]) -> Result<(), String> {
+    let mut vtt = String::from("WEBVTT\n");
+    for cue in cues {
+        vtt.push_str(&format_cue(cue));
+    }
+    std::fs::write(path, vtt).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Add `cue` to the end of a WebVTT file started with `write`
+pub fn append(path: &Path, cue: &Cue) -> Result<(), String> {
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(format_cue(cue).as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn format_cue(cue: &Cue) -> String {
+    // A blank line would end the cue and "-->" would read as a timing line
+    let text = cue
+        .text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.replace("-->", "->"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "\n{} --> {}\n{}\n",
+        timestamp(cue.start_ms),
+        timestamp(cue.end_ms.max(cue.start_ms)),
+        text
+    )
+}
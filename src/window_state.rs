@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::ActiveEventLoop;
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window, WindowAttributes};
+
+/// Mirror window geometry saved on exit and restored on the next launch
+/// Sizes and positions are physical pixels in desktop coordinates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    /// Name of the monitor the window was on
+    pub monitor: Option<String>,
+    pub fullscreen: bool,
+}
+
+impl WindowState {
+    /// `window.json` next to the config file
+    pub fn path() -> Option<PathBuf> {
+        Some(crate::config::Config::path()?.parent()?.join("window.json"))
+    }
+
+    /// The saved state, if there is one and it can be read
+    pub fn load() -> Option<Self> {
+        let contents = std::fs::read(Self::path()?).ok()?;
+        match serde_json::from_slice(&contents) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                eprintln!("Ignoring saved window state: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No config directory for the window state")?;
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let contents = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to encode window state: {}", e))?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Current geometry of `window`; while fullscreen, the windowed size and position from
+    /// `previous` are kept so leaving fullscreen next time restores them
+    pub fn of_window(window: &Window, previous: Option<&Self>) -> Option<Self> {
+        let fullscreen = window.fullscreen().is_some();
+        let monitor = window.current_monitor().and_then(|monitor| monitor.name());
+        if fullscreen && let Some(previous) = previous {
+            return Some(Self {
+                monitor,
+                fullscreen,
+                ..previous.clone()
+            });
+        }
+        let size = window.inner_size();
+        let position = window.outer_position().ok()?;
+        Some(Self {
+            width: size.width,
+            height: size.height,
+            x: position.x,
+            y: position.y,
+            monitor,
+            fullscreen,
+        })
+    }
+
+    /// Apply the saved geometry to `attributes`
+    /// The position is only restored if it is still on a connected monitor (the saved one,
+    /// or any other when that one is gone), so a window never opens off screen
+    pub fn apply(
+        &self,
+        attributes: WindowAttributes,
+        event_loop: &ActiveEventLoop,
+    ) -> WindowAttributes {
+        let mut attributes =
+            attributes.with_inner_size(PhysicalSize::new(self.width.max(1), self.height.max(1)));
+
+        let saved_monitor = self.monitor.as_ref().and_then(|name| {
+            event_loop
+                .available_monitors()
+                .find(|monitor| monitor.name().as_ref() == Some(name))
+        });
+        let visible_on = saved_monitor.or_else(|| {
+            event_loop
+                .available_monitors()
+                .find(|monitor| contains(monitor, self.x, self.y))
+        });
+        if let Some(monitor) = &visible_on
+            && contains(monitor, self.x, self.y)
+        {
+            attributes = attributes.with_position(PhysicalPosition::new(self.x, self.y));
+        }
+        if self.fullscreen {
+            // On the saved monitor if it is still there, otherwise the one the window opens on
+            attributes = attributes.with_fullscreen(Some(Fullscreen::Borderless(visible_on)));
+        }
+        attributes
+    }
+}
+
+/// Whether desktop point (x, y) is on `monitor`
+fn contains(monitor: &MonitorHandle, x: i32, y: i32) -> bool {
+    let position = monitor.position();
+    let size = monitor.size();
+    x >= position.x
+        && y >= position.y
+        && x < position.x + size.width as i32
+        && y < position.y + size.height as i32
+}